@@ -28,6 +28,8 @@ use std::fs;
 use std::path::{Path, PathBuf};
 
 fn main() {
+    // The UI resources are only needed by the GTK application
+    #[cfg(feature = "gui")]
     glib_build_tools::compile_resources(
         &["resources"],
         "resources/resources.gresource.xml",
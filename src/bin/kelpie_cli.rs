@@ -0,0 +1,326 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+#![deny(unsafe_code)]
+
+//! Build a plan without the user interface, e.g.
+//!
+//! `kelpie_cli --from YSSY --to YBBN --via TW --type airways --aircraft 737-800 --format fpl --output YSSY-YBBN.fpl`
+
+use std::cell::Cell;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use kelpie_rust_planner::earth::coordinate::Coordinate;
+use kelpie_rust_planner::earth;
+use kelpie_rust_planner::earth::{get_earth_model, NavDataPaths};
+use kelpie_rust_planner::hangar::hangar::get_hangar;
+use kelpie_rust_planner::model::location::Location;
+use kelpie_rust_planner::model::cruise_level::FlightRules;
+use kelpie_rust_planner::model::plan::Plan;
+use kelpie_rust_planner::model::sector::Sector;
+use kelpie_rust_planner::model::waypoint::Waypoint;
+use kelpie_rust_planner::model::wind::{WindProfile, WindsAloft};
+use kelpie_rust_planner::planner::planner::{recalc_plan_elevations, Planner};
+use kelpie_rust_planner::preference::{USE_AIRWAYS, USE_FIXES, USE_GPS, USE_RADIO_BEACONS};
+use kelpie_rust_planner::util::plan_reader::enroute_navaids;
+use kelpie_rust_planner::util::plan_writer_fms::export_plan_fms_to;
+use kelpie_rust_planner::util::plan_writer_gpx::export_plan_gpx_to;
+use kelpie_rust_planner::util::plan_writer_kml::export_plan_kml_to;
+use kelpie_rust_planner::util::plan_writer_route_manager::export_plan_fg_to;
+use kelpie_rust_planner::util::plan_writer_text::{write_nav_log_html_to, write_nav_log_to};
+use kelpie_rust_planner::util::plan_writer_xml::write_plan_to;
+
+const USAGE: &str = "Usage: kelpie_cli --from <airport> --to <airport> [options]
+
+Options:
+  --via <id>            Route through a navaid, fix or airport, may be repeated
+  --type <type>         radio, fixes, gps or airways (default from preferences)
+  --aircraft <name>     Aircraft from the hangar (default aircraft if not given)
+  --altitude <feet>     Maximum cruise altitude
+  --rules <rules>       IFR or VFR, which decide the cruise level (default IFR)
+  --winds <profile>     Winds aloft, e.g. 270/25 or \"3000:270/15 9000:280/30\"
+  --format <format>     fpl, fg, fms, gpx, kml, html or text (default from the output file extension, else text)
+  --output <file>       Write to a file instead of stdout
+  --fg-dir <dir>        Flightgear data directory to load navigation data from
+  --airports <file>     apt.dat.gz to load instead of the one in the preferences
+  --navaids <file>      nav.dat.gz to load instead of the one in the preferences
+  --fixes <file>        fix.dat.gz to load instead of the one in the preferences
+  --airways <file>      awy.dat.gz to load instead of the one in the preferences
+  --help                Show this help";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Fpl,
+    Fg,
+    Fms,
+    Gpx,
+    Kml,
+    Html,
+    Text,
+}
+
+#[derive(Default)]
+struct Args {
+    from: Option<String>,
+    to: Option<String>,
+    via: Vec<String>,
+    plan_type: Option<String>,
+    aircraft: Option<String>,
+    altitude: Option<i32>,
+    flight_rules: FlightRules,
+    winds: Option<String>,
+    format: Option<Format>,
+    output: Option<PathBuf>,
+    fg_dir: Option<PathBuf>,
+    airports: Option<PathBuf>,
+    navaids: Option<PathBuf>,
+    fixes: Option<PathBuf>,
+    airways: Option<PathBuf>,
+    help: bool,
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(msg) => {
+            eprintln!("{}\n\n{}", msg, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    if args.help {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            parsed.help = true;
+            continue;
+        }
+        let mut value = || args.next().ok_or(format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--from" => parsed.from = Some(value()?.to_uppercase()),
+            "--to" => parsed.to = Some(value()?.to_uppercase()),
+            "--via" => parsed.via.push(value()?.to_uppercase()),
+            "--type" => {
+                let plan_type = match value()?.as_str() {
+                    "radio" => USE_RADIO_BEACONS,
+                    "fixes" => USE_FIXES,
+                    "gps" => USE_GPS,
+                    "airways" => USE_AIRWAYS,
+                    other => return Err(format!("Unknown plan type {}", other)),
+                };
+                parsed.plan_type = Some(plan_type.to_string());
+            }
+            "--aircraft" => parsed.aircraft = Some(value()?),
+            "--altitude" => {
+                let altitude = value()?;
+                parsed.altitude = Some(altitude.parse::<i32>().map_err(|_| format!("Invalid altitude {}", altitude))?);
+            }
+            "--rules" => parsed.flight_rules = value()?.parse::<FlightRules>()?,
+            "--winds" => parsed.winds = Some(value()?),
+            "--format" => {
+                let format = match value()?.as_str() {
+                    "fpl" => Format::Fpl,
+                    "fg" => Format::Fg,
+                    "fms" => Format::Fms,
+                    "gpx" => Format::Gpx,
+                    "kml" => Format::Kml,
+                    "html" => Format::Html,
+                    "text" => Format::Text,
+                    other => return Err(format!("Unknown format {}", other)),
+                };
+                parsed.format = Some(format);
+            }
+            "--output" => parsed.output = Some(PathBuf::from(value()?)),
+            "--fg-dir" => parsed.fg_dir = Some(PathBuf::from(value()?)),
+            "--airports" => parsed.airports = Some(PathBuf::from(value()?)),
+            "--navaids" => parsed.navaids = Some(PathBuf::from(value()?)),
+            "--fixes" => parsed.fixes = Some(PathBuf::from(value()?)),
+            "--airways" => parsed.airways = Some(PathBuf::from(value()?)),
+            _ => return Err(format!("Unknown option {}", arg)),
+        }
+    }
+    if !parsed.help && (parsed.from.is_none() || parsed.to.is_none()) {
+        return Err("Both --from and --to are required".to_string());
+    }
+    Ok(parsed)
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    // The planner exits straight away, so the caches are left for the application to build
+    earth::load_with(&navdata_paths(args)?, false).map_err(|e| format!("Unable to load navigation data: {}", e))?;
+
+    let plan = build_plan(args)?;
+
+    let format = args.format.unwrap_or_else(|| {
+        match args.output.as_ref().and_then(|p| p.extension()).and_then(|e| e.to_str()) {
+            Some("fpl") => Format::Fpl,
+            Some("xml") => Format::Fg,
+            Some("fms") => Format::Fms,
+            Some("gpx") => Format::Gpx,
+            Some("kml") => Format::Kml,
+            Some("html") => Format::Html,
+            _ => Format::Text,
+        }
+    });
+    let out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(std::fs::File::create(path)
+            .map_err(|e| format!("Unable to create {}: {}", path.display(), e))?),
+        None => Box::new(std::io::stdout()),
+    };
+    match format {
+        Format::Fpl => write_plan_to(&plan, out),
+        Format::Fg => export_plan_fg_to(&plan, out),
+        Format::Fms => export_plan_fms_to(&plan, out),
+        Format::Gpx => export_plan_gpx_to(&plan, out),
+        Format::Kml => export_plan_kml_to(&plan, out),
+        Format::Html => write_nav_log_html_to(&plan, out),
+        Format::Text => write_nav_log_to(&plan, out),
+    }
+}
+
+// Use the navigation data in the preferences unless the files are given on the command line
+fn navdata_paths(args: &Args) -> Result<NavDataPaths, String> {
+    let mut paths = match &args.fg_dir {
+        Some(dir) => Some(NavDataPaths::from_fg_dir(dir)),
+        None => NavDataPaths::from_preferences().ok(),
+    };
+    if let (None, Some(airports), Some(navaids), Some(fixes)) = (&paths, &args.airports, &args.navaids, &args.fixes) {
+        paths = Some(NavDataPaths {
+            airports: airports.clone(),
+            navaids: navaids.clone(),
+            fixes: fixes.clone(),
+            airways: None,
+        });
+    }
+    let mut paths = paths.ok_or("Navigation data is not set in the preferences, use --fg-dir or --airports, --navaids and --fixes")?;
+    if let Some(airports) = &args.airports {
+        paths.airports = airports.clone();
+    }
+    if let Some(navaids) = &args.navaids {
+        paths.navaids = navaids.clone();
+    }
+    if let Some(fixes) = &args.fixes {
+        paths.fixes = fixes.clone();
+    }
+    if let Some(airways) = &args.airways {
+        paths.airways = Some(airways.clone());
+    }
+    Ok(paths)
+}
+
+fn build_plan(args: &Args) -> Result<Plan, String> {
+    let from_id = args.from.as_deref().unwrap_or_default();
+    let to_id = args.to.as_deref().unwrap_or_default();
+    let from = get_earth_model().get_airport_by_id(from_id).ok_or(format!("Unknown airport {}", from_id))?;
+    let to = get_earth_model().get_airport_by_id(to_id).ok_or(format!("Unknown airport {}", to_id))?;
+
+    let mut plan = Plan::new();
+    let aircraft = match &args.aircraft {
+        Some(name) => Some(get_hangar().get(name).ok_or(format!("Unknown aircraft {}", name))?),
+        None => get_hangar().get_default_aircraft(),
+    };
+    plan.set_aircraft(&aircraft);
+    plan.set_max_altitude(args.altitude);
+    plan.set_flight_rules(args.flight_rules);
+    if let Some(winds) = &args.winds {
+        plan.set_winds(Some(WindsAloft::Profile(WindProfile::parse(winds)?)));
+    }
+
+    let mut sector = Sector::new();
+    let mut last_loc = from.get_loc().clone();
+    sector.set_start(Some(from));
+    sector.set_end(Some(to));
+    for id in &args.via {
+        let wp = find_waypoint(id, &last_loc).ok_or(format!("Unknown waypoint {}", id))?;
+        last_loc = wp.get_loc().clone();
+        sector.add_waypoint(wp);
+    }
+
+    let mut planner = Planner::new();
+    if let Some(plan_type) = &args.plan_type {
+        planner.set_plan_type(plan_type);
+    }
+    let waypoints = planner.make_plan(&sector);
+    sector.remove_all_waypoints();
+    sector.add_all_waypoint(waypoints);
+
+    plan.add_sector(sector);
+    plan.set_settings(Some(planner.get_settings()));
+    recalc_plan_elevations(&mut plan);
+    Ok(plan)
+}
+
+// Idents are not unique, so take the navaid, fix or airport nearest the previous point on the route
+fn find_waypoint(id: &str, near: &Coordinate) -> Option<Waypoint> {
+    let earth = get_earth_model();
+    let navaid = enroute_navaids(earth, id).into_iter()
+        .map(|n| (n.get_loc().distance_to(near), Waypoint::Navaid { navaid: n, elevation: Cell::new(0), locked: true, airway: None, constraints: Cell::default() }));
+    let fix = earth.get_fixes_by_id(id).into_iter()
+        .map(|f| (f.get_loc().distance_to(near), Waypoint::Fix { fix: f, elevation: Cell::new(0), locked: true, airway: None, constraints: Cell::default() }));
+    let airport = earth.get_airports_by_id(id).into_iter()
+        .map(|a| (a.get_loc().distance_to(near), Waypoint::Airport { airport: a, locked: true }));
+    navaid.chain(fix).chain(airport)
+        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(_, wp)| wp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_args, FlightRules, Format, USE_AIRWAYS};
+
+    fn args(line: &str) -> impl Iterator<Item = String> + '_ {
+        line.split_whitespace().map(|s| s.to_string())
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let parsed = parse_args(args("--from yssy --to YBBN --via tw --via BN --type airways --altitude 9000 --rules vfr --format fg")).unwrap();
+        assert_eq!(parsed.from.as_deref(), Some("YSSY"));
+        assert_eq!(parsed.to.as_deref(), Some("YBBN"));
+        assert_eq!(parsed.via, vec!["TW", "BN"]);
+        assert_eq!(parsed.plan_type.as_deref(), Some(USE_AIRWAYS));
+        assert_eq!(parsed.altitude, Some(9000));
+        assert_eq!(parsed.flight_rules, FlightRules::Vfr);
+        assert_eq!(parsed.format, Some(Format::Fg));
+
+        assert!(parse_args(args("--from YSSY")).is_err());
+        assert!(parse_args(args("--from YSSY --to YBBN --type rocket")).is_err());
+        assert!(parse_args(args("--from YSSY --to YBBN --altitude")).is_err());
+        assert!(parse_args(args("--from YSSY --to YBBN --rules SVFR")).is_err());
+        assert!(parse_args(args("--help")).unwrap().help);
+    }
+}
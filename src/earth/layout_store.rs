@@ -0,0 +1,219 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+#![forbid(unsafe_code)]
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Error, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+
+use crate::earth::navdata_cache::{self, CacheKey, read_str, read_u32, read_u64, write_str, write_u32, write_u64};
+
+const MAGIC: &[u8; 8] = b"KELPIELY";
+// Bump whenever the layout below changes, older stores are then rebuilt
+const STORE_VERSION: u32 = 1;
+
+/// The runways, taxiways and other layout records of every airport, extracted from apt.dat so that
+/// one airport can be read without decompressing and scanning the whole file.
+///
+/// The file holds a header, the records of each airport deflated on their own, the index of where
+/// each airport's records are and finally the position of that index.
+pub struct LayoutStore {
+    path: PathBuf,
+    index: HashMap<String, (u64, u64)>,
+}
+
+/// Where the store is kept, in the user's cache directory.
+pub fn get_store_path() -> Option<PathBuf> {
+    navdata_cache::get_cache_file("layouts.cache")
+}
+
+impl LayoutStore {
+    /// Open the store, as long as it was built by this version from apt.dat as it is now.
+    /// Returns None when the store is missing or stale.
+    pub fn open(path: &Path, key: &CacheKey) -> Result<Option<Self>, Error> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let mut input = BufReader::new(file);
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC || read_u32(&mut input)? != STORE_VERSION || CacheKey::read(&mut input)? != *key {
+            return Ok(None);
+        }
+
+        input.seek(SeekFrom::End(-8))?;
+        let index_offset = read_u64(&mut input)?;
+        input.seek(SeekFrom::Start(index_offset))?;
+        let mut index = HashMap::new();
+        for _ in 0..read_u32(&mut input)? {
+            index.insert(read_str(&mut input)?, (read_u64(&mut input)?, read_u64(&mut input)?));
+        }
+        Ok(Some(Self { path: path.to_path_buf(), index }))
+    }
+
+    /// Build the store from the lines of apt.dat. Where an id is used twice the last airport is kept,
+    /// as it is for the runway offsets.
+    pub fn build<R: BufRead>(path: &Path, key: &CacheKey, reader: &mut R) -> Result<Self, Error> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temp_path = navdata_cache::get_temp_path(path);
+        let mut out = BufWriter::new(File::create(&temp_path)?);
+
+        let mut header = Vec::new();
+        header.extend_from_slice(MAGIC);
+        write_u32(&mut header, STORE_VERSION)?;
+        key.write(&mut header)?;
+        out.write_all(&header)?;
+        let mut offset = header.len() as u64;
+
+        let mut index = HashMap::new();
+        let mut current: Option<(String, DeflateEncoder<Vec<u8>>)> = None;
+        let mut line = Vec::<u8>::with_capacity(256);
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+            match airport_id(&line) {
+                Some(id) => {
+                    if let Some((id, encoder)) = current.take() {
+                        let chunk = encoder.finish()?;
+                        out.write_all(&chunk)?;
+                        index.insert(id, (offset, chunk.len() as u64));
+                        offset += chunk.len() as u64;
+                    }
+                    current = Some((id, DeflateEncoder::new(Vec::new(), Compression::fast())));
+                }
+                None => {
+                    if let Some((_, encoder)) = current.as_mut() {
+                        encoder.write_all(&line)?;
+                    }
+                }
+            }
+        }
+        if let Some((id, encoder)) = current.take() {
+            let chunk = encoder.finish()?;
+            out.write_all(&chunk)?;
+            index.insert(id, (offset, chunk.len() as u64));
+            offset += chunk.len() as u64;
+        }
+
+        write_u32(&mut out, index.len() as u32)?;
+        for (id, (chunk_offset, length)) in &index {
+            write_str(&mut out, id)?;
+            write_u64(&mut out, *chunk_offset)?;
+            write_u64(&mut out, *length)?;
+        }
+        write_u64(&mut out, offset)?;
+        out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&temp_path, path)?;
+        Ok(Self { path: path.to_path_buf(), index })
+    }
+
+    /// The apt.dat lines following the airport's own, up to the next airport.
+    pub fn read_layout(&self, id: &str) -> Result<Option<Vec<u8>>, Error> {
+        let Some((offset, length)) = self.index.get(id) else {
+            return Ok(None);
+        };
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(*offset))?;
+        let mut layout = Vec::new();
+        DeflateDecoder::new(file.take(*length)).read_to_end(&mut layout)?;
+        Ok(Some(layout))
+    }
+}
+
+// The id from an airport, seaplane base or heliport line, e.g. "1 433 1 0 KSEA Seattle-Tacoma Intl"
+fn airport_id(line: &[u8]) -> Option<String> {
+    let line = String::from_utf8_lossy(line);
+    let mut tokenizer = line.split_whitespace();
+    match tokenizer.next() {
+        Some("1") | Some("16") | Some("17") => tokenizer.nth(3).map(|id| id.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::BufReader;
+    use std::sync::Arc;
+
+    use crate::model::test_utils::tests::make_airport;
+    use crate::util::airport_parser::AirportParserFG850;
+
+    use super::{CacheKey, LayoutStore};
+
+    const APT_DAT: &str = "I
+1000 Version - data cycle 2013.10
+
+1    433 1 0 KSEA Seattle-Tacoma Intl
+1302 city Seattle
+100 45.11 1 0 0.25 1 3 0 16L 47.46379089 -122.30775900 100.0 0.00 3 12 0 1 34R 47.43119539 -122.30804267 0.00 50.0 3 0 0 1
+50 11800 ATIS
+1    20 0 0 KBFI Boeing Field
+100 45.11 1 0 0.25 1 3 0 14L 47.54 -122.31 0.00 0.00 3 12 0 1 32R 47.52 -122.30 0.00 0.00 3 0 0 1
+100 45.11 1 0 0.25 1 3 0 13R 47.54 -122.31 0.00 0.00 3 12 0 1 31L 47.52 -122.30 0.00 0.00 3 0 0 1
+99
+";
+
+    #[test]
+    fn test_build_and_read() {
+        let dir = std::env::temp_dir().join(format!("kelpie-layouts-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let apt_dat = dir.join("apt.dat");
+        fs::write(&apt_dat, APT_DAT).unwrap();
+        let key = CacheKey::of(&[&apt_dat]).unwrap();
+        let path = dir.join("layouts.cache");
+
+        assert!(LayoutStore::open(&path, &key).unwrap().is_none());
+        LayoutStore::build(&path, &key, &mut BufReader::new(APT_DAT.as_bytes())).unwrap();
+        let store = LayoutStore::open(&path, &key).unwrap().unwrap();
+        assert!(store.read_layout("KPAE").unwrap().is_none());
+
+        let layout = store.read_layout("KBFI").unwrap().unwrap();
+        let airport: Arc<_> = make_airport("KBFI");
+        AirportParserFG850::new().load_layout(&airport, &mut layout.as_slice()).unwrap();
+        assert_eq!(airport.get_runways().read().unwrap().len(), 2);
+
+        let layout = String::from_utf8(store.read_layout("KSEA").unwrap().unwrap()).unwrap();
+        assert!(layout.starts_with("1302 city Seattle"));
+        assert!(layout.ends_with("50 11800 ATIS\n"));
+
+        // A new apt.dat makes the store stale
+        fs::write(&apt_dat, "I\n").unwrap();
+        assert!(LayoutStore::open(&path, &CacheKey::of(&[&apt_dat]).unwrap()).unwrap().is_none());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -23,6 +23,7 @@
  */
 #![forbid(unsafe_code)]
 
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fs;
 use std::io::{BufReader, Error};
@@ -31,79 +32,141 @@ use std::sync::{Arc, LazyLock, RwLock};
 
 use flate2::read;
 use gettextrs::gettext;
-use log::info;
+use log::{info, warn};
 
 use crate::event;
 use crate::event::Event;
 use crate::model::airport::Airport;
-use crate::model::fix::Fix;
+use crate::model::airway::{AirwayGraph, AirwaySegment};
+use crate::model::fix::{self, Fix};
 use crate::model::location::Location;
 use crate::model::navaid::Navaid;
+use crate::earth::coordinate::Coordinate;
+use crate::earth::layout_store::LayoutStore;
+use crate::earth::navdata_cache::{CacheKey, NavData};
+use crate::earth::spatial_index::SpatialIndex;
 use crate::util::airport_parser::AirportParserFG850;
+use crate::util::airway_parser::AirwayParserFG;
 use crate::util::fix_parser::FixParserFG;
 
 use crate::util::navaid_parser::NavaidParserFG;
 pub mod coordinate;
 pub mod geomagnetism;
-pub(crate) mod spherical_projector;
-pub(crate) mod airport_list_model;
-pub(crate) mod navaid_list_model;
-pub(crate) mod fix_list_model;
-pub (crate) mod solar;
+pub mod layout_store;
+pub mod navdata_cache;
+pub mod spherical_projector;
+#[cfg(feature = "gui")]
+pub mod airport_list_model;
+#[cfg(feature = "gui")]
+pub mod navaid_list_model;
+#[cfg(feature = "gui")]
+pub mod fix_list_model;
+pub mod solar;
+pub mod spatial_index;
+pub mod terrain;
 
 pub const FEET_PER_DEGREE: i32 = 6076 * 60;
+pub const FEET_PER_METRE: f64 = 3.28084;
 
 
-static EARTH: LazyLock<Earth> = LazyLock::new(|| Earth {
-    airports: RwLock::new(Vec::new()),
-    navaids: RwLock::new(Vec::new()),
-    fixes: RwLock::new(Vec::new()),
-    runway_offsets: RwLock::new(HashMap::new()),
-    ils: RwLock::new(HashMap::new()),
-});
+static EARTH: LazyLock<Earth> = LazyLock::new(Earth::new);
 
 pub struct Earth {
     airports: RwLock<Vec<Arc<Airport>>>,
     navaids: RwLock<Vec<Arc<Navaid>>>,
     fixes: RwLock<Vec<Arc<Fix>>>,
+    airport_index: RwLock<SpatialIndex<Airport>>,
+    navaid_index: RwLock<SpatialIndex<Navaid>>,
+    fix_index: RwLock<SpatialIndex<Fix>>,
+    airport_ids: RwLock<HashMap<String, Vec<Arc<Airport>>>>,
+    navaid_ids: RwLock<HashMap<String, Vec<Arc<Navaid>>>>,
+    fix_ids: RwLock<HashMap<String, Vec<Arc<Fix>>>>,
+    airways: RwLock<AirwayGraph>,
     runway_offsets: RwLock<HashMap<String, usize>>,
     ils: RwLock<HashMap<String, Vec<(String, f64)>>>,
+    airports_path: RwLock<Option<PathBuf>>,
+    layout_store: RwLock<Option<Arc<LayoutStore>>>,
 }
 
 impl Earth {
+    pub(crate) fn new() -> Self {
+        Earth {
+            airports: RwLock::new(Vec::new()),
+            navaids: RwLock::new(Vec::new()),
+            fixes: RwLock::new(Vec::new()),
+            airport_index: RwLock::new(SpatialIndex::default()),
+            navaid_index: RwLock::new(SpatialIndex::default()),
+            fix_index: RwLock::new(SpatialIndex::default()),
+            airport_ids: RwLock::new(HashMap::new()),
+            navaid_ids: RwLock::new(HashMap::new()),
+            fix_ids: RwLock::new(HashMap::new()),
+            airways: RwLock::new(AirwayGraph::new()),
+            runway_offsets: RwLock::new(HashMap::new()),
+            ils: RwLock::new(HashMap::new()),
+            airports_path: RwLock::new(None),
+            layout_store: RwLock::new(None),
+        }
+    }
+
     pub fn get_airports(&self) -> &RwLock<Vec<Arc<Airport>>> {
         &self.airports
     }
 
     pub fn get_airport_by_id(&self, id: &str) -> Option<Arc<Airport>> {
-        self.airports
+        self.airport_ids
             .read()
             .expect("Unable to get lock on Airports")
-            .iter()
-            .find(|airport| airport.get_id() == id)
+            .get(id)
+            .and_then(|airports| airports.first())
+            .cloned()
+    }
+
+    /// All airports with the given id, normally there is only one.
+    pub fn get_airports_by_id(&self, id: &str) -> Vec<Arc<Airport>> {
+        self.airport_ids
+            .read()
+            .expect("Unable to get lock on Airports")
+            .get(id)
             .cloned()
+            .unwrap_or_default()
     }
 
     pub fn set_airports(&self, airports: Vec<Arc<Airport>>) {
+        *self.airport_ids.write().expect("Unable to get lock on Airports") = build_id_index(&airports);
+        *self.airport_index.write().expect("Unable to get lock on Airport index") = SpatialIndex::new(&airports);
         let mut aps = self.airports.write().expect("Unable to get lock on Airports");
         aps.clear();
         aps.extend(airports);
     }
 
     pub fn get_navaid_by_id_and_name(&self, id: &str, name: &str) -> Option<Arc<Navaid>> {
-        self.navaids
+        self.navaid_ids
             .read()
             .expect("Unable to get lock on Navaids")
+            .get(id)?
             .iter()
-            .find(|navaid| navaid.get_id() == id && navaid.get_name().eq_ignore_ascii_case(name))
+            .find(|navaid| navaid.get_name().eq_ignore_ascii_case(name))
             .cloned()
     }
 
+    /// All navaids with the given id. Navaid idents are only unique within a region
+    /// so there may be several.
+    pub fn get_navaids_by_id(&self, id: &str) -> Vec<Arc<Navaid>> {
+        self.navaid_ids
+            .read()
+            .expect("Unable to get lock on Navaids")
+            .get(id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
     pub fn get_navaids(&self) -> &RwLock<Vec<Arc<Navaid>>> {
         &self.navaids
     }
 
     pub fn set_navaids(&self, navaids: Vec<Arc<Navaid>>) {
+        *self.navaid_ids.write().expect("Unable to get lock on Navaids") = build_id_index(&navaids);
+        *self.navaid_index.write().expect("Unable to get lock on Navaid index") = SpatialIndex::new(&navaids);
         let mut navs = self.navaids.write().expect("Unable to get lock on Navaids");
         navs.clear();
         navs.extend(navaids);
@@ -120,24 +183,133 @@ impl Earth {
     }
 
     pub fn get_fix_by_id(&self, id: &str) -> Option<Arc<Fix>> {
-        self.fixes
+        self.fix_ids
             .read()
-            .expect("Unable to get lock on Ils")
-            .iter()
-            .find(|fix| fix.get_id() == id)
+            .expect("Unable to get lock on Fixes")
+            .get(id)
+            .and_then(|fixes| fixes.first())
             .cloned()
     }
 
+    /// All fixes with the given id. Fix idents are only unique within a region
+    /// so there may be several.
+    pub fn get_fixes_by_id(&self, id: &str) -> Vec<Arc<Fix>> {
+        self.fix_ids
+            .read()
+            .expect("Unable to get lock on Fixes")
+            .get(id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// The fix with the given id, preferring those in the region and then the one
+    /// nearest the location.
+    pub fn find_fix(&self, id: &str, region: Option<&str>, terminal_area: Option<&str>, near: Option<&Coordinate>) -> Option<Arc<Fix>> {
+        let candidates = fix::in_terminal_area(fix::in_region(self.get_fixes_by_id(id), region, |f| f.get_region()), terminal_area);
+        match near {
+            Some(near) => candidates.into_iter().min_by(|a, b| {
+                a.get_loc().distance_to(near)
+                    .partial_cmp(&b.get_loc().distance_to(near))
+                    .unwrap_or(Ordering::Equal)
+            }),
+            None => candidates.into_iter().next(),
+        }
+    }
+
     pub fn get_fixes(&self) -> &RwLock<Vec<Arc<Fix>>> {
         &self.fixes
     }
 
     pub fn set_fixes(&self, fixes: Vec<Arc<Fix>>) {
+        *self.fix_ids.write().expect("Unable to get lock on Fixes") = build_id_index(&fixes);
+        *self.fix_index.write().expect("Unable to get lock on Fix index") = SpatialIndex::new(&fixes);
         let mut fxs = self.fixes.write().expect("Unable to get lock on fixes");
         fxs.clear();
         fxs.extend(fixes);
     }
 
+    pub fn get_navaid_index(&self) -> &RwLock<SpatialIndex<Navaid>> {
+        &self.navaid_index
+    }
+
+    pub fn get_fix_index(&self) -> &RwLock<SpatialIndex<Fix>> {
+        &self.fix_index
+    }
+
+    pub fn get_airports_near(&self, coord: &Coordinate, range: f64) -> Vec<Arc<Airport>> {
+        self.airport_index
+            .read()
+            .expect("Unable to get lock on Airport index")
+            .within_range(coord, range)
+    }
+
+    pub fn get_navaids_near(&self, coord: &Coordinate, range: f64) -> Vec<Arc<Navaid>> {
+        self.navaid_index
+            .read()
+            .expect("Unable to get lock on Navaid index")
+            .within_range(coord, range)
+    }
+
+    pub fn get_fixes_near(&self, coord: &Coordinate, range: f64) -> Vec<Arc<Fix>> {
+        self.fix_index
+            .read()
+            .expect("Unable to get lock on Fix index")
+            .within_range(coord, range)
+    }
+
+    pub fn get_nearest_airport<F>(&self, coord: &Coordinate, range: f64, filter: F) -> Option<Arc<Airport>>
+    where
+        F: Fn(&Airport) -> bool,
+    {
+        self.airport_index
+            .read()
+            .expect("Unable to get lock on Airport index")
+            .nearest(coord, range, filter)
+    }
+
+    pub fn get_nearest_navaid<F>(&self, coord: &Coordinate, range: f64, filter: F) -> Option<Arc<Navaid>>
+    where
+        F: Fn(&Navaid) -> bool,
+    {
+        self.navaid_index
+            .read()
+            .expect("Unable to get lock on Navaid index")
+            .nearest(coord, range, filter)
+    }
+
+    pub fn get_nearest_fix<F>(&self, coord: &Coordinate, range: f64, filter: F) -> Option<Arc<Fix>>
+    where
+        F: Fn(&Fix) -> bool,
+    {
+        self.fix_index
+            .read()
+            .expect("Unable to get lock on Fix index")
+            .nearest(coord, range, filter)
+    }
+
+    pub fn get_navaids_in_corridor(&self, from: &Coordinate, to: &Coordinate, ratio: f64) -> Vec<Arc<Navaid>> {
+        self.navaid_index
+            .read()
+            .expect("Unable to get lock on Navaid index")
+            .within_corridor(from, to, ratio)
+    }
+
+    pub fn get_fixes_in_corridor(&self, from: &Coordinate, to: &Coordinate, ratio: f64) -> Vec<Arc<Fix>> {
+        self.fix_index
+            .read()
+            .expect("Unable to get lock on Fix index")
+            .within_corridor(from, to, ratio)
+    }
+
+    pub fn get_airways(&self) -> &RwLock<AirwayGraph> {
+        &self.airways
+    }
+
+    pub fn set_airways(&self, airways: AirwayGraph) {
+        let mut awys = self.airways.write().expect("Unable to get lock on airways");
+        *awys = airways;
+    }
+
     pub fn set_runway_offsets(&self, runway_offsets: HashMap<String, usize>) {
         let mut rns = self.runway_offsets.write().expect("Unable to get lock on runways");
         rns.clear();
@@ -147,63 +319,237 @@ impl Earth {
     pub fn get_runway_offsets(&self) -> &RwLock<HashMap<String, usize>> {
         &self.runway_offsets
     }
+
+    /// The apt.dat the airports were loaded from, where their layouts are read from.
+    pub fn get_airports_path(&self) -> Option<PathBuf> {
+        self.airports_path.read().expect("Unable to get lock on airports path").clone()
+    }
+
+    pub fn set_airports_path(&self, path: Option<PathBuf>) {
+        *self.airports_path.write().expect("Unable to get lock on airports path") = path;
+    }
+
+    /// The store of airport layouts, once it has been opened or built.
+    pub fn get_layout_store(&self) -> Option<Arc<LayoutStore>> {
+        self.layout_store.read().expect("Unable to get lock on layout store").clone()
+    }
+
+    pub fn set_layout_store(&self, store: Option<Arc<LayoutStore>>) {
+        *self.layout_store.write().expect("Unable to get lock on layout store") = store;
+    }
 }
 
 pub fn get_earth_model() -> &'static Earth {
     &EARTH
 }
 
-pub fn initialise() -> Result<(), Error> {
-    let prefs = crate::preference::manager();
+fn build_id_index<T: Location>(items: &[Arc<T>]) -> HashMap<String, Vec<Arc<T>>> {
+    let mut index: HashMap<String, Vec<Arc<T>>> = HashMap::with_capacity(items.len());
+    for item in items {
+        index.entry(item.get_id().to_string())
+            .or_default()
+            .push(item.clone());
+    }
+    index
+}
 
-    // Check if the default path should be used
-    let use_default_path = prefs.get::<bool>(crate::preference::FGFS_USE_DFT_PATH).unwrap_or(false);
+/// The navigation data files the earth model is loaded from.
+#[derive(Clone, Debug)]
+pub struct NavDataPaths {
+    pub airports: PathBuf,
+    pub navaids: PathBuf,
+    pub fixes: PathBuf,
+    // Airways are optional, they are only needed for airway routing
+    pub airways: Option<PathBuf>,
+}
 
-    let airports_path;
-    let navaids_path;
-    let fixes_path;
+impl NavDataPaths {
+    /// The files in their usual places under a Flightgear data directory.
+    pub fn from_fg_dir(base_path: &Path) -> Self {
+        Self {
+            airports: base_path.join("Airports").join("apt.dat.gz"),
+            navaids: base_path.join("Navaids").join("nav.dat.gz"),
+            fixes: base_path.join("Navaids").join("fix.dat.gz"),
+            airways: Some(base_path.join("Navaids").join("awy.dat.gz")),
+        }
+    }
 
-    if use_default_path {
-        let base_dir : String = match prefs.get::<String>(crate::preference::FGFS_DIR) {
-            Some(path) => path,
-            None => return Err(Error::new(std::io::ErrorKind::NotFound, "Flightgear base path not set")),
-        };
-        // Use the default Flightgear paths
-        let base_path = Path::new(&base_dir);
-        airports_path = base_path.join("Airports").join("apt.dat.gz");
-        navaids_path = base_path.join("Navaids").join("nav.dat.gz");
-        fixes_path = base_path.join("Navaids").join("fix.dat.gz");
-    } else {
-        // Use the user-defined paths
-        airports_path = match prefs.get::<String>(crate::preference::AIRPORTS_PATH) {
-            Some(path) => Path::new(&path).to_path_buf(),
-            None => return Err(Error::new(std::io::ErrorKind::NotFound, "Flightgear Airport path not set")),
-        };
-        navaids_path = match prefs.get::<String>(crate::preference::NAVAIDS_PATH) {
-            Some(path) => Path::new(&path).to_path_buf(),
-            None => return Err(Error::new(std::io::ErrorKind::NotFound, "Flightgear Navaid path not set")),
-        };
-        fixes_path = match prefs.get::<String>(crate::preference::FIXES_PATH) {
-            Some(path) => Path::new(&path).to_path_buf(),
-            None => return Err(Error::new(std::io::ErrorKind::NotFound, "Flightgear Fix path not set")),
-        };
+    /// The files set in the preferences.
+    pub fn from_preferences() -> Result<Self, Error> {
+        let prefs = crate::preference::manager();
+
+        // Check if the default path should be used
+        let use_default_path = prefs.get::<bool>(crate::preference::FGFS_USE_DFT_PATH).unwrap_or(false);
+
+        if use_default_path {
+            let base_dir : String = match prefs.get::<String>(crate::preference::FGFS_DIR) {
+                Some(path) => path,
+                None => return Err(Error::new(std::io::ErrorKind::NotFound, "Flightgear base path not set")),
+            };
+            // Use the default Flightgear paths
+            Ok(Self::from_fg_dir(Path::new(&base_dir)))
+        } else {
+            // Use the user-defined paths
+            let airports = match prefs.get::<String>(crate::preference::AIRPORTS_PATH) {
+                Some(path) => Path::new(&path).to_path_buf(),
+                None => return Err(Error::new(std::io::ErrorKind::NotFound, "Flightgear Airport path not set")),
+            };
+            let navaids = match prefs.get::<String>(crate::preference::NAVAIDS_PATH) {
+                Some(path) => Path::new(&path).to_path_buf(),
+                None => return Err(Error::new(std::io::ErrorKind::NotFound, "Flightgear Navaid path not set")),
+            };
+            let fixes = match prefs.get::<String>(crate::preference::FIXES_PATH) {
+                Some(path) => Path::new(&path).to_path_buf(),
+                None => return Err(Error::new(std::io::ErrorKind::NotFound, "Flightgear Fix path not set")),
+            };
+            let airways = prefs.get::<String>(crate::preference::AIRWAYS_PATH)
+                .filter(|path| !path.is_empty())
+                .map(|path| Path::new(&path).to_path_buf());
+            Ok(Self { airports, navaids, fixes, airways })
+        }
     }
+}
+
+pub fn initialise() -> Result<(), Error> {
+    load(&NavDataPaths::from_preferences()?)
+}
+
+/// Load the earth model from the given navigation data files.
+/// The airports, navaids and fixes come from the cache when it was built from the files as they are now,
+/// otherwise they are parsed and the cache rebuilt in the background.
+pub fn load(paths: &NavDataPaths) -> Result<(), Error> {
+    load_with(paths, true)
+}
 
-    let timer = std::time::Instant::now();
-    load_airports(&airports_path)?;
-    info!("{} Airports loaded in {:?}", get_earth_model().get_airports().read().expect("Unable to get lock on Airports").len(), timer.elapsed());
+/// Load the earth model as [load] does, only building the cache and layout store when `build_caches` is set.
+/// A process that exits as soon as it is done would leave them half written.
+pub fn load_with(paths: &NavDataPaths, build_caches: bool) -> Result<(), Error> {
+    // Layouts are read from the files being loaded, not whatever the preferences say
+    get_earth_model().set_airports_path(Some(paths.airports.clone()));
+    get_earth_model().set_layout_store(None);
+
+    let cache_path = navdata_cache::get_cache_path();
+    let key = CacheKey::of(&[&paths.airports, &paths.navaids, &paths.fixes]);
+    let cached = match (&cache_path, &key) {
+        (Some(cache_path), Ok(key)) => {
+            let timer = std::time::Instant::now();
+            match navdata_cache::read_cache(cache_path, key) {
+                Ok(Some(data)) => {
+                    load_cached(data);
+                    info!("Navigation data loaded from cache in {:?}", timer.elapsed());
+                    true
+                }
+                Ok(None) => false,
+                Err(e) => {
+                    warn!("Unable to read the navigation data cache {:?}: {}", cache_path, e);
+                    false
+                }
+            }
+        }
+        _ => false,
+    };
 
-    let timer = std::time::Instant::now();
-    load_navaids(&navaids_path)?;
-    info!("{} Navaids loaded in {:?}", get_earth_model().get_navaids().read().expect("Unable to get lock on Navaids").len(), timer.elapsed());
+    if !cached {
+        let timer = std::time::Instant::now();
+        load_airports(&paths.airports)?;
+        info!("{} Airports loaded in {:?}", get_earth_model().get_airports().read().expect("Unable to get lock on Airports").len(), timer.elapsed());
 
-    let timer = std::time::Instant::now();
-    load_fixes(&fixes_path)?;
-    info!("{} Fixes loaded in {:?}", get_earth_model().get_fixes().read().expect("Unable to get lock on Fixes").len(), timer.elapsed());
+        let timer = std::time::Instant::now();
+        load_navaids(&paths.navaids)?;
+        info!("{} Navaids loaded in {:?}", get_earth_model().get_navaids().read().expect("Unable to get lock on Navaids").len(), timer.elapsed());
+
+        let timer = std::time::Instant::now();
+        load_fixes(&paths.fixes)?;
+        info!("{} Fixes loaded in {:?}", get_earth_model().get_fixes().read().expect("Unable to get lock on Fixes").len(), timer.elapsed());
+
+        if let (true, Some(cache_path), Ok(key)) = (build_caches, cache_path, key) {
+            rebuild_cache(cache_path, key);
+        }
+    }
+    load_layout_store(&paths.airports, build_caches);
+
+    // Airways are resolved against the fixes and navaids so must be loaded last
+    if let Some(airways_path) = &paths.airways {
+        let timer = std::time::Instant::now();
+        match load_airways(airways_path) {
+            Ok(()) => info!("{} Airway segments loaded in {:?}", get_earth_model().get_airways().read().expect("Unable to get lock on Airways").get_segment_count(), timer.elapsed()),
+            Err(e) => warn!("Unable to load airways from {:?}: {}", airways_path, e),
+        }
+    }
 
     Ok(())
 }
 
+fn load_cached(data: NavData) {
+    let status = gettext("Loading navigation data from cache");
+    event::manager().notify_listeners(Event::StatusChange(status));
+    let earth = get_earth_model();
+    earth.set_airports(data.airports);
+    earth.set_runway_offsets(data.runway_offsets);
+    event::manager().notify_listeners(Event::AirportsLoaded);
+    earth.set_navaids(data.navaids);
+    earth.set_ils(data.ils);
+    event::manager().notify_listeners(Event::NavaidsLoaded);
+    earth.set_fixes(data.fixes);
+    event::manager().notify_listeners(Event::FixesLoaded);
+    event::manager().notify_listeners(Event::StatusChange("".to_string()));
+}
+
+// The layout store lets an airport's runways and taxiways be read without scanning apt.dat.
+// It is built in the background the first time and whenever apt.dat changes, until then they are scanned for.
+fn load_layout_store(airports_path: &Path, build: bool) {
+    let (Some(store_path), Ok(key)) = (layout_store::get_store_path(), CacheKey::of(&[airports_path])) else {
+        return;
+    };
+    match LayoutStore::open(&store_path, &key) {
+        Ok(Some(store)) => {
+            get_earth_model().set_layout_store(Some(Arc::new(store)));
+            return;
+        }
+        Ok(None) => (),
+        Err(e) => warn!("Unable to read the airport layout store {:?}: {}", store_path, e),
+    }
+    if !build {
+        return;
+    }
+    let airports_path = airports_path.to_path_buf();
+    std::thread::spawn(move || {
+        let timer = std::time::Instant::now();
+        let store = fs::File::open(&airports_path).and_then(|input| {
+            let mut reader = BufReader::new(read::GzDecoder::new(input));
+            LayoutStore::build(&store_path, &key, &mut reader)
+        });
+        match store {
+            // The navigation data may have been reloaded from elsewhere while this was built
+            Ok(store) if get_earth_model().get_airports_path().as_ref() == Some(&airports_path) => {
+                info!("Airport layout store built in {:?}", timer.elapsed());
+                get_earth_model().set_layout_store(Some(Arc::new(store)));
+            }
+            Ok(_) => (),
+            Err(e) => warn!("Unable to build the airport layout store {:?}: {}", store_path, e),
+        }
+    });
+}
+
+// Writing the cache takes a while, so it is done on its own thread while the planner is used
+fn rebuild_cache(cache_path: PathBuf, key: CacheKey) {
+    let earth = get_earth_model();
+    let data = NavData {
+        airports: earth.get_airports().read().expect("Unable to get lock on Airports").clone(),
+        runway_offsets: earth.get_runway_offsets().read().expect("Unable to get lock on runway offsets").clone(),
+        navaids: earth.get_navaids().read().expect("Unable to get lock on Navaids").clone(),
+        ils: earth.get_ils().read().expect("Unable to get lock on Ils").clone(),
+        fixes: earth.get_fixes().read().expect("Unable to get lock on Fixes").clone(),
+    };
+    std::thread::spawn(move || {
+        let timer = std::time::Instant::now();
+        match navdata_cache::write_cache(&cache_path, &key, &data) {
+            Ok(()) => info!("Navigation data cache written to {:?} in {:?}", cache_path, timer.elapsed()),
+            Err(e) => warn!("Unable to write the navigation data cache {:?}: {}", cache_path, e),
+        }
+    });
+}
+
 fn load_airports(path: &PathBuf) -> Result<(), Error> {
     let status = gettext("Loading Airports from : {}").replace("{}", path.to_str().unwrap_or(""));
     event::manager().notify_listeners(Event::StatusChange(status));
@@ -282,3 +628,131 @@ fn load_fixes(path: &PathBuf) -> Result<(), Error> {
     event::manager().notify_listeners(Event::StatusChange("".to_string()));
     result
 }
+
+fn load_airways(path: &PathBuf) -> Result<(), Error> {
+    let status = gettext("Loading Airways from : {}").replace("{}", path.to_str().unwrap_or(""));
+    event::manager().notify_listeners(Event::StatusChange(status));
+    let mut segments: Vec<AirwaySegment> = Vec::new();
+    let file = fs::File::open(path);
+    let result = match file {
+        Ok(input) => {
+            let mut parser = AirwayParserFG {};
+            let decoder = read::GzDecoder::new(input);
+            let mut reader = BufReader::new(decoder);
+            let fixes = get_earth_model().get_fixes().read().expect("Unable to get lock on Fixes").clone();
+            let navaids = get_earth_model().get_navaids().read().expect("Unable to get lock on Navaids").clone();
+            match parser.load_airways(&mut segments, &fixes, &navaids, &mut reader) {
+                Ok(()) => {
+                    get_earth_model().set_airways(AirwayGraph::from_segments(&segments));
+                    event::manager().notify_listeners(Event::AirwaysLoaded);
+                    Ok(())
+                }
+                Err(msg) => Err(msg),
+            }
+        }
+        Err(e) => Err(e),
+    };
+    event::manager().notify_listeners(Event::StatusChange("".to_string()));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::earth::coordinate::Coordinate;
+    use crate::model::fix::Fix;
+    use crate::model::location::Location;
+    use crate::model::navaid::{Navaid, NavaidType};
+    use crate::model::test_utils::tests::make_airport;
+    use crate::util::location_filter::{Filter, FoundFilter};
+
+    use super::Earth;
+
+    #[test]
+    fn test_airport_by_id() {
+        let earth = Earth::new();
+        earth.set_airports(vec![make_airport("YSSY"), make_airport("YMML")]);
+
+        assert_eq!(earth.get_airport_by_id("YMML").unwrap().get_id(), "YMML");
+        assert!(earth.get_airport_by_id("YBBN").is_none());
+        assert_eq!(earth.get_airports_by_id("YSSY").len(), 1);
+
+        // Replacing the airports rebuilds the index
+        earth.set_airports(vec![make_airport("YBBN")]);
+        assert!(earth.get_airport_by_id("YMML").is_none());
+        assert!(earth.get_airport_by_id("YBBN").is_some());
+    }
+
+    #[test]
+    fn test_duplicate_ids() {
+        let earth = Earth::new();
+        earth.set_fixes(vec![
+            Arc::new(Fix::new("ALPHA".to_string(), -33.0, 151.0)),
+            Arc::new(Fix::new("ALPHA".to_string(), 51.0, 0.0)),
+            Arc::new(Fix::new("BRAVO".to_string(), 51.0, 1.0)),
+        ]);
+        earth.set_navaids(vec![
+            Arc::new(Navaid::new("SY".to_string(), NavaidType::Vor, -33.9, 151.2, 0, 112.1, 100, "12E".to_string(), "SYDNEY".to_string())),
+            Arc::new(Navaid::new("SY".to_string(), NavaidType::Ndb, 60.0, -1.3, 0, 350.0, 25, "0".to_string(), "SUMBURGH".to_string())),
+        ]);
+
+        assert_eq!(earth.get_fixes_by_id("ALPHA").len(), 2);
+        assert_eq!(earth.get_fixes_by_id("BRAVO").len(), 1);
+        assert!(earth.get_fixes_by_id("CHARLIE").is_empty());
+        assert_eq!(earth.get_fix_by_id("ALPHA").unwrap().get_lat(), -33.0);
+
+        assert_eq!(earth.get_navaids_by_id("SY").len(), 2);
+        let navaid = earth.get_navaid_by_id_and_name("SY", "Sumburgh").unwrap();
+        assert!(navaid.get_type() == NavaidType::Ndb);
+        assert!(earth.get_navaid_by_id_and_name("SY", "Perth").is_none());
+    }
+
+    #[test]
+    fn test_find_fix() {
+        let make_fix = |lat: f64, lon: f64, region: &str| {
+            let mut fix = Fix::new("ABBEY".to_string(), lat, lon);
+            fix.set_region(Some(region.to_string()));
+            Arc::new(fix)
+        };
+        let earth = Earth::new();
+        earth.set_fixes(vec![make_fix(51.3, -1.1, "EG"), make_fix(-33.9, 151.1, "YM"), make_fix(-34.5, 150.0, "YM")]);
+
+        let sydney = Coordinate::new(-33.9, 151.2);
+        assert_eq!(earth.find_fix("ABBEY", Some("EG"), None, Some(&sydney)).unwrap().get_region(), Some("EG"));
+        assert_eq!(earth.find_fix("ABBEY", Some("YM"), None, Some(&sydney)).unwrap().get_lat(), -33.9);
+        // An unknown region falls back to the nearest
+        assert_eq!(earth.find_fix("ABBEY", Some("K2"), None, Some(&sydney)).unwrap().get_lat(), -33.9);
+        assert_eq!(earth.find_fix("ABBEY", None, None, None).unwrap().get_region(), Some("EG"));
+        assert!(earth.find_fix("BOREE", None, None, Some(&sydney)).is_none());
+
+        // A terminal fix of one airport and another with the same ident near it
+        let make_terminal_fix = |lon: f64, area: &str| {
+            let mut fix = Fix::new("RW16".to_string(), -33.9, lon);
+            fix.set_region(Some("YM".to_string()));
+            fix.set_terminal_area(Some(area.to_string()));
+            Arc::new(fix)
+        };
+        earth.set_fixes(vec![make_terminal_fix(151.2, "YSSY"), make_terminal_fix(151.0, "YSBK")]);
+        assert_eq!(earth.find_fix("RW16", Some("YM"), Some("YSBK"), Some(&sydney)).unwrap().get_long(), 151.0);
+        assert_eq!(earth.find_fix("RW16", Some("YM"), None, Some(&sydney)).unwrap().get_long(), 151.2);
+    }
+
+    #[test]
+    fn test_search_near() {
+        let earth = Earth::new();
+        earth.set_fixes(vec![
+            Arc::new(Fix::new("ALPHA".to_string(), -33.0, 151.0)),
+            Arc::new(Fix::new("ALPHA".to_string(), 51.0, 0.0)),
+            Arc::new(Fix::new("BRAVO".to_string(), -33.5, 151.5)),
+        ]);
+
+        let near = earth.get_fixes_near(&Coordinate::new(-33.2, 151.2), 100.0);
+        assert_eq!(near.len(), 2);
+        // The filter recognises the fixes found, not just others with the same id
+        let filter = FoundFilter::new(&near);
+        let fixes = earth.get_fixes_by_id("ALPHA");
+        assert_eq!(fixes.iter().filter(|f| filter.filter(f.as_ref())).count(), 1);
+        assert!(filter.filter(earth.get_fix_by_id("BRAVO").unwrap().as_ref()));
+    }
+}
@@ -0,0 +1,507 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+#![forbid(unsafe_code)]
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::UNIX_EPOCH;
+
+use dirs_next::cache_dir;
+
+use crate::model::airport::{Airport, AirportType};
+use crate::model::fix::Fix;
+use crate::model::location::Location;
+use crate::model::navaid::{Navaid, NavaidType};
+use crate::preference::APP_INFO;
+
+const MAGIC: &[u8; 8] = b"KELPIENV";
+// Bump whenever the layout below changes, older caches are then rebuilt
+const CACHE_VERSION: u32 = 1;
+
+// Navaid types are stored as their position in this list
+const NAVAID_TYPES: [NavaidType; 12] = [
+    NavaidType::Vor, NavaidType::VorDme, NavaidType::Vortac, NavaidType::Ndb, NavaidType::Dme, NavaidType::Tacan,
+    NavaidType::Ils, NavaidType::Localizer, NavaidType::Glideslope,
+    NavaidType::OuterMarker, NavaidType::MiddleMarker, NavaidType::InnerMarker,
+];
+
+// The keys of the airport metadata, in the order they are stored
+const METADATA_KEYS: [&str; 7] = ["city", "state", "country", "icao_code", "iata_code", "faa_code", "region_code"];
+
+/// Everything parsed from the airport, navaid and fix files at startup.
+pub struct NavData {
+    pub airports: Vec<Arc<Airport>>,
+    pub runway_offsets: HashMap<String, usize>,
+    pub navaids: Vec<Arc<Navaid>>,
+    pub ils: HashMap<String, Vec<(String, f64)>>,
+    pub fixes: Vec<Arc<Fix>>,
+}
+
+/// Identifies the source files a cache was built from, by path, size and modification time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CacheKey {
+    sources: Vec<(String, u64, u64, u32)>,
+}
+
+impl CacheKey {
+    pub fn of(sources: &[&Path]) -> Result<Self, Error> {
+        let mut key = Vec::with_capacity(sources.len());
+        for path in sources {
+            let metadata = fs::metadata(path)?;
+            let modified = metadata.modified()?
+                .duration_since(UNIX_EPOCH)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+            key.push((path.to_string_lossy().to_string(), metadata.len(), modified.as_secs(), modified.subsec_nanos()));
+        }
+        Ok(Self { sources: key })
+    }
+
+    pub(super) fn write<W: Write>(&self, out: &mut W) -> Result<(), Error> {
+        write_u32(out, self.sources.len() as u32)?;
+        for (path, size, secs, nanos) in &self.sources {
+            write_str(out, path)?;
+            write_u64(out, *size)?;
+            write_u64(out, *secs)?;
+            write_u32(out, *nanos)?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn read<R: Read>(input: &mut R) -> Result<Self, Error> {
+        let count = read_u32(input)?;
+        let mut sources = Vec::new();
+        for _ in 0..count {
+            sources.push((read_str(input)?, read_u64(input)?, read_u64(input)?, read_u32(input)?));
+        }
+        Ok(Self { sources })
+    }
+}
+
+/// Where the cache is kept, in the user's cache directory.
+pub fn get_cache_path() -> Option<PathBuf> {
+    get_cache_file("navdata.cache")
+}
+
+pub(super) fn get_cache_file(name: &str) -> Option<PathBuf> {
+    cache_dir().map(|mut p| {
+        p.push(APP_INFO.name);
+        p.push(name);
+        p
+    })
+}
+
+// Each write gets a file of its own, so two builds running at once can't write into one another's
+pub(super) fn get_temp_path(path: &Path) -> PathBuf {
+    static COUNT: AtomicU32 = AtomicU32::new(0);
+    path.with_extension(format!("{}.{}.tmp", std::process::id(), COUNT.fetch_add(1, Ordering::Relaxed)))
+}
+
+/// Read the cache, as long as it was built by this version from the files in the key as they are now.
+/// Returns None when the cache is missing or stale.
+pub fn read_cache(path: &Path, key: &CacheKey) -> Result<Option<NavData>, Error> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let mut input = BufReader::new(file);
+    let mut magic = [0u8; 8];
+    input.read_exact(&mut magic)?;
+    if &magic != MAGIC || read_u32(&mut input)? != CACHE_VERSION || CacheKey::read(&mut input)? != *key {
+        return Ok(None);
+    }
+
+    let mut airports = Vec::new();
+    for _ in 0..read_u32(&mut input)? {
+        airports.push(Arc::new(read_airport(&mut input)?));
+    }
+    let mut runway_offsets = HashMap::new();
+    for _ in 0..read_u32(&mut input)? {
+        runway_offsets.insert(read_str(&mut input)?, read_u64(&mut input)? as usize);
+    }
+    let mut navaids = Vec::new();
+    for _ in 0..read_u32(&mut input)? {
+        navaids.push(Arc::new(read_navaid(&mut input)?));
+    }
+    let mut ils = HashMap::new();
+    for _ in 0..read_u32(&mut input)? {
+        let airport_id = read_str(&mut input)?;
+        let mut runways = Vec::new();
+        for _ in 0..read_u32(&mut input)? {
+            runways.push((read_str(&mut input)?, read_f64(&mut input)?));
+        }
+        ils.insert(airport_id, runways);
+    }
+    let mut fixes = Vec::new();
+    for _ in 0..read_u32(&mut input)? {
+        let mut fix = Fix::new(read_str(&mut input)?, read_f64(&mut input)?, read_f64(&mut input)?);
+        fix.set_region(read_opt_str(&mut input)?);
+        fix.set_terminal_area(read_opt_str(&mut input)?);
+        fixes.push(Arc::new(fix));
+    }
+    Ok(Some(NavData { airports, runway_offsets, navaids, ils, fixes }))
+}
+
+/// Write the cache. It is written alongside and then renamed, so a reader never sees half a cache.
+pub fn write_cache(path: &Path, key: &CacheKey, data: &NavData) -> Result<(), Error> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temp_path = get_temp_path(path);
+    let mut out = BufWriter::new(File::create(&temp_path)?);
+    out.write_all(MAGIC)?;
+    write_u32(&mut out, CACHE_VERSION)?;
+    key.write(&mut out)?;
+
+    write_u32(&mut out, data.airports.len() as u32)?;
+    for airport in &data.airports {
+        write_airport(&mut out, airport)?;
+    }
+    write_u32(&mut out, data.runway_offsets.len() as u32)?;
+    for (id, offset) in &data.runway_offsets {
+        write_str(&mut out, id)?;
+        write_u64(&mut out, *offset as u64)?;
+    }
+    write_u32(&mut out, data.navaids.len() as u32)?;
+    for navaid in &data.navaids {
+        write_navaid(&mut out, navaid)?;
+    }
+    write_u32(&mut out, data.ils.len() as u32)?;
+    for (airport_id, runways) in &data.ils {
+        write_str(&mut out, airport_id)?;
+        write_u32(&mut out, runways.len() as u32)?;
+        for (runway, freq) in runways {
+            write_str(&mut out, runway)?;
+            write_f64(&mut out, *freq)?;
+        }
+    }
+    write_u32(&mut out, data.fixes.len() as u32)?;
+    for fix in &data.fixes {
+        write_str(&mut out, fix.get_id())?;
+        write_f64(&mut out, fix.get_lat())?;
+        write_f64(&mut out, fix.get_long())?;
+        write_opt_str(&mut out, fix.get_region())?;
+        write_opt_str(&mut out, fix.get_terminal_area())?;
+    }
+    out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    fs::rename(temp_path, path)
+}
+
+fn write_airport<W: Write>(out: &mut W, airport: &Airport) -> Result<(), Error> {
+    write_str(out, airport.get_id())?;
+    write_str(out, airport.get_name())?;
+    write_f64(out, airport.get_lat())?;
+    write_f64(out, airport.get_long())?;
+    write_i32(out, *airport.get_elevation())?;
+    write_u8(out, match airport.get_type() {
+        None => 0,
+        Some(AirportType::Airport) => 1,
+        Some(AirportType::SeaBase) => 2,
+        Some(AirportType::Heliport) => 3,
+    })?;
+    write_u8(out, airport.get_control_tower() as u8)?;
+    write_u8(out, airport.get_show_default_buildings() as u8)?;
+    write_u64(out, airport.get_max_runway_length() as u64)?;
+    let metadata = airport.get_metadata();
+    for value in [metadata.get_city(), metadata.get_state(), metadata.get_country(), metadata.get_icao_code(),
+                  metadata.get_iata_code(), metadata.get_faa_code(), metadata.get_region_code()] {
+        write_opt_str(out, value)?;
+    }
+    Ok(())
+}
+
+fn read_airport<R: Read>(input: &mut R) -> Result<Airport, Error> {
+    let id = read_str(input)?;
+    let name = read_str(input)?;
+    let latitude = read_f64(input)?;
+    let longitude = read_f64(input)?;
+    let elevation = read_i32(input)?;
+    let airport_type = match read_u8(input)? {
+        1 => Some(AirportType::Airport),
+        2 => Some(AirportType::SeaBase),
+        3 => Some(AirportType::Heliport),
+        _ => None,
+    };
+    let control_tower = read_u8(input)? != 0;
+    let show_default_buildings = read_u8(input)? != 0;
+    let max_runway_length = read_u64(input)? as i64;
+    let mut airport = Airport::new(id, latitude, longitude, elevation, airport_type, control_tower,
+                                   show_default_buildings, name, max_runway_length);
+    for key in METADATA_KEYS {
+        if let Some(value) = read_opt_str(input)? {
+            airport.set_metadata(key, &value);
+        }
+    }
+    Ok(airport)
+}
+
+fn write_navaid<W: Write>(out: &mut W, navaid: &Navaid) -> Result<(), Error> {
+    write_str(out, navaid.get_id())?;
+    let type_code = NAVAID_TYPES.iter().position(|t| *t == navaid.get_type()).unwrap_or(0);
+    write_u8(out, type_code as u8)?;
+    write_f64(out, navaid.get_lat())?;
+    write_f64(out, navaid.get_long())?;
+    write_i32(out, *navaid.get_elevation())?;
+    write_f64(out, *navaid.get_freq())?;
+    write_i32(out, navaid.get_range())?;
+    write_str(out, &navaid.get_mag_variation())?;
+    write_str(out, navaid.get_name())?;
+    write_opt_str(out, navaid.get_airport_id())?;
+    write_opt_str(out, navaid.get_runway())?;
+    write_opt_f64(out, navaid.get_course())?;
+    write_opt_f64(out, navaid.get_glideslope_angle())?;
+    write_opt_f64(out, navaid.get_dme_bias())
+}
+
+fn read_navaid<R: Read>(input: &mut R) -> Result<Navaid, Error> {
+    let id = read_str(input)?;
+    let navaid_type = NAVAID_TYPES.get(read_u8(input)? as usize)
+        .copied()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Unknown navaid type in cache"))?;
+    let latitude = read_f64(input)?;
+    let longitude = read_f64(input)?;
+    let elevation = read_i32(input)?;
+    let freq = read_f64(input)?;
+    let range = read_i32(input)?;
+    let mag_variation = read_str(input)?;
+    let name = read_str(input)?;
+    let mut navaid = Navaid::new(id, navaid_type, latitude, longitude, elevation, freq, range, mag_variation, name);
+    if let (Some(airport_id), Some(runway)) = (read_opt_str(input)?, read_opt_str(input)?) {
+        navaid.set_runway(&airport_id, &runway);
+    }
+    if let Some(course) = read_opt_f64(input)? {
+        navaid.set_course(course);
+    }
+    if let Some(angle) = read_opt_f64(input)? {
+        navaid.set_glideslope_angle(angle);
+    }
+    if let Some(bias) = read_opt_f64(input)? {
+        navaid.set_dme_bias(bias);
+    }
+    Ok(navaid)
+}
+
+fn write_u8<W: Write>(out: &mut W, v: u8) -> Result<(), Error> {
+    out.write_all(&[v])
+}
+
+pub(super) fn write_u32<W: Write>(out: &mut W, v: u32) -> Result<(), Error> {
+    out.write_all(&v.to_le_bytes())
+}
+
+fn write_i32<W: Write>(out: &mut W, v: i32) -> Result<(), Error> {
+    out.write_all(&v.to_le_bytes())
+}
+
+pub(super) fn write_u64<W: Write>(out: &mut W, v: u64) -> Result<(), Error> {
+    out.write_all(&v.to_le_bytes())
+}
+
+fn write_f64<W: Write>(out: &mut W, v: f64) -> Result<(), Error> {
+    out.write_all(&v.to_le_bytes())
+}
+
+pub(super) fn write_str<W: Write>(out: &mut W, v: &str) -> Result<(), Error> {
+    write_u32(out, v.len() as u32)?;
+    out.write_all(v.as_bytes())
+}
+
+fn write_opt_str<W: Write>(out: &mut W, v: Option<&str>) -> Result<(), Error> {
+    match v {
+        Some(v) => {
+            write_u8(out, 1)?;
+            write_str(out, v)
+        }
+        None => write_u8(out, 0),
+    }
+}
+
+fn write_opt_f64<W: Write>(out: &mut W, v: Option<f64>) -> Result<(), Error> {
+    match v {
+        Some(v) => {
+            write_u8(out, 1)?;
+            write_f64(out, v)
+        }
+        None => write_u8(out, 0),
+    }
+}
+
+fn read_bytes<R: Read, const N: usize>(input: &mut R) -> Result<[u8; N], Error> {
+    let mut bytes = [0u8; N];
+    input.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_u8<R: Read>(input: &mut R) -> Result<u8, Error> {
+    Ok(read_bytes::<R, 1>(input)?[0])
+}
+
+pub(super) fn read_u32<R: Read>(input: &mut R) -> Result<u32, Error> {
+    Ok(u32::from_le_bytes(read_bytes(input)?))
+}
+
+fn read_i32<R: Read>(input: &mut R) -> Result<i32, Error> {
+    Ok(i32::from_le_bytes(read_bytes(input)?))
+}
+
+pub(super) fn read_u64<R: Read>(input: &mut R) -> Result<u64, Error> {
+    Ok(u64::from_le_bytes(read_bytes(input)?))
+}
+
+fn read_f64<R: Read>(input: &mut R) -> Result<f64, Error> {
+    Ok(f64::from_le_bytes(read_bytes(input)?))
+}
+
+pub(super) fn read_str<R: Read>(input: &mut R) -> Result<String, Error> {
+    let mut bytes = vec![0u8; read_u32(input)? as usize];
+    input.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+fn read_opt_str<R: Read>(input: &mut R) -> Result<Option<String>, Error> {
+    match read_u8(input)? {
+        0 => Ok(None),
+        _ => read_str(input).map(Some),
+    }
+}
+
+fn read_opt_f64<R: Read>(input: &mut R) -> Result<Option<f64>, Error> {
+    match read_u8(input)? {
+        0 => Ok(None),
+        _ => read_f64(input).map(Some),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+
+    use crate::model::airport::{Airport, AirportType};
+    use crate::model::fix::Fix;
+    use crate::model::location::Location;
+    use crate::model::navaid::{Navaid, NavaidType};
+
+    use super::{read_cache, write_cache, CacheKey, NavData};
+
+    fn make_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kelpie-cache-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for source in ["apt.dat.gz", "nav.dat.gz", "fix.dat.gz"] {
+            fs::write(dir.join(source), source).unwrap();
+        }
+        dir
+    }
+
+    fn make_key(dir: &Path) -> CacheKey {
+        CacheKey::of(&[&dir.join("apt.dat.gz"), &dir.join("nav.dat.gz"), &dir.join("fix.dat.gz")]).unwrap()
+    }
+
+    fn make_data() -> NavData {
+        let mut airport = Airport::new("YSSY".to_string(), -33.946, 151.177, 21, Some(AirportType::Airport), true, false,
+                                       "Sydney Kingsford Smith".to_string(), 12999);
+        airport.set_metadata("city", "Sydney");
+        airport.set_metadata("country", "Australia");
+        let mut ils = Navaid::new("ISS".to_string(), NavaidType::Ils, -33.96, 151.18, 21, 109.5, 18, "0".to_string(), "YSSY 16R ILS-cat-I".to_string());
+        ils.set_runway("YSSY", "16R");
+        ils.set_course(155.7);
+        ils.set_dme_bias(0.2);
+        let mut fix = Fix::new("ABBEY".to_string(), -33.9636, 151.0583);
+        fix.set_region(Some("YM".to_string()));
+        NavData {
+            airports: vec![Arc::new(airport)],
+            runway_offsets: HashMap::from([("YSSY".to_string(), 1234)]),
+            navaids: vec![
+                Arc::new(Navaid::new("SY".to_string(), NavaidType::VorDme, -33.9, 151.2, 0, 112.1, 100, "12E".to_string(), "SYDNEY".to_string())),
+                Arc::new(ils),
+            ],
+            ils: HashMap::from([("YSSY".to_string(), vec![("16R".to_string(), 109.5)])]),
+            fixes: vec![Arc::new(fix)],
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = make_dir("round-trip");
+        let cache = dir.join("navdata.cache");
+        assert!(read_cache(&cache, &make_key(&dir)).unwrap().is_none());
+
+        write_cache(&cache, &make_key(&dir), &make_data()).unwrap();
+        let data = read_cache(&cache, &make_key(&dir)).unwrap().unwrap();
+
+        let airport = &data.airports[0];
+        assert_eq!(airport.get_id(), "YSSY");
+        assert_eq!(airport.get_name(), "Sydney Kingsford Smith");
+        assert_eq!(airport.get_lat(), -33.946);
+        assert_eq!(*airport.get_elevation(), 21);
+        assert!(airport.get_type() == Some(AirportType::Airport));
+        assert!(airport.get_control_tower());
+        assert_eq!(airport.get_max_runway_length(), 12999);
+        assert_eq!(airport.get_metadata().get_city(), Some("Sydney"));
+        assert_eq!(airport.get_metadata().get_state(), None);
+        assert_eq!(data.runway_offsets.get("YSSY"), Some(&1234));
+
+        assert_eq!(data.navaids.len(), 2);
+        assert!(data.navaids[0].get_type() == NavaidType::VorDme);
+        assert_eq!(data.navaids[0].get_mag_variation(), "12E");
+        let ils = &data.navaids[1];
+        assert!(ils.get_type() == NavaidType::Ils);
+        assert_eq!(ils.get_runway(), Some("16R"));
+        assert_eq!(ils.get_course(), Some(155.7));
+        assert_eq!(ils.get_glideslope_angle(), None);
+        assert_eq!(ils.get_dme_bias(), Some(0.2));
+        assert_eq!(data.ils.get("YSSY"), Some(&vec![("16R".to_string(), 109.5)]));
+
+        assert_eq!(data.fixes[0].get_id(), "ABBEY");
+        assert_eq!(data.fixes[0].get_region(), Some("YM"));
+        assert_eq!(data.fixes[0].get_terminal_area(), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_stale() {
+        let dir = make_dir("stale");
+        let cache = dir.join("navdata.cache");
+        write_cache(&cache, &make_key(&dir), &make_data()).unwrap();
+
+        // A new fix file makes the cache stale
+        fs::write(dir.join("fix.dat.gz"), "a newer fix.dat.gz").unwrap();
+        assert!(read_cache(&cache, &make_key(&dir)).unwrap().is_none());
+
+        // As does pointing at other files
+        let other = make_dir("stale-other");
+        assert!(read_cache(&cache, &make_key(&other)).unwrap().is_none());
+
+        fs::remove_dir_all(dir).unwrap();
+        fs::remove_dir_all(other).unwrap();
+    }
+}
@@ -23,7 +23,7 @@
  */
 use chrono::{DateTime, Datelike, Duration, Timelike, Utc};
 
-pub(crate) fn subsolar_point(datetime: DateTime<Utc>) -> (f64, f64) {
+pub fn subsolar_point(datetime: DateTime<Utc>) -> (f64, f64) {
     let timestamp = datetime.timestamp() as f64;
 
     // Days since J2000 epoch (Jan 1, 2000, 12:00 UTC)
@@ -0,0 +1,245 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+#![forbid(unsafe_code)]
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::earth::coordinate::Coordinate;
+use crate::model::location::Location;
+
+// Nautical miles in one degree of latitude
+const NM_PER_DEGREE: f64 = 60.0;
+
+/// A grid of one degree cells over the earth, used to find locations near a point
+/// without looking at every location.
+///
+/// Queries that cross the antimeridian wrap around to the cells on the other side,
+/// and queries that reach a pole take in every cell of the rows near it.
+pub struct SpatialIndex<T: Location> {
+    cells: HashMap<(i32, i32), Vec<Arc<T>>>,
+    len: usize,
+}
+
+impl<T: Location> Default for SpatialIndex<T> {
+    fn default() -> Self {
+        Self {
+            cells: HashMap::new(),
+            len: 0,
+        }
+    }
+}
+
+impl<T: Location> SpatialIndex<T> {
+    pub fn new(items: &[Arc<T>]) -> Self {
+        let mut cells: HashMap<(i32, i32), Vec<Arc<T>>> = HashMap::new();
+        for item in items {
+            cells.entry(Self::cell_for(item.get_loc()))
+                .or_default()
+                .push(item.clone());
+        }
+        Self {
+            cells,
+            len: items.len(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// All locations within `range` nautical miles of `coord`.
+    pub fn within_range(&self, coord: &Coordinate, range: f64) -> Vec<Arc<T>> {
+        self.query(coord, range, |item| item.get_loc().distance_to(coord) <= range)
+    }
+
+    /// The nearest location within `range` nautical miles of `coord` that is accepted by `filter`.
+    pub fn nearest<F>(&self, coord: &Coordinate, range: f64, filter: F) -> Option<Arc<T>>
+    where
+        F: Fn(&T) -> bool,
+    {
+        let mut best: Option<Arc<T>> = None;
+        let mut nearest = f64::MAX;
+        for item in self.candidates(coord, range) {
+            if filter(item) {
+                let distance = item.get_loc().distance_to(coord);
+                if distance <= range && distance < nearest {
+                    nearest = distance;
+                    best = Some(item.clone());
+                }
+            }
+        }
+        best
+    }
+
+    /// All locations in the corridor between `from` and `to`.
+    /// The corridor is the ellipse of points where the distance from `from` plus the distance
+    /// to `to` is no more than `ratio` times the direct distance, so it is widest half way.
+    pub fn within_corridor(&self, from: &Coordinate, to: &Coordinate, ratio: f64) -> Vec<Arc<T>> {
+        let distance = from.distance_to(to);
+        let limit = distance * ratio;
+        let midpoint = Coordinate::midpoint(from, to);
+        self.query(&midpoint, limit / 2.0, |item| {
+            item.get_loc().distance_to(from) + item.get_loc().distance_to(to) <= limit
+        })
+    }
+
+    fn query<F>(&self, coord: &Coordinate, range: f64, filter: F) -> Vec<Arc<T>>
+    where
+        F: Fn(&T) -> bool,
+    {
+        self.candidates(coord, range)
+            .filter(|item| filter(item))
+            .cloned()
+            .collect()
+    }
+
+    /// Every location in the cells covering a circle of `range` nautical miles about `coord`.
+    fn candidates<'a>(&'a self, coord: &Coordinate, range: f64) -> impl Iterator<Item = &'a Arc<T>> + 'a {
+        let lat_span = range / NM_PER_DEGREE;
+        let lat_min = coord.get_latitude() - lat_span;
+        let lat_max = coord.get_latitude() + lat_span;
+        let min_row = Self::row_for(lat_min);
+        let max_row = Self::row_for(lat_max);
+
+        // The width of a degree of longitude shrinks towards the poles, so use the row nearest
+        // the pole to work out how many columns the circle covers.
+        let polar_lat = lat_min.abs().max(lat_max.abs());
+        let columns = if polar_lat >= 89.0 {
+            None
+        } else {
+            let long_span = lat_span / polar_lat.to_radians().cos();
+            if long_span >= 180.0 {
+                None
+            } else {
+                let first = (coord.get_longitude() - long_span).floor() as i32;
+                let last = (coord.get_longitude() + long_span).floor() as i32;
+                Some((first, last))
+            }
+        };
+
+        let cells: Vec<(i32, i32)> = (min_row..=max_row)
+            .flat_map(|row| {
+                let (first, last) = columns.unwrap_or((-180, 179));
+                (first..=last).map(move |col| (row, Self::wrap_column(col)))
+            })
+            .collect();
+
+        cells.into_iter()
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+    }
+
+    fn cell_for(coord: &Coordinate) -> (i32, i32) {
+        (Self::row_for(coord.get_latitude()), Self::wrap_column(coord.get_longitude().floor() as i32))
+    }
+
+    fn row_for(latitude: f64) -> i32 {
+        (latitude.floor() as i32).clamp(-90, 89)
+    }
+
+    fn wrap_column(column: i32) -> i32 {
+        (column + 180).rem_euclid(360) - 180
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::earth::coordinate::Coordinate;
+    use crate::model::fix::Fix;
+    use crate::model::location::Location;
+
+    use super::SpatialIndex;
+
+    fn make_index() -> SpatialIndex<Fix> {
+        let fixes = vec![
+            Arc::new(Fix::new("SYD".to_string(), -33.9, 151.2)),
+            Arc::new(Fix::new("WOL".to_string(), -34.4, 150.9)),
+            Arc::new(Fix::new("MEL".to_string(), -37.7, 144.8)),
+            Arc::new(Fix::new("EAST".to_string(), -17.0, 179.9)),
+            Arc::new(Fix::new("WEST".to_string(), -17.0, -179.9)),
+            Arc::new(Fix::new("POLE".to_string(), 89.9, 10.0)),
+        ];
+        SpatialIndex::new(&fixes)
+    }
+
+    #[test]
+    fn test_within_range() {
+        let index = make_index();
+        assert_eq!(index.len(), 6);
+
+        let mut ids: Vec<String> = index.within_range(&Coordinate::new(-34.0, 151.0), 50.0)
+            .iter()
+            .map(|f| f.get_id().to_string())
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec!["SYD", "WOL"]);
+    }
+
+    #[test]
+    fn test_antimeridian() {
+        let index = make_index();
+
+        let found = index.within_range(&Coordinate::new(-17.0, 179.95), 20.0);
+        assert_eq!(found.len(), 2);
+        let found = index.within_range(&Coordinate::new(-17.0, -179.95), 20.0);
+        assert_eq!(found.len(), 2);
+    }
+
+    #[test]
+    fn test_pole() {
+        let index = make_index();
+
+        let found = index.within_range(&Coordinate::new(89.9, -170.0), 30.0);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].get_id(), "POLE");
+    }
+
+    #[test]
+    fn test_nearest() {
+        let index = make_index();
+
+        let nearest = index.nearest(&Coordinate::new(-34.3, 151.0), 100.0, |_| true);
+        assert_eq!(nearest.unwrap().get_id(), "WOL");
+        let nearest = index.nearest(&Coordinate::new(-34.3, 151.0), 100.0, |f| f.get_id() != "WOL");
+        assert_eq!(nearest.unwrap().get_id(), "SYD");
+        assert!(index.nearest(&Coordinate::new(0.0, 0.0), 100.0, |_| true).is_none());
+    }
+
+    #[test]
+    fn test_within_corridor() {
+        let index = make_index();
+
+        let found = index.within_corridor(&Coordinate::new(-33.0, 152.0), &Coordinate::new(-38.0, 144.0), 1.2);
+        assert_eq!(found.len(), 3);
+        let found = index.within_corridor(&Coordinate::new(-17.0, 179.0), &Coordinate::new(-17.0, -179.0), 1.2);
+        assert_eq!(found.len(), 2);
+    }
+}
@@ -0,0 +1,323 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+#![forbid(unsafe_code)]
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Error, ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, RwLock};
+
+use async_channel::Receiver;
+use flate2::read::GzDecoder;
+use log::{info, warn};
+
+use crate::earth::coordinate::Coordinate;
+use crate::earth::FEET_PER_METRE;
+use crate::event;
+use crate::event::{Event, EventType};
+use crate::preference::{TERRAIN_CLEARANCE, TERRAIN_PATH};
+
+// SRTM marks holes in the data with this value
+const VOID: i16 = -32768;
+// Distance between samples taken along a leg in nautical miles, about two SRTM3 cells
+const SAMPLE_INTERVAL: f64 = 0.1;
+const MAX_SAMPLES: usize = 5000;
+pub const DEFAULT_CLEARANCE: i32 = 1000;
+
+static TERRAIN: LazyLock<Terrain> = LazyLock::new(|| Terrain::new(get_directory()));
+// Tells the terrain model when the preferences change, the only time the tile directory can have moved
+static PREFERENCES_CHANGED: LazyLock<Option<Receiver<Event>>> =
+    LazyLock::new(|| event::manager().register_listener(&[EventType::PreferencesChanged]));
+
+/// Get the terrain model, pointed at the tile directory in the preferences.
+pub fn get_terrain() -> &'static Terrain {
+    // Listen before the directory is first looked up, so no change is missed
+    let changes = PREFERENCES_CHANGED.as_ref();
+    let terrain = &*TERRAIN;
+    let changed = changes.is_some_and(|rx| std::iter::from_fn(|| rx.try_recv().ok()).count() > 0);
+    if changed {
+        terrain.set_directory(get_directory());
+    }
+    terrain
+}
+
+fn get_directory() -> Option<PathBuf> {
+    crate::preference::manager()
+        .get::<String>(TERRAIN_PATH)
+        .filter(|p| !p.trim().is_empty())
+        .map(PathBuf::from)
+}
+
+/// The clearance above terrain in feet required by the preferences.
+pub fn get_clearance() -> i32 {
+    crate::preference::manager()
+        .get::<i32>(TERRAIN_CLEARANCE)
+        .unwrap_or(DEFAULT_CLEARANCE)
+}
+
+/// One SRTM tile, covering the one degree square north and east of its corner.
+/// Samples run in rows from north to south and each row from west to east; heights are in metres.
+struct HgtTile {
+    size: usize,
+    samples: Vec<i16>,
+}
+
+impl HgtTile {
+    fn read(path: &Path) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        let mut bytes = Vec::new();
+        if path.extension().is_some_and(|e| e == "gz") {
+            GzDecoder::new(file).read_to_end(&mut bytes)?;
+        } else {
+            let mut file = file;
+            file.read_to_end(&mut bytes)?;
+        }
+        let samples: Vec<i16> = bytes.chunks_exact(2)
+            .map(|b| i16::from_be_bytes([b[0], b[1]]))
+            .collect();
+        // SRTM1 tiles are 3601 samples square and SRTM3 tiles 1201, the size comes from the file length
+        let size = (samples.len() as f64).sqrt() as usize;
+        if size < 2 || size * size != samples.len() {
+            return Err(Error::new(ErrorKind::InvalidData, format!("{:?} is not a square height tile", path)));
+        }
+        Ok(Self { size, samples })
+    }
+
+    fn sample(&self, row: usize, col: usize) -> Option<f64> {
+        let value = self.samples[row * self.size + col];
+        if value == VOID {
+            None
+        } else {
+            Some(value as f64)
+        }
+    }
+
+    /// Height in metres at a position within the tile, given as fractions of a degree from the south west corner.
+    /// Heights between samples are interpolated from the four around them, ignoring any holes.
+    fn elevation(&self, lat_fraction: f64, long_fraction: f64) -> Option<f64> {
+        let last = (self.size - 1) as f64;
+        let row = ((1.0 - lat_fraction) * last).clamp(0.0, last);
+        let col = (long_fraction * last).clamp(0.0, last);
+        let (r0, c0) = (row.floor() as usize, col.floor() as usize);
+        let (r1, c1) = ((r0 + 1).min(self.size - 1), (c0 + 1).min(self.size - 1));
+        let (dr, dc) = (row - r0 as f64, col - c0 as f64);
+
+        let corners = [
+            (self.sample(r0, c0), (1.0 - dr) * (1.0 - dc)),
+            (self.sample(r0, c1), (1.0 - dr) * dc),
+            (self.sample(r1, c0), dr * (1.0 - dc)),
+            (self.sample(r1, c1), dr * dc),
+        ];
+        let (sum, weight) = corners.iter()
+            .filter_map(|(h, w)| h.map(|h| (h * w, *w)))
+            .fold((0.0, 0.0), |(s, tw), (h, w)| (s + h, tw + w));
+        if weight > 0.0 {
+            Some(sum / weight)
+        } else {
+            // All four samples are holes, or we are right on one of them
+            corners.iter().filter_map(|(h, _)| *h).reduce(f64::max)
+        }
+    }
+}
+
+// Tiles by south west corner, None where there is no tile
+type TileCache = HashMap<(i32, i32), Option<Arc<HgtTile>>>;
+
+/// Ground heights from SRTM `.hgt` tiles kept in a local directory.
+///
+/// Tiles are named for their south west corner, e.g. `N46E007.hgt`, and may be gzipped.
+/// They are read as they are first needed and kept. Where there is no tile, as over the sea,
+/// the height is unknown.
+pub struct Terrain {
+    directory: RwLock<Option<PathBuf>>,
+    tiles: RwLock<TileCache>,
+}
+
+impl Terrain {
+    pub fn new(directory: Option<PathBuf>) -> Self {
+        Self {
+            directory: RwLock::new(directory),
+            tiles: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn set_directory(&self, directory: Option<PathBuf>) {
+        let mut current = self.directory.write().expect("Can't get terrain lock");
+        if *current != directory {
+            *current = directory;
+            self.tiles.write().expect("Can't get terrain lock").clear();
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.directory.read().expect("Can't get terrain lock")
+            .as_ref()
+            .is_some_and(|d| d.is_dir())
+    }
+
+    /// Height of the ground in feet.
+    pub fn get_elevation(&self, coord: &Coordinate) -> Option<i32> {
+        let lat = coord.get_latitude();
+        let long = coord.get_longitude();
+        let corner = (lat.floor() as i32, long.floor() as i32);
+        let tile = self.get_tile(corner)?;
+        tile.elevation(lat - corner.0 as f64, long - corner.1 as f64)
+            .map(|metres| (metres * FEET_PER_METRE).round() as i32)
+    }
+
+    /// Highest ground in feet along the great circle between two points.
+    pub fn get_max_elevation_between(&self, from: &Coordinate, to: &Coordinate) -> Option<i32> {
+        if !self.is_available() {
+            return None;
+        }
+        let distance = from.distance_to(to);
+        let heading = from.bearing_to_deg(to);
+        let samples = ((distance / SAMPLE_INTERVAL).ceil() as usize).clamp(1, MAX_SAMPLES);
+        let step = distance / samples as f64;
+
+        (0..=samples)
+            .filter_map(|i| {
+                let point = if i == 0 { from.clone() } else { from.coordinate_at(step * i as f64, heading) };
+                self.get_elevation(&point)
+            })
+            .max()
+    }
+
+    /// The minimum safe altitude for a leg: the highest ground along it plus the clearance,
+    /// rounded up to the next hundred feet. None if there is no terrain data for the leg.
+    pub fn get_minimum_safe_altitude(&self, from: &Coordinate, to: &Coordinate, clearance: i32) -> Option<i32> {
+        self.get_max_elevation_between(from, to)
+            .map(|h| {
+                let msa = h.max(0) + clearance;
+                (msa + 99) / 100 * 100
+            })
+    }
+
+    fn get_tile(&self, corner: (i32, i32)) -> Option<Arc<HgtTile>> {
+        if let Some(tile) = self.tiles.read().expect("Can't get terrain lock").get(&corner) {
+            return tile.clone();
+        }
+        let tile = self.load_tile(corner);
+        self.tiles.write().expect("Can't get terrain lock").insert(corner, tile.clone());
+        tile
+    }
+
+    fn load_tile(&self, corner: (i32, i32)) -> Option<Arc<HgtTile>> {
+        let directory = self.directory.read().expect("Can't get terrain lock").clone()?;
+        let name = tile_name(corner);
+        for file_name in [format!("{}.hgt", name), format!("{}.hgt.gz", name)] {
+            let path = directory.join(file_name);
+            if path.exists() {
+                return match HgtTile::read(&path) {
+                    Ok(tile) => {
+                        info!("Loaded terrain tile {:?}", path);
+                        Some(Arc::new(tile))
+                    }
+                    Err(e) => {
+                        warn!("Unable to read terrain tile {:?}: {}", path, e);
+                        None
+                    }
+                };
+            }
+        }
+        None
+    }
+}
+
+fn tile_name(corner: (i32, i32)) -> String {
+    let (lat, long) = corner;
+    format!("{}{:02}{}{:03}",
+            if lat < 0 { 'S' } else { 'N' }, lat.abs(),
+            if long < 0 { 'W' } else { 'E' }, long.abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::earth::coordinate::Coordinate;
+
+    use super::{tile_name, Terrain};
+
+    // Write an 11 x 11 sample tile, flat at 100m with a 2000m plateau in the middle
+    fn make_tile_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kelpie-terrain-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut bytes = Vec::new();
+        for row in 0..11 {
+            for col in 0..11 {
+                let h: i16 = if (4..=6).contains(&row) && (4..=6).contains(&col) { 2000 } else if row == 0 && col == 0 { -32768 } else { 100 };
+                bytes.extend_from_slice(&h.to_be_bytes());
+            }
+        }
+        fs::write(dir.join("N46E007.hgt"), bytes).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_tile_name() {
+        assert_eq!(tile_name((46, 7)), "N46E007");
+        assert_eq!(tile_name((-34, 151)), "S34E151");
+        assert_eq!(tile_name((-1, -119)), "S01W119");
+    }
+
+    #[test]
+    fn test_elevation() {
+        let dir = make_tile_dir("elevation");
+        let terrain = Terrain::new(Some(dir.clone()));
+
+        assert_eq!(terrain.get_elevation(&Coordinate::new(46.5, 7.5)), Some(6562));
+        assert_eq!(terrain.get_elevation(&Coordinate::new(46.2, 7.2)), Some(328));
+        // The hole in the north west corner takes the height around it
+        assert_eq!(terrain.get_elevation(&Coordinate::new(46.99, 7.01)), Some(328));
+        // No tile
+        assert_eq!(terrain.get_elevation(&Coordinate::new(45.5, 7.5)), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_minimum_safe_altitude() {
+        let dir = make_tile_dir("msa");
+        let terrain = Terrain::new(Some(dir.clone()));
+
+        // Across the plateau
+        let msa = terrain.get_minimum_safe_altitude(&Coordinate::new(46.5, 7.1), &Coordinate::new(46.5, 7.9), 1000);
+        assert_eq!(msa, Some(7600));
+        // Along the southern edge, away from it
+        let msa = terrain.get_minimum_safe_altitude(&Coordinate::new(46.05, 7.1), &Coordinate::new(46.05, 7.9), 1000);
+        assert_eq!(msa, Some(1400));
+        // Off the tile
+        let msa = terrain.get_minimum_safe_altitude(&Coordinate::new(40.0, 7.1), &Coordinate::new(40.0, 7.9), 1000);
+        assert_eq!(msa, None);
+
+        terrain.set_directory(None);
+        assert!(!terrain.is_available());
+        assert_eq!(terrain.get_elevation(&Coordinate::new(46.5, 7.5)), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
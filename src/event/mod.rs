@@ -35,6 +35,8 @@ pub enum EventType {
     AirportsLoaded,
     NavaidsLoaded,
     FixesLoaded,
+    AirwaysLoaded,
+    HangarChanged,
     PlanChanged,
     PreferencesChanged,
     SetupRequired,
@@ -46,6 +48,8 @@ pub enum Event {
     AirportsLoaded,
     NavaidsLoaded,
     FixesLoaded,
+    AirwaysLoaded,
+    HangarChanged,
     PlanChanged,
     PreferencesChanged,
     SetupRequired,
@@ -58,6 +62,8 @@ impl Event {
             Event::AirportsLoaded => EventType::AirportsLoaded,
             Event::NavaidsLoaded => EventType::NavaidsLoaded,
             Event::FixesLoaded => EventType::FixesLoaded,
+            Event::AirwaysLoaded => EventType::AirwaysLoaded,
+            Event::HangarChanged => EventType::HangarChanged,
             Event::PlanChanged => EventType::PlanChanged,
             Event::PreferencesChanged => EventType::PreferencesChanged,
             Event::SetupRequired => EventType::SetupRequired,
@@ -125,10 +131,11 @@ impl EventManager {
 }
 
 
+#[cfg(feature = "gui")]
 #[macro_export]
 macro_rules! listen_events {
     ($self:ident, $events:expr, $rename_to:ident,  $ev_ident:ident, $body:block) => {
-        if let Some(rx) = crate::event::manager().register_listener($events) {
+        if let Some(rx) = $crate::event::manager().register_listener($events) {
             gtk::glib::MainContext::default().spawn_local(gtk::glib::clone!(#[weak(rename_to = $rename_to)] $self, async move {
                 while let Ok($ev_ident) = rx.recv().await {
                     $body
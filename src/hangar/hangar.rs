@@ -21,17 +21,19 @@
  *      Trevor Campbell
  *
  */
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{Read, Write};
 use std::path::PathBuf;
-use std::sync::{Arc, LazyLock};
+use std::sync::{Arc, LazyLock, RwLock};
 
 use dirs_next::config_dir;
-use gtk::{gio, glib, subclass::prelude::*};
 use log::{error, warn};
 use yaml_rust::{Yaml, YamlEmitter, YamlLoader};
 use yaml_rust::yaml::Hash;
-use crate::model::aircraft::Aircraft;
+use crate::event;
+use crate::event::Event;
+use crate::model::aircraft::{Aircraft, AircraftFuel};
 use crate::preference::APP_INFO;
 
 // This is where all the planes live.
@@ -42,6 +44,14 @@ static DEFAULT_ARICRAFT: &str = "---
   climb-speed: 250
   cruise-altitude: 35000
   cruise-speed: 490
+  fuel-alternate-minutes: 45
+  fuel-capacity: 137000
+  fuel-climb-burn: 9000
+  fuel-contingency: 5
+  fuel-cruise-burn: 6800
+  fuel-descent-burn: 2500
+  fuel-reserve-minutes: 30
+  fuel-taxi: 500
   is-default: false
   name: 777-200B
   sink-rate: 3000
@@ -50,6 +60,14 @@ static DEFAULT_ARICRAFT: &str = "---
   climb-speed: 300
   cruise-altitude: 36000
   cruise-speed: 450
+  fuel-alternate-minutes: 45
+  fuel-capacity: 20800
+  fuel-climb-burn: 3500
+  fuel-contingency: 5
+  fuel-cruise-burn: 2500
+  fuel-descent-burn: 1200
+  fuel-reserve-minutes: 30
+  fuel-taxi: 200
   is-default: false
   name: Boeing 737
   sink-rate: 1000
@@ -58,184 +76,136 @@ static DEFAULT_ARICRAFT: &str = "---
   climb-speed: 110
   cruise-altitude: 7000
   cruise-speed: 140
+  fuel-alternate-minutes: 30
+  fuel-capacity: 53
+  fuel-climb-burn: 11
+  fuel-contingency: 5
+  fuel-cruise-burn: 8.5
+  fuel-descent-burn: 6
+  fuel-reserve-minutes: 45
+  fuel-taxi: 1.4
   is-default: true
   name: Cessna C-172 - High wing
   sink-rate: 500
   sink-speed: 100
 ";
-// To use the Hangar as a Gio::ListModel it needs to ba a glib::Object, so we do all this fancy subclassing stuff
-// Public part of the Model type.
-glib::wrapper! {
-    pub struct Hangar(ObjectSubclass<imp::Hangar>) @implements gio::ListModel;
+/// All the aircraft the user has set up, in name order.
+/// Changes are saved to the hangar file and announced with `Event::HangarChanged`.
+pub struct Hangar {
+    aircraft: Arc<RwLock<BTreeMap<String, Arc<Aircraft>>>>,
 }
 
 impl Hangar {
     pub fn new() -> Hangar {
-        glib::Object::new()
+        let mut aircraft = BTreeMap::new();
+        for a in load_hangar() {
+            aircraft.insert(a.get_name().to_string(), a);
+        }
+        Hangar {
+            aircraft: Arc::new(RwLock::new(aircraft)),
+        }
     }
-}
 
-impl Default for Hangar {
-    fn default() -> Self {
-        Self::new()
+    pub fn get_default_aircraft(&self) -> Option<Arc<Aircraft>> {
+        self.aircraft
+            .read()
+            .expect("Unable to get a lock on the aircraft hangar")
+            .values()
+            .find(|a| *a.is_default())
+            .cloned()
     }
-}
 
-mod imp {
-    use std::collections::BTreeMap;
-    use std::sync::{Arc, RwLock};
-
-    use gtk::{gio, glib, StringObject};
-    use adw::prelude::{ListModelExt, StaticType};
-    use adw::subclass::prelude::{ListModelImpl, ObjectImpl, ObjectImplExt, ObjectSubclass, ObjectSubclassExt};
-
-    use crate::hangar::hangar::{load_hangar, save_hangar};
-    use crate::model::aircraft::Aircraft;
-
-    #[derive(Default)]
-    pub struct Hangar {
-        pub aircraft: Arc<RwLock<BTreeMap<String, Arc<Aircraft>>>>,
+    pub fn get_all(&self) -> &Arc<RwLock<BTreeMap<String, Arc<Aircraft>>>> {
+        &self.aircraft
     }
 
-    impl Hangar {
-        pub fn get_default_aircraft(&self) -> Option<Arc<Aircraft>> {
-            self.aircraft
-                .read()
-                .expect("Unable to get a lock on the aircraft hangar")
-                .values()
-                .find(|a| *a.is_default())
-                .cloned()
-        }
-
-        pub fn get_all(&self) -> &Arc<RwLock<BTreeMap<String, Arc<Aircraft>>>> {
-            &self.aircraft
-        }
-
-        pub fn get(&self, name: &str) -> Option<Arc<Aircraft>> {
-            self.aircraft.read().expect("Can't get aircraft lock").get(name).cloned()
-        }
-
-        pub fn put(&self, aircraft: Aircraft) {
-            let name = aircraft.get_name().to_string().clone();
-            let old_p = self.get_item_position(&name);
-            let mut binding = self.aircraft.write().expect("Can't get aircraft lock");
-            let _old = binding.insert(aircraft.get_name().to_string(), Arc::new(aircraft));
-            drop(binding);
-            self.save();
-            if let Some(pos) = old_p {
-                self.obj().items_changed(pos, 0, 0);
-            } else if let Some(pos) = self.get_item_position(&name) {
-                self.obj().items_changed(pos, 0, 1);
-            }
-        }
-
-        pub fn remove(&self, name: &str) {
-            let p = self.get_item_position(name);
-            let mut binding = self.aircraft.write().expect("Can't get aircraft lock");
-            let _old = binding.remove(name);
-            drop(binding);
-            self.save();
-            if let Some(pos) = p {
-                self.obj().items_changed(pos, 1, 0);
-            }
-        }
+    pub fn get(&self, name: &str) -> Option<Arc<Aircraft>> {
+        self.aircraft.read().expect("Can't get aircraft lock").get(name).cloned()
+    }
 
-        pub fn set_default(&self, name: &str) {
-            // We need to unset the old default and set the new default.
-            if let Some(a) = self.get_default_aircraft() {
-                let prior_default = Aircraft::new(
-                    a.get_name().to_string(),
-                    *a.get_cruise_speed(),
-                    *a.get_cruise_altitude(),
-                    *a.get_climb_speed(),
-                    *a.get_climb_rate(),
-                    *a.get_sink_speed(),
-                    *a.get_sink_rate(),
-                    false,
-                );
-                self.put(prior_default);
-            }
-            if let Some(a) = self.get(name) {
-                let new_default = Aircraft::new(
-                    a.get_name().to_string(),
-                    *a.get_cruise_speed(),
-                    *a.get_cruise_altitude(),
-                    *a.get_climb_speed(),
-                    *a.get_climb_rate(),
-                    *a.get_sink_speed(),
-                    *a.get_sink_rate(),
-                    true,
-                );
-                self.put(new_default);
-            }
-        }
+    /// An aircraft that cruises at the given speed, the default aircraft if it does.
+    pub fn get_by_cruise_speed(&self, speed: i32) -> Option<Arc<Aircraft>> {
+        let aircraft = self.aircraft.read().expect("Can't get aircraft lock");
+        let mut matching = aircraft.values().filter(|a| *a.get_cruise_speed() == speed);
+        let first = matching.next().cloned();
+        matching.find(|a| *a.is_default()).cloned().or(first)
+    }
 
-        pub fn save(&self) {
-            save_hangar();
-        }
+    pub fn put(&self, aircraft: Aircraft) {
+        let mut binding = self.aircraft.write().expect("Can't get aircraft lock");
+        let _old = binding.insert(aircraft.get_name().to_string(), Arc::new(aircraft));
+        drop(binding);
+        self.save();
+        event::manager().notify_listeners(Event::HangarChanged);
+    }
 
-        fn get_item_position(&self, plane: &str) -> Option<u32> {
-            let aircraft = self
-                .aircraft
-                .read()
-                .expect("Unable to get a lock on the aircraft hangar");
+    pub fn remove(&self, name: &str) {
+        let mut binding = self.aircraft.write().expect("Can't get aircraft lock");
+        let _old = binding.remove(name);
+        drop(binding);
+        self.save();
+        event::manager().notify_listeners(Event::HangarChanged);
+    }
 
-            (0_u32..).zip(aircraft.keys())
-                .find(|(_i, name)| *name == plane)
-                .map(|(i, _name)| i)
+    pub fn set_default(&self, name: &str) {
+        // We need to unset the old default and set the new default.
+        if let Some(a) = self.get_default_aircraft() {
+            let prior_default = Aircraft::new(
+                a.get_name().to_string(),
+                *a.get_cruise_speed(),
+                *a.get_cruise_altitude(),
+                *a.get_climb_speed(),
+                *a.get_climb_rate(),
+                *a.get_sink_speed(),
+                *a.get_sink_rate(),
+                a.get_fuel().clone(),
+                false,
+            );
+            self.put(prior_default);
         }
-
-        pub fn aircraft_at(&self, position: u32) -> Option<Arc<Aircraft>> {
-            let aircraft = self
-                .aircraft
-                .read()
-                .expect("Unable to get a lock on the aircraft hangar");
-            aircraft.values().nth(position as usize).cloned()
+        if let Some(a) = self.get(name) {
+            let new_default = Aircraft::new(
+                a.get_name().to_string(),
+                *a.get_cruise_speed(),
+                *a.get_cruise_altitude(),
+                *a.get_climb_speed(),
+                *a.get_climb_rate(),
+                *a.get_sink_speed(),
+                *a.get_sink_rate(),
+                a.get_fuel().clone(),
+                true,
+            );
+            self.put(new_default);
         }
     }
 
-    /// Basic declaration of our type for the GObject type system
-    #[glib::object_subclass]
-    impl ObjectSubclass for Hangar {
-        const NAME: &'static str = "Hangar";
-        type Type = super::Hangar;
-        type Interfaces = (gio::ListModel, );
+    pub fn save(&self) {
+        save_hangar();
     }
 
-    impl ObjectImpl for Hangar {
-        fn constructed(&self) {
-            self.parent_constructed();
-
-            let mut aircraft = self.aircraft.write().expect("Can't get lock on aircraft");
-            for a in load_hangar() {
-                aircraft.insert(a.get_name().to_string(), a);
-            }
-        }
+    pub fn aircraft_at(&self, position: u32) -> Option<Arc<Aircraft>> {
+        let aircraft = self
+            .aircraft
+            .read()
+            .expect("Unable to get a lock on the aircraft hangar");
+        aircraft.values().nth(position as usize).cloned()
     }
 
-    impl ListModelImpl for Hangar {
-        fn item_type(&self) -> glib::Type {
-            StringObject::static_type()
-        }
+    pub fn len(&self) -> usize {
+        self.aircraft
+            .read()
+            .expect("Unable to get a lock on the aircraft hangar")
+            .len()
+    }
 
-        fn n_items(&self) -> u32 {
-            let aircraft = self
-                .aircraft
-                .read()
-                .expect("Unable to get a lock on the aircraft hangar");
-            aircraft.len() as u32
-        }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
 
-        fn item(&self, position: u32) -> Option<glib::Object> {
-            self.aircraft_at(position).map(|plane| {
-                let mut name_string = plane.get_name().to_string();
-                    // Get the aircraft and see if it is the default
-                if *plane.is_default() {
-                    name_string.push('*');
-                }
-                glib::Object::from(StringObject::new(name_string.as_str()))
-            })
-        }
+impl Default for Hangar {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -247,6 +217,14 @@ const KEY_CLIMB_RATE: &str = "climb-rate";
 const KEY_SINK_SPEED: &str = "sink-speed";
 const KEY_SINK_RATE: &str = "sink-rate";
 const KEY_IS_DEFAULT: &str = "is-default";
+const KEY_FUEL_CLIMB_BURN: &str = "fuel-climb-burn";
+const KEY_FUEL_CRUISE_BURN: &str = "fuel-cruise-burn";
+const KEY_FUEL_DESCENT_BURN: &str = "fuel-descent-burn";
+const KEY_FUEL_CAPACITY: &str = "fuel-capacity";
+const KEY_FUEL_TAXI: &str = "fuel-taxi";
+const KEY_FUEL_CONTINGENCY: &str = "fuel-contingency";
+const KEY_FUEL_ALTERNATE_MINUTES: &str = "fuel-alternate-minutes";
+const KEY_FUEL_RESERVE_MINUTES: &str = "fuel-reserve-minutes";
 
 pub fn get_hangar() -> &'static Hangar {
     &HANGAR
@@ -281,6 +259,7 @@ pub fn load_hangar() -> Vec<Arc<Aircraft>> {
                                     get_i32(map, KEY_CLIMB_RATE),
                                     get_i32(map, KEY_SINK_SPEED),
                                     get_i32(map, KEY_SINK_RATE),
+                                    get_fuel(map),
                                     get_bool(map, KEY_IS_DEFAULT),
                                 );
                                 hangar.push(Arc::new(aircraft));
@@ -297,6 +276,21 @@ pub fn load_hangar() -> Vec<Arc<Aircraft>> {
     hangar
 }
 
+// Aircraft saved before fuel planning have no fuel figures, these all load as zero
+fn get_fuel(map: &Hash) -> AircraftFuel {
+    AircraftFuel::new(
+        get_f64(map, KEY_FUEL_CLIMB_BURN),
+        get_f64(map, KEY_FUEL_CRUISE_BURN),
+        get_f64(map, KEY_FUEL_DESCENT_BURN),
+        get_f64(map, KEY_FUEL_CAPACITY),
+    ).with_allowances(
+        get_f64(map, KEY_FUEL_TAXI),
+        get_f64(map, KEY_FUEL_CONTINGENCY),
+        get_f64(map, KEY_FUEL_ALTERNATE_MINUTES),
+        get_f64(map, KEY_FUEL_RESERVE_MINUTES),
+    )
+}
+
 fn get_bool(map: &Hash, key: &str) -> bool {
     map.get(&Yaml::String(key.to_string()))
         .unwrap_or(&Yaml::Boolean(false))
@@ -311,6 +305,14 @@ fn get_i32(map: &Hash, key: &str) -> i32 {
         .unwrap_or(0) as i32
 }
 
+fn get_f64(map: &Hash, key: &str) -> f64 {
+    match map.get(&Yaml::String(key.to_string())) {
+        Some(Yaml::Integer(i)) => *i as f64,
+        Some(v) => v.as_f64().unwrap_or(0.0),
+        None => 0.0,
+    }
+}
+
 fn get_string(map: &Hash, key: &str) -> String {
     map.get(&Yaml::String(key.to_string()))
         .unwrap_or(&Yaml::String("".to_string()))
@@ -321,7 +323,7 @@ fn get_string(map: &Hash, key: &str) -> String {
 
 pub fn save_hangar() {
     if let Some(path) = get_hangar_path() {
-        let hangar = get_hangar().imp().get_all();
+        let hangar = get_hangar().get_all();
         let all = hangar.read().expect("Unable to get read lock on hangar");
 
         let mut vec = Vec::new();
@@ -336,6 +338,15 @@ pub fn save_hangar() {
             put_i32(&mut inner_map, KEY_CLIMB_RATE, a.get_climb_rate());
             put_i32(&mut inner_map, KEY_SINK_SPEED, a.get_sink_speed());
             put_i32(&mut inner_map, KEY_SINK_RATE, a.get_sink_rate());
+            let fuel = a.get_fuel();
+            put_f64(&mut inner_map, KEY_FUEL_CLIMB_BURN, fuel.get_climb_burn());
+            put_f64(&mut inner_map, KEY_FUEL_CRUISE_BURN, fuel.get_cruise_burn());
+            put_f64(&mut inner_map, KEY_FUEL_DESCENT_BURN, fuel.get_descent_burn());
+            put_f64(&mut inner_map, KEY_FUEL_CAPACITY, fuel.get_capacity());
+            put_f64(&mut inner_map, KEY_FUEL_TAXI, fuel.get_taxi());
+            put_f64(&mut inner_map, KEY_FUEL_CONTINGENCY, fuel.get_contingency());
+            put_f64(&mut inner_map, KEY_FUEL_ALTERNATE_MINUTES, fuel.get_alternate_minutes());
+            put_f64(&mut inner_map, KEY_FUEL_RESERVE_MINUTES, fuel.get_reserve_minutes());
             put_bool(&mut inner_map, KEY_IS_DEFAULT, a.is_default());
 
             let map = Yaml::Hash(inner_map);
@@ -378,6 +389,10 @@ fn put_i32(map: &mut Hash, key: &str, v: &i32) {
     map.insert(Yaml::String(key.to_string()), Yaml::Integer(*v as i64));
 }
 
+fn put_f64(map: &mut Hash, key: &str, v: &f64) {
+    map.insert(Yaml::String(key.to_string()), Yaml::Real(v.to_string()));
+}
+
 fn put_string(map: &mut Hash, key: &str, v: &str) {
     map.insert(Yaml::String(key.to_string()), Yaml::String(v.to_string()));
 }
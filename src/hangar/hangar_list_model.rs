@@ -0,0 +1,126 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+// This is a list model that wraps the hangar, showing the aircraft names with the default marked
+
+use gtk::gio::ListModel;
+use gtk::glib;
+
+// To use the Hangar as a Gio::ListModel it needs to ba a glib::Object, so we do all this fancy subclassing stuff
+// Public part of the Model type.
+glib::wrapper! {
+    pub struct HangarListModel(ObjectSubclass<imp::HangarListModel>) @implements ListModel;
+}
+
+impl HangarListModel {
+    pub fn new() -> HangarListModel {
+        glib::Object::new()
+    }
+}
+
+impl Default for HangarListModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+mod imp {
+    use std::cell::RefCell;
+
+    use gtk::{gio, glib, StringObject};
+    use gtk::glib::Object;
+    use adw::prelude::{ListModelExt, StaticType};
+    use adw::subclass::prelude::{ListModelImpl, ObjectImpl, ObjectImplExt, ObjectSubclass, ObjectSubclassExt};
+
+    use crate::event::EventType;
+    use crate::hangar::hangar::get_hangar;
+    use crate::listen_events;
+
+    #[derive(Default)]
+    pub struct HangarListModel {
+        // The aircraft names last reported to the view
+        names: RefCell<Vec<String>>,
+    }
+
+    impl HangarListModel {
+        fn hangar_names() -> Vec<String> {
+            get_hangar().get_all().read().expect("Unable to get a lock on the aircraft hangar")
+                .keys()
+                .cloned()
+                .collect()
+        }
+    }
+
+    /// Basic declaration of our type for the GObject type system
+    #[glib::object_subclass]
+    impl ObjectSubclass for HangarListModel {
+        const NAME: &'static str = "HangarListModel";
+        type Type = super::HangarListModel;
+        type Interfaces = (gio::ListModel, );
+    }
+
+    impl ObjectImpl for HangarListModel {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.names.replace(Self::hangar_names());
+
+            // Only report the names that changed so selections in the views are kept
+            listen_events!(self, &[EventType::HangarChanged], model, _ev, {
+                let new = Self::hangar_names();
+                let old = model.names.replace(new.clone());
+                let prefix = old.iter().zip(new.iter()).take_while(|(a, b)| a == b).count();
+                let suffix = old[prefix..].iter().rev()
+                    .zip(new[prefix..].iter().rev())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                let removed = old.len() - prefix - suffix;
+                let added = new.len() - prefix - suffix;
+                if removed > 0 || added > 0 {
+                    model.obj().items_changed(prefix as u32, removed as u32, added as u32);
+                }
+            });
+        }
+    }
+
+    impl ListModelImpl for HangarListModel {
+        fn item_type(&self) -> glib::Type {
+            StringObject::static_type()
+        }
+
+        fn n_items(&self) -> u32 {
+            self.names.borrow().len() as u32
+        }
+
+        fn item(&self, position: u32) -> Option<Object> {
+            // The names reported to the view are used, the hangar may already have changed
+            self.names.borrow().get(position as usize).map(|name| {
+                let mut name_string = name.clone();
+                // Get the aircraft and see if it is the default
+                if get_hangar().get(name).is_some_and(|plane| *plane.is_default()) {
+                    name_string.push('*');
+                }
+                Object::from(StringObject::new(name_string.as_str()))
+            })
+        }
+    }
+}
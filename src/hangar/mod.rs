@@ -24,3 +24,5 @@
 #![forbid(unsafe_code)]
 
 pub mod hangar;
+#[cfg(feature = "gui")]
+pub mod hangar_list_model;
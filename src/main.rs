@@ -42,13 +42,9 @@ use gettextrs::{TextDomain, TextDomainError};
 use crate::util::info;
 use crate::window::util::show_help_about;
 
-mod earth;
-mod event;
-mod hangar;
-mod model;
-mod planner;
-mod preference;
-mod util;
+use kelpie_rust_planner::{earth, event, hangar, model, planner, preference, util};
+use kelpie_rust_planner::listen_events;
+
 mod window;
 
 const APP_ID: &str = "com.shartrec.KelpiePlanner";
@@ -211,12 +207,48 @@ fn connect_actions(app: &Application, window: &Window) {
     }));
     app.add_action(&action);
 
+    let action = SimpleAction::new("export_xplane", None);
+    action.connect_activate(clone!(#[weak] window, move  |_action, _parameter| {
+        let _ = &window.imp().export_plan_xplane();
+    }));
+    app.add_action(&action);
+
+    let action = SimpleAction::new("export_gpx", None);
+    action.connect_activate(clone!(#[weak] window, move  |_action, _parameter| {
+        let _ = &window.imp().export_plan_gpx();
+    }));
+    app.add_action(&action);
+
+    let action = SimpleAction::new("export_kml", None);
+    action.connect_activate(clone!(#[weak] window, move  |_action, _parameter| {
+        let _ = &window.imp().export_plan_kml();
+    }));
+    app.add_action(&action);
+
+    let action = SimpleAction::new("export_nav_log", None);
+    action.connect_activate(clone!(#[weak] window, move  |_action, _parameter| {
+        let _ = &window.imp().export_nav_log();
+    }));
+    app.add_action(&action);
+
     let action = SimpleAction::new("quit", None);
     action.connect_activate(clone!(#[weak] app, move |_action, _parameter| {
         app.quit()
     }));
     app.add_action(&action);
 
+    let action = SimpleAction::new("undo", None);
+    action.connect_activate(clone!(#[weak] window, move |_action, _parameter| {
+        window.imp().undo();
+    }));
+    app.add_action(&action);
+
+    let action = SimpleAction::new("redo", None);
+    action.connect_activate(clone!(#[weak] window, move |_action, _parameter| {
+        window.imp().redo();
+    }));
+    app.add_action(&action);
+
     let action = SimpleAction::new("preferences", None);
     action.connect_activate(clone!(#[weak] window, move |_action, _parameter| {
         let pref_dialog = PreferenceDialog::new();
@@ -246,6 +278,8 @@ fn connect_actions(app: &Application, window: &Window) {
     }));
     app.add_action(&action);
     app.set_accels_for_action("app.help-contents", &["F1"]);
+    app.set_accels_for_action("app.undo", &["<Primary>z"]);
+    app.set_accels_for_action("app.redo", &["<Primary><Shift>z", "<Primary>y"]);
 }
 
 fn build_ui(app: &Application) {
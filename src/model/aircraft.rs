@@ -31,6 +31,7 @@ pub struct Aircraft {
     climb_rate: i32,
     sink_speed: i32,
     sink_rate: i32,
+    fuel: AircraftFuel,
     is_default: bool,
 }
 
@@ -45,6 +46,7 @@ impl Aircraft {
         climb_rate: i32,
         sink_speed: i32,
         sink_rate: i32,
+        fuel: AircraftFuel,
         is_default: bool,
     ) -> Self {
         Aircraft {
@@ -55,6 +57,7 @@ impl Aircraft {
             climb_rate,
             sink_speed,
             sink_rate,
+            fuel,
             is_default,
         }
     }
@@ -87,6 +90,10 @@ impl Aircraft {
         &self.sink_rate
     }
 
+    pub fn get_fuel(&self) -> &AircraftFuel {
+        &self.fuel
+    }
+
     pub fn is_default(&self) -> &bool {
         &self.is_default
     }
@@ -102,7 +109,98 @@ impl Default for Aircraft {
             climb_rate: 500,
             sink_speed: 100,
             sink_rate: 500,
+            fuel: AircraftFuel::default(),
             is_default: false,
         }
     }
 }
+
+/// The fuel figures for an aircraft.
+/// Quantities may be in any unit (gallons, litres, pounds, kilograms) as long as the same
+/// unit is used throughout; burns are quantities per hour. An aircraft with no capacity or
+/// no cruise burn has no fuel figures and is not fuel planned.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AircraftFuel {
+    climb_burn: f64,
+    cruise_burn: f64,
+    descent_burn: f64,
+    capacity: f64,
+    taxi: f64,
+    contingency: f64,
+    alternate_minutes: f64,
+    reserve_minutes: f64,
+}
+
+impl AircraftFuel {
+    /// Burns are per hour. The allowances are none until set by `with_allowances`.
+    pub fn new(climb_burn: f64, cruise_burn: f64, descent_burn: f64, capacity: f64) -> Self {
+        AircraftFuel {
+            climb_burn,
+            cruise_burn,
+            descent_burn,
+            capacity,
+            ..Default::default()
+        }
+    }
+
+    /// The fuel for taxi, the contingency percentage and the minutes of fuel held for an alternate and in reserve
+    pub fn with_allowances(self, taxi: f64, contingency: f64, alternate_minutes: f64, reserve_minutes: f64) -> Self {
+        AircraftFuel {
+            taxi,
+            contingency,
+            alternate_minutes,
+            reserve_minutes,
+            ..self
+        }
+    }
+
+    pub fn get_climb_burn(&self) -> &f64 {
+        &self.climb_burn
+    }
+
+    pub fn get_cruise_burn(&self) -> &f64 {
+        &self.cruise_burn
+    }
+
+    pub fn get_descent_burn(&self) -> &f64 {
+        &self.descent_burn
+    }
+
+    /// Usable fuel
+    pub fn get_capacity(&self) -> &f64 {
+        &self.capacity
+    }
+
+    /// Fuel used before take off
+    pub fn get_taxi(&self) -> &f64 {
+        &self.taxi
+    }
+
+    /// Contingency fuel as a percentage of the trip fuel
+    pub fn get_contingency(&self) -> &f64 {
+        &self.contingency
+    }
+
+    /// Minutes at cruise burn to reach an alternate
+    pub fn get_alternate_minutes(&self) -> &f64 {
+        &self.alternate_minutes
+    }
+
+    /// Minutes at cruise burn held as a final reserve
+    pub fn get_reserve_minutes(&self) -> &f64 {
+        &self.reserve_minutes
+    }
+
+    pub fn is_planned(&self) -> bool {
+        self.capacity > 0.0 && self.cruise_burn > 0.0
+    }
+
+    /// Hours the usable fuel lasts at cruise burn
+    pub fn get_endurance(&self) -> f64 {
+        if self.cruise_burn > 0.0 {
+            self.capacity / self.cruise_burn
+        } else {
+            0.0
+        }
+    }
+}
@@ -25,7 +25,8 @@
 use std::{fmt, fs};
 use std::any::Any;
 use std::io::BufReader;
-use std::sync::{Arc, RwLock};
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
 
 use flate2::read;
 use log::{error, warn};
@@ -33,8 +34,11 @@ use log::{error, warn};
 use crate::earth::{FEET_PER_DEGREE, get_earth_model};
 use crate::earth::coordinate::Coordinate;
 use crate::util::airport_parser::AirportParserFG850;
+use crate::util::cifp_parser::CifpParser;
 
 use super::location::Location;
+use super::procedure::{Procedure, ProcedureType};
+use super::wind::Wind;
 
 #[derive(Clone)]
 pub struct Airport {
@@ -45,9 +49,15 @@ pub struct Airport {
     runways: Arc<RwLock<Vec<Runway>>>,
     show_default_buildings: bool,
     taxiways: Arc<RwLock<Vec<Taxiway>>>,
+    frequencies: Arc<RwLock<Vec<Frequency>>>,
+    start_locations: Arc<RwLock<Vec<StartLocation>>>,
+    beacons: Arc<RwLock<Vec<Beacon>>>,
+    windsocks: Arc<RwLock<Vec<Windsock>>>,
+    procedures: Arc<OnceLock<Vec<Arc<Procedure>>>>,
     max_runway_length: i64,
     airport_type: Option<AirportType>,
     name: String,
+    metadata: AirportMetadata,
 }
 
 impl Airport {
@@ -72,9 +82,15 @@ impl Airport {
             runways: Arc::new(RwLock::new(Vec::new())),
             show_default_buildings,
             taxiways: Arc::new(RwLock::new(Vec::new())),
+            frequencies: Arc::new(RwLock::new(Vec::new())),
+            start_locations: Arc::new(RwLock::new(Vec::new())),
+            beacons: Arc::new(RwLock::new(Vec::new())),
+            windsocks: Arc::new(RwLock::new(Vec::new())),
+            procedures: Arc::new(OnceLock::new()),
             max_runway_length,
             airport_type,
             name,
+            metadata: AirportMetadata::default(),
         }
     }
 
@@ -92,6 +108,34 @@ impl Airport {
             .push(taxiway);
     }
 
+    pub fn add_frequency(&self, frequency: Frequency) {
+        self.frequencies
+            .write()
+            .expect("Can't get airport lock")
+            .push(frequency);
+    }
+
+    pub fn add_start_location(&self, start_location: StartLocation) {
+        self.start_locations
+            .write()
+            .expect("Can't get airport lock")
+            .push(start_location);
+    }
+
+    pub fn add_beacon(&self, beacon: Beacon) {
+        self.beacons
+            .write()
+            .expect("Can't get airport lock")
+            .push(beacon);
+    }
+
+    pub fn add_windsock(&self, windsock: Windsock) {
+        self.windsocks
+            .write()
+            .expect("Can't get airport lock")
+            .push(windsock);
+    }
+
     pub fn get_control_tower(&self) -> bool {
         self.control_tower
     }
@@ -129,25 +173,106 @@ impl Airport {
         runway.cloned()
     }
 
+    /// Get the runway that has an end with the given number, e.g. the 16R/34L runway for "34L"
+    pub fn get_runway_for(&self, number: &str) -> Option<Runway> {
+        let binding = self.get_runways()
+            .read()
+            .expect("Can't get airport lock");
+        let runway = binding
+            .iter()
+            .find(|runway| runway.heading_of(number).is_some());
+        runway.cloned()
+    }
+
+    /// Choose the runway end best suited to the surface wind: the one with the most headwind,
+    /// or the longer runway where there is little to choose between them.
+    /// In calm conditions this is the longest runway.
+    pub fn select_runway(&self, wind: &Wind) -> Option<String> {
+        let binding = self.get_runways()
+            .read()
+            .expect("Can't get airport lock");
+        let mut best: Option<(String, f64, i32)> = None;
+        for runway in binding.iter().filter(|r| r.runway_type() != Some(RunwayType::Helipad)) {
+            for number in runway.ends() {
+                let headwind = if wind.is_calm() {
+                    0.0
+                } else {
+                    runway.heading_of(&number).map_or(0.0, |h| wind.components(h).0)
+                };
+                let better = match &best {
+                    None => true,
+                    Some((_, best_headwind, best_length)) => {
+                        headwind > best_headwind + 1.0
+                            || (headwind > best_headwind - 1.0 && runway.length() > *best_length)
+                    }
+                };
+                if better {
+                    best = Some((number, headwind, runway.length()));
+                }
+            }
+        }
+        best.map(|(number, _, _)| number)
+    }
+
     pub fn get_show_default_buildings(&self) -> bool {
         self.show_default_buildings
     }
 
     pub fn get_taxiways(&self) -> &Arc<RwLock<Vec<Taxiway>>> {
-        // We check runways here as all airports have a runway, but in FlightGear maybe no Taxiways defined
-        let mut loaded = false;
-        if !self
+        self.ensure_layout_loaded();
+        &self.taxiways
+    }
+
+    /// The ATC frequencies, loaded with the runways
+    pub fn get_frequencies(&self) -> &Arc<RwLock<Vec<Frequency>>> {
+        self.ensure_layout_loaded();
+        &self.frequencies
+    }
+
+    /// The gates, hangars, tie downs and ramp starts, loaded with the runways
+    pub fn get_start_locations(&self) -> &Arc<RwLock<Vec<StartLocation>>> {
+        self.ensure_layout_loaded();
+        &self.start_locations
+    }
+
+    pub fn get_beacons(&self) -> &Arc<RwLock<Vec<Beacon>>> {
+        self.ensure_layout_loaded();
+        &self.beacons
+    }
+
+    pub fn get_windsocks(&self) -> &Arc<RwLock<Vec<Windsock>>> {
+        self.ensure_layout_loaded();
+        &self.windsocks
+    }
+
+    /// The first of a frequency of the given type, e.g. the tower
+    pub fn get_frequency_of(&self, frequency_type: FrequencyType) -> Option<Frequency> {
+        self.get_frequencies()
+            .read()
+            .expect("Can't get airport lock")
+            .iter()
+            .find(|f| f.get_type() == frequency_type)
+            .cloned()
+    }
+
+    pub fn get_metadata(&self) -> &AirportMetadata {
+        &self.metadata
+    }
+
+    pub fn set_metadata(&mut self, key: &str, value: &str) {
+        self.metadata.set(key, value);
+    }
+
+    // We check runways here as all airports have a runway, but in FlightGear maybe no Taxiways defined
+    fn ensure_layout_loaded(&self) {
+        if self
             .runways
             .read()
             .expect("Can't get airport lock")
             .is_empty()
         {
-            loaded = true;
-        }
-        if !loaded {
             self.load_runways_and_taxiways()
         }
-        &self.taxiways
     }
 
     pub fn get_type(&self) -> Option<AirportType> {
@@ -155,23 +280,32 @@ impl Airport {
     }
 
     pub fn load_runways_and_taxiways(&self) {
-        let pref = crate::preference::manager();
-
-        let runway_offsets = get_earth_model().get_runway_offsets();
-
-        let file = match pref.get::<String>(crate::preference::AIRPORTS_PATH) {
-            Some(p) => fs::File::open(p),
-            None => {
-                error!("Path to airports file not found");
-                return;
+        let earth = get_earth_model();
+        let parser = AirportParserFG850::new();
+
+        // The layout store has just this airport's records, until it is built apt.dat is scanned for them
+        if let Some(store) = earth.get_layout_store() {
+            match store.read_layout(self.get_id()) {
+                Ok(Some(layout)) => {
+                    if let Err(e) = parser.load_layout(self, &mut layout.as_slice()) {
+                        warn!("{}", e)
+                    }
+                    return;
+                }
+                Ok(None) => (),
+                Err(e) => warn!("Unable to read the layout of {} from the store: {}", self.get_id(), e),
             }
+        }
+
+        let Some(path) = earth.get_airports_path() else {
+            error!("Path to airports file not found");
+            return;
         };
-        match file {
+        match fs::File::open(path) {
             Ok(input) => {
-                let parser = AirportParserFG850::new();
                 let decoder = read::GzDecoder::new(input);
                 let mut reader = BufReader::new(decoder);
-                let result = parser.load_runways(self, runway_offsets, &mut reader);
+                let result = parser.load_runways(self, earth.get_runway_offsets(), &mut reader);
                 if let Err(e) = result {
                     warn!("{}", e)
                 }
@@ -180,6 +314,46 @@ impl Airport {
         }
     }
 
+    /// The SIDs, STARs and approaches published for the airport, read from the CIFP data when first needed.
+    /// Nothing is kept while the CIFP data can't be read, so setting the path later still finds them.
+    pub fn get_procedures(&self) -> Vec<Arc<Procedure>> {
+        if let Some(procedures) = self.procedures.get() {
+            return procedures.clone();
+        }
+        match self.load_procedures() {
+            Some(procedures) => self.procedures.get_or_init(|| procedures).clone(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn get_procedures_of(&self, procedure_type: ProcedureType) -> Vec<Arc<Procedure>> {
+        self.get_procedures()
+            .into_iter()
+            .filter(|p| p.get_type() == procedure_type)
+            .collect()
+    }
+
+    fn load_procedures(&self) -> Option<Vec<Arc<Procedure>>> {
+        let pref = crate::preference::manager();
+        let dir = pref.get::<String>(crate::preference::CIFP_PATH)?;
+        let dir = Path::new(&dir);
+        if !dir.is_dir() {
+            return None;
+        }
+        // Most airports have no published procedures, so a missing file is not an error
+        let Ok(input) = fs::File::open(dir.join(format!("{}.dat", self.id))) else {
+            return Some(Vec::new());
+        };
+        let mut reader = BufReader::new(input);
+        match CifpParser::new().load_procedures(self, get_earth_model(), &mut reader) {
+            Ok(procedures) => Some(procedures.into_iter().map(Arc::new).collect()),
+            Err(e) => {
+                warn!("{}", e);
+                None
+            }
+        }
+    }
+
     pub fn get_max_runway_length(&self) -> i64 {
         self.max_runway_length
     }
@@ -366,9 +540,15 @@ impl Default for Airport {
             runways: Arc::new(RwLock::new(Vec::new())),
             show_default_buildings: false,
             taxiways: Arc::new(RwLock::new(Vec::new())),
+            frequencies: Arc::new(RwLock::new(Vec::new())),
+            start_locations: Arc::new(RwLock::new(Vec::new())),
+            beacons: Arc::new(RwLock::new(Vec::new())),
+            windsocks: Arc::new(RwLock::new(Vec::new())),
+            procedures: Arc::new(OnceLock::new()),
             max_runway_length: 0,
             airport_type: None,
             name: "".to_string(),
+            metadata: AirportMetadata::default(),
         }
     }
 }
@@ -385,6 +565,8 @@ pub struct Runway {
     centre_line_lights: bool,
     surface: String,
     edge_lights: String,
+    displaced_thresholds: [i32; 2],
+    overruns: [i32; 2],
 }
 
 impl Runway {
@@ -413,9 +595,32 @@ impl Runway {
             centre_line_lights,
             surface,
             edge_lights,
+            displaced_thresholds: [0; 2],
+            overruns: [0; 2],
         }
     }
 
+    /// Set the displaced thresholds and overruns in feet, for this end then the opposite end
+    pub fn set_end_distances(&mut self, displaced_thresholds: [i32; 2], overruns: [i32; 2]) {
+        self.displaced_thresholds = displaced_thresholds;
+        self.overruns = overruns;
+    }
+
+    // The index of the end with the given number
+    fn end_index(&self, number: &str) -> Option<usize> {
+        self.ends().iter().position(|n| n == number)
+    }
+
+    /// How far in feet the landing threshold is displaced from the end of the runway
+    pub fn displaced_threshold_of(&self, number: &str) -> Option<i32> {
+        self.end_index(number).map(|i| self.displaced_thresholds[i])
+    }
+
+    /// The length in feet of the overrun, or blast pad, before the end of the runway
+    pub fn overrun_of(&self, number: &str) -> Option<i32> {
+        self.end_index(number).map(|i| self.overruns[i])
+    }
+
     pub fn centre_line_lights(&self) -> bool {
         self.centre_line_lights
     }
@@ -452,7 +657,7 @@ impl Runway {
         &self.number
     }
 
-    pub(crate) fn opposite_number(&self) -> Option<String> {
+    pub fn opposite_number(&self) -> Option<String> {
         match self.runway_type() {
             Some(RunwayType::Runway) | Some(RunwayType::WaterRunway) => {
                 match self.number.as_str() {
@@ -481,6 +686,33 @@ impl Runway {
         }
     }
 
+    /// The numbers of the ends of the runway, a helipad has only one
+    pub fn ends(&self) -> Vec<String> {
+        let mut ends = vec![self.number.clone()];
+        if let Some(opposite) = self.opposite_number() {
+            ends.push(opposite);
+        }
+        ends
+    }
+
+    /// The true heading of a take off or landing on the given end of the runway
+    pub fn heading_of(&self, number: &str) -> Option<f64> {
+        if number == self.number {
+            Some(self.heading)
+        } else if self.opposite_number().is_some_and(|n| n == number) {
+            Some((self.heading + 180.0).rem_euclid(360.0))
+        } else {
+            None
+        }
+    }
+
+    /// The position of the threshold at the given end of the runway
+    pub fn threshold_of(&self, number: &str) -> Option<Coordinate> {
+        let heading = self.heading_of(number)?;
+        let centre = Coordinate::new(self.lat, self.long);
+        Some(centre.coordinate_at(self.length as f64 / 6076.0 / 2.0, (heading + 180.0).rem_euclid(360.0)))
+    }
+
     pub fn number_pair(&self) -> String {
         if let Some(opposite) = self.opposite_number() {
             format!("{}/{}", self.number(), opposite)
@@ -557,6 +789,266 @@ impl LayoutNode {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FrequencyType {
+    Atis,
+    Unicom,
+    Clearance,
+    Ground,
+    Tower,
+    Approach,
+    Departure,
+}
+
+impl FrequencyType {
+    /// The type of a frequency record, 50-56 in 10kHz units or 1050-1056 in kHz
+    pub fn type_for(record_type: &str) -> Option<FrequencyType> {
+        match record_type {
+            "50" | "1050" => Some(FrequencyType::Atis),
+            "51" | "1051" => Some(FrequencyType::Unicom),
+            "52" | "1052" => Some(FrequencyType::Clearance),
+            "53" | "1053" => Some(FrequencyType::Ground),
+            "54" | "1054" => Some(FrequencyType::Tower),
+            "55" | "1055" => Some(FrequencyType::Approach),
+            "56" | "1056" => Some(FrequencyType::Departure),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FrequencyType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            FrequencyType::Atis => "ATIS",
+            FrequencyType::Unicom => "Unicom",
+            FrequencyType::Clearance => "Clearance",
+            FrequencyType::Ground => "Ground",
+            FrequencyType::Tower => "Tower",
+            FrequencyType::Approach => "Approach",
+            FrequencyType::Departure => "Departure",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frequency {
+    frequency_type: FrequencyType,
+    frequency: f64,
+    name: String,
+}
+
+impl Frequency {
+    pub fn new(frequency_type: FrequencyType, frequency: f64, name: String) -> Self {
+        Self { frequency_type, frequency, name }
+    }
+
+    pub fn get_type(&self) -> FrequencyType {
+        self.frequency_type
+    }
+
+    /// The frequency in MHz
+    pub fn get_frequency(&self) -> f64 {
+        self.frequency
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StartLocationType {
+    Gate,
+    Hangar,
+    TieDown,
+    Misc,
+}
+
+impl StartLocationType {
+    pub fn type_for(location_type: &str) -> StartLocationType {
+        match location_type {
+            "gate" => StartLocationType::Gate,
+            "hangar" => StartLocationType::Hangar,
+            "tie-down" | "tie_down" => StartLocationType::TieDown,
+            _ => StartLocationType::Misc,
+        }
+    }
+}
+
+impl fmt::Display for StartLocationType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            StartLocationType::Gate => "Gate",
+            StartLocationType::Hangar => "Hangar",
+            StartLocationType::TieDown => "Tie down",
+            StartLocationType::Misc => "Ramp",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A gate, hangar, tie down or other place an aircraft can start from
+#[derive(Clone, Debug, PartialEq)]
+pub struct StartLocation {
+    location_type: StartLocationType,
+    lat: f64,
+    long: f64,
+    heading: f64,
+    aircraft: String,
+    name: String,
+}
+
+impl StartLocation {
+    pub fn new(location_type: StartLocationType, lat: f64, long: f64, heading: f64, aircraft: String, name: String) -> Self {
+        Self { location_type, lat, long, heading, aircraft, name }
+    }
+
+    pub fn get_type(&self) -> StartLocationType {
+        self.location_type
+    }
+
+    pub fn get_lat(&self) -> f64 {
+        self.lat
+    }
+
+    pub fn get_long(&self) -> f64 {
+        self.long
+    }
+
+    pub fn get_heading(&self) -> f64 {
+        self.heading
+    }
+
+    /// The types of aircraft that can use it, e.g. "heavy|jets", blank for any
+    pub fn get_aircraft(&self) -> &str {
+        &self.aircraft
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Beacon {
+    lat: f64,
+    long: f64,
+    beacon_type: i32,
+    name: String,
+}
+
+impl Beacon {
+    pub fn new(lat: f64, long: f64, beacon_type: i32, name: String) -> Self {
+        Self { lat, long, beacon_type, name }
+    }
+
+    pub fn get_lat(&self) -> f64 {
+        self.lat
+    }
+
+    pub fn get_long(&self) -> f64 {
+        self.long
+    }
+
+    /// 1 for an airport, 2 a seaplane base, 3 a heliport and 4 a military field
+    pub fn get_type(&self) -> i32 {
+        self.beacon_type
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Windsock {
+    lat: f64,
+    long: f64,
+    lit: bool,
+    name: String,
+}
+
+impl Windsock {
+    pub fn new(lat: f64, long: f64, lit: bool, name: String) -> Self {
+        Self { lat, long, lit, name }
+    }
+
+    pub fn get_lat(&self) -> f64 {
+        self.lat
+    }
+
+    pub fn get_long(&self) -> f64 {
+        self.long
+    }
+
+    pub fn is_lit(&self) -> bool {
+        self.lit
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// The 1302 metadata records of an airport
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AirportMetadata {
+    city: Option<String>,
+    state: Option<String>,
+    country: Option<String>,
+    icao_code: Option<String>,
+    iata_code: Option<String>,
+    faa_code: Option<String>,
+    region_code: Option<String>,
+}
+
+impl AirportMetadata {
+    /// Set one of the values we keep, the others are ignored
+    pub fn set(&mut self, key: &str, value: &str) {
+        let value = value.trim();
+        let value = if value.is_empty() { None } else { Some(value.to_string()) };
+        match key {
+            "city" => self.city = value,
+            "state" => self.state = value,
+            "country" => self.country = value,
+            "icao_code" => self.icao_code = value,
+            "iata_code" => self.iata_code = value,
+            "faa_code" => self.faa_code = value,
+            "region_code" => self.region_code = value,
+            _ => (),
+        }
+    }
+
+    pub fn get_city(&self) -> Option<&str> {
+        self.city.as_deref()
+    }
+
+    pub fn get_state(&self) -> Option<&str> {
+        self.state.as_deref()
+    }
+
+    pub fn get_country(&self) -> Option<&str> {
+        self.country.as_deref()
+    }
+
+    pub fn get_icao_code(&self) -> Option<&str> {
+        self.icao_code.as_deref()
+    }
+
+    pub fn get_iata_code(&self) -> Option<&str> {
+        self.iata_code.as_deref()
+    }
+
+    pub fn get_faa_code(&self) -> Option<&str> {
+        self.faa_code.as_deref()
+    }
+
+    pub fn get_region_code(&self) -> Option<&str> {
+        self.region_code.as_deref()
+    }
+}
+
 #[derive(Clone, PartialEq)]
 pub enum AirportType {
     Airport,
@@ -598,3 +1090,43 @@ impl RunwayType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::model::test_utils::tests::make_airport_at;
+    use crate::model::wind::Wind;
+
+    use super::{Runway, RunwayType};
+
+    fn make_runway(number: &str, heading: f64, length: i32) -> Runway {
+        Runway::new(number.to_string(), Some(RunwayType::Runway), -33.95, 151.18, length, 150, heading,
+                    false, "1".to_string(), "2".to_string())
+    }
+
+    #[test]
+    fn test_runway_ends() {
+        let runway = make_runway("16R", 160.0, 12000);
+        assert_eq!(runway.ends(), vec!["16R".to_string(), "34L".to_string()]);
+        assert_eq!(runway.heading_of("34L"), Some(340.0));
+        assert_eq!(runway.heading_of("07"), None);
+
+        // The threshold for 16R is at the northern end, half the length back from the centre
+        let threshold = runway.threshold_of("16R").unwrap();
+        assert!(threshold.get_latitude() > runway.lat());
+        assert_eq!((threshold.distance_to(&runway.threshold_of("34L").unwrap()) * 6076.0).round(), 12000.0);
+    }
+
+    #[test]
+    fn test_select_runway() {
+        let airport = make_airport_at("YSSY", -33.95, 151.18);
+        airport.add_runway(make_runway("16R", 160.0, 12000));
+        airport.add_runway(make_runway("07", 70.0, 8000));
+
+        assert_eq!(airport.select_runway(&Wind::new(340.0, 15.0)), Some("34L".to_string()));
+        assert_eq!(airport.select_runway(&Wind::new(80.0, 15.0)), Some("07".to_string()));
+        // A little more headwind on the shorter runway isn't worth giving up the length for
+        assert_eq!(airport.select_runway(&Wind::new(113.0, 15.0)), Some("16R".to_string()));
+        assert_eq!(airport.select_runway(&Wind::default()), Some("16R".to_string()));
+        assert_eq!(airport.get_runway_for("25").map(|r| r.number().to_string()), Some("07".to_string()));
+    }
+}
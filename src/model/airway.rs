@@ -0,0 +1,366 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use crate::earth::coordinate::Coordinate;
+
+/// A single published segment of an airway as read from awy.dat.
+/// Where one line of the file names several airways (e.g. "J13-J14"), the parser
+/// produces one segment for each airway.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AirwaySegment {
+    from_id: String,
+    from: Coordinate,
+    to_id: String,
+    to: Coordinate,
+    name: String,
+    high: bool,
+    base: i32,
+    top: i32,
+    one_way: bool,
+}
+
+impl AirwaySegment {
+    /// The ends of the segment are each given as an ident and position.
+    pub fn new(from: (String, Coordinate), to: (String, Coordinate), name: String,
+               high: bool, base: i32, top: i32, one_way: bool) -> Self {
+        let (from_id, from) = from;
+        let (to_id, to) = to;
+        Self {
+            from_id,
+            from,
+            to_id,
+            to,
+            name,
+            high,
+            base,
+            top,
+            one_way,
+        }
+    }
+
+    pub fn get_from_id(&self) -> &str {
+        &self.from_id
+    }
+
+    pub fn get_from(&self) -> &Coordinate {
+        &self.from
+    }
+
+    pub fn get_to_id(&self) -> &str {
+        &self.to_id
+    }
+
+    pub fn get_to(&self) -> &Coordinate {
+        &self.to
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_high(&self) -> bool {
+        self.high
+    }
+
+    /// Base of the airway in flight levels
+    pub fn get_base(&self) -> &i32 {
+        &self.base
+    }
+
+    /// Top of the airway in flight levels
+    pub fn get_top(&self) -> &i32 {
+        &self.top
+    }
+
+    pub fn is_one_way(&self) -> bool {
+        self.one_way
+    }
+}
+
+/// The airway a waypoint was reached by, together with the fixes where the route
+/// joins and leaves that airway.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AirwayLeg {
+    airway: String,
+    entry: String,
+    exit: String,
+}
+
+impl AirwayLeg {
+    pub fn new(airway: String, entry: String, exit: String) -> Self {
+        Self {
+            airway,
+            entry,
+            exit,
+        }
+    }
+
+    pub fn get_airway(&self) -> &str {
+        &self.airway
+    }
+
+    pub fn get_entry(&self) -> &str {
+        &self.entry
+    }
+
+    pub fn get_exit(&self) -> &str {
+        &self.exit
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AirwayEdge {
+    to: usize,
+    airway: Arc<str>,
+    high: bool,
+    distance: f64,
+}
+
+impl AirwayEdge {
+    /// Index of the node at the far end of this edge
+    pub fn get_to(&self) -> usize {
+        self.to
+    }
+
+    pub fn get_airway(&self) -> &str {
+        &self.airway
+    }
+
+    pub fn is_high(&self) -> bool {
+        self.high
+    }
+
+    pub fn get_distance(&self) -> f64 {
+        self.distance
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AirwayNode {
+    id: String,
+    coordinate: Coordinate,
+    edges: Vec<AirwayEdge>,
+}
+
+impl AirwayNode {
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn get_loc(&self) -> &Coordinate {
+        &self.coordinate
+    }
+
+    pub fn get_edges(&self) -> &Vec<AirwayEdge> {
+        &self.edges
+    }
+}
+
+/// Directed graph of the airway network.
+/// Nodes are the fixes and navaids that airways are published between, identified by
+/// their ident and position, so that two different fixes sharing an ident stay separate.
+#[derive(Default)]
+pub struct AirwayGraph {
+    nodes: Vec<AirwayNode>,
+    segment_count: usize,
+}
+
+impl AirwayGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_segments(segments: &[AirwaySegment]) -> Self {
+        let mut graph = Self::new();
+        let mut node_index: HashMap<(String, i64, i64), usize> = HashMap::new();
+        let mut names: HashMap<String, Arc<str>> = HashMap::new();
+
+        for segment in segments {
+            let from = graph.node_for(&mut node_index, &segment.from_id, &segment.from);
+            let to = graph.node_for(&mut node_index, &segment.to_id, &segment.to);
+            if from == to {
+                continue;
+            }
+            let airway = names
+                .entry(segment.name.clone())
+                .or_insert_with(|| Arc::from(segment.name.as_str()))
+                .clone();
+            let distance = segment.from.distance_to(&segment.to);
+
+            graph.nodes[from].edges.push(AirwayEdge {
+                to,
+                airway: airway.clone(),
+                high: segment.high,
+                distance,
+            });
+            if !segment.one_way {
+                graph.nodes[to].edges.push(AirwayEdge {
+                    to: from,
+                    airway,
+                    high: segment.high,
+                    distance,
+                });
+            }
+            graph.segment_count += 1;
+        }
+        graph
+    }
+
+    fn node_for(&mut self, node_index: &mut HashMap<(String, i64, i64), usize>, id: &str, coordinate: &Coordinate) -> usize {
+        // Positions are rounded to about 10m so the same fix read from different lines matches
+        let key = (
+            id.to_string(),
+            (coordinate.get_latitude() * 10000.0).round() as i64,
+            (coordinate.get_longitude() * 10000.0).round() as i64,
+        );
+        *node_index.entry(key).or_insert_with(|| {
+            self.nodes.push(AirwayNode {
+                id: id.to_string(),
+                coordinate: coordinate.clone(),
+                edges: Vec::new(),
+            });
+            self.nodes.len() - 1
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn get_nodes(&self) -> &Vec<AirwayNode> {
+        &self.nodes
+    }
+
+    pub fn get_node(&self, index: usize) -> Option<&AirwayNode> {
+        self.nodes.get(index)
+    }
+
+    pub fn get_segment_count(&self) -> usize {
+        self.segment_count
+    }
+
+    /// Indexes of all nodes within `range` nautical miles of `coord`.
+    pub fn get_nodes_near(&self, coord: &Coordinate, range: f64) -> Vec<usize> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.coordinate.distance_to(coord) <= range)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// The nodes passed following the named airway from node `from` to the first node with ident `to_id`.
+    /// The path doesn't include `from`, and is `None` if the airway doesn't get there.
+    pub fn get_route_along(&self, airway: &str, from: usize, to_id: &str) -> Option<Vec<usize>> {
+        let mut parents: HashMap<usize, usize> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(idx) = queue.pop_front() {
+            if idx != from && self.nodes[idx].id == to_id {
+                let mut path = vec![idx];
+                let mut curr = idx;
+                while let Some(parent) = parents.get(&curr).filter(|p| **p != from) {
+                    path.push(*parent);
+                    curr = *parent;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for edge in self.nodes[idx].edges.iter().filter(|e| &*e.airway == airway) {
+                if edge.to != from && !parents.contains_key(&edge.to) {
+                    parents.insert(edge.to, idx);
+                    queue.push_back(edge.to);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::earth::coordinate::Coordinate;
+
+    use super::{AirwayGraph, AirwaySegment};
+
+    fn segment(from: (&str, f64, f64), to: (&str, f64, f64), name: &str, one_way: bool) -> AirwaySegment {
+        AirwaySegment::new(
+            (from.0.to_string(), Coordinate::new(from.1, from.2)),
+            (to.0.to_string(), Coordinate::new(to.1, to.2)),
+            name.to_string(), false, 50, 245, one_way)
+    }
+
+    #[test]
+    fn test_graph_shares_nodes() {
+        let segments = vec![
+            segment(("AAA", -33.0, 150.0), ("BBB", -33.0, 149.0), "H1", false),
+            segment(("BBB", -33.0, 149.0), ("CCC", -33.0, 148.0), "H1", false),
+            segment(("BBB", -33.0, 149.0), ("DDD", -32.0, 149.0), "J2", true),
+        ];
+        let graph = AirwayGraph::from_segments(&segments);
+
+        assert_eq!(graph.get_nodes().len(), 4);
+        assert_eq!(graph.get_segment_count(), 3);
+
+        let bbb = graph.get_nodes().iter().position(|n| n.get_id() == "BBB").unwrap();
+        assert_eq!(graph.get_nodes()[bbb].get_edges().len(), 3);
+
+        // One way segments have no edge back
+        let ddd = graph.get_nodes().iter().position(|n| n.get_id() == "DDD").unwrap();
+        assert!(graph.get_nodes()[ddd].get_edges().is_empty());
+    }
+
+    #[test]
+    fn test_route_along() {
+        let segments = vec![
+            segment(("AAA", -33.0, 150.0), ("BBB", -33.0, 149.0), "H1", false),
+            segment(("BBB", -33.0, 149.0), ("CCC", -33.0, 148.0), "H1", false),
+            segment(("CCC", -33.0, 148.0), ("DDD", -33.0, 147.0), "H1", true),
+            segment(("BBB", -33.0, 149.0), ("EEE", -32.0, 149.0), "J2", false),
+        ];
+        let graph = AirwayGraph::from_segments(&segments);
+        let index = |id: &str| graph.get_nodes().iter().position(|n| n.get_id() == id).unwrap();
+        let ids = |path: Vec<usize>| path.iter().map(|i| graph.get_nodes()[*i].get_id().to_string()).collect::<Vec<_>>();
+
+        assert_eq!(ids(graph.get_route_along("H1", index("AAA"), "DDD").unwrap()), vec!["BBB", "CCC", "DDD"]);
+        assert_eq!(ids(graph.get_route_along("H1", index("CCC"), "AAA").unwrap()), vec!["BBB", "AAA"]);
+        // Not against a one way segment, and not onto another airway
+        assert!(graph.get_route_along("H1", index("DDD"), "CCC").is_none());
+        assert!(graph.get_route_along("H1", index("AAA"), "EEE").is_none());
+    }
+
+    #[test]
+    fn test_same_ident_different_place() {
+        let segments = vec![
+            segment(("AAA", -33.0, 150.0), ("BBB", -33.0, 149.0), "H1", false),
+            segment(("AAA", 40.0, -100.0), ("CCC", 40.0, -101.0), "V3", false),
+        ];
+        let graph = AirwayGraph::from_segments(&segments);
+
+        assert_eq!(graph.get_nodes().len(), 4);
+        assert_eq!(graph.get_nodes_near(&Coordinate::new(-33.0, 150.1), 10.0).len(), 1);
+    }
+}
@@ -0,0 +1,118 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+/// An altitude restriction in feet at a waypoint, as published on a procedure.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AltitudeConstraint {
+    At(i32),
+    AtOrAbove(i32),
+    AtOrBelow(i32),
+    /// Lower and upper limits
+    Between(i32, i32),
+}
+
+impl AltitudeConstraint {
+    /// Whether an altitude satisfies the constraint
+    pub fn allows(&self, altitude: i32) -> bool {
+        match *self {
+            AltitudeConstraint::At(a) => altitude == a,
+            AltitudeConstraint::AtOrAbove(a) => altitude >= a,
+            AltitudeConstraint::AtOrBelow(a) => altitude <= a,
+            AltitudeConstraint::Between(lower, upper) => altitude >= lower && altitude <= upper,
+        }
+    }
+
+    /// The altitude nearest to the one given that satisfies the constraint
+    pub fn limit(&self, altitude: i32) -> i32 {
+        match *self {
+            AltitudeConstraint::At(a) => a,
+            AltitudeConstraint::AtOrAbove(a) => altitude.max(a),
+            AltitudeConstraint::AtOrBelow(a) => altitude.min(a),
+            AltitudeConstraint::Between(lower, upper) => altitude.clamp(lower, upper),
+        }
+    }
+}
+
+/// A speed restriction in knots at a waypoint.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpeedConstraint {
+    At(i32),
+    AtOrAbove(i32),
+    AtOrBelow(i32),
+}
+
+impl SpeedConstraint {
+    pub fn get_speed(&self) -> i32 {
+        match *self {
+            SpeedConstraint::At(s) | SpeedConstraint::AtOrAbove(s) | SpeedConstraint::AtOrBelow(s) => s,
+        }
+    }
+}
+
+/// The altitude and speed constraints at a waypoint, from a procedure or set by the user.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Constraints {
+    altitude: Option<AltitudeConstraint>,
+    speed: Option<SpeedConstraint>,
+}
+
+impl Constraints {
+    pub fn new(altitude: Option<AltitudeConstraint>, speed: Option<SpeedConstraint>) -> Self {
+        Self { altitude, speed }
+    }
+
+    pub fn get_altitude(&self) -> Option<AltitudeConstraint> {
+        self.altitude
+    }
+
+    pub fn get_speed(&self) -> Option<SpeedConstraint> {
+        self.speed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.altitude.is_none() && self.speed.is_none()
+    }
+
+    /// The altitude nearest to the one given that meets the altitude constraint, if there is one
+    pub fn limit_altitude(&self, altitude: i32) -> i32 {
+        self.altitude.map_or(altitude, |a| a.limit(altitude))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AltitudeConstraint;
+
+    #[test]
+    fn test_altitude_limit() {
+        assert_eq!(AltitudeConstraint::At(5000).limit(8000), 5000);
+        assert_eq!(AltitudeConstraint::AtOrAbove(5000).limit(3000), 5000);
+        assert_eq!(AltitudeConstraint::AtOrAbove(5000).limit(8000), 8000);
+        assert_eq!(AltitudeConstraint::AtOrBelow(5000).limit(8000), 5000);
+        assert_eq!(AltitudeConstraint::Between(4000, 6000).limit(8000), 6000);
+        assert_eq!(AltitudeConstraint::Between(4000, 6000).limit(3000), 4000);
+        assert!(AltitudeConstraint::Between(4000, 6000).allows(5000));
+        assert!(!AltitudeConstraint::AtOrBelow(4000).allows(5000));
+    }
+}
@@ -0,0 +1,281 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::earth::FEET_PER_METRE;
+use crate::preference::CRUISE_LEVEL_REGIONS;
+
+/// Regions whose cruise levels don't follow the ICAO rule, by the start of their airport idents.
+/// France, Italy and Portugal fly odd levels on magnetic tracks from 090 to 269, New Zealand from 270 to 089.
+pub const DEFAULT_CRUISE_LEVEL_REGIONS: &str = "LF=090,LI=090,LP=090,NZ=270";
+
+/// The flight rules a plan is flown under, which decide the cruise levels that can be used.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FlightRules {
+    #[default]
+    Ifr,
+    Vfr,
+}
+
+impl fmt::Display for FlightRules {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FlightRules::Ifr => write!(f, "IFR"),
+            FlightRules::Vfr => write!(f, "VFR"),
+        }
+    }
+}
+
+impl FromStr for FlightRules {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_uppercase().as_str() {
+            "IFR" => Ok(FlightRules::Ifr),
+            "VFR" => Ok(FlightRules::Vfr),
+            _ => Err(format!("Flight rules \"{}\" should be IFR or VFR", s)),
+        }
+    }
+}
+
+/// The hemispheric, or semicircular, rule for cruise levels.
+/// Odd thousands of feet are flown on magnetic tracks in the half circle starting at `odd_from`,
+/// even thousands on the others. VFR flights add 500ft and keep below FL290.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LevelRule {
+    odd_from: i32,
+}
+
+impl LevelRule {
+    pub fn new(odd_from: i32) -> Self {
+        Self { odd_from: odd_from.rem_euclid(360) }
+    }
+
+    /// The ICAO rule, odd levels eastbound from 000 to 179
+    pub fn icao() -> Self {
+        Self::new(0)
+    }
+
+    pub fn get_odd_from(&self) -> i32 {
+        self.odd_from
+    }
+
+    pub fn is_odd(&self, magnetic_track: f64) -> bool {
+        (magnetic_track - self.odd_from as f64).rem_euclid(360.0) < 180.0
+    }
+
+    /// The cruise levels in feet for a magnetic track, lowest first
+    pub fn get_levels(&self, magnetic_track: f64, flight_rules: FlightRules) -> Vec<i32> {
+        let odd = self.is_odd(magnetic_track);
+        let mut levels: Vec<i32> = (1..=41).filter(|l| (l % 2 == 1) == odd).map(|l| l * 1000).collect();
+        // Above FL410 the levels are 4000ft apart
+        let above = if odd { 45000 } else { 43000 };
+        levels.extend((0..5).map(|i| above + i * 4000));
+        match flight_rules {
+            FlightRules::Ifr => levels,
+            FlightRules::Vfr => levels.into_iter()
+                .filter(|l| (3000..29000).contains(l))
+                .map(|l| l + 500)
+                .collect(),
+        }
+    }
+
+    pub fn allows(&self, altitude: i32, magnetic_track: f64, flight_rules: FlightRules) -> bool {
+        self.get_levels(magnetic_track, flight_rules).contains(&altitude)
+    }
+
+    /// The highest cruise level at or below the altitude
+    pub fn level_at_or_below(&self, altitude: i32, magnetic_track: f64, flight_rules: FlightRules) -> Option<i32> {
+        self.get_levels(magnetic_track, flight_rules).into_iter().rev().find(|l| *l <= altitude)
+    }
+
+    /// The lowest cruise level at or above the altitude
+    pub fn level_at_or_above(&self, altitude: i32, magnetic_track: f64, flight_rules: FlightRules) -> Option<i32> {
+        self.get_levels(magnetic_track, flight_rules).into_iter().find(|l| *l >= altitude)
+    }
+}
+
+/// The cruise level rule in each region that differs from ICAO, a region being known by the
+/// start of the idents of its airports, e.g. "LF=090,NZ=270".
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LevelRegions {
+    regions: Vec<(String, LevelRule)>,
+}
+
+impl LevelRegions {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut regions = Vec::new();
+        for region in text.split(',').map(|r| r.trim()).filter(|r| !r.is_empty()) {
+            let (prefix, odd_from) = region.split_once('=')
+                .ok_or(format!("Region \"{}\" should be prefix=track", region))?;
+            let odd_from = odd_from.trim().parse::<i32>()
+                .map_err(|_| format!("Invalid track in region \"{}\"", region))?;
+            regions.push((prefix.trim().to_uppercase(), LevelRule::new(odd_from)));
+        }
+        Ok(Self { regions })
+    }
+
+    /// The regions set in the preferences, or the defaults if there are none
+    pub fn from_preferences() -> Self {
+        let pref = crate::preference::manager();
+        let text = pref.get::<String>(CRUISE_LEVEL_REGIONS).unwrap_or(DEFAULT_CRUISE_LEVEL_REGIONS.to_string());
+        Self::parse(&text).unwrap_or_else(|_| Self::parse(DEFAULT_CRUISE_LEVEL_REGIONS).unwrap_or_default())
+    }
+
+    /// The rule for the region of an airport, the longest matching prefix wins
+    pub fn get_rule(&self, airport_id: &str) -> LevelRule {
+        self.regions.iter()
+            .filter(|(prefix, _)| airport_id.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(LevelRule::icao(), |(_, rule)| *rule)
+    }
+}
+
+impl fmt::Display for LevelRegions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let regions: Vec<String> = self.regions.iter()
+            .map(|(prefix, rule)| format!("{}={:03}", prefix, rule.get_odd_from()))
+            .collect();
+        write!(f, "{}", regions.join(","))
+    }
+}
+
+/// A climb to a higher cruise level once a distance in nautical miles along the sector has been flown.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StepClimb {
+    distance: f64,
+    level: i32,
+}
+
+impl StepClimb {
+    pub fn new(distance: f64, level: i32) -> Self {
+        Self { distance, level }
+    }
+
+    pub fn get_distance(&self) -> f64 {
+        self.distance
+    }
+
+    pub fn get_level(&self) -> i32 {
+        self.level
+    }
+
+    /// Parse step climbs such as "300/FL370 800/39000", distances in nautical miles.
+    pub fn parse_all(text: &str) -> Result<Vec<Self>, String> {
+        let mut steps = Vec::new();
+        for step in text.split_whitespace() {
+            let (distance, level) = step.split_once('/')
+                .ok_or(format!("Step climb \"{}\" should be distance/level", step))?;
+            let distance = distance.parse::<f64>()
+                .map_err(|_| format!("Invalid distance in step climb \"{}\"", step))?;
+            let level = parse_level(level).ok_or(format!("Invalid level in step climb \"{}\"", step))?;
+            steps.push(StepClimb::new(distance, level));
+        }
+        steps.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        Ok(steps)
+    }
+
+    pub fn format_all(steps: &[Self]) -> String {
+        steps.iter().map(|s| s.to_string()).collect::<Vec<String>>().join(" ")
+    }
+}
+
+impl fmt::Display for StepClimb {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.0}/FL{:03}", self.distance, self.level / 100)
+    }
+}
+
+/// A level in feet, e.g. 9000, or as a flight level, e.g. FL350.
+/// The ICAO flight plan forms are read too, in hundreds of feet, e.g. F350 or A045,
+/// or in tens of metres, e.g. S1130 or M0840.
+pub fn parse_level(text: &str) -> Option<i32> {
+    let text = text.trim().to_uppercase();
+    if let Some(level) = text.strip_prefix("FL").or_else(|| text.strip_prefix('F')).or_else(|| text.strip_prefix('A')) {
+        return level.parse::<i32>().ok().map(|l| l * 100);
+    }
+    if let Some(level) = text.strip_prefix('S').or_else(|| text.strip_prefix('M')) {
+        return level.parse::<f64>().ok().map(|l| (l * 10.0 * FEET_PER_METRE).round() as i32);
+    }
+    text.parse::<i32>().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_level, FlightRules, LevelRegions, LevelRule, StepClimb};
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(parse_level("9000"), Some(9000));
+        assert_eq!(parse_level("05000"), Some(5000));
+        assert_eq!(parse_level("fl350"), Some(35000));
+        assert_eq!(parse_level("F350"), Some(35000));
+        assert_eq!(parse_level("A045"), Some(4500));
+        assert_eq!(parse_level("S1130"), Some(37073));
+        assert_eq!(parse_level("VFR"), None);
+        assert_eq!(parse_level("high"), None);
+    }
+
+    #[test]
+    fn test_levels() {
+        let icao = LevelRule::icao();
+        assert!(icao.allows(35000, 90.0, FlightRules::Ifr));
+        assert!(!icao.allows(36000, 90.0, FlightRules::Ifr));
+        assert!(icao.allows(36000, 270.0, FlightRules::Ifr));
+        assert!(icao.allows(43000, 270.0, FlightRules::Ifr));
+        assert!(!icao.allows(43000, 90.0, FlightRules::Ifr));
+        assert!(icao.allows(5500, 179.0, FlightRules::Vfr));
+        assert!(icao.allows(6500, 180.0, FlightRules::Vfr));
+        assert_eq!(icao.level_at_or_below(12000, 90.0, FlightRules::Ifr), Some(11000));
+        assert_eq!(icao.level_at_or_below(12000, 270.0, FlightRules::Vfr), Some(10500));
+        assert_eq!(icao.level_at_or_above(12100, 270.0, FlightRules::Ifr), Some(14000));
+
+        // Odd levels southbound
+        let north_south = LevelRule::new(90);
+        assert!(north_south.allows(35000, 180.0, FlightRules::Ifr));
+        assert!(north_south.allows(36000, 45.0, FlightRules::Ifr));
+    }
+
+    #[test]
+    fn test_regions() {
+        let regions = LevelRegions::parse("LF=090, NZ=270,NZAA=0").unwrap();
+        assert_eq!(regions.get_rule("LFPG"), LevelRule::new(90));
+        assert_eq!(regions.get_rule("NZWN"), LevelRule::new(270));
+        assert_eq!(regions.get_rule("NZAA"), LevelRule::icao());
+        assert_eq!(regions.get_rule("YSSY"), LevelRule::icao());
+        assert_eq!(regions.to_string(), "LF=090,NZ=270,NZAA=000");
+        assert!(LevelRegions::parse("LF").is_err());
+    }
+
+    #[test]
+    fn test_step_climbs() {
+        let steps = StepClimb::parse_all("800/39000 300/fl370").unwrap();
+        assert_eq!(steps, vec![StepClimb::new(300.0, 37000), StepClimb::new(800.0, 39000)]);
+        assert_eq!(StepClimb::format_all(&steps), "300/FL370 800/FL390");
+        assert!(StepClimb::parse_all("300").is_err());
+        assert!(StepClimb::parse_all("300/high").is_err());
+    }
+}
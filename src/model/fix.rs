@@ -22,6 +22,8 @@
  *
  */
 use std::any::Any;
+use std::sync::Arc;
+
 use crate::earth::coordinate::Coordinate;
 
 use super::location::Location;
@@ -30,6 +32,8 @@ use super::location::Location;
 pub struct Fix {
     id: String,
     coordinate: Coordinate,
+    region: Option<String>,
+    terminal_area: Option<String>,
 }
 
 impl Fix {
@@ -37,7 +41,50 @@ impl Fix {
         Self {
             id,
             coordinate: Coordinate::new(latitude, longitude),
+            region: None,
+            terminal_area: None,
+        }
+    }
+
+    /// The ICAO region, e.g. K2. Idents are only unique within a region.
+    pub fn get_region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    /// The airport whose terminal area the fix belongs to, none for an enroute fix
+    pub fn get_terminal_area(&self) -> Option<&str> {
+        self.terminal_area.as_deref()
+    }
+
+    pub fn set_region(&mut self, region: Option<String>) {
+        self.region = region;
+    }
+
+    pub fn set_terminal_area(&mut self, terminal_area: Option<String>) {
+        self.terminal_area = terminal_area;
+    }
+}
+
+/// Keep only the candidates in the region, unless none of them are. Older data has no regions.
+pub fn in_region<T, F>(candidates: Vec<T>, region: Option<&str>, region_of: F) -> Vec<T>
+where
+    F: Fn(&T) -> Option<&str>,
+{
+    match region {
+        Some(region) if candidates.iter().any(|c| region_of(c) == Some(region)) => {
+            candidates.into_iter().filter(|c| region_of(c) == Some(region)).collect()
+        }
+        _ => candidates,
+    }
+}
+
+/// Keep only the fixes in the terminal area, unless none of them are. Enroute fixes have none.
+pub fn in_terminal_area(candidates: Vec<Arc<Fix>>, terminal_area: Option<&str>) -> Vec<Arc<Fix>> {
+    match terminal_area {
+        Some(area) if candidates.iter().any(|f| f.get_terminal_area() == Some(area)) => {
+            candidates.into_iter().filter(|f| f.get_terminal_area() == Some(area)).collect()
         }
+        _ => candidates,
     }
 }
 
@@ -0,0 +1,139 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+use crate::model::aircraft::AircraftFuel;
+
+/// The fuel needed for a sector, or for a whole plan.
+///
+/// The trip fuel is what is burnt flying the route. On top of that there is the taxi fuel,
+/// the contingency (a percentage of the trip), the fuel to reach an alternate and the final
+/// reserve, the last two at the aircraft's cruise burn.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FuelPlan {
+    taxi: f64,
+    trip: f64,
+    contingency: f64,
+    alternate: f64,
+    reserve: f64,
+    capacity: f64,
+}
+
+impl FuelPlan {
+    pub fn new(fuel: &AircraftFuel, trip: f64) -> Self {
+        Self {
+            taxi: *fuel.get_taxi(),
+            trip,
+            contingency: trip * fuel.get_contingency() / 100.0,
+            alternate: fuel.get_cruise_burn() * fuel.get_alternate_minutes() / 60.0,
+            reserve: fuel.get_cruise_burn() * fuel.get_reserve_minutes() / 60.0,
+            capacity: *fuel.get_capacity(),
+        }
+    }
+
+    pub fn get_taxi(&self) -> f64 {
+        self.taxi
+    }
+
+    pub fn get_trip(&self) -> f64 {
+        self.trip
+    }
+
+    pub fn get_contingency(&self) -> f64 {
+        self.contingency
+    }
+
+    pub fn get_alternate(&self) -> f64 {
+        self.alternate
+    }
+
+    pub fn get_reserve(&self) -> f64 {
+        self.reserve
+    }
+
+    pub fn get_capacity(&self) -> f64 {
+        self.capacity
+    }
+
+    /// The minimum fuel to carry at engine start
+    pub fn get_required(&self) -> f64 {
+        self.taxi + self.trip + self.contingency + self.alternate + self.reserve
+    }
+
+    /// Fuel that can be carried over the minimum, negative when the tanks are too small
+    pub fn get_extra(&self) -> f64 {
+        self.capacity - self.get_required()
+    }
+
+    pub fn exceeds_endurance(&self) -> bool {
+        self.get_required() > self.capacity
+    }
+
+    /// Add the following sector to this one. Taxi, trip and contingency fuel add up,
+    /// while the alternate and reserve are those of the final sector.
+    pub fn add_sector(&mut self, next: &FuelPlan) {
+        self.taxi += next.taxi;
+        self.trip += next.trip;
+        self.contingency += next.contingency;
+        self.alternate = next.alternate;
+        self.reserve = next.reserve;
+        self.capacity = next.capacity;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::aircraft::AircraftFuel;
+
+    use super::FuelPlan;
+
+    fn make_fuel() -> AircraftFuel {
+        AircraftFuel::new(12.0, 10.0, 6.0, 50.0).with_allowances(1.0, 10.0, 30.0, 45.0)
+    }
+
+    #[test]
+    fn test_fuel_plan() {
+        let plan = FuelPlan::new(&make_fuel(), 20.0);
+        assert_eq!(plan.get_taxi(), 1.0);
+        assert_eq!(plan.get_contingency(), 2.0);
+        assert_eq!(plan.get_alternate(), 5.0);
+        assert_eq!(plan.get_reserve(), 7.5);
+        assert_eq!(plan.get_required(), 35.5);
+        assert_eq!(plan.get_extra(), 14.5);
+        assert!(!plan.exceeds_endurance());
+
+        let plan = FuelPlan::new(&make_fuel(), 40.0);
+        assert!(plan.exceeds_endurance());
+    }
+
+    #[test]
+    fn test_add_sector() {
+        let mut total = FuelPlan::new(&make_fuel(), 20.0);
+        total.add_sector(&FuelPlan::new(&make_fuel(), 10.0));
+        assert_eq!(total.get_taxi(), 2.0);
+        assert_eq!(total.get_trip(), 30.0);
+        assert_eq!(total.get_contingency(), 3.0);
+        assert_eq!(total.get_reserve(), 7.5);
+        assert_eq!(total.get_required(), 47.5);
+    }
+}
@@ -25,18 +25,33 @@
 
 pub mod aircraft;
 pub mod airport;
+pub mod airway;
+pub mod constraint;
+pub mod cruise_level;
 pub mod fix;
+pub mod fuel;
 pub mod location;
 pub mod navaid;
 pub mod plan;
+pub mod plan_history;
+pub mod plan_settings;
+pub mod procedure;
 pub mod sector;
 pub mod waypoint;
+pub mod wind;
 
 pub(crate) mod test_utils;
-pub(crate) mod airport_object;
-pub(crate) mod navaid_object;
-pub(crate) mod fix_object;
-pub(crate) mod sector_object;
-pub(crate) mod waypoint_object;
-pub(crate) mod plan_object;
-pub(crate) mod runway_object;
+#[cfg(feature = "gui")]
+pub mod airport_object;
+#[cfg(feature = "gui")]
+pub mod navaid_object;
+#[cfg(feature = "gui")]
+pub mod fix_object;
+#[cfg(feature = "gui")]
+pub mod sector_object;
+#[cfg(feature = "gui")]
+pub mod waypoint_object;
+#[cfg(feature = "gui")]
+pub mod plan_object;
+#[cfg(feature = "gui")]
+pub mod runway_object;
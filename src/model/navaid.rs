@@ -22,6 +22,8 @@
  *
  */
 use std::any::Any;
+use std::fmt;
+
 use crate::earth::coordinate::Coordinate;
 
 use super::location::Location;
@@ -36,6 +38,11 @@ pub struct Navaid {
     freq: f64,
     range: i32,
     mag_variation: String,
+    airport_id: Option<String>,
+    runway: Option<String>,
+    course: Option<f64>,
+    glideslope_angle: Option<f64>,
+    dme_bias: Option<f64>,
 }
 
 impl Navaid {
@@ -60,11 +67,16 @@ impl Navaid {
             freq,
             range,
             mag_variation,
+            airport_id: None,
+            runway: None,
+            course: None,
+            glideslope_angle: None,
+            dme_bias: None,
         }
     }
 
     pub fn get_type(&self) -> NavaidType {
-        self.type_.clone()
+        self.type_
     }
 
     pub fn get_freq(&self) -> &f64 {
@@ -78,6 +90,54 @@ impl Navaid {
     pub fn get_mag_variation(&self) -> String {
         self.mag_variation.clone()
     }
+
+    pub fn set_type(&mut self, type_: NavaidType) {
+        self.type_ = type_;
+    }
+
+    /// The airport for the landing aids, and DMEs paired with them
+    pub fn get_airport_id(&self) -> Option<&str> {
+        self.airport_id.as_deref()
+    }
+
+    pub fn get_runway(&self) -> Option<&str> {
+        self.runway.as_deref()
+    }
+
+    pub fn set_runway(&mut self, airport_id: &str, runway: &str) {
+        self.airport_id = Some(airport_id.to_string());
+        self.runway = Some(runway.to_string());
+    }
+
+    /// The true course of a localizer, glideslope or marker beacon
+    pub fn get_course(&self) -> Option<f64> {
+        self.course
+    }
+
+    pub fn set_course(&mut self, course: f64) {
+        self.course = Some(course);
+    }
+
+    pub fn get_glideslope_angle(&self) -> Option<f64> {
+        self.glideslope_angle
+    }
+
+    pub fn set_glideslope_angle(&mut self, angle: f64) {
+        self.glideslope_angle = Some(angle);
+    }
+
+    /// The bias in nautical miles of the navaid's DME, either its own or one paired with it
+    pub fn get_dme_bias(&self) -> Option<f64> {
+        self.dme_bias
+    }
+
+    pub fn set_dme_bias(&mut self, bias: f64) {
+        self.dme_bias = Some(bias);
+    }
+
+    pub fn has_dme(&self) -> bool {
+        self.dme_bias.is_some() || matches!(self.type_, NavaidType::VorDme | NavaidType::Vortac | NavaidType::Dme | NavaidType::Tacan)
+    }
 }
 
 impl Location for Navaid {
@@ -111,23 +171,76 @@ impl Location for Navaid {
     }
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum NavaidType {
     Vor,
+    VorDme,
+    Vortac,
     Ndb,
     Dme,
+    Tacan,
+    Ils,
+    Localizer,
+    Glideslope,
+    OuterMarker,
+    MiddleMarker,
+    InnerMarker,
 }
 
 impl NavaidType {
-    pub fn type_for(navaid_type: &str) -> Option<NavaidType> {
-        if navaid_type == "0" {
-            Some(NavaidType::Dme)
-        } else if navaid_type == "2" {
-            Some(NavaidType::Ndb)
-        } else if navaid_type == "3" {
-            Some(NavaidType::Vor)
-        } else {
-            None
+    /// The type of a nav.dat record, the name tells a VORTAC or TACAN from a plain VOR or DME.
+    pub fn type_for(record_type: &str, name: &str) -> Option<NavaidType> {
+        let name = name.trim_end().to_uppercase();
+        match record_type {
+            "2" => Some(NavaidType::Ndb),
+            "3" if name.ends_with("VORTAC") => Some(NavaidType::Vortac),
+            "3" if name.ends_with("VOR-DME") || name.ends_with("VOR/DME") => Some(NavaidType::VorDme),
+            "3" => Some(NavaidType::Vor),
+            "4" => Some(NavaidType::Ils),
+            "5" => Some(NavaidType::Localizer),
+            "6" => Some(NavaidType::Glideslope),
+            "7" => Some(NavaidType::OuterMarker),
+            "8" => Some(NavaidType::MiddleMarker),
+            "9" => Some(NavaidType::InnerMarker),
+            "12" | "13" if name.ends_with("TACAN") => Some(NavaidType::Tacan),
+            "12" | "13" => Some(NavaidType::Dme),
+            _ => None,
         }
     }
+
+    /// A VOR, whether or not it has a DME or TACAN with it
+    pub fn is_vor(&self) -> bool {
+        matches!(self, NavaidType::Vor | NavaidType::VorDme | NavaidType::Vortac)
+    }
+
+    /// The beacons a route can be flown between
+    pub fn is_enroute(&self) -> bool {
+        self.is_vor() || *self == NavaidType::Ndb
+    }
+
+    /// The navaids that only serve the approach to a runway
+    pub fn is_landing_aid(&self) -> bool {
+        matches!(self, NavaidType::Ils | NavaidType::Localizer | NavaidType::Glideslope
+            | NavaidType::OuterMarker | NavaidType::MiddleMarker | NavaidType::InnerMarker)
+    }
+}
+
+impl fmt::Display for NavaidType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            NavaidType::Vor => "VOR",
+            NavaidType::VorDme => "VOR-DME",
+            NavaidType::Vortac => "VORTAC",
+            NavaidType::Ndb => "NDB",
+            NavaidType::Dme => "DME",
+            NavaidType::Tacan => "TACAN",
+            NavaidType::Ils => "ILS",
+            NavaidType::Localizer => "LOC",
+            NavaidType::Glideslope => "GS",
+            NavaidType::OuterMarker => "OM",
+            NavaidType::MiddleMarker => "MM",
+            NavaidType::InnerMarker => "IM",
+        };
+        write!(f, "{}", name)
+    }
 }
@@ -26,9 +26,17 @@ use std::path::PathBuf;
 use std::rc::Rc;
 use std::sync::Arc;
 
+use chrono::{DateTime, Utc};
+use gettextrs::gettext;
+
 use crate::earth::coordinate::Coordinate;
 use crate::earth::geomagnetism::Geomagnetism;
+use crate::earth::terrain::{get_clearance, get_terrain};
+use crate::model::cruise_level::{FlightRules, LevelRegions};
+use crate::model::fuel::FuelPlan;
+use crate::model::plan_settings::PlanSettings;
 use crate::model::waypoint::Waypoint;
+use crate::model::wind::{Wind, WindCorrection, WindsAloft};
 use crate::preference::{UNITS, USE_MAGNETIC_HEADINGS};
 use crate::util::distance_format::DistanceFormat;
 use crate::util::hour_format::HourFormat;
@@ -38,6 +46,14 @@ use super::aircraft::Aircraft;
 use super::airport::Airport;
 use super::sector::Sector;
 
+/// The part of a flight a leg is flown in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FlightPhase {
+    Climb,
+    Cruise,
+    Descent,
+}
+
 #[derive(Default)]
 pub struct Plan {
     dirty: bool,
@@ -45,6 +61,13 @@ pub struct Plan {
     sectors: Vec<Rc<RefCell<Sector>>>,
     aircraft: Option<Arc<Aircraft>>,
     max_altitude: Option<i32>,
+    flight_rules: FlightRules,
+    winds: Option<WindsAloft>,
+    created: Option<DateTime<Utc>>,
+    author: String,
+    remarks: String,
+    departure_time: Option<DateTime<Utc>>,
+    settings: Option<PlanSettings>,
 }
 
 impl Plan {
@@ -55,6 +78,13 @@ impl Plan {
             sectors: Vec::with_capacity(2),
             aircraft: None,
             max_altitude: None,
+            flight_rules: FlightRules::Ifr,
+            winds: None,
+            created: Some(Utc::now()),
+            author: String::new(),
+            remarks: String::new(),
+            departure_time: None,
+            settings: None,
         }
     }
 
@@ -119,6 +149,71 @@ impl Plan {
         &self.max_altitude
     }
 
+    /// The flight rules, which decide the cruise levels of the sectors
+    pub fn get_flight_rules(&self) -> FlightRules {
+        self.flight_rules
+    }
+
+    pub fn set_flight_rules(&mut self, flight_rules: FlightRules) {
+        self.flight_rules = flight_rules;
+        self.dirty = true;
+    }
+
+    pub fn get_winds(&self) -> &Option<WindsAloft> {
+        &self.winds
+    }
+
+    pub fn set_winds(&mut self, winds: Option<WindsAloft>) {
+        self.winds = winds;
+        self.dirty = true;
+    }
+
+    /// When the plan was first made, None for plans saved before this was recorded
+    pub fn get_created(&self) -> &Option<DateTime<Utc>> {
+        &self.created
+    }
+
+    pub fn set_created(&mut self, created: Option<DateTime<Utc>>) {
+        self.created = created;
+    }
+
+    pub fn get_author(&self) -> &str {
+        &self.author
+    }
+
+    pub fn set_author(&mut self, author: &str) {
+        self.author = author.to_string();
+        self.dirty = true;
+    }
+
+    pub fn get_remarks(&self) -> &str {
+        &self.remarks
+    }
+
+    pub fn set_remarks(&mut self, remarks: &str) {
+        self.remarks = remarks.to_string();
+        self.dirty = true;
+    }
+
+    pub fn get_departure_time(&self) -> &Option<DateTime<Utc>> {
+        &self.departure_time
+    }
+
+    pub fn set_departure_time(&mut self, departure_time: Option<DateTime<Utc>>) {
+        self.departure_time = departure_time;
+        self.dirty = true;
+    }
+
+    /// The planner settings the plan was last made with
+    pub fn get_settings(&self) -> &Option<PlanSettings> {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: Option<PlanSettings>) {
+        self.settings = settings;
+        self.dirty = true;
+    }
+
     pub fn get_plan_altitude(&self) -> i32 {
         self.max_altitude.unwrap_or_else(|| match &self.aircraft {
             Some(a) => *a.get_cruise_altitude(),
@@ -214,12 +309,47 @@ impl Plan {
 
     //
     //	 Get the coordinate of the waypoint that precedes this one in the plan.
+    //	 Where that is the airport a sector starts from, it is the departure runway threshold if there is one.
     //	 @param loc
     //	 @return previous location
 
     pub fn get_previous_location(&self, wp: &Waypoint) -> Option<Coordinate> {
-        self.get_previous_waypoint(wp)
-            .map(|wp| wp.get_loc().clone())
+        let prev = self.get_previous_waypoint(wp)?;
+        // An airport can start more than one sector, each from its own runway
+        let threshold = self.get_sector_of(wp).and_then(|s| {
+            let s = s.borrow();
+            s.get_start()
+                .filter(|start| compare_wp(start, &prev))
+                .and_then(|_| s.get_departure_threshold())
+        });
+        Some(threshold.unwrap_or_else(|| prev.get_loc().clone()))
+    }
+
+    //
+    //	 Get the coordinate a leg to this waypoint ends at.
+    //	 Where it is the airport a sector ends at, it is the arrival runway threshold if there is one.
+    //	 @param loc
+    //	 @return location
+
+    pub fn get_arrival_location(&self, wp: &Waypoint) -> Coordinate {
+        self.get_sector_of(wp)
+            .and_then(|s| {
+                let s = s.borrow();
+                s.get_end()
+                    .filter(|end| compare_wp(end, wp))
+                    .and_then(|_| s.get_arrival_threshold())
+            })
+            .unwrap_or_else(|| wp.get_loc().clone())
+    }
+
+    // The sector the leg to this waypoint is flown in, searched in the same order as get_previous_waypoint
+    fn get_sector_of(&self, wp: &Waypoint) -> Option<&Rc<RefCell<Sector>>> {
+        self.sectors.iter().find(|s| {
+            let s = s.borrow();
+            s.get_start().is_some_and(|start| compare_wp(&start, wp))
+                || s.get_end().is_some_and(|end| compare_wp(&end, wp))
+                || s.get_waypoints().iter().any(|w| compare_wp(w, wp))
+        })
     }
 
     pub fn add_airport(&mut self, airport: Arc<Airport>) {
@@ -237,26 +367,111 @@ impl Plan {
     }
 
     /**
-     * Get the heading from the previous waypoint to the specified.
+     * Get the heading to fly from the previous waypoint to the specified,
+     * corrected for wind and given as magnetic or true depending on the preferences.
      * @param loc
      * @return double Heading
      */
     pub fn get_leg_heading_to(&self, wp: &Waypoint) -> f64 {
         let pref = crate::preference::manager();
 
-        let mut heading = 0.0;
+        if pref.get::<bool>(USE_MAGNETIC_HEADINGS).unwrap_or(false) {
+            self.get_leg_magnetic_heading_to(wp)
+        } else {
+            self.get_leg_true_heading_to(wp)
+        }
+    }
 
-        if let Some(prev) = self.get_previous_location(wp) {
-            heading = prev.bearing_to_deg(wp.get_loc());
-            if pref.get::<bool>(USE_MAGNETIC_HEADINGS).unwrap_or(false) {
-                let geo = Geomagnetism::new(wp.get_lat(), wp.get_long(), None, None);
-                heading -= geo.get_declination()
-            }
-            if heading < 0.0 {
-                heading += 360.0;
+    /**
+     * Get the true course from the previous waypoint to the specified, without any wind correction.
+     * @param loc
+     * @return double Course
+     */
+    pub fn get_leg_course_to(&self, wp: &Waypoint) -> f64 {
+        match self.get_previous_location(wp) {
+            Some(prev) => prev.bearing_to_deg(&self.get_arrival_location(wp)).rem_euclid(360.0),
+            None => 0.0,
+        }
+    }
+
+    /**
+     * Get the magnetic course from the previous waypoint to the specified, without any wind correction.
+     * @param loc
+     * @return double Course
+     */
+    pub fn get_leg_magnetic_course_to(&self, wp: &Waypoint) -> f64 {
+        if self.get_previous_location(wp).is_none() {
+            return 0.0;
+        }
+        let geo = Geomagnetism::new(wp.get_lat(), wp.get_long(), None, None);
+        (self.get_leg_course_to(wp) - geo.get_declination()).rem_euclid(360.0)
+    }
+
+    /**
+     * Get the wind corrected true heading from the previous waypoint to the specified.
+     * @param loc
+     * @return double Heading
+     */
+    pub fn get_leg_true_heading_to(&self, wp: &Waypoint) -> f64 {
+        if self.get_previous_location(wp).is_none() {
+            return 0.0;
+        }
+        self.get_wind_correction_to(wp).get_heading()
+    }
+
+    /**
+     * Get the wind corrected magnetic heading from the previous waypoint to the specified.
+     * @param loc
+     * @return double Heading
+     */
+    pub fn get_leg_magnetic_heading_to(&self, wp: &Waypoint) -> f64 {
+        if self.get_previous_location(wp).is_none() {
+            return 0.0;
+        }
+        let geo = Geomagnetism::new(wp.get_lat(), wp.get_long(), None, None);
+        (self.get_leg_true_heading_to(wp) - geo.get_declination()).rem_euclid(360.0)
+    }
+
+    /**
+     * Get the wind for the leg to the specified waypoint, taken half way along the leg
+     * at the average altitude of the leg. Without winds aloft the air is still.
+     * @param loc
+     * @return Wind
+     */
+    pub fn get_leg_wind_to(&self, wp: &Waypoint) -> Wind {
+        match (&self.winds, self.get_previous_location(wp)) {
+            (Some(winds), Some(prev)) => {
+                let midpoint = Coordinate::midpoint(&prev, &self.get_arrival_location(wp));
+                winds.wind_at(&midpoint, self.get_leg_avg_alt_to(wp))
             }
+            _ => Wind::default(),
         }
-        heading
+    }
+
+    /**
+     * Solve the wind triangle for the leg to the specified waypoint.
+     * @param loc
+     * @return WindCorrection wind correction angle, true heading and ground speed
+     */
+    pub fn get_wind_correction_to(&self, wp: &Waypoint) -> WindCorrection {
+        let course = self.get_leg_course_to(wp);
+        self.get_leg_wind_to(wp).correct(course, self.get_tas_to(wp))
+    }
+
+    /**
+     * Get the ground speed for the leg to the specified waypoint in knots.
+     * @param loc
+     * @return double Ground speed
+     */
+    pub fn get_ground_speed_to(&self, wp: &Waypoint) -> f64 {
+        self.get_wind_correction_to(wp).get_ground_speed()
+    }
+
+    pub fn get_ground_speed_to_as_string(&self, wp: &Waypoint) -> String {
+        let pref = crate::preference::manager();
+        let units = pref.get::<String>(UNITS).unwrap_or("Nm".to_string());
+        let speed_format = SpeedFormat::new(&units);
+        speed_format.format(&self.get_ground_speed_to(wp))
     }
 
     /**
@@ -266,7 +481,7 @@ impl Plan {
      */
     pub fn get_leg_distance_to(&self, wp: &Waypoint) -> f64 {
         match self.get_previous_location(wp) {
-            Some(prev) => prev.distance_to(wp.get_loc()),
+            Some(prev) => prev.distance_to(&self.get_arrival_location(wp)),
             None => 0.0,
         }
     }
@@ -298,16 +513,24 @@ impl Plan {
         distance_format.format(distance)
     }
     pub fn get_time_to(&self, waypoint: &Waypoint) -> f64 {
-        let isa = self.get_speed_to(waypoint);
-        if isa == 0 {
+        let ground_speed = self.get_ground_speed_to(waypoint);
+        if ground_speed <= 0.0 {
             return 0.0;
         }
-        let alt = self.get_leg_avg_alt_to(waypoint);
-        let tas = isa as f64 * (1.0 + (alt as f64 / 1000.0) * 0.02);
-
-
         let leg_distance = self.get_leg_distance_to(waypoint);
-        leg_distance / tas
+        leg_distance / ground_speed
+    }
+
+    /**
+     * Get the true airspeed for the leg to the specified waypoint in knots.
+     * This uses the rule of thumb of 2% per thousand feet above the indicated airspeed.
+     * @param loc
+     * @return double True airspeed
+     */
+    pub fn get_tas_to(&self, waypoint: &Waypoint) -> f64 {
+        let isa = self.get_speed_to(waypoint);
+        let alt = self.get_leg_avg_alt_to(waypoint);
+        isa as f64 * (1.0 + (alt as f64 / 1000.0) * 0.02)
     }
 
     pub fn get_time_to_as_string(&self, waypoint: &Waypoint) -> String {
@@ -317,39 +540,222 @@ impl Plan {
     }
 
     pub fn get_speed_to(&self, waypoint: &Waypoint) -> i32 {
-        if let Some(aircraft) = &self.aircraft {
-            let climb = aircraft.get_climb_speed();
-            let cruise = aircraft.get_cruise_speed();
-            let sink = aircraft.get_sink_speed();
-
-            for s in &self.sectors {
-                let mut speed = *climb;
-                if let Some(start_wp) = s.borrow().get_start() {
-                    if compare_wp(&start_wp, waypoint) {
-                        return 0;
-                    }
+        match (&self.aircraft, self.get_phase_to(waypoint)) {
+            (Some(aircraft), Some(phase)) => match phase {
+                FlightPhase::Climb => *aircraft.get_climb_speed(),
+                FlightPhase::Cruise => *aircraft.get_cruise_speed(),
+                FlightPhase::Descent => *aircraft.get_sink_speed(),
+            },
+            _ => 0,
+        }
+    }
+
+    /**
+     * Get the phase of flight for the leg to the waypoint. Legs are flown in the climb until
+     * the top of climb, then in the cruise until the beginning of descent.
+     * @param loc
+     * @return FlightPhase or None for the start of a sector
+     */
+    pub fn get_phase_to(&self, waypoint: &Waypoint) -> Option<FlightPhase> {
+        for s in &self.sectors {
+            let mut phase = FlightPhase::Climb;
+            if s.borrow().get_start().is_some_and(|start_wp| compare_wp(&start_wp, waypoint)) {
+                return None;
+            }
+            for wp in s.borrow().get_waypoints()
+            {
+                if compare_wp(wp, waypoint) {
+                    return Some(phase);
                 }
-                for wp in s.borrow().get_waypoints()
-                {
-                    if compare_wp(wp, waypoint) {
-                        return speed;
-                    }
-                    speed = match wp {
-                        Waypoint::Toc { .. } => *cruise,
-                        Waypoint::Bod { .. } => *sink,
-                        _ => speed,
-                    };
+                phase = match wp {
+                    Waypoint::Toc { .. } => FlightPhase::Cruise,
+                    Waypoint::Bod { .. } => FlightPhase::Descent,
+                    _ => phase,
+                };
+            }
+            if s.borrow().get_end().is_some_and(|end_wp| compare_wp(&end_wp, waypoint)) {
+                return Some(FlightPhase::Descent);
+            }
+        }
+        None
+    }
+
+    /**
+     * Get the fuel burnt on the leg to the waypoint.
+     * @param loc
+     * @return double Fuel, zero if the aircraft has no fuel figures
+     */
+    pub fn get_fuel_burn_to(&self, waypoint: &Waypoint) -> f64 {
+        match (&self.aircraft, self.get_phase_to(waypoint)) {
+            (Some(aircraft), Some(phase)) => {
+                let fuel = aircraft.get_fuel();
+                let burn = match phase {
+                    FlightPhase::Climb => fuel.get_climb_burn(),
+                    FlightPhase::Cruise => fuel.get_cruise_burn(),
+                    FlightPhase::Descent => fuel.get_descent_burn(),
+                };
+                self.get_time_to(waypoint) * burn
+            }
+            _ => 0.0,
+        }
+    }
+
+    /**
+     * Get the fuel remaining on arrival at the waypoint.
+     * Each sector is taken to start with full tanks, less the taxi fuel.
+     * @param loc
+     * @return double Fuel or None if the aircraft has no fuel figures
+     */
+    pub fn get_fuel_remaining_at(&self, waypoint: &Waypoint) -> Option<f64> {
+        let fuel = self.aircraft.as_ref().map(|a| a.get_fuel()).filter(|f| f.is_planned())?;
+        for s in &self.sectors {
+            let sector = s.borrow();
+            let mut remaining = fuel.get_capacity() - fuel.get_taxi();
+            if sector.get_start().is_some_and(|start_wp| compare_wp(&start_wp, waypoint)) {
+                return Some(*fuel.get_capacity());
+            }
+            for wp in sector.get_waypoints().iter().chain(sector.get_end().iter()) {
+                remaining -= self.get_fuel_burn_to(wp);
+                if compare_wp(wp, waypoint) {
+                    return Some(remaining);
                 }
-                if let Some(end_wp) = s.borrow().get_end() {
-                    if compare_wp(&end_wp, waypoint) {
-                        return *sink;
-                    }
+            }
+        }
+        None
+    }
+
+    /**
+     * Get the fuel breakdown for the whole plan.
+     * @return FuelPlan or None if the aircraft has no fuel figures
+     */
+    pub fn get_fuel_plan(&self) -> Option<FuelPlan> {
+        self.sectors
+            .iter()
+            .filter_map(|s| s.borrow().get_fuel_plan(self))
+            .reduce(|mut total, next| {
+                total.add_sector(&next);
+                total
+            })
+    }
+
+    /**
+     * Get a warning for each sector that needs more fuel than the aircraft can carry.
+     * @return Vec<String> warnings
+     */
+    pub fn get_endurance_warnings(&self) -> Vec<String> {
+        self.sectors
+            .iter()
+            .filter_map(|s| {
+                let sector = s.borrow();
+                sector.get_fuel_plan(self)
+                    .filter(|f| f.exceeds_endurance())
+                    .map(|f| {
+                        gettext("{sector} needs {required} fuel but only {capacity} can be carried")
+                            .replace("{sector}", &sector.get_name())
+                            .replace("{required}", &format!("{:.1}", f.get_required()))
+                            .replace("{capacity}", &format!("{:.1}", f.get_capacity()))
+                    })
+            })
+            .collect()
+    }
+
+    /**
+     * Get the minimum safe altitude for the leg, the highest terrain along it plus the clearance.
+     * @param loc
+     * @return MSA in feet, None if there is no terrain data for the leg
+     */
+    pub fn get_leg_msa_to(&self, wp: &Waypoint) -> Option<i32> {
+        let prev = self.get_previous_location(wp)?;
+        get_terrain().get_minimum_safe_altitude(&prev, &self.get_arrival_location(wp), get_clearance())
+    }
+
+    /**
+     * Check if the leg is planned below its minimum safe altitude.
+     * A leg is flown at the higher of the elevations at its ends, so a leg in the climb is
+     * judged by the altitude reached at its end and one in the descent by where it starts.
+     * @param loc
+     * @return bool
+     */
+    pub fn is_below_msa_to(&self, wp: &Waypoint) -> bool {
+        match (self.get_previous_waypoint(wp), self.get_leg_msa_to(wp)) {
+            (Some(prev), Some(msa)) => prev.get_elevation().max(wp.get_elevation()) < msa,
+            _ => false,
+        }
+    }
+
+    /**
+     * Get a warning for each leg planned below its minimum safe altitude.
+     * @return Vec<String> warnings
+     */
+    pub fn get_terrain_warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if !get_terrain().is_available() {
+            return warnings;
+        }
+        for s in &self.sectors {
+            let sector = s.borrow();
+            for wp in sector.get_waypoints().iter().chain(sector.get_end().iter()) {
+                // Sampling the terrain is slow, so the leg's MSA is found once and compared here as in is_below_msa_to
+                let (Some(prev), Some(msa)) = (self.get_previous_waypoint(wp), self.get_leg_msa_to(wp)) else {
+                    continue;
+                };
+                if prev.get_elevation().max(wp.get_elevation()) < msa {
+                    warnings.push(gettext("{sector}: the leg to {waypoint} is below the minimum safe altitude of {msa}")
+                        .replace("{sector}", &sector.get_name())
+                        .replace("{waypoint}", wp.get_id())
+                        .replace("{msa}", &msa.to_string()));
+                }
+            }
+        }
+        warnings
+    }
+
+    /**
+     * Get a warning for each altitude constraint the aircraft can not climb or descend in time to meet.
+     * @return Vec<String> warnings
+     */
+    pub fn get_constraint_warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        for s in &self.sectors {
+            let sector = s.borrow();
+            for wp in sector.get_waypoints() {
+                let unmet = wp.get_constraints().get_altitude().is_some_and(|a| !a.allows(wp.get_elevation()));
+                if unmet {
+                    warnings.push(gettext("{sector}: the altitude constraint at {waypoint} can not be met, it is planned at {altitude}ft")
+                        .replace("{sector}", &sector.get_name())
+                        .replace("{waypoint}", wp.get_id())
+                        .replace("{altitude}", &wp.get_elevation().to_string()));
                 }
             }
-            0
-        } else {
-            0
         }
+        warnings
+    }
+
+    /**
+     * Get a warning for each sector whose chosen cruise level, or a step climb, breaks the
+     * semicircular rule for its magnetic track in the region it departs from.
+     * @return Vec<String> warnings
+     */
+    pub fn get_cruise_level_warnings(&self) -> Vec<String> {
+        let regions = LevelRegions::from_preferences();
+        let mut warnings = Vec::new();
+        for s in &self.sectors {
+            let sector = s.borrow();
+            let (Some(start), Some(track)) = (sector.get_start(), sector.get_magnetic_track()) else {
+                continue;
+            };
+            let rule = regions.get_rule(start.get_id());
+            let levels = sector.get_cruise_level().into_iter()
+                .chain(sector.get_step_climbs().iter().map(|step| step.get_level()));
+            for level in levels.filter(|level| !rule.allows(*level, track, self.flight_rules)) {
+                warnings.push(gettext("{sector}: {level}ft is not a {rules} cruise level for a magnetic track of {track}°")
+                    .replace("{sector}", &sector.get_name())
+                    .replace("{level}", &level.to_string())
+                    .replace("{rules}", &self.flight_rules.to_string())
+                    .replace("{track}", &format!("{:03.0}", track)));
+            }
+        }
+        warnings
     }
 
     pub fn get_speed_to_as_string(&self, wp: &Waypoint) -> String {
@@ -392,8 +798,17 @@ fn compare_wp(a: &Waypoint, b: &Waypoint) -> bool {
 
 #[cfg(test)]
 mod tests {
+    use std::cell::Cell;
+    use std::sync::Arc;
+
+    use crate::earth::coordinate::Coordinate;
+    use crate::model::aircraft::{Aircraft, AircraftFuel};
+    use crate::model::airport::{Runway, RunwayType};
+    use crate::model::cruise_level::{FlightRules, StepClimb};
     use crate::model::sector::Sector;
-    use crate::model::test_utils::tests::make_airport;
+    use crate::model::test_utils::tests::{make_airport, make_airport_at};
+    use crate::model::waypoint::Waypoint;
+    use crate::model::wind::{WindProfile, WindsAloft};
 
     use super::Plan;
 
@@ -425,4 +840,154 @@ mod tests {
         plan.add_sector(s);
         assert_eq!(plan.get_name(), "new_plan");
     }
+
+    fn make_plan() -> Plan {
+        let mut plan = Plan::new();
+        let mut s = Sector::new();
+        s.set_start(Some(make_airport_at("YAAA", 0.0, 10.0)));
+        s.set_end(Some(make_airport_at("YBBB", 0.0, 12.0)));
+        s.add_waypoint(Waypoint::Simple { loc: Coordinate::new(0.0, 11.0), elevation: Cell::new(0), locked: false, constraints: Cell::default() });
+        plan.add_sector(s);
+        let fuel = AircraftFuel::new(12.0, 10.0, 6.0, 30.0).with_allowances(1.0, 10.0, 30.0, 45.0);
+        let aircraft = Arc::new(Aircraft::new("Test".to_string(), 100, 5000, 100, 500, 100, 500, fuel, false));
+        plan.set_aircraft(&Some(aircraft));
+        plan
+    }
+
+    #[test]
+    fn test_still_air() {
+        let plan = make_plan();
+        let end = plan.get_sectors()[0].borrow().get_end().unwrap();
+
+        assert_eq!(plan.get_leg_course_to(&end).round(), 90.0);
+        assert_eq!(plan.get_leg_true_heading_to(&end).round(), 90.0);
+        assert_eq!(plan.get_ground_speed_to(&end).round(), 100.0);
+        assert_eq!((plan.get_time_to(&end) * 60.0).round(), 36.0);
+    }
+
+    #[test]
+    fn test_winds_aloft() {
+        let mut plan = make_plan();
+        let end = plan.get_sectors()[0].borrow().get_end().unwrap();
+
+        // Tailwind
+        plan.set_winds(Some(WindsAloft::Profile(WindProfile::parse("270/20").unwrap())));
+        assert_eq!(plan.get_ground_speed_to(&end).round(), 120.0);
+        assert_eq!((plan.get_time_to(&end) * 60.0).round(), 30.0);
+        assert_eq!(plan.get_leg_true_heading_to(&end).round(), 90.0);
+
+        // Crosswind from the north, so head into it
+        plan.set_winds(Some(WindsAloft::Profile(WindProfile::parse("360/20").unwrap())));
+        assert_eq!(plan.get_wind_correction_to(&end).get_wca().round(), -12.0);
+        assert_eq!(plan.get_leg_true_heading_to(&end).round(), 78.0);
+        assert_eq!(plan.get_leg_course_to(&end).round(), 90.0);
+        assert_eq!(plan.get_ground_speed_to(&end).round(), 98.0);
+
+        // The start of the plan has no leg
+        let start = plan.get_sectors()[0].borrow().get_start().unwrap();
+        assert_eq!(plan.get_time_to(&start), 0.0);
+        assert_eq!(plan.get_leg_true_heading_to(&start), 0.0);
+    }
+
+    #[test]
+    fn test_runway_thresholds() {
+        let plan = make_plan();
+        let sector = plan.get_sectors()[0].clone();
+        let gps = sector.borrow().get_waypoint(0).unwrap();
+        let end = sector.borrow().get_end().unwrap();
+        assert_eq!(sector.borrow().get_distance(&plan).round(), 120.0);
+
+        // Two mile long runways, so each threshold is a mile from the airport
+        for wp in [sector.borrow().get_start().unwrap(), end.clone()] {
+            wp.get_airport().add_runway(Runway::new("09".to_string(), Some(RunwayType::Runway), wp.get_lat(), wp.get_long(),
+                                                    6076 * 2, 150, 90.0, false, "1".to_string(), "2".to_string()));
+        }
+        sector.borrow_mut().set_departure_runway(Some("09".to_string()));
+        sector.borrow_mut().set_arrival_runway(Some("27".to_string()));
+
+        assert_eq!(plan.get_leg_distance_to(&gps).round(), 61.0);
+        assert_eq!(plan.get_leg_distance_to(&end).round(), 61.0);
+        assert_eq!(plan.get_leg_course_to(&end).round(), 90.0);
+        assert_eq!(sector.borrow().get_distance(&plan).round(), 122.0);
+    }
+
+    #[test]
+    fn test_thresholds_of_owning_sector() {
+        let mut plan = make_plan();
+        let yaaa = plan.get_sectors()[0].borrow().get_start().unwrap();
+        yaaa.get_airport().add_runway(Runway::new("09".to_string(), Some(RunwayType::Runway), yaaa.get_lat(), yaaa.get_long(),
+                                                  6076 * 2, 150, 90.0, false, "1".to_string(), "2".to_string()));
+        plan.get_sectors()[0].borrow_mut().set_departure_runway(Some("09".to_string()));
+
+        // A second sector from the same airport, off the other end of the runway
+        let mut s = Sector::new();
+        s.set_start(Some(make_airport_at("YAAA", 0.0, 10.0)));
+        s.set_end(Some(make_airport_at("YCCC", 0.0, 13.0)));
+        s.add_waypoint(Waypoint::Simple { loc: Coordinate::new(0.0, 11.5), elevation: Cell::new(0), locked: false, constraints: Cell::default() });
+        s.set_departure_runway(Some("27".to_string()));
+        plan.add_sector(s);
+        plan.get_sectors()[1].borrow().get_start().unwrap().get_airport()
+            .add_runway(Runway::new("09".to_string(), Some(RunwayType::Runway), 0.0, 10.0,
+                                    6076 * 2, 150, 90.0, false, "1".to_string(), "2".to_string()));
+
+        let first = plan.get_sectors()[0].borrow().get_waypoint(0).unwrap();
+        let second = plan.get_sectors()[1].borrow().get_waypoint(0).unwrap();
+        assert_eq!(plan.get_leg_distance_to(&first).round(), 61.0);
+        assert_eq!(plan.get_leg_distance_to(&second).round(), 89.0);
+    }
+
+    #[test]
+    fn test_fuel() {
+        let mut plan = make_plan();
+        let sector = plan.get_sectors()[0].clone();
+        let start = sector.borrow().get_start().unwrap();
+        let gps = sector.borrow().get_waypoint(0).unwrap();
+        let end = sector.borrow().get_end().unwrap();
+
+        // 36 minutes in the climb then 36 minutes in the descent
+        assert_eq!((plan.get_fuel_burn_to(&gps) * 10.0).round(), 72.0);
+        assert_eq!((plan.get_fuel_burn_to(&end) * 10.0).round(), 36.0);
+        assert_eq!(plan.get_fuel_burn_to(&start), 0.0);
+        assert_eq!((sector.borrow().get_trip_fuel(&plan) * 10.0).round(), 108.0);
+
+        assert_eq!(plan.get_fuel_remaining_at(&start), Some(30.0));
+        assert_eq!((plan.get_fuel_remaining_at(&gps).unwrap() * 10.0).round(), 218.0);
+        assert_eq!((plan.get_fuel_remaining_at(&end).unwrap() * 10.0).round(), 182.0);
+
+        let fuel_plan = plan.get_fuel_plan().unwrap();
+        assert_eq!((fuel_plan.get_required() * 10.0).round(), 254.0);
+        assert!(plan.get_endurance_warnings().is_empty());
+
+        // A headwind stretches the trip past what the tanks hold
+        plan.set_winds(Some(WindsAloft::Profile(WindProfile::parse("090/60").unwrap())));
+        assert!(sector.borrow().get_fuel_plan(&plan).unwrap().exceeds_endurance());
+        assert_eq!(plan.get_endurance_warnings().len(), 1);
+
+        // Without fuel figures there is no fuel plan
+        plan.set_aircraft(&Some(Arc::new(Aircraft::default())));
+        assert!(plan.get_fuel_plan().is_none());
+        assert!(plan.get_fuel_remaining_at(&end).is_none());
+    }
+
+    #[test]
+    fn test_cruise_level_warnings() {
+        let mut plan = Plan::new();
+        let mut s = Sector::new();
+        // Eastbound, where the odd levels are flown
+        s.set_start(Some(make_airport_at("YSSY", 0.0, 0.0)));
+        s.set_end(Some(make_airport_at("YMLB", 0.0, 4.0)));
+        s.set_cruise_level(Some(11000));
+        s.set_step_climbs(vec![StepClimb::new(100.0, 13000)]);
+        plan.add_sector(s);
+        assert!(plan.get_cruise_level_warnings().is_empty());
+
+        plan.set_flight_rules(FlightRules::Vfr);
+        assert_eq!(plan.get_cruise_level_warnings().len(), 2);
+
+        plan.set_flight_rules(FlightRules::Ifr);
+        plan.get_sectors()[0].borrow_mut().set_cruise_level(Some(12000));
+        let warnings = plan.get_cruise_level_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("12000"));
+    }
 }
@@ -0,0 +1,305 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use crate::model::aircraft::Aircraft;
+use crate::model::cruise_level::FlightRules;
+use crate::model::plan::Plan;
+use crate::model::plan_settings::PlanSettings;
+use crate::model::sector::Sector;
+use crate::model::wind::WindsAloft;
+
+// Edits older than this are forgotten
+const MAX_HISTORY: usize = 100;
+
+/// Everything about a plan that can be edited, enough to put it back as it was.
+struct PlanState {
+    sectors: Vec<Sector>,
+    aircraft: Option<Arc<Aircraft>>,
+    max_altitude: Option<i32>,
+    flight_rules: FlightRules,
+    winds: Option<WindsAloft>,
+    author: String,
+    remarks: String,
+    departure_time: Option<DateTime<Utc>>,
+    settings: Option<PlanSettings>,
+}
+
+impl PlanState {
+    fn of(plan: &Plan) -> Self {
+        Self {
+            sectors: plan.get_sectors().iter().map(|s| s.borrow().clone()).collect(),
+            aircraft: plan.get_aircraft().clone(),
+            max_altitude: *plan.get_max_altitude(),
+            flight_rules: plan.get_flight_rules(),
+            winds: plan.get_winds().clone(),
+            author: plan.get_author().to_string(),
+            remarks: plan.get_remarks().to_string(),
+            departure_time: *plan.get_departure_time(),
+            settings: plan.get_settings().clone(),
+        }
+    }
+
+    fn restore(self, plan: &mut Plan) {
+        *plan.get_sectors_mut() = self.sectors.into_iter().map(|s| Rc::new(RefCell::new(s))).collect();
+        plan.set_aircraft(&self.aircraft);
+        plan.set_max_altitude(self.max_altitude);
+        plan.set_flight_rules(self.flight_rules);
+        plan.set_winds(self.winds);
+        plan.set_author(&self.author);
+        plan.set_remarks(&self.remarks);
+        plan.set_departure_time(self.departure_time);
+        plan.set_settings(self.settings);
+    }
+}
+
+/// A single plan wide field, holding its value on one side of an edit.
+/// Editing one of these only needs the field remembered, not every sector of the plan.
+#[derive(Clone)]
+pub enum PlanField {
+    MaxAltitude(Option<i32>),
+    Winds(Option<WindsAloft>),
+    Author(String),
+    Remarks(String),
+    DepartureTime(Option<DateTime<Utc>>),
+}
+
+impl PlanField {
+    // The same field with the value it has in the plan now
+    fn of(&self, plan: &Plan) -> Self {
+        match self {
+            PlanField::MaxAltitude(_) => PlanField::MaxAltitude(*plan.get_max_altitude()),
+            PlanField::Winds(_) => PlanField::Winds(plan.get_winds().clone()),
+            PlanField::Author(_) => PlanField::Author(plan.get_author().to_string()),
+            PlanField::Remarks(_) => PlanField::Remarks(plan.get_remarks().to_string()),
+            PlanField::DepartureTime(_) => PlanField::DepartureTime(*plan.get_departure_time()),
+        }
+    }
+
+    fn restore(self, plan: &mut Plan) {
+        match self {
+            PlanField::MaxAltitude(max_altitude) => plan.set_max_altitude(max_altitude),
+            PlanField::Winds(winds) => plan.set_winds(winds),
+            PlanField::Author(author) => plan.set_author(&author),
+            PlanField::Remarks(remarks) => plan.set_remarks(&remarks),
+            PlanField::DepartureTime(departure_time) => plan.set_departure_time(departure_time),
+        }
+    }
+}
+
+// What is needed to put the plan back, either all of it or just the field edited
+enum Memento {
+    Plan(PlanState),
+    Field(PlanField),
+}
+
+impl Memento {
+    // The same part of the plan as it is now, to go back to on the other side of the edit
+    fn of(&self, plan: &Plan) -> Self {
+        match self {
+            Memento::Plan(_) => Memento::Plan(PlanState::of(plan)),
+            Memento::Field(field) => Memento::Field(field.of(plan)),
+        }
+    }
+
+    fn restore(self, plan: &mut Plan) {
+        match self {
+            Memento::Plan(state) => state.restore(plan),
+            Memento::Field(field) => field.restore(plan),
+        }
+    }
+}
+
+/// An edit that can be undone, with the plan as it was on the other side of it.
+struct PlanEdit {
+    description: String,
+    memento: Memento,
+}
+
+/// The undo and redo history of a plan.
+/// Each edit is recorded just before it is made; undoing it puts the plan back as it was then,
+/// and redoing it puts back the plan as it was when it was undone.
+#[derive(Default)]
+pub struct PlanHistory {
+    undo: Vec<PlanEdit>,
+    redo: Vec<PlanEdit>,
+}
+
+impl PlanHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the plan as it is before an edit. Anything undone can no longer be redone.
+    pub fn record(&mut self, description: &str, plan: &Plan) {
+        self.push(description, Memento::Plan(PlanState::of(plan)));
+    }
+
+    /// Record a field of the plan as it is before it is edited, unless the last edit was to
+    /// the same field, such as typing in it, so the whole change is undone at once.
+    pub fn record_field(&mut self, description: &str, field: PlanField) {
+        let continuing = self.redo.is_empty() && self.undo.last().is_some_and(|e| match &e.memento {
+            Memento::Field(last) => std::mem::discriminant(last) == std::mem::discriminant(&field),
+            Memento::Plan(_) => false,
+        });
+        if !continuing {
+            self.push(description, Memento::Field(field));
+        }
+    }
+
+    fn push(&mut self, description: &str, memento: Memento) {
+        if self.undo.len() == MAX_HISTORY {
+            self.undo.remove(0);
+        }
+        self.undo.push(PlanEdit { description: description.to_string(), memento });
+        self.redo.clear();
+    }
+
+    /// Undo the last edit, returning what it was.
+    pub fn undo(&mut self, plan: &mut Plan) -> Option<String> {
+        let edit = self.undo.pop()?;
+        self.redo.push(PlanEdit { description: edit.description.clone(), memento: edit.memento.of(plan) });
+        edit.memento.restore(plan);
+        Some(edit.description)
+    }
+
+    /// Redo the last edit undone, returning what it was.
+    pub fn redo(&mut self, plan: &mut Plan) -> Option<String> {
+        let edit = self.redo.pop()?;
+        self.undo.push(PlanEdit { description: edit.description.clone(), memento: edit.memento.of(plan) });
+        edit.memento.restore(plan);
+        Some(edit.description)
+    }
+
+    pub fn get_undo_description(&self) -> Option<&str> {
+        self.undo.last().map(|e| e.description.as_str())
+    }
+
+    pub fn get_redo_description(&self) -> Option<&str> {
+        self.redo.last().map(|e| e.description.as_str())
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use crate::earth::coordinate::Coordinate;
+    use crate::model::plan::Plan;
+    use crate::model::sector::Sector;
+    use crate::model::test_utils::tests::make_airport;
+    use crate::model::waypoint::Waypoint;
+
+    use super::{PlanField, PlanHistory, MAX_HISTORY};
+
+    fn make_waypoint(lat: f64) -> Waypoint {
+        Waypoint::Simple { loc: Coordinate::new(lat, 151.0), elevation: Cell::new(0), locked: true, constraints: Cell::default() }
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut plan = Plan::new();
+        let mut history = PlanHistory::new();
+        let mut sector = Sector::new();
+        sector.set_start(Some(make_airport("YSSY")));
+        plan.add_sector(sector);
+
+        history.record("Add waypoint", &plan);
+        plan.get_sectors()[0].borrow_mut().add_waypoint(make_waypoint(-33.0));
+        history.record("Maximum altitude", &plan);
+        plan.set_max_altitude(Some(9000));
+
+        assert_eq!(history.undo(&mut plan).as_deref(), Some("Maximum altitude"));
+        assert_eq!(plan.get_max_altitude(), &None);
+        assert_eq!(plan.get_sectors()[0].borrow().get_waypoint_count(), 1);
+        assert_eq!(history.undo(&mut plan).as_deref(), Some("Add waypoint"));
+        assert_eq!(plan.get_sectors()[0].borrow().get_waypoint_count(), 0);
+        assert!(history.undo(&mut plan).is_none());
+
+        assert_eq!(history.redo(&mut plan).as_deref(), Some("Add waypoint"));
+        assert_eq!(plan.get_sectors()[0].borrow().get_waypoint_count(), 1);
+        assert_eq!(history.get_redo_description(), Some("Maximum altitude"));
+
+        // A new edit can't be followed by the ones undone before it
+        history.record("Remove sector", &plan);
+        plan.remove_sector_at(0);
+        assert!(history.get_redo_description().is_none());
+        history.undo(&mut plan);
+        assert_eq!(plan.get_sectors()[0].borrow().get_start().unwrap().get_id(), "YSSY");
+    }
+
+    #[test]
+    fn test_continuing_edits() {
+        let mut plan = Plan::new();
+        let mut history = PlanHistory::new();
+        for remarks in ["G", "Gr", "Gro"] {
+            history.record_field("Remarks", PlanField::Remarks(plan.get_remarks().to_string()));
+            plan.set_remarks(remarks);
+        }
+        assert_eq!(history.undo(&mut plan).as_deref(), Some("Remarks"));
+        assert_eq!(plan.get_remarks(), "");
+        assert!(history.get_undo_description().is_none());
+
+        // Another field is a separate edit, and redoing puts back only that field
+        history.record_field("Remarks", PlanField::Remarks(plan.get_remarks().to_string()));
+        plan.set_remarks("Gro");
+        history.record_field("Maximum altitude", PlanField::MaxAltitude(*plan.get_max_altitude()));
+        plan.set_max_altitude(Some(9000));
+        history.record_field("Remarks", PlanField::Remarks(plan.get_remarks().to_string()));
+        plan.set_remarks("Grow");
+        assert_eq!(history.undo(&mut plan).as_deref(), Some("Remarks"));
+        assert_eq!(plan.get_remarks(), "Gro");
+        assert_eq!(history.undo(&mut plan).as_deref(), Some("Maximum altitude"));
+        assert_eq!(plan.get_max_altitude(), &None);
+        assert_eq!(plan.get_remarks(), "Gro");
+        assert_eq!(history.redo(&mut plan).as_deref(), Some("Maximum altitude"));
+        assert_eq!(plan.get_max_altitude(), &Some(9000));
+    }
+
+    #[test]
+    fn test_history_limit() {
+        let mut plan = Plan::new();
+        let mut history = PlanHistory::new();
+        for i in 0..MAX_HISTORY + 10 {
+            history.record("Maximum altitude", &plan);
+            plan.set_max_altitude(Some(i as i32));
+        }
+        let mut count = 0;
+        while history.undo(&mut plan).is_some() {
+            count += 1;
+        }
+        assert_eq!(count, MAX_HISTORY);
+        assert_eq!(plan.get_max_altitude(), &Some(9));
+    }
+}
@@ -0,0 +1,86 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+/// The settings a plan was made with, saved with the plan so it is known how it was planned
+/// even after the preferences have changed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlanSettings {
+    units: String,
+    plan_type: String,
+    vor_only: bool,
+    vor_preferred: bool,
+    add_gps_waypoints: bool,
+    max_leg_length: f64,
+    min_leg_length: f64,
+}
+
+impl PlanSettings {
+    pub fn new(
+        units: String,
+        plan_type: String,
+        vor_only: bool,
+        vor_preferred: bool,
+        add_gps_waypoints: bool,
+        max_leg_length: f64,
+        min_leg_length: f64,
+    ) -> Self {
+        Self {
+            units,
+            plan_type,
+            vor_only,
+            vor_preferred,
+            add_gps_waypoints,
+            max_leg_length,
+            min_leg_length,
+        }
+    }
+
+    pub fn get_units(&self) -> &str {
+        &self.units
+    }
+
+    pub fn get_plan_type(&self) -> &str {
+        &self.plan_type
+    }
+
+    pub fn is_vor_only(&self) -> bool {
+        self.vor_only
+    }
+
+    pub fn is_vor_preferred(&self) -> bool {
+        self.vor_preferred
+    }
+
+    pub fn is_add_gps_waypoints(&self) -> bool {
+        self.add_gps_waypoints
+    }
+
+    pub fn get_max_leg_length(&self) -> f64 {
+        self.max_leg_length
+    }
+
+    pub fn get_min_leg_length(&self) -> f64 {
+        self.min_leg_length
+    }
+}
@@ -0,0 +1,391 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+use std::cell::Cell;
+use std::sync::Arc;
+
+use super::constraint::{AltitudeConstraint, Constraints, SpeedConstraint};
+use super::fix::Fix;
+use super::location::Location;
+use super::navaid::Navaid;
+use super::waypoint::Waypoint;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProcedureType {
+    Sid,
+    Star,
+    Approach,
+}
+
+/// The place a procedure leg ends at. Runway thresholds are held as fixes named for the runway, e.g. RW16R.
+#[derive(Clone, PartialEq)]
+pub enum LegPoint {
+    Navaid(Arc<Navaid>),
+    Fix(Arc<Fix>),
+}
+
+/// One leg of a procedure. Legs that end at an altitude or on a heading rather than at a fix,
+/// e.g. VA or CA legs, have no point and are not flown as waypoints.
+#[derive(Clone, PartialEq)]
+pub struct ProcedureLeg {
+    fix_id: String,
+    path_terminator: String,
+    point: Option<LegPoint>,
+    altitude: Option<AltitudeConstraint>,
+    speed: Option<SpeedConstraint>,
+}
+
+impl ProcedureLeg {
+    pub fn new(
+        fix_id: String,
+        path_terminator: String,
+        point: Option<LegPoint>,
+        altitude: Option<AltitudeConstraint>,
+        speed: Option<SpeedConstraint>,
+    ) -> Self {
+        Self {
+            fix_id,
+            path_terminator,
+            point,
+            altitude,
+            speed,
+        }
+    }
+
+    pub fn get_fix_id(&self) -> &str {
+        &self.fix_id
+    }
+
+    pub fn get_path_terminator(&self) -> &str {
+        &self.path_terminator
+    }
+
+    pub fn get_altitude(&self) -> Option<AltitudeConstraint> {
+        self.altitude
+    }
+
+    pub fn get_speed(&self) -> Option<SpeedConstraint> {
+        self.speed
+    }
+
+    pub fn get_constraints(&self) -> Constraints {
+        Constraints::new(self.altitude, self.speed)
+    }
+
+    /// The waypoint flown for this leg, if it ends at a fix
+    pub fn to_waypoint(&self) -> Option<Waypoint> {
+        match &self.point {
+            Some(LegPoint::Navaid(navaid)) => Some(Waypoint::Navaid {
+                navaid: navaid.clone(),
+                elevation: Cell::new(0),
+                locked: true,
+                airway: None,
+                constraints: Cell::new(self.get_constraints()),
+            }),
+            Some(LegPoint::Fix(fix)) => Some(Waypoint::Fix {
+                fix: fix.clone(),
+                elevation: Cell::new(0),
+                locked: true,
+                airway: None,
+                constraints: Cell::new(self.get_constraints()),
+            }),
+            None => None,
+        }
+    }
+
+    /// Whether the waypoint is the one flown for this leg
+    pub fn is_at(&self, waypoint: &Waypoint) -> bool {
+        match (&self.point, waypoint) {
+            (Some(LegPoint::Navaid(navaid)), Waypoint::Navaid { navaid: other, .. }) => {
+                navaid.get_id() == other.get_id() && navaid.get_loc() == other.get_loc()
+            }
+            (Some(LegPoint::Fix(fix)), Waypoint::Fix { fix: other, .. }) => {
+                fix.get_id() == other.get_id() && fix.get_loc() == other.get_loc()
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A run of legs sharing a route type and transition, e.g. the runway transition of a SID.
+#[derive(Clone)]
+struct ProcedureSegment {
+    route_type: char,
+    transition: String,
+    legs: Vec<ProcedureLeg>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum SegmentKind {
+    Runway,
+    Common,
+    Transition,
+}
+
+/// A SID, STAR or approach published for an airport.
+#[derive(Clone)]
+pub struct Procedure {
+    procedure_type: ProcedureType,
+    ident: String,
+    segments: Vec<ProcedureSegment>,
+}
+
+impl Procedure {
+    pub fn new(procedure_type: ProcedureType, ident: String) -> Self {
+        Self {
+            procedure_type,
+            ident,
+            segments: Vec::new(),
+        }
+    }
+
+    pub fn get_type(&self) -> ProcedureType {
+        self.procedure_type
+    }
+
+    pub fn get_ident(&self) -> &str {
+        &self.ident
+    }
+
+    /// Add the next leg, in the order they appear in the procedure data
+    pub fn add_leg(&mut self, route_type: char, transition: &str, leg: ProcedureLeg) {
+        match self.segments.last_mut() {
+            Some(segment) if segment.route_type == route_type && segment.transition == transition => {
+                segment.legs.push(leg);
+            }
+            _ => self.segments.push(ProcedureSegment {
+                route_type,
+                transition: transition.to_string(),
+                legs: vec![leg],
+            }),
+        }
+    }
+
+    fn kind_of(&self, segment: &ProcedureSegment) -> SegmentKind {
+        match (self.procedure_type, segment.route_type) {
+            (ProcedureType::Sid, '1' | '4' | 'F' | 'T') => SegmentKind::Runway,
+            (ProcedureType::Sid, '3' | '6' | 'S' | 'V') => SegmentKind::Transition,
+            (ProcedureType::Star, '1' | '4' | '7' | 'F') => SegmentKind::Transition,
+            (ProcedureType::Star, '3' | '6' | '9' | 'S') => SegmentKind::Runway,
+            (ProcedureType::Approach, 'A') => SegmentKind::Transition,
+            _ => SegmentKind::Common,
+        }
+    }
+
+    /// The runways the procedure can be flown from or to, e.g. "16R" or "16B" for all runways 16
+    pub fn get_runways(&self) -> Vec<String> {
+        if self.procedure_type == ProcedureType::Approach {
+            let runway: String = self.ident.chars()
+                .skip(1)
+                .take_while(|c| c.is_ascii_digit() || matches!(c, 'L' | 'R' | 'C'))
+                .collect();
+            return if runway.is_empty() { vec![] } else { vec![runway] };
+        }
+        let mut runways = vec![];
+        for segment in &self.segments {
+            if self.kind_of(segment) == SegmentKind::Runway {
+                let runway = segment.transition.trim_start_matches("RW").to_string();
+                if !runways.contains(&runway) {
+                    runways.push(runway);
+                }
+            }
+        }
+        runways
+    }
+
+    /// Whether the procedure can be flown from or to the runway
+    pub fn serves_runway(&self, runway: &str) -> bool {
+        let runways = self.get_runways();
+        runways.is_empty() || runways.iter().any(|r| runway_matches(r, runway))
+    }
+
+    /// The en-route transitions of a SID or STAR, or the approach transitions of an approach
+    pub fn get_transitions(&self) -> Vec<String> {
+        let mut transitions = vec![];
+        for segment in &self.segments {
+            if self.kind_of(segment) == SegmentKind::Transition && !transitions.contains(&segment.transition) {
+                transitions.push(segment.transition.clone());
+            }
+        }
+        transitions
+    }
+
+    /// The legs in the order they are flown for the runway and transition.
+    pub fn get_legs(&self, runway: Option<&str>, transition: Option<&str>) -> Vec<ProcedureLeg> {
+        let runway_segment = self.segments.iter().find(|s| {
+            self.kind_of(s) == SegmentKind::Runway
+                && runway.is_some_and(|r| runway_matches(s.transition.trim_start_matches("RW"), r))
+        });
+        let transition_segment = self.segments.iter().find(|s| {
+            self.kind_of(s) == SegmentKind::Transition && transition.is_some_and(|t| s.transition == t)
+        });
+        let common = self.segments.iter().filter(|s| self.kind_of(s) == SegmentKind::Common);
+
+        let ordered: Vec<&ProcedureSegment> = match self.procedure_type {
+            ProcedureType::Sid => runway_segment.into_iter().chain(common).chain(transition_segment).collect(),
+            ProcedureType::Star => transition_segment.into_iter().chain(common).chain(runway_segment).collect(),
+            ProcedureType::Approach => transition_segment.into_iter().chain(common).collect(),
+        };
+
+        // Segments join at a shared fix, which is only flown once
+        let mut legs: Vec<ProcedureLeg> = vec![];
+        for leg in ordered.iter().flat_map(|s| s.legs.iter()) {
+            match legs.last_mut() {
+                Some(last) if !leg.fix_id.is_empty() && last.fix_id == leg.fix_id => {
+                    last.altitude = last.altitude.or(leg.altitude);
+                    last.speed = last.speed.or(leg.speed);
+                }
+                _ => legs.push(leg.clone()),
+            }
+        }
+        legs
+    }
+}
+
+/// Runway transitions ending in B serve every parallel runway, e.g. 16B serves 16L, 16C and 16R
+fn runway_matches(published: &str, runway: &str) -> bool {
+    published == runway
+        || published.strip_suffix('B').is_some_and(|number| runway.trim_end_matches(['L', 'R', 'C']) == number)
+}
+
+/// A procedure chosen for a sector, with the runway and transition it is flown with.
+#[derive(Clone)]
+pub struct ProcedureSelection {
+    procedure: Arc<Procedure>,
+    runway: Option<String>,
+    transition: Option<String>,
+    legs: Vec<ProcedureLeg>,
+}
+
+impl ProcedureSelection {
+    pub fn new(procedure: Arc<Procedure>, runway: Option<String>, transition: Option<String>) -> Self {
+        let legs = procedure.get_legs(runway.as_deref(), transition.as_deref());
+        Self {
+            procedure,
+            runway,
+            transition,
+            legs,
+        }
+    }
+
+    pub fn get_procedure(&self) -> &Arc<Procedure> {
+        &self.procedure
+    }
+
+    pub fn get_ident(&self) -> &str {
+        self.procedure.get_ident()
+    }
+
+    pub fn get_runway(&self) -> Option<&String> {
+        self.runway.as_ref()
+    }
+
+    pub fn get_transition(&self) -> Option<&String> {
+        self.transition.as_ref()
+    }
+
+    pub fn get_legs(&self) -> &Vec<ProcedureLeg> {
+        &self.legs
+    }
+
+    /// The waypoints flown, in order
+    pub fn get_waypoints(&self) -> Vec<Waypoint> {
+        self.legs.iter().filter_map(|leg| leg.to_waypoint()).collect()
+    }
+
+    /// The leg flown to the waypoint, if it is part of the procedure
+    pub fn get_leg(&self, waypoint: &Waypoint) -> Option<&ProcedureLeg> {
+        self.legs.iter().find(|leg| leg.is_at(waypoint))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::Arc;
+
+    use crate::model::constraint::AltitudeConstraint;
+    use crate::model::fix::Fix;
+
+    use super::{LegPoint, Procedure, ProcedureLeg, ProcedureType};
+
+    pub fn make_leg(id: &str, lat: f64, long: f64, altitude: Option<AltitudeConstraint>) -> ProcedureLeg {
+        let fix = Arc::new(Fix::new(id.to_string(), lat, long));
+        ProcedureLeg::new(id.to_string(), "TF".to_string(), Some(LegPoint::Fix(fix)), altitude, None)
+    }
+
+    pub fn make_sid() -> Procedure {
+        let mut sid = Procedure::new(ProcedureType::Sid, "ABBEY3".to_string());
+        sid.add_leg('1', "RW16B", ProcedureLeg::new("".to_string(), "VA".to_string(), None, Some(AltitudeConstraint::AtOrAbove(1000)), None));
+        sid.add_leg('1', "RW16B", make_leg("AAA", 1.1, 1.0, None));
+        sid.add_leg('1', "RW34B", make_leg("BBB", 0.9, 1.0, None));
+        sid.add_leg('1', "RW34B", make_leg("AAA", 1.1, 1.0, None));
+        sid.add_leg('2', "", make_leg("AAA", 1.1, 1.0, Some(AltitudeConstraint::AtOrAbove(5000))));
+        sid.add_leg('2', "", make_leg("CCC", 1.3, 1.0, None));
+        sid.add_leg('3', "EAST", make_leg("CCC", 1.3, 1.0, None));
+        sid.add_leg('3', "EAST", make_leg("DDD", 1.3, 1.5, None));
+        sid.add_leg('3', "WEST", make_leg("CCC", 1.3, 1.0, None));
+        sid.add_leg('3', "WEST", make_leg("EEE", 1.3, 0.5, None));
+        sid
+    }
+
+    #[test]
+    fn test_runways_and_transitions() {
+        let sid = make_sid();
+        assert_eq!(sid.get_runways(), vec!["16B", "34B"]);
+        assert_eq!(sid.get_transitions(), vec!["EAST", "WEST"]);
+        assert!(sid.serves_runway("16L"));
+        assert!(!sid.serves_runway("07"));
+
+        let approach = Procedure::new(ProcedureType::Approach, "I16R".to_string());
+        assert_eq!(approach.get_runways(), vec!["16R"]);
+    }
+
+    #[test]
+    fn test_sid_legs() {
+        let sid = make_sid();
+        let legs = sid.get_legs(Some("16R"), Some("WEST"));
+        let ids: Vec<&str> = legs.iter().map(|l| l.get_fix_id()).collect();
+        assert_eq!(ids, vec!["", "AAA", "CCC", "EEE"]);
+        assert_eq!(legs[1].get_altitude(), Some(AltitudeConstraint::AtOrAbove(5000)));
+        assert!(legs[0].to_waypoint().is_none());
+
+        let legs = sid.get_legs(Some("34L"), None);
+        let ids: Vec<&str> = legs.iter().map(|l| l.get_fix_id()).collect();
+        assert_eq!(ids, vec!["BBB", "AAA", "CCC"]);
+    }
+
+    #[test]
+    fn test_star_legs() {
+        let mut star = Procedure::new(ProcedureType::Star, "BOREE4".to_string());
+        star.add_leg('1', "NORTH", make_leg("NNN", 2.0, 1.0, None));
+        star.add_leg('1', "NORTH", make_leg("MMM", 1.5, 1.0, None));
+        star.add_leg('2', "ALL", make_leg("MMM", 1.5, 1.0, Some(AltitudeConstraint::Between(7000, 9000))));
+        star.add_leg('2', "ALL", make_leg("LLL", 1.2, 1.0, None));
+        star.add_leg('3', "RW16R", make_leg("KKK", 1.1, 1.0, None));
+        let legs = star.get_legs(Some("16R"), Some("NORTH"));
+        let ids: Vec<&str> = legs.iter().map(|l| l.get_fix_id()).collect();
+        assert_eq!(ids, vec!["NNN", "MMM", "LLL", "KKK"]);
+        assert_eq!(legs[1].get_altitude(), Some(AltitudeConstraint::Between(7000, 9000)));
+    }
+}
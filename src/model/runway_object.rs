@@ -35,13 +35,14 @@ glib::wrapper! {
 }
 
 impl RunwayObject {
-    pub fn new(runway: &Runway, airport: Arc<Airport>) -> RunwayObject {
+    pub fn new(runway: &Runway, airport: Arc<Airport>, in_use: Option<String>) -> RunwayObject {
         let obj: RunwayObject = glib::Object::new();
         let runway_impl = obj.downcast_ref::<RunwayObject>()
             .expect("The item has to be an <RunwayObject>.")
             .imp();
         runway_impl.set_runway(runway.clone());
         runway_impl.set_airport(airport);
+        runway_impl.set_in_use(in_use);
         obj
     }
 }
@@ -58,6 +59,7 @@ mod imp {
     pub struct RunwayObject {
         runway: RefCell<Option<Runway>>,
         airport: RefCell<Option<Arc<Airport>>>,
+        in_use: RefCell<Option<String>>,
     }
 
     impl RunwayObject {
@@ -73,6 +75,13 @@ mod imp {
         pub fn airport(&self) -> Arc<Airport> {
             self.airport.borrow().as_ref().unwrap().clone()
         }
+        // The end of this runway the plan uses, if it uses it
+        pub fn set_in_use(&self, in_use: Option<String>) {
+            self.in_use.replace(in_use);
+        }
+        pub fn in_use(&self) -> Option<String> {
+            self.in_use.borrow().clone()
+        }
     }
 
     /// Basic declaration of our type for the GObject type system
@@ -22,6 +22,9 @@
  *
  */
 use crate::earth::coordinate::Coordinate;
+use crate::earth::geomagnetism::Geomagnetism;
+use crate::model::cruise_level::StepClimb;
+use crate::model::fuel::FuelPlan;
 use crate::model::plan::Plan;
 use crate::preference::UNITS;
 use crate::util::distance_format::DistanceFormat;
@@ -30,12 +33,22 @@ use std::cell::Cell;
 use std::sync::Arc;
 
 use super::airport::Airport;
+use super::location::Location;
+use super::procedure::ProcedureSelection;
 use super::waypoint::Waypoint;
+use super::wind::Wind;
 
 #[derive(Clone)]
 pub struct Sector {
     airport_start: Option<Waypoint>,
     airport_end: Option<Waypoint>,
+    departure_runway: Option<String>,
+    arrival_runway: Option<String>,
+    sid: Option<ProcedureSelection>,
+    star: Option<ProcedureSelection>,
+    approach: Option<ProcedureSelection>,
+    cruise_level: Option<i32>,
+    step_climbs: Vec<StepClimb>,
     waypoints: Vec<Waypoint>,
     dirty: bool,
 }
@@ -45,12 +58,24 @@ impl Sector {
         Sector {
             airport_start: None,
             airport_end: None,
+            departure_runway: None,
+            arrival_runway: None,
+            sid: None,
+            star: None,
+            approach: None,
+            cruise_level: None,
+            step_climbs: Vec::new(),
             waypoints: Vec::with_capacity(10),
             dirty: false,
         }
     }
 
     pub fn set_start(&mut self, start: Option<Arc<Airport>>) {
+        // A runway or procedure only makes sense at the airport it was chosen for
+        if self.airport_start.as_ref().map(|wp| wp.get_id()) != start.as_ref().map(|a| a.get_id()) {
+            self.departure_runway = None;
+            self.set_sid(None);
+        }
         self.airport_start = start.map(|a| {
             Waypoint::Airport {
                 airport: a.clone(),
@@ -61,6 +86,10 @@ impl Sector {
     }
 
     pub fn set_end(&mut self, end: Option<Arc<Airport>>) {
+        if self.airport_end.as_ref().map(|wp| wp.get_id()) != end.as_ref().map(|a| a.get_id()) {
+            self.arrival_runway = None;
+            self.replace_arrival(None, None);
+        }
         self.airport_end = end.map(|a| {
             Waypoint::Airport {
                 airport: a.clone(),
@@ -70,6 +99,164 @@ impl Sector {
         self.dirty = true;
     }
 
+    pub fn get_departure_runway(&self) -> Option<&String> {
+        self.departure_runway.as_ref()
+    }
+
+    pub fn set_departure_runway(&mut self, runway: Option<String>) {
+        self.departure_runway = runway;
+        self.dirty = true;
+    }
+
+    pub fn get_arrival_runway(&self) -> Option<&String> {
+        self.arrival_runway.as_ref()
+    }
+
+    pub fn set_arrival_runway(&mut self, runway: Option<String>) {
+        self.arrival_runway = runway;
+        self.dirty = true;
+    }
+
+    /// Choose the departure runway for the surface wind at the departure airport
+    pub fn select_departure_runway(&mut self, wind: &Wind) {
+        if let Some(Waypoint::Airport { airport, .. }) = &self.airport_start {
+            let runway = airport.select_runway(wind);
+            self.set_departure_runway(runway);
+        }
+    }
+
+    /// Choose the arrival runway for the surface wind at the destination airport
+    pub fn select_arrival_runway(&mut self, wind: &Wind) {
+        if let Some(Waypoint::Airport { airport, .. }) = &self.airport_end {
+            let runway = airport.select_runway(wind);
+            self.set_arrival_runway(runway);
+        }
+    }
+
+    /// The threshold of the departure runway, if one has been chosen
+    pub fn get_departure_threshold(&self) -> Option<Coordinate> {
+        runway_threshold(&self.airport_start, &self.departure_runway)
+    }
+
+    /// The threshold of the arrival runway, if one has been chosen
+    pub fn get_arrival_threshold(&self) -> Option<Coordinate> {
+        runway_threshold(&self.airport_end, &self.arrival_runway)
+    }
+
+    /// The cruise level chosen for the sector, otherwise one is chosen from the plan altitude
+    pub fn get_cruise_level(&self) -> Option<i32> {
+        self.cruise_level
+    }
+
+    pub fn set_cruise_level(&mut self, cruise_level: Option<i32>) {
+        self.cruise_level = cruise_level;
+        self.dirty = true;
+    }
+
+    pub fn get_step_climbs(&self) -> &Vec<StepClimb> {
+        &self.step_climbs
+    }
+
+    pub fn set_step_climbs(&mut self, mut step_climbs: Vec<StepClimb>) {
+        step_climbs.sort_by(|a, b| a.get_distance().total_cmp(&b.get_distance()));
+        self.step_climbs = step_climbs;
+        self.dirty = true;
+    }
+
+    /// The magnetic track from the departure to the destination, which the cruise level rule is applied to
+    pub fn get_magnetic_track(&self) -> Option<f64> {
+        let start = self.airport_start.as_ref()?;
+        let end = self.airport_end.as_ref()?;
+        let geo = Geomagnetism::new(start.get_lat(), start.get_long(), None, None);
+        Some((start.get_loc().bearing_to_deg(end.get_loc()) - geo.get_declination()).rem_euclid(360.0))
+    }
+
+    pub fn get_sid(&self) -> Option<&ProcedureSelection> {
+        self.sid.as_ref()
+    }
+
+    /// Replace the departure procedure, swapping its waypoints for those at the start of the sector
+    pub fn set_sid(&mut self, sid: Option<ProcedureSelection>) {
+        let count = self.get_departure_leg_count();
+        self.waypoints.drain(..count);
+        if let Some(sid) = &sid {
+            for (i, wp) in sid.get_waypoints().into_iter().enumerate() {
+                self.waypoints.insert(i, wp);
+            }
+            if let Some(runway) = sid.get_runway() {
+                self.departure_runway = Some(runway.clone());
+            }
+        }
+        self.sid = sid;
+        self.dirty = true;
+    }
+
+    pub fn get_star(&self) -> Option<&ProcedureSelection> {
+        self.star.as_ref()
+    }
+
+    pub fn set_star(&mut self, star: Option<ProcedureSelection>) {
+        let approach = self.approach.clone();
+        self.replace_arrival(star, approach);
+    }
+
+    pub fn get_approach(&self) -> Option<&ProcedureSelection> {
+        self.approach.as_ref()
+    }
+
+    pub fn set_approach(&mut self, approach: Option<ProcedureSelection>) {
+        let star = self.star.clone();
+        self.replace_arrival(star, approach);
+    }
+
+    /// Swap the waypoints of the arrival procedures at the end of the sector for those of the STAR then the approach
+    fn replace_arrival(&mut self, star: Option<ProcedureSelection>, approach: Option<ProcedureSelection>) {
+        let count = self.get_arrival_leg_count();
+        self.waypoints.truncate(self.waypoints.len() - count);
+        for selection in star.iter().chain(approach.iter()) {
+            for wp in selection.get_waypoints() {
+                // The approach usually starts where the STAR ends
+                let repeated = self.waypoints.last()
+                    .is_some_and(|last| last.get_id() == wp.get_id() && last.get_loc() == wp.get_loc());
+                if !repeated {
+                    self.waypoints.push(wp);
+                }
+            }
+        }
+        if let Some(runway) = approach.as_ref().or(star.as_ref()).and_then(|p| p.get_runway()) {
+            self.arrival_runway = Some(runway.clone());
+        }
+        self.star = star;
+        self.approach = approach;
+        self.dirty = true;
+    }
+
+    /// Set the procedures of a sector read back with their waypoints already in place
+    pub fn restore_procedures(&mut self, sid: Option<ProcedureSelection>, star: Option<ProcedureSelection>, approach: Option<ProcedureSelection>) {
+        self.sid = sid;
+        self.star = star;
+        self.approach = approach;
+    }
+
+    /// The number of waypoints at the start of the sector flown on the departure procedure
+    pub fn get_departure_leg_count(&self) -> usize {
+        match &self.sid {
+            Some(sid) => self.waypoints.iter().take_while(|wp| sid.get_leg(wp).is_some()).count(),
+            None => 0,
+        }
+    }
+
+    /// The number of waypoints at the end of the sector flown on the arrival procedures
+    pub fn get_arrival_leg_count(&self) -> usize {
+        let departure = self.get_departure_leg_count();
+        self.waypoints[departure..].iter()
+            .rev()
+            .take_while(|wp| {
+                self.star.iter().chain(self.approach.iter()).any(|p| p.get_leg(wp).is_some())
+            })
+            .count()
+    }
+
     pub fn insert_waypoint(&mut self, index: usize, waypoint: Waypoint) {
         if index <= self.waypoints.len() {
             self.waypoints.insert(index, waypoint);
@@ -135,6 +322,7 @@ impl Sector {
                             loc: Coordinate::new(0.0, 0.0),
                             elevation: Cell::new(0),
                             locked: false,
+                            constraints: Cell::default(),
                         })
                     } else {
                         self.waypoints.as_slice()[close_wp_index as usize - 1].clone()
@@ -146,6 +334,7 @@ impl Sector {
                             loc: Coordinate::new(0.0, 0.0),
                             elevation: Cell::new(0),
                             locked: false,
+                            constraints: Cell::default(),
                         })
                     } else {
                         self.waypoints.as_slice()[close_wp_index as usize + 1].clone()
@@ -288,6 +477,28 @@ impl Sector {
         d + d1
     }
 
+    pub fn get_trip_fuel(&self, plan: &Plan) -> f64 {
+        let f = self.waypoints
+            .iter()
+            .map(move |wp| plan.get_fuel_burn_to(wp))
+            .reduce(|acc, t| acc + t)
+            .unwrap_or(0.0);
+        let f1 = if let Some(end) = &self.airport_end {
+            plan.get_fuel_burn_to(end)
+        } else {
+            0.0
+        };
+        f + f1
+    }
+
+    pub fn get_fuel_plan(&self, plan: &Plan) -> Option<FuelPlan> {
+        plan.get_aircraft()
+            .as_ref()
+            .map(|a| a.get_fuel())
+            .filter(|f| f.is_planned())
+            .map(|f| FuelPlan::new(f, self.get_trip_fuel(plan)))
+    }
+
     pub fn is_dirty(&self) -> bool {
         self.dirty
     }
@@ -297,6 +508,15 @@ impl Sector {
     }
 }
 
+fn runway_threshold(airport: &Option<Waypoint>, runway: &Option<String>) -> Option<Coordinate> {
+    match (airport, runway) {
+        (Some(Waypoint::Airport { airport, .. }), Some(number)) => {
+            airport.get_runway_for(number).and_then(|r| r.threshold_of(number))
+        }
+        _ => None,
+    }
+}
+
 impl Default for Sector {
     fn default() -> Self {
         Self::new()
@@ -307,7 +527,12 @@ impl Default for Sector {
 mod tests {
     use std::cell::Cell;
 
+    use std::sync::Arc;
+
     use crate::earth::coordinate::Coordinate;
+    use crate::model::constraint::AltitudeConstraint;
+    use crate::model::procedure::tests::{make_leg, make_sid};
+    use crate::model::procedure::{Procedure, ProcedureSelection, ProcedureType};
     use crate::model::test_utils::tests::make_airport;
     use crate::model::waypoint::Waypoint;
 
@@ -336,21 +561,25 @@ mod tests {
             loc: Coordinate::new(13.0, 111.0),
             elevation: Cell::new(10),
             locked: false,
+            constraints: Cell::default(),
         };
         let w2 = Waypoint::Simple {
             loc: Coordinate::new(23.0, 121.0),
             elevation: Cell::new(20),
             locked: false,
+            constraints: Cell::default(),
         };
         let w3 = Waypoint::Simple {
             loc: Coordinate::new(33.0, 131.0),
             elevation: Cell::new(30),
             locked: false,
+            constraints: Cell::default(),
         };
         let w4 = Waypoint::Simple {
             loc: Coordinate::new(43.0, 141.0),
             elevation: Cell::new(40),
             locked: false,
+            constraints: Cell::default(),
         };
 
         s.add_waypoint(w1.clone());
@@ -372,4 +601,50 @@ mod tests {
         assert_eq!(wps.get(1).unwrap().get_loc(), w4.get_loc());
         assert_eq!(wps.get(2).unwrap().get_loc(), w3.get_loc());
     }
+
+    #[test]
+    fn test_procedures() {
+        let mut s = Sector::new();
+        s.set_start(Some(make_airport("YSSY")));
+        s.set_end(Some(make_airport("YMLB")));
+        let enroute = Waypoint::Simple {
+            loc: Coordinate::new(3.0, 1.0),
+            elevation: Cell::new(0),
+            locked: true,
+            constraints: Cell::default(),
+        };
+        s.add_waypoint(enroute.clone());
+
+        let sid = ProcedureSelection::new(Arc::new(make_sid()), Some("16R".to_string()), Some("WEST".to_string()));
+        s.set_sid(Some(sid));
+        let ids: Vec<&str> = s.get_waypoints().iter().map(|wp| wp.get_id()).collect();
+        assert_eq!(ids, vec!["AAA", "CCC", "EEE", "GPS"]);
+        assert_eq!(s.get_departure_leg_count(), 3);
+        assert_eq!(s.get_departure_runway(), Some(&"16R".to_string()));
+        let aaa = s.get_waypoint(0).unwrap();
+        assert_eq!(aaa.get_constraints().get_altitude(), Some(AltitudeConstraint::AtOrAbove(5000)));
+
+        let mut star = Procedure::new(ProcedureType::Star, "BOREE4".to_string());
+        star.add_leg('2', "ALL", make_leg("MMM", 4.0, 1.0, None));
+        star.add_leg('2', "ALL", make_leg("LLL", 4.5, 1.0, None));
+        let mut approach = Procedure::new(ProcedureType::Approach, "I16R".to_string());
+        approach.add_leg('I', "", make_leg("LLL", 4.5, 1.0, Some(AltitudeConstraint::At(3000))));
+        approach.add_leg('I', "", make_leg("RW16R", 4.8, 1.0, None));
+        s.set_approach(Some(ProcedureSelection::new(Arc::new(approach), Some("16R".to_string()), None)));
+        s.set_star(Some(ProcedureSelection::new(Arc::new(star), None, None)));
+        let ids: Vec<&str> = s.get_waypoints().iter().map(|wp| wp.get_id()).collect();
+        assert_eq!(ids, vec!["AAA", "CCC", "EEE", "GPS", "MMM", "LLL", "RW16R"]);
+        assert_eq!(s.get_arrival_leg_count(), 3);
+        assert_eq!(s.get_arrival_runway(), Some(&"16R".to_string()));
+
+        s.set_sid(None);
+        s.set_star(None);
+        let ids: Vec<&str> = s.get_waypoints().iter().map(|wp| wp.get_id()).collect();
+        assert_eq!(ids, vec!["GPS", "LLL", "RW16R"]);
+
+        // Changing the destination drops its procedures
+        s.set_end(Some(make_airport("YSCB")));
+        assert_eq!(s.get_waypoint_count(), 1);
+        assert!(s.get_approach().is_none());
+    }
 }
@@ -50,7 +50,7 @@ mod imp {
     use adw::glib::Object;
     use gtk::{glib, Label};
     use adw::prelude::StaticType;
-    use adw::subclass::prelude::{ListModelImpl, ObjectImpl, ObjectImplExt, ObjectSubclass};
+    use adw::subclass::prelude::{ListModelImpl, ObjectImpl, ObjectImplExt, ObjectSubclass, ObjectSubclassIsExt};
 
     use crate::model::sector::Sector;
     use crate::model::waypoint_object::WaypointObject;
@@ -118,7 +118,10 @@ mod imp {
             let mut pos = position as usize;
             if let Some(wp) = sector.get_start() {
                 if pos == 0 {
-                    return Some(Object::from(WaypointObject::new(&wp)));
+                    let object = WaypointObject::new(&wp);
+                    object.imp().set_runway(sector.get_departure_runway().cloned());
+                    object.imp().set_procedures(sector.get_sid().map(|p| p.get_ident().to_string()));
+                    return Some(Object::from(object));
                 }
                 pos -= 1;
             }
@@ -129,7 +132,16 @@ mod imp {
             }
             if pos == sector.get_waypoint_count() {
                 if let Some(wp) = sector.get_end() {
-                    return Some(Object::from(WaypointObject::new(&wp)))
+                    let object = WaypointObject::new(&wp);
+                    object.imp().set_runway(sector.get_arrival_runway().cloned());
+                    let procedures: Vec<&str> = sector.get_star().iter()
+                        .chain(sector.get_approach().iter())
+                        .map(|p| p.get_ident())
+                        .collect();
+                    if !procedures.is_empty() {
+                        object.imp().set_procedures(Some(procedures.join(" / ")));
+                    }
+                    return Some(Object::from(object))
                 }
             }
             None
@@ -28,7 +28,7 @@ use std::sync::Arc;
 use crate::earth::coordinate::Coordinate;
 use crate::model::location::Location;
 
-use super::{airport::Airport, fix::Fix, navaid::Navaid};
+use super::{airport::Airport, airway::AirwayLeg, constraint::Constraints, fix::Fix, navaid::Navaid};
 
 #[derive(Clone, PartialEq)]
 pub enum Waypoint {
@@ -36,6 +36,7 @@ pub enum Waypoint {
         loc: Coordinate,
         elevation: Cell<i32>,
         locked: bool,
+        constraints: Cell<Constraints>,
     },
     Toc {
         loc: Coordinate,
@@ -51,11 +52,15 @@ pub enum Waypoint {
         navaid: Arc<Navaid>,
         elevation: Cell<i32>,
         locked: bool,
+        airway: Option<AirwayLeg>,
+        constraints: Cell<Constraints>,
     },
     Fix {
         fix: Arc<Fix>,
         elevation: Cell<i32>,
         locked: bool,
+        airway: Option<AirwayLeg>,
+        constraints: Cell<Constraints>,
     },
     Airport {
         airport: Arc<Airport>,
@@ -64,7 +69,7 @@ pub enum Waypoint {
 }
 
 impl Waypoint {
-    pub(crate) fn get_type_name(&self) -> &str {
+    pub fn get_type_name(&self) -> &str {
         match self {
             Waypoint::Simple { .. } => "GPS",
             Waypoint::Toc { .. } => "TOC",
@@ -75,7 +80,7 @@ impl Waypoint {
         }
     }
 
-    pub(crate) fn get_id(&self) -> &str {
+    pub fn get_id(&self) -> &str {
         match self {
             Waypoint::Simple { .. } => "GPS",
             Waypoint::Toc { .. } => "TOC",
@@ -97,7 +102,7 @@ impl Waypoint {
         }
     }
 
-    pub(crate) fn get_elevation(&self) -> i32 {
+    pub fn get_elevation(&self) -> i32 {
         match self {
             Waypoint::Simple {
                 loc: _, elevation, ..
@@ -120,7 +125,7 @@ impl Waypoint {
         }
     }
 
-    pub(crate) fn get_loc(&self) -> &Coordinate {
+    pub fn get_loc(&self) -> &Coordinate {
         match self {
             Waypoint::Simple { loc, .. } => loc,
             Waypoint::Toc { loc, .. } => loc,
@@ -141,7 +146,7 @@ impl Waypoint {
             _ => None,
         }
     }
-    pub(crate) fn get_lat_as_string(&self) -> String {
+    pub fn get_lat_as_string(&self) -> String {
         self.get_loc().get_latitude_as_string()
     }
 
@@ -149,7 +154,7 @@ impl Waypoint {
         self.get_loc().get_longitude()
     }
 
-    pub(crate) fn get_long_as_string(&self) -> String {
+    pub fn get_long_as_string(&self) -> String {
         self.get_loc().get_longitude_as_string()
     }
 
@@ -160,6 +165,7 @@ impl Waypoint {
                 loc: _,
                 elevation: _,
                 locked,
+                ..
             } => locked,
             Waypoint::Toc {
                 loc: _,
@@ -175,11 +181,13 @@ impl Waypoint {
                 navaid: _,
                 elevation: _,
                 locked,
+                ..
             } => locked,
             Waypoint::Fix {
                 fix: _,
                 elevation: _,
                 locked,
+                ..
             } => locked,
             Waypoint::Airport {
                 airport: _,
@@ -188,7 +196,35 @@ impl Waypoint {
         }
     }
 
-    pub(crate) fn get_airport(&self) -> Arc<Airport> {
+    /// The airway this waypoint is reached by, if the leg to it follows an airway.
+    pub fn get_airway(&self) -> Option<&AirwayLeg> {
+        match self {
+            Waypoint::Navaid { airway, .. } => airway.as_ref(),
+            Waypoint::Fix { airway, .. } => airway.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// The altitude and speed constraints at the waypoint. Computed and airport waypoints have none.
+    pub fn get_constraints(&self) -> Constraints {
+        match self {
+            Waypoint::Simple { constraints, .. } => constraints.get(),
+            Waypoint::Navaid { constraints, .. } => constraints.get(),
+            Waypoint::Fix { constraints, .. } => constraints.get(),
+            _ => Constraints::default(),
+        }
+    }
+
+    pub fn set_constraints(&self, new_constraints: Constraints) {
+        match self {
+            Waypoint::Simple { constraints, .. } => constraints.set(new_constraints),
+            Waypoint::Navaid { constraints, .. } => constraints.set(new_constraints),
+            Waypoint::Fix { constraints, .. } => constraints.set(new_constraints),
+            _ => {}
+        }
+    }
+
+    pub fn get_airport(&self) -> Arc<Airport> {
         match self {
             Waypoint::Airport { airport, .. } => airport.clone(),
             _ => {
@@ -197,7 +233,7 @@ impl Waypoint {
         }
     }
 
-    pub(crate) fn set_elevation(&self, elev: &i32) {
+    pub fn set_elevation(&self, elev: &i32) {
         match self {
             Waypoint::Simple {
                 loc: _, elevation, ..
@@ -241,9 +277,9 @@ mod tests {
     #[test]
     fn test_equality() {
         let w1 =
-            Waypoint::Simple{loc: Coordinate::new(13.0, 111.0), elevation: Cell::new(10), locked: false};
+            Waypoint::Simple{loc: Coordinate::new(13.0, 111.0), elevation: Cell::new(10), locked: false, constraints: Cell::default()};
         let w2 =
-            Waypoint::Simple{loc: Coordinate::new(23.0, 121.0), elevation: Cell::new(20), locked: false};
+            Waypoint::Simple{loc: Coordinate::new(23.0, 121.0), elevation: Cell::new(20), locked: false, constraints: Cell::default()};
 
         let a = Box::new(w1.clone());
         let b = Box::new(w1.clone());
@@ -270,7 +306,7 @@ mod tests {
     #[test]
     fn test_equality_diff_type() {
         let w1 =
-            Waypoint::Simple { loc: Coordinate::new(13.0, 111.0), elevation: Cell::new(10), locked: false };
+            Waypoint::Simple { loc: Coordinate::new(13.0, 111.0), elevation: Cell::new(10), locked: false, constraints: Cell::default() };
         let ap = make_airport("YSSY");
         let w2 = Waypoint::Airport {airport: ap, locked: false};
         let a = Box::new(w1.clone());
@@ -54,6 +54,8 @@ mod imp {
     #[derive(Default)]
     pub struct WaypointObject {
         waypoint: RefCell<Option<Waypoint>>,
+        runway: RefCell<Option<String>>,
+        procedures: RefCell<Option<String>>,
         ui: RefCell<Option<Label>>
     }
 
@@ -66,6 +68,24 @@ mod imp {
             self.waypoint.clone()
         }
 
+        // The runway in use, for an airport a sector starts or ends at
+        pub fn set_runway(&self, runway: Option<String>) {
+            self.runway.replace(runway);
+        }
+
+        pub fn runway(&self) -> Option<String> {
+            self.runway.borrow().clone()
+        }
+
+        // The procedures flown from or to an airport a sector starts or ends at
+        pub fn set_procedures(&self, procedures: Option<String>) {
+            self.procedures.replace(procedures);
+        }
+
+        pub fn procedures(&self) -> Option<String> {
+            self.procedures.borrow().clone()
+        }
+
         pub fn set_ui(&self, label: Option<Label>) {
             self.ui.replace(label);
         }
@@ -112,7 +132,8 @@ mod imp {
                             .read()
                             .expect("Could not get waypoint lock");
                         runways.get(pos).map(|rwy| {
-                            Object::from(RunwayObject::new(rwy, airport.clone()))
+                            let in_use = self.runway().filter(|n| rwy.heading_of(n).is_some());
+                            Object::from(RunwayObject::new(rwy, airport.clone(), in_use))
                         })
                     } else {
                         None
@@ -0,0 +1,325 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+use std::path::PathBuf;
+
+use crate::earth::coordinate::Coordinate;
+
+/// A wind, given as the true direction it blows from in degrees and its speed in knots.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Wind {
+    direction: f64,
+    speed: f64,
+}
+
+impl Wind {
+    pub fn new(direction: f64, speed: f64) -> Self {
+        Self {
+            direction: direction.rem_euclid(360.0),
+            speed,
+        }
+    }
+
+    pub fn get_direction(&self) -> f64 {
+        self.direction
+    }
+
+    pub fn get_speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn is_calm(&self) -> bool {
+        self.speed < 0.5
+    }
+
+    /// Parse a wind such as "270/15".
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (dir, speed) = text.split_once('/').ok_or(format!("Wind \"{}\" should be direction/speed", text))?;
+        let dir = dir.trim().parse::<f64>().map_err(|_| format!("Invalid direction in wind \"{}\"", text))?;
+        let speed = speed.trim().parse::<f64>().map_err(|_| format!("Invalid speed in wind \"{}\"", text))?;
+        Ok(Wind::new(dir, speed))
+    }
+
+    /// The headwind and crosswind components for a runway or course with the given true heading.
+    /// A negative headwind is a tailwind, a positive crosswind blows from the right.
+    pub fn components(&self, heading: f64) -> (f64, f64) {
+        let angle = (self.direction - heading).to_radians();
+        (self.speed * angle.cos(), self.speed * angle.sin())
+    }
+
+    /// Solve the wind triangle for a leg flown along the true `course` at `tas` knots.
+    pub fn correct(&self, course: f64, tas: f64) -> WindCorrection {
+        if tas <= 0.0 {
+            return WindCorrection {
+                wca: 0.0,
+                heading: course,
+                ground_speed: 0.0,
+            };
+        }
+        let angle = (self.direction - course).to_radians();
+        // If the wind is stronger than the aircraft can correct for, crab as far as possible
+        let sin_wca = (self.speed * angle.sin() / tas).clamp(-1.0, 1.0);
+        let wca = sin_wca.asin();
+        let ground_speed = (tas * wca.cos() - self.speed * angle.cos()).max(0.0);
+        WindCorrection {
+            wca: wca.to_degrees(),
+            heading: (course + wca.to_degrees()).rem_euclid(360.0),
+            ground_speed,
+        }
+    }
+
+    fn to_components(&self) -> (f64, f64) {
+        let rad = self.direction.to_radians();
+        (self.speed * rad.sin(), self.speed * rad.cos())
+    }
+
+    fn from_components(u: f64, v: f64) -> Self {
+        let speed = (u * u + v * v).sqrt();
+        if speed == 0.0 {
+            Wind::default()
+        } else {
+            Wind::new(u.atan2(v).to_degrees(), speed)
+        }
+    }
+}
+
+/// The result of solving the wind triangle for a leg.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindCorrection {
+    wca: f64,
+    heading: f64,
+    ground_speed: f64,
+}
+
+impl WindCorrection {
+    /// Wind correction angle in degrees, positive to the right of course
+    pub fn get_wca(&self) -> f64 {
+        self.wca
+    }
+
+    /// True heading to fly in degrees
+    pub fn get_heading(&self) -> f64 {
+        self.heading
+    }
+
+    /// Ground speed in knots
+    pub fn get_ground_speed(&self) -> f64 {
+        self.ground_speed
+    }
+}
+
+/// The winds at a series of altitudes. Between levels the wind is interpolated,
+/// above and below them the nearest level is used.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WindProfile {
+    levels: Vec<(i32, Wind)>,
+}
+
+impl WindProfile {
+    pub fn new(mut levels: Vec<(i32, Wind)>) -> Self {
+        levels.sort_by_key(|(alt, _)| *alt);
+        Self { levels }
+    }
+
+    pub fn get_levels(&self) -> &Vec<(i32, Wind)> {
+        &self.levels
+    }
+
+    pub fn wind_at(&self, altitude: i32) -> Wind {
+        let Some(first) = self.levels.first() else {
+            return Wind::default();
+        };
+        if altitude <= first.0 {
+            return first.1.clone();
+        }
+        for pair in self.levels.windows(2) {
+            let (lower_alt, lower) = &pair[0];
+            let (upper_alt, upper) = &pair[1];
+            if altitude <= *upper_alt {
+                let ratio = (altitude - lower_alt) as f64 / (upper_alt - lower_alt) as f64;
+                let (lu, lv) = lower.to_components();
+                let (uu, uv) = upper.to_components();
+                return Wind::from_components(lu + (uu - lu) * ratio, lv + (uv - lv) * ratio);
+            }
+        }
+        self.levels.last().map(|(_, w)| w.clone()).unwrap_or_default()
+    }
+
+    /// Parse a profile such as "270/25" (the same wind at all levels) or
+    /// "3000:270/15 9000:280/30" (wind by altitude in feet).
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut levels = Vec::new();
+        for token in text.split([' ', ',']).filter(|t| !t.is_empty()) {
+            let (alt, wind) = match token.split_once(':') {
+                Some((alt, wind)) => {
+                    let alt = alt.parse::<i32>().map_err(|_| format!("Invalid altitude in wind \"{}\"", token))?;
+                    (alt, wind)
+                }
+                None => (0, token),
+            };
+            levels.push((alt, Wind::parse(wind)?));
+        }
+        Ok(WindProfile::new(levels))
+    }
+}
+
+impl std::fmt::Display for WindProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let levels: Vec<String> = self.levels.iter()
+            .map(|(alt, wind)| {
+                if self.levels.len() == 1 && *alt == 0 {
+                    format!("{:03.0}/{:.0}", wind.direction, wind.speed)
+                } else {
+                    format!("{}:{:03.0}/{:.0}", alt, wind.direction, wind.speed)
+                }
+            })
+            .collect();
+        write!(f, "{}", levels.join(" "))
+    }
+}
+
+/// Winds aloft used to correct headings, ground speeds and times in a plan.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WindsAloft {
+    /// The same profile everywhere along the route
+    Profile(WindProfile),
+    /// A gridded field, loaded from a local file; each point has its own profile
+    Grid {
+        path: PathBuf,
+        points: Vec<(Coordinate, WindProfile)>,
+    },
+}
+
+impl WindsAloft {
+    pub fn wind_at(&self, loc: &Coordinate, altitude: i32) -> Wind {
+        match self {
+            WindsAloft::Profile(profile) => profile.wind_at(altitude),
+            WindsAloft::Grid { points, .. } => {
+                // Use the nearest grid point, distances on a one degree grid are small enough
+                // that comparing squared degrees is good enough to pick it.
+                points.iter()
+                    .min_by(|(a, _), (b, _)| {
+                        grid_distance(a, loc).partial_cmp(&grid_distance(b, loc)).unwrap_or(std::cmp::Ordering::Equal)
+                    })
+                    .map(|(_, profile)| profile.wind_at(altitude))
+                    .unwrap_or_default()
+            }
+        }
+    }
+}
+
+fn grid_distance(a: &Coordinate, b: &Coordinate) -> f64 {
+    let d_lat = a.get_latitude() - b.get_latitude();
+    let mut d_long = (a.get_longitude() - b.get_longitude()).abs();
+    if d_long > 180.0 {
+        d_long = 360.0 - d_long;
+    }
+    let d_long = d_long * b.get_latitude().to_radians().cos();
+    d_lat * d_lat + d_long * d_long
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::earth::coordinate::Coordinate;
+
+    use super::{Wind, WindProfile, WindsAloft};
+
+    #[test]
+    fn test_wind_correction() {
+        // Direct headwind
+        let c = Wind::new(360.0, 20.0).correct(0.0, 100.0);
+        assert_eq!(c.get_wca().round(), 0.0);
+        assert_eq!(c.get_ground_speed().round(), 80.0);
+
+        // Direct tailwind
+        let c = Wind::new(180.0, 20.0).correct(0.0, 100.0);
+        assert_eq!(c.get_ground_speed().round(), 120.0);
+
+        // Crosswind from the right needs the heading to the right
+        let c = Wind::new(90.0, 20.0).correct(0.0, 100.0);
+        assert_eq!(c.get_wca().round(), 12.0);
+        assert_eq!(c.get_heading().round(), 12.0);
+        assert_eq!(c.get_ground_speed().round(), 98.0);
+
+        // Crosswind from the left, wrapping through north
+        let c = Wind::new(270.0, 20.0).correct(0.0, 100.0);
+        assert_eq!(c.get_heading().round(), 348.0);
+
+        // Calm
+        let c = Wind::default().correct(123.0, 100.0);
+        assert_eq!(c.get_heading(), 123.0);
+        assert_eq!(c.get_ground_speed(), 100.0);
+    }
+
+    #[test]
+    fn test_components() {
+        // Landing on 16 with the wind from 190
+        let (head, cross) = Wind::new(190.0, 20.0).components(160.0);
+        assert_eq!(head.round(), 17.0);
+        assert_eq!(cross.round(), 10.0);
+
+        // The other way it is a tailwind from the left
+        let (head, cross) = Wind::new(190.0, 20.0).components(340.0);
+        assert_eq!(head.round(), -17.0);
+        assert_eq!(cross.round(), -10.0);
+
+        assert_eq!(Wind::parse("270/15"), Ok(Wind::new(270.0, 15.0)));
+        assert!(Wind::parse("270").is_err());
+    }
+
+    #[test]
+    fn test_profile() {
+        let profile = WindProfile::parse("3000:270/10 9000:270/30").unwrap();
+        assert_eq!(profile.wind_at(0).get_speed().round(), 10.0);
+        assert_eq!(profile.wind_at(6000).get_speed().round(), 20.0);
+        assert_eq!(profile.wind_at(6000).get_direction().round(), 270.0);
+        assert_eq!(profile.wind_at(20000).get_speed().round(), 30.0);
+        assert_eq!(profile.to_string(), "3000:270/10 9000:270/30");
+
+        let profile = WindProfile::parse("090/15").unwrap();
+        assert_eq!(profile.wind_at(35000), Wind::new(90.0, 15.0));
+        assert_eq!(profile.to_string(), "090/15");
+
+        assert!(WindProfile::parse("270-15").is_err());
+        assert!(WindProfile::parse("FL100:270/15").is_err());
+        assert!(WindProfile::parse("").unwrap().wind_at(1000).is_calm());
+    }
+
+    #[test]
+    fn test_grid() {
+        let grid = WindsAloft::Grid {
+            path: PathBuf::from("winds.txt"),
+            points: vec![
+                (Coordinate::new(-34.0, 151.0), WindProfile::parse("270/20").unwrap()),
+                (Coordinate::new(-34.0, 150.0), WindProfile::parse("180/10").unwrap()),
+                (Coordinate::new(-17.0, 180.0), WindProfile::parse("090/5").unwrap()),
+            ],
+        };
+        assert_eq!(grid.wind_at(&Coordinate::new(-34.1, 150.8), 5000), Wind::new(270.0, 20.0));
+        assert_eq!(grid.wind_at(&Coordinate::new(-34.1, 150.2), 5000), Wind::new(180.0, 10.0));
+        assert_eq!(grid.wind_at(&Coordinate::new(-17.0, -179.8), 5000), Wind::new(90.0, 5.0));
+    }
+}
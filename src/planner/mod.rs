@@ -23,4 +23,4 @@
  */
 #![forbid(unsafe_code)]
 
-pub(crate) mod planner;
+pub mod planner;
@@ -25,44 +25,31 @@
 use std::cell::Cell;
 use std::collections::BinaryHeap;
 use std::cmp::Ordering;
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
 use log::debug;
 use crate::earth;
 use crate::earth::coordinate::Coordinate;
+use crate::earth::Earth;
+use crate::earth::terrain::{get_clearance, get_terrain};
 use crate::model::aircraft::Aircraft;
+use crate::model::airway::AirwayLeg;
+use crate::model::cruise_level::{LevelRegions, StepClimb};
 use crate::model::fix::Fix;
 use crate::model::location::Location;
-use crate::model::navaid::{Navaid, NavaidType};
+use crate::model::navaid::Navaid;
 use crate::model::plan::Plan;
+use crate::model::plan_settings::PlanSettings;
 use crate::model::sector::Sector;
 use crate::model::waypoint::Waypoint;
 use crate::preference::*;
-use crate::util::location_filter::{AndFilter, Filter, RangeFilter, VorFilter};
 
 pub const ARRIVAL_BEACON_RANGE: f64 = 10.0;
-
-struct CigarFilter {
-    from: Coordinate,
-    to: Coordinate,
-    distance: f64,
-}
-impl CigarFilter {
-    fn new(from: Coordinate, to: Coordinate) -> Self {
-        let distance = from.distance_to(&to) * 1.2;
-        Self {
-            from,
-            to,
-            distance,
-        }
-    }
-}
-impl Filter for CigarFilter {
-    fn filter(&self, loc: &dyn Location) -> bool {
-        let d1 = pythagorean_distance(&self.from, loc.get_loc());
-        let d2 = pythagorean_distance(&self.to, loc.get_loc());
-        d1 + d2 < self.distance
-    }
-}
+// How far off the direct route navaids and fixes are considered, see SpatialIndex::within_corridor
+const CORRIDOR_RATIO: f64 = 1.2;
+// Legs flown off airway are made to look this much longer so published airways are preferred
+const OFF_AIRWAY_PENALTY: f64 = 1.5;
+// Cost in nm of changing from one airway to another
+const AIRWAY_CHANGE_PENALTY: f64 = 5.0;
 
 // Map waypoint ID (if any) or coordinate to index might be slow,
 // using index in pq instead might be better but we need the index.
@@ -91,8 +78,7 @@ pub struct Planner<'a> {
     plan_type: String,
     add_gps_waypoints: bool,
     add_waypoint_bias: bool,
-    navaids: &'a RwLock<Vec<Arc<Navaid>>>,
-    fixes: &'a RwLock<Vec<Arc<Fix>>>,
+    earth: &'a Earth,
 }
 
 impl Planner<'_> {
@@ -110,59 +96,105 @@ impl Planner<'_> {
             plan_type: pref
                 .get::<String>(PLAN_TYPE)
                 .unwrap_or(USE_RADIO_BEACONS.to_string()),
-            navaids: earth::get_earth_model().get_navaids(),
-            fixes: earth::get_earth_model().get_fixes(),
+            earth: earth::get_earth_model(),
         }
     }
-    pub(crate) fn make_plan(&self, sector: &Sector) -> Vec<Waypoint> {
+    /// Plan with `plan_type` instead of the type set in the preferences.
+    pub fn set_plan_type(&mut self, plan_type: &str) {
+        self.plan_type = plan_type.to_string();
+    }
+
+    /// The settings this planner plans with, to be kept with the plan it makes.
+    pub fn get_settings(&self) -> PlanSettings {
+        PlanSettings::new(
+            manager().get::<String>(UNITS).unwrap_or(UNITS_NM.to_string()),
+            self.plan_type.clone(),
+            self.vor_only,
+            self.vor_preferred,
+            self.add_gps_waypoints,
+            self.max_leg_distance,
+            self.min_leg_distance,
+        )
+    }
+
+    pub fn make_plan(&self, sector: &Sector) -> Vec<Waypoint> {
 
         let timer = std::time::Instant::now();
         let mut plan: Vec<Waypoint> = Vec::new();
 
-        let from = sector.get_start();
-        let to = sector.get_end();
+        // Departure and arrival procedures are flown as published, only the en-route part is planned
+        let waypoints = sector.get_waypoints();
+        let departure = &waypoints[..sector.get_departure_leg_count()];
+        let arrival = &waypoints[waypoints.len() - sector.get_arrival_leg_count()..];
+        let enroute = &waypoints[departure.len()..waypoints.len() - arrival.len()];
+
+        // Otherwise plan from and to the runway thresholds where runways have been chosen
+        let from = sector.get_start().map(|wp| match departure.last() {
+            Some(last) => last.clone(),
+            None => at_threshold(wp, sector.get_departure_threshold()),
+        });
+        let to = sector.get_end().map(|wp| match arrival.first() {
+            Some(first) => first.clone(),
+            None => at_threshold(wp, sector.get_arrival_threshold()),
+        });
 
         if let Some(from) = from {
             if let Some(to) = to {
+                let mut route: Vec<Waypoint> = Vec::new();
                 if self.plan_type == USE_RADIO_BEACONS {
                     let mut prev_wp = from.clone();
                     // add all the manually added waypoints into the plan
-                    let old_wps = sector.get_waypoints();
-                    for wp in old_wps.iter() {
+                    for wp in enroute.iter() {
                         if *wp.is_locked() {
-                            plan.extend(self.dijkstra_navaids(&prev_wp, &wp.clone()));
-                            plan.push(wp.clone());
+                            route.extend(self.dijkstra_navaids(&prev_wp, &wp.clone()));
+                            route.push(wp.clone());
                             prev_wp = wp.clone();
                         }
                     }
 
-                    if let Some(wp) = self.get_arrival_beacon(&to) {
-                        plan.extend(self.dijkstra_navaids(&prev_wp, &wp));
-                        plan.push(wp);
+                    if let Some(wp) = self.get_arrival_beacon(&to).filter(|_| arrival.is_empty()) {
+                        route.extend(self.dijkstra_navaids(&prev_wp, &wp));
+                        route.push(wp);
                     } else {
-                        plan.extend(self.dijkstra_navaids(&prev_wp, &to.clone()));
+                        route.extend(self.dijkstra_navaids(&prev_wp, &to.clone()));
                     }
                     if self.add_gps_waypoints {
-                        self.add_waypoints(&from, &to, &mut plan);
+                        self.add_waypoints(&from, &to, &mut route);
                     }
                 } else if self.plan_type == USE_FIXES {
                     let mut prev_wp = from.clone();
                     // add all the manually added waypoints into the plan
-                    let old_wps = sector.get_waypoints();
-                    for wp in old_wps.iter() {
+                    for wp in enroute.iter() {
+                        if *wp.is_locked() {
+                            route.extend(self.dijkstra_fixes(&prev_wp, &wp.clone()));
+                            route.push(wp.clone());
+                            prev_wp = wp.clone();
+                        }
+                    }
+                    route.extend(self.dijkstra_fixes(&prev_wp, &to.clone()));
+                    if self.add_gps_waypoints {
+                        self.add_waypoints(&from, &to, &mut route);
+                    }
+                } else if self.plan_type == USE_AIRWAYS {
+                    let mut prev_wp = from.clone();
+                    // add all the manually added waypoints into the plan
+                    for wp in enroute.iter() {
                         if *wp.is_locked() {
-                            plan.extend(self.dijkstra_fixes(&prev_wp, &wp.clone()));
-                            plan.push(wp.clone());
+                            route.extend(self.dijkstra_airways(&prev_wp, &wp.clone()));
+                            route.push(wp.clone());
                             prev_wp = wp.clone();
                         }
                     }
-                    plan.extend(self.dijkstra_fixes(&prev_wp, &to.clone()));
+                    route.extend(self.dijkstra_airways(&prev_wp, &to.clone()));
                     if self.add_gps_waypoints {
-                        self.add_waypoints(&from, &to, &mut plan);
+                        self.add_waypoints(&from, &to, &mut route);
                     }
                 } else {
-                    self.add_waypoints(&from, &to, &mut plan);
+                    self.add_waypoints(&from, &to, &mut route);
                 }
+                plan.extend_from_slice(departure);
+                plan.extend(route);
+                plan.extend_from_slice(arrival);
             }
         }
         debug!("Planning took {} ms", timer.elapsed().as_millis());
@@ -189,11 +221,13 @@ impl Planner<'_> {
         let mut nodes = Vec::new();
         nodes.push((from.clone(), false));  // (waypoint, is_vor)
         for n in relevant_navaids {
-            let is_vor = n.get_type() == NavaidType::Vor;
+            let is_vor = n.get_type().is_vor();
             nodes.push((Waypoint::Navaid {
                 navaid: n,
                 elevation: Cell::new(0),
                 locked: false,
+                airway: None,
+                constraints: Cell::default(),
             }, is_vor));
         }
         nodes.push((to.clone(), false));
@@ -289,6 +323,8 @@ impl Planner<'_> {
                 fix: f,
                 elevation: Cell::new(0),
                 locked: false,
+                airway: None,
+                constraints: Cell::default(),
             });
         }
         nodes.push(to.clone());
@@ -351,104 +387,192 @@ impl Planner<'_> {
         path
     }
 
+    /// Route from one waypoint to another along the airway network.
+    /// The route joins the network at a node within max leg distance of `from` and leaves it
+    /// at a node within max leg distance of `to`. If there is no such route the leg is flown direct.
+    fn dijkstra_airways(&self, from: &Waypoint, to: &Waypoint) -> Vec<Waypoint> {
+        let total_dist = from.get_loc().distance_to(to.get_loc());
+        if total_dist < self.max_leg_distance {
+            return Vec::new();
+        }
+
+        let graph = self.earth.get_airways().read().expect("Unable to get lock on Airways");
+        let entries = graph.get_nodes_near(from.get_loc(), self.max_leg_distance);
+        let exits = graph.get_nodes_near(to.get_loc(), self.max_leg_distance);
+        if entries.is_empty() || exits.is_empty() {
+            return Vec::new();
+        }
+
+        // Airway nodes keep their graph index, the start and end are added after them
+        let node_count = graph.get_nodes().len();
+        let start_idx = node_count;
+        let end_idx = node_count + 1;
+
+        let mut is_exit = vec![false; node_count];
+        for i in &exits {
+            is_exit[*i] = true;
+        }
+
+        let mut dists = vec![f64::INFINITY; node_count + 2];
+        // (parent, airway used to get here)
+        let mut parents: Vec<Option<(usize, Option<&str>)>> = vec![None; node_count + 2];
+        let mut visited = vec![false; node_count + 2];
+
+        // Flying the whole leg direct is always possible
+        dists[start_idx] = 0.0;
+        dists[end_idx] = total_dist * OFF_AIRWAY_PENALTY;
+        parents[end_idx] = Some((start_idx, None));
+
+        let mut pq = BinaryHeap::new();
+        pq.push(PqItem { idx: start_idx, cost: 0.0 });
+
+        while let Some(PqItem { idx, .. }) = pq.pop() {
+            if visited[idx] {
+                continue;
+            }
+            if idx == end_idx {
+                break;
+            }
+            visited[idx] = true;
+
+            // (node, cost, airway) for every node reachable from this one
+            let mut candidates: Vec<(usize, f64, Option<&str>)> = Vec::new();
+            if idx == start_idx {
+                for i in &entries {
+                    let d = from.get_loc().distance_to(graph.get_nodes()[*i].get_loc());
+                    candidates.push((*i, d * OFF_AIRWAY_PENALTY, None));
+                }
+            } else {
+                let node = &graph.get_nodes()[idx];
+                let arrived_by = parents[idx].and_then(|(_, airway)| airway);
+                for edge in node.get_edges() {
+                    let mut edge_weight = edge.get_distance();
+                    if arrived_by.is_some_and(|a| a != edge.get_airway()) {
+                        edge_weight += AIRWAY_CHANGE_PENALTY;
+                    }
+                    candidates.push((edge.get_to(), dists[idx] + edge_weight, Some(edge.get_airway())));
+                }
+                if is_exit[idx] {
+                    let d = node.get_loc().distance_to(to.get_loc());
+                    candidates.push((end_idx, dists[idx] + d * OFF_AIRWAY_PENALTY, None));
+                }
+            }
+
+            for (v_idx, cost, airway) in candidates {
+                if !visited[v_idx] && cost < dists[v_idx] {
+                    dists[v_idx] = cost;
+                    parents[v_idx] = Some((idx, airway));
+                    pq.push(PqItem { idx: v_idx, cost });
+                }
+            }
+        }
+
+        let mut route: Vec<(usize, Option<&str>)> = Vec::new();
+        let mut curr = parents[end_idx];
+        while let Some((idx, _)) = curr {
+            if idx == start_idx {
+                break;
+            }
+            route.push((idx, parents[idx].and_then(|(_, airway)| airway)));
+            curr = parents[idx];
+        }
+        route.reverse();
+
+        let mut path = Vec::new();
+        for (i, (idx, airway)) in route.iter().enumerate() {
+            // The route joins an airway at the node before the first one reached by it,
+            // and leaves it at the last node reached by it.
+            let airway_leg = airway.map(|name| {
+                let entry = (0..i).rev()
+                    .find(|j| route[*j].1 != Some(name))
+                    .unwrap_or(0);
+                let exit = (i..route.len())
+                    .take_while(|j| route[*j].1 == Some(name))
+                    .last()
+                    .unwrap_or(i);
+                AirwayLeg::new(
+                    name.to_string(),
+                    graph.get_nodes()[route[entry].0].get_id().to_string(),
+                    graph.get_nodes()[route[exit].0].get_id().to_string())
+            });
+            let node = &graph.get_nodes()[*idx];
+            path.push(self.make_airway_waypoint(node.get_id(), node.get_loc(), airway_leg));
+        }
+        path
+    }
+
+    /// Find the navaid or fix an airway node refers to.
+    fn make_airway_waypoint(&self, id: &str, loc: &Coordinate, airway: Option<AirwayLeg>) -> Waypoint {
+        let navaid = self.earth.get_nearest_navaid(loc, 1.0, |n| n.get_id() == id);
+        if let Some(navaid) = navaid {
+            return Waypoint::Navaid {
+                navaid,
+                elevation: Cell::new(0),
+                locked: false,
+                airway,
+                constraints: Cell::default(),
+            };
+        }
+        let fix = self.earth.get_nearest_fix(loc, 1.0, |f| f.get_id() == id)
+            .unwrap_or_else(|| Arc::new(Fix::new(id.to_string(), loc.get_latitude(), loc.get_longitude())));
+        Waypoint::Fix {
+            fix,
+            elevation: Cell::new(0),
+            locked: false,
+            airway,
+            constraints: Cell::default(),
+        }
+    }
+
     fn get_arrival_beacon(&self, to: &Waypoint) -> Option<Waypoint> {
         self.get_navaid_nearest(to.get_loc(), ARRIVAL_BEACON_RANGE). map(|arrival_beacon| {
             Waypoint::Navaid {
                 navaid: arrival_beacon.clone(),
                 elevation: Cell::new(0),
                 locked: false,
+                airway: None,
+                constraints: Cell::default(),
             }
         })
     }
 
     fn get_relevant_navaids(&self, from: &Coordinate, to: &Coordinate) -> Vec<Arc<Navaid>> {
-
-
-        let mut relevant_navaids: Vec<Arc<Navaid>> = Vec::new();
-
         let distance = from.distance_to(to);
 
         if distance >= self.max_leg_distance {
-            let rf = CigarFilter::new(from.clone(), to.clone());
-
-            // Add the filters, putting the most discriminating ones first
-            let mut filter = AndFilter::new();
-            filter.add(Box::new(rf));
+            let mut relevant_navaids = self.earth.get_navaids_in_corridor(from, to, CORRIDOR_RATIO);
             if self.vor_only {
-                let vf = VorFilter::new();
-                filter.add(Box::new(vf));
+                relevant_navaids.retain(|n| n.get_type().is_vor());
+            } else {
+                relevant_navaids.retain(|n| n.get_type().is_enroute());
             }
-
-            let binding = self.navaids
-                .read()
-                .unwrap();
-            let near_aids = binding
-                .iter()
-                .filter(|loc| filter.filter(&***loc));
-            relevant_navaids.extend(near_aids.map(|n| n.clone()));
+            relevant_navaids
+        } else {
+            Vec::new()
         }
-        relevant_navaids
     }
 
     fn get_navaid_nearest(&self, coord: &Coordinate, max_range: f64) -> Option<Arc<Navaid>> {
-
-        let mut filter = AndFilter::new();
+        let nearest_vor = if self.vor_only || self.vor_preferred {
+            self.earth.get_nearest_navaid(coord, max_range, |n| n.get_type().is_vor())
+        } else {
+            None
+        };
         if self.vor_only {
-            let vf = VorFilter::new();
-            filter.add(Box::new(vf));
-        }
-        let rf = RangeFilter::new(coord.clone(), max_range);
-        filter.add(Box::new(rf));
-
-        self.get_nearest_navaids_with_filter(coord, Box::new(filter))
-    }
-
-    fn get_nearest_navaids_with_filter(&self, midpoint: &Coordinate, filter: Box<dyn Filter>) -> Option<Arc<Navaid>> {
-        let binding = self.navaids
-            .read()
-            .unwrap();
-        let near_aids = binding
-            .iter()
-            .filter(|loc| filter.filter(&***loc));
-
-        let mut best_loc: Option<Arc<Navaid>> = None;
-        let mut best_vor: Option<Arc<Navaid>> = None;
-        let mut nearest = 100000.0;
-        let mut nearest_vor = 100000.0;
-        for navaid in near_aids {
-            let distance = midpoint.distance_to(navaid.get_loc());
-            if self.vor_preferred && navaid.get_type() == NavaidType::Vor {
-                if distance < nearest_vor {
-                    best_vor = Some(navaid.clone());
-                    nearest_vor = distance;
-                }
-            } else if best_vor.is_none() && distance < nearest {
-                best_loc = Some(navaid.clone());
-                nearest = distance;
-            }
+            nearest_vor
+        } else {
+            nearest_vor.or_else(|| self.earth.get_nearest_navaid(coord, max_range, |n| n.get_type().is_enroute()))
         }
-        best_vor.or(best_loc)
     }
 
-    #[allow(dead_code)]
     fn get_relevant_fixes(&self, from: &Coordinate, to: &Coordinate) -> Vec<Arc<Fix>> {
-
-        let mut relevant_fixes: Vec<Arc<Fix>> = Vec::new();
-
         let distance = from.distance_to(to);
 
         if distance >= self.max_leg_distance {
-            let filter = CigarFilter::new(from.clone(), to.clone());
-
-            let binding = self.fixes
-                .read()
-                .unwrap();
-            let fixes = binding
-                .iter()
-                .filter(|loc| filter.filter(&***loc));
-            relevant_fixes.extend(fixes.map(|n| n.clone()));
+            self.earth.get_fixes_in_corridor(from, to, CORRIDOR_RATIO)
+        } else {
+            Vec::new()
         }
-        relevant_fixes
     }
 
     #[allow(dead_code)]
@@ -466,6 +590,7 @@ impl Planner<'_> {
             loc: midpoint,
             elevation: Cell::new(0),
             locked: false,
+            constraints: Cell::default(),
         };
         let save_wp = wp.clone();
 
@@ -528,6 +653,7 @@ impl Planner<'_> {
                 loc: x_loc,
                 elevation: Cell::new(0),
                 locked: false,
+                constraints: Cell::default(),
             };
             let save_wp = wp.clone();
             plan.insert(i + a_pos, wp);
@@ -539,16 +665,50 @@ impl Planner<'_> {
 
 }
 
+fn at_threshold(wp: Waypoint, threshold: Option<Coordinate>) -> Waypoint {
+    match threshold {
+        Some(loc) => Waypoint::Simple {
+            loc,
+            elevation: Cell::new(wp.get_elevation()),
+            locked: true,
+            constraints: Cell::default(),
+        },
+        None => wp,
+    }
+}
+
 pub fn recalc_plan_elevations(plan: &mut Plan) {
+    recalc_plan_elevations_with(plan, &LevelRegions::from_preferences())
+}
+
+/// Work out the elevations of the plan keeping to the cruise levels of the regions given.
+pub fn recalc_plan_elevations_with(plan: &mut Plan, regions: &LevelRegions) {
     let aircraft = plan.get_aircraft().clone();
     let altitude = plan.get_plan_altitude();
+    let flight_rules = plan.get_flight_rules();
+    // A maximum altitude set by the user is kept, any terrain above it is flagged by the plan instead
+    let clear_terrain = plan.get_max_altitude().is_none();
 
     for sector in plan.get_sectors_mut() {
         if sector.borrow().get_start().is_none() || sector.borrow().get_end().is_none() {
             continue;
         }
-        let start_wp = &sector.borrow().get_start().unwrap();
-        let end_wp = &sector.borrow().get_end().unwrap();
+        let start_wp = &at_threshold(sector.borrow().get_start().unwrap(), sector.borrow().get_departure_threshold());
+        let end_wp = &at_threshold(sector.borrow().get_end().unwrap(), sector.borrow().get_arrival_threshold());
+
+        // A level chosen for the sector is flown as it is, otherwise the plan altitude is brought down
+        // to a cruise level for the magnetic track, and kept to those levels if it has to come down further
+        let (cruise_level, levels, step_climbs) = {
+            let sector = sector.borrow();
+            let levels = match (sector.get_cruise_level(), sector.get_start(), sector.get_magnetic_track()) {
+                (None, Some(start), Some(track)) => regions.get_rule(start.get_id()).get_levels(track, flight_rules),
+                _ => vec![],
+            };
+            let cruise_level = sector.get_cruise_level()
+                .or_else(|| levels.iter().rev().find(|l| **l <= altitude).copied())
+                .unwrap_or(altitude);
+            (cruise_level, levels, sector.get_step_climbs().clone())
+        };
 
         // Remove the previous top of climb and beginning of descent
         let mut ref_mut = sector.borrow_mut();
@@ -556,20 +716,31 @@ pub fn recalc_plan_elevations(plan: &mut Plan) {
         waypoints.retain(|wp| !matches!(wp, Waypoint::Toc { .. } | Waypoint::Bod { .. }));
 
 
-        let max_alt = calc_max_altitude(
+        let mut max_alt = calc_max_altitude(
             &aircraft,
-            altitude,
+            cruise_level,
             start_wp,
             end_wp,
             waypoints,
+            &levels,
         );
 
+        let terrain_alt = if clear_terrain { calc_terrain_altitude(start_wp, end_wp, waypoints) } else { None };
+        if let Some(terrain_alt) = terrain_alt.filter(|alt| *alt > max_alt) {
+            let level = levels.iter().find(|l| **l >= terrain_alt).copied().unwrap_or(terrain_alt);
+            debug!("Raising cruise altitude from {} to {} to clear terrain", max_alt, level);
+            // The climb and descent still have to fit in the sector, any terrain left above the
+            // altitude that does fit is flagged by the plan
+            max_alt = calc_max_altitude(&aircraft, level, start_wp, end_wp, waypoints, &levels);
+        }
+
         add_toc(
             &aircraft,
             start_wp,
             end_wp,
             waypoints,
             max_alt,
+            &step_climbs,
         );
 
         add_bod(
@@ -578,6 +749,7 @@ pub fn recalc_plan_elevations(plan: &mut Plan) {
             end_wp,
             waypoints,
             max_alt,
+            &step_climbs,
         );
 
         set_elevations(
@@ -586,6 +758,7 @@ pub fn recalc_plan_elevations(plan: &mut Plan) {
             end_wp,
             waypoints,
             max_alt,
+            &step_climbs,
         );
     }
 }
@@ -595,70 +768,40 @@ pub fn add_bod(
     to: &Waypoint,
     waypoints: &mut Vec<Waypoint>,
     max_alt: i32,
+    step_climbs: &[StepClimb],
 ) {
-    let mut done = false;
-    let alt_to_bod = max_alt - to.get_elevation();
-    let time_to_bod = alt_to_bod as f64
-        / aircraft
-        .as_ref()
-        .map(|a| *a.get_sink_rate() as f64)
-        .unwrap_or(500.0)
-        / 60.0;
-    let dist_to_bod = aircraft
-        .as_ref()
-        .map(|a| *a.get_sink_speed() as f64)
-        .unwrap_or(100.0)
-        * time_to_bod;
-
-    let mut distance_remaining = dist_to_bod;
-    let mut next_wp: &Waypoint = to;
-
-    let mut insertion_spot = None;
-    let mut bod = None;
-
-    for i in (0..waypoints.len()).rev() {
-        let wp = &waypoints[i];
-        let leg_length = wp.get_loc().distance_to(next_wp.get_loc());
-
-        if leg_length >= distance_remaining {
-            let heading = wp.get_loc().bearing_to_deg(next_wp.get_loc());
-            let bod_loc = wp
-                .get_loc()
-                .coordinate_at(leg_length - distance_remaining, heading);
-            bod = Some(Waypoint::Bod {
-                loc: bod_loc,
-                elevation: Cell::new(max_alt),
+    let bod = {
+        let profile = calc_vertical_profile(aircraft, from, to, waypoints, max_alt, step_climbs);
+        let (points, climb, descent) = (&profile.points, &profile.climb, &profile.descent);
+        let mut levels: Vec<f64> = points.iter().map(|p| p.ceiling).collect();
+        levels.dedup();
+
+        // The descent begins on the last leg that leaves the highest cruise level reached
+        levels.iter().rev().find_map(|&level| {
+            let i = (1..points.len()).rev().find(|&i| descent[i - 1] >= level && descent[i] < level)?;
+            let leg_length = points[i].distance - points[i - 1].distance;
+            let to_bod = leg_length - (level - descent[i]) / profile.sink_gradient;
+            let to_level = if climb[i - 1] >= level {
+                0.0
+            } else if climb[i] >= level {
+                (level - climb[i - 1]) / profile.climb_gradient
+            } else {
+                return None;
+            };
+            if to_level > to_bod {
+                return None;
+            }
+            let heading = points[i - 1].loc.bearing_to_deg(&points[i].loc);
+            Some((points[i].insert_at, Waypoint::Bod {
+                loc: points[i - 1].loc.coordinate_at(to_bod, heading),
+                elevation: Cell::new(level as i32),
                 locked: false,
-            });
-            insertion_spot = Some(i + 1);
-            done = true;
-            break;
-        }
+            }))
+        })
+    };
 
-        distance_remaining -= leg_length;
-        next_wp = wp;
-    }
-
-    if !done {
-        let leg_length = from.get_loc().distance_to(&next_wp.get_loc().clone());
-
-        if leg_length >= distance_remaining {
-            let heading = from.get_loc().bearing_to_deg(next_wp.get_loc());
-            let bod_loc = from
-                .get_loc()
-                .coordinate_at(leg_length - distance_remaining, heading);
-            bod = Some(Waypoint::Bod {
-                loc: bod_loc,
-                elevation: Cell::new(max_alt),
-                locked: false,
-            });
-            insertion_spot = Some(0);
-        }
-    }
-    if let Some(i) = insertion_spot {
-        if let Some(wp) = bod {
-            waypoints.insert(i, wp);
-        }
+    if let Some((i, wp)) = bod {
+        waypoints.insert(i, wp);
     }
 }
 
@@ -668,65 +811,36 @@ pub fn add_toc(
     to: &Waypoint,
     waypoints: &mut Vec<Waypoint>,
     max_alt: i32,
+    step_climbs: &[StepClimb],
 ) {
-    let mut done = false;
-    let alt_to_toc = max_alt - from.get_elevation();
-
-    let time_to_toc = alt_to_toc as f64
-        / aircraft
-        .as_ref()
-        .map(|a| *a.get_climb_rate() as f64)
-        .unwrap_or(1000.0)
-        / 60.0;
-    let dist_to_toc = aircraft
-        .as_ref()
-        .map(|a| *a.get_climb_speed() as f64)
-        .unwrap_or(120.0)
-        * time_to_toc;
-
-    let mut distance_remaining = dist_to_toc;
-    let mut prev_wp = from;
-    let mut insertion_spot = None;
-    let mut toc = None;
-
-    for (i, wp) in waypoints.iter().enumerate() {
-        let leg_length = prev_wp.get_loc().distance_to(wp.get_loc());
-
-        if leg_length >= distance_remaining {
-            let heading = prev_wp.get_loc().bearing_to_deg(wp.get_loc());
-            let toc_loc = prev_wp.get_loc().coordinate_at(distance_remaining, heading);
-            toc = Some(Waypoint::Toc {
-                loc: toc_loc,
-                elevation: Cell::new(max_alt),
-                locked: false,
-            });
-            insertion_spot = Some(i);
-            done = true;
-            break;
+    let mut tocs = Vec::new();
+    {
+        let profile = calc_vertical_profile(aircraft, from, to, waypoints, max_alt, step_climbs);
+        let (points, climb, descent) = (&profile.points, &profile.climb, &profile.descent);
+
+        // The climb to each cruise level ends on the first leg that reaches it, unless the descent has begun by then
+        for i in 1..points.len() {
+            let level = points[i].ceiling;
+            if climb[i - 1] >= level || climb[i] < level {
+                continue;
+            }
+            let leg_length = points[i].distance - points[i - 1].distance;
+            let to_toc = (level - climb[i - 1]) / profile.climb_gradient;
+            let to_descent = leg_length - (level - descent[i].min(level)) / profile.sink_gradient;
+            if to_toc <= to_descent {
+                let heading = points[i - 1].loc.bearing_to_deg(&points[i].loc);
+                tocs.push((points[i].insert_at, Waypoint::Toc {
+                    loc: points[i - 1].loc.coordinate_at(to_toc, heading),
+                    elevation: Cell::new(level as i32),
+                    locked: false,
+                }));
+            }
         }
-
-        distance_remaining -= leg_length;
-        prev_wp = wp;
     }
 
-    if !done {
-        let leg_length = prev_wp.get_loc().distance_to(to.get_loc());
-
-        if leg_length >= distance_remaining {
-            let heading = prev_wp.get_loc().bearing_to_deg(to.get_loc());
-            let toc_loc = prev_wp.get_loc().coordinate_at(distance_remaining, heading);
-            let wp = Waypoint::Toc {
-                loc: toc_loc,
-                elevation: Cell::new(max_alt),
-                locked: false,
-            };
-            waypoints.push(wp);
-        }
-    }
-    if let Some(i) = insertion_spot {
-        if let Some(wp) = toc {
-            waypoints.insert(i, wp);
-        }
+    // Later markers go in first so the places of the earlier ones still hold
+    for (i, wp) in tocs.into_iter().rev() {
+        waypoints.insert(i, wp);
     }
 }
 
@@ -736,6 +850,7 @@ fn calc_max_altitude(
     from: &Waypoint,
     to: &Waypoint,
     waypoints: &[Waypoint],
+    levels: &[i32],
 ) -> i32 {
     let mut dist = 0.0;
     let mut prev_wp = from;
@@ -752,22 +867,55 @@ fn calc_max_altitude(
     let mut alt = max_altitude;
 
     while calc_climb_sink_distance(aircraft, to, from, alt) > dist {
-        alt -= 500;
+        // Down to the next cruise level, or by 500ft when there are no levels to keep to
+        alt = levels.iter().rev().find(|l| **l < alt).copied().unwrap_or(alt - 500);
     }
     alt
 }
 
+// The lowest cruise altitude, in 500ft steps, that clears the terrain along the whole sector.
+fn calc_terrain_altitude(
+    from: &Waypoint,
+    to: &Waypoint,
+    waypoints: &[Waypoint],
+) -> Option<i32> {
+    let terrain = get_terrain();
+    if !terrain.is_available() {
+        return None;
+    }
+    let clearance = get_clearance();
+
+    let mut msa = None;
+    let mut prev_wp = from;
+    for wp in waypoints.iter().chain(std::iter::once(to)) {
+        msa = msa.max(terrain.get_minimum_safe_altitude(prev_wp.get_loc(), wp.get_loc(), clearance));
+        prev_wp = wp;
+    }
+    msa.map(|alt| (alt + 499) / 500 * 500)
+}
+
 pub fn set_elevations(
     aircraft: &Option<Arc<Aircraft>>,
     from: &Waypoint,
-    _to: &Waypoint,
+    to: &Waypoint,
     waypoints: &Vec<Waypoint>,
     max_alt: i32,
+    step_climbs: &[StepClimb],
 ) {
-    let mut alt = from.get_elevation();
-    let mut ascent = true;
-    let mut descent = false;
+    let profile = calc_vertical_profile(aircraft, from, to, waypoints, max_alt, step_climbs);
+
+    for i in 1..profile.points.len() - 1 {
+        let waypoint = profile.points[i].waypoint
+            .filter(|wp| !matches!(wp, Waypoint::Toc { .. } | Waypoint::Bod { .. }));
+        if let Some(wp) = waypoint {
+            let alt = profile.climb[i].min(profile.descent[i]) as i32;
+            wp.set_elevation(&alt);
+        }
+    }
+}
 
+// Feet climbed and descended in each nautical mile
+fn calc_gradients(aircraft: &Option<Arc<Aircraft>>) -> (f64, f64) {
     let (climb_rate, climb_speed, sink_rate, sink_speed) = aircraft.as_ref()
         .map(|a| (
             *a.get_climb_rate() as f64, *a.get_climb_speed() as f64,
@@ -775,59 +923,117 @@ pub fn set_elevations(
         ))
         .unwrap_or((1000.0, 120.0, 700.0, 80.0));
 
-    let mut prev_wp = from;
+    (climb_rate * 60.0 / climb_speed, sink_rate * 60.0 / sink_speed)
+}
 
-    for wp in waypoints {
-        match wp {
-            Waypoint::Toc { .. } => {
-                ascent = false;
-                alt = max_alt;
-            }
-            Waypoint::Bod { .. } => {
-                ascent = false;
-                descent = true;
-                alt = max_alt;
-            }
-            _ => {
-                if ascent {
-                    let distance = prev_wp.get_loc().distance_to(wp.get_loc());
-                    let leg_time = distance / climb_speed;
-                    alt += (leg_time * climb_rate * 60.0) as i32;
-
-                    if alt > max_alt {
-                        alt = max_alt;
-                        ascent = false;
-                    }
-                    wp.set_elevation(&alt);
-                } else if descent {
-                    let distance = prev_wp.get_loc().distance_to(wp.get_loc());
-                    let leg_time = distance / sink_speed;
-                    alt -= (leg_time * sink_rate * 60.0) as i32;
-                    wp.set_elevation(&alt);
-                } else {
-                    wp.set_elevation(&alt);
-                }
-            }
+// A point of the vertical profile, a waypoint or where a step climb begins
+struct ProfilePoint<'a> {
+    loc: Coordinate,
+    distance: f64,
+    waypoint: Option<&'a Waypoint>,
+    // The cruise level on the way to the point
+    ceiling: f64,
+    // Where a marker on the way to the point goes in the sector's waypoints
+    insert_at: usize,
+}
+
+struct VerticalProfile<'a> {
+    points: Vec<ProfilePoint<'a>>,
+    climb: Vec<f64>,
+    descent: Vec<f64>,
+    climb_gradient: f64,
+    sink_gradient: f64,
+}
+
+// The highest altitude at each point when climbing from the departure and when descending to the arrival,
+// up to the cruise level and any step climbs after it.
+// Constraints in the first half of the route shape the climb and those in the second half the descent.
+fn calc_vertical_profile<'a>(
+    aircraft: &Option<Arc<Aircraft>>,
+    from: &'a Waypoint,
+    to: &'a Waypoint,
+    waypoints: &'a [Waypoint],
+    max_alt: i32,
+    step_climbs: &[StepClimb],
+) -> VerticalProfile<'a> {
+    let (climb_gradient, sink_gradient) = calc_gradients(aircraft);
+    let ceiling_at = |distance: f64| step_climbs.iter()
+        .filter(|s| s.get_distance() < distance)
+        .fold(max_alt, |level, s| level.max(s.get_level())) as f64;
+
+    let mut points = vec![ProfilePoint {
+        loc: from.get_loc().clone(),
+        distance: 0.0,
+        waypoint: Some(from),
+        ceiling: max_alt as f64,
+        insert_at: 0,
+    }];
+    let mut steps = step_climbs.iter().filter(|s| s.get_distance() > 0.0).peekable();
+    let mut prev_wp = from;
+    let mut distance = 0.0;
+    for (i, wp) in waypoints.iter().chain(std::iter::once(to)).enumerate() {
+        let leg_length = prev_wp.get_loc().distance_to(wp.get_loc());
+        let heading = prev_wp.get_loc().bearing_to_deg(wp.get_loc());
+        // The climb starts part way along the leg
+        while let Some(step) = steps.next_if(|s| s.get_distance() < distance + leg_length) {
+            points.push(ProfilePoint {
+                loc: prev_wp.get_loc().coordinate_at(step.get_distance() - distance, heading),
+                distance: step.get_distance(),
+                waypoint: None,
+                ceiling: ceiling_at(step.get_distance()),
+                insert_at: i,
+            });
         }
+        distance += leg_length;
+        points.push(ProfilePoint {
+            loc: wp.get_loc().clone(),
+            distance,
+            waypoint: Some(wp),
+            ceiling: ceiling_at(distance),
+            insert_at: i,
+        });
         prev_wp = wp;
     }
+
+    let n = points.len();
+    let half_way = distance / 2.0;
+    // A constraint can hold the aircraft down but not lift it higher than it can climb or stay up
+    // longer than it can descend, so one out of reach is left unmet and reported by the plan
+    let limit = |point: &ProfilePoint, alt: f64| match point.waypoint {
+        Some(wp) => (wp.get_constraints().limit_altitude(alt as i32) as f64).min(alt),
+        None => alt,
+    };
+
+    let mut climb = vec![from.get_elevation() as f64; n];
+    for i in 1..n {
+        let alt = (climb[i - 1] + (points[i].distance - points[i - 1].distance) * climb_gradient).min(points[i].ceiling);
+        climb[i] = if points[i].distance <= half_way { limit(&points[i], alt) } else { alt };
+    }
+    // Level off in time for a constraint further along rather than descend during the climb
+    for i in (0..n - 1).rev() {
+        climb[i] = climb[i].min(climb[i + 1]);
+    }
+
+    // The descent is from the last cruise level
+    let top = points[n - 1].ceiling;
+    let mut descent = vec![to.get_elevation() as f64; n];
+    for i in (0..n - 1).rev() {
+        let alt = (descent[i + 1] + (points[i + 1].distance - points[i].distance) * sink_gradient).min(top);
+        descent[i] = if points[i].distance > half_way { limit(&points[i], alt) } else { alt };
+    }
+    // Likewise stay down after a constraint rather than climb during the descent
+    for i in 1..n {
+        descent[i] = descent[i].min(descent[i - 1]);
+    }
+
+    VerticalProfile { points, climb, descent, climb_gradient, sink_gradient }
 }
 
 fn calc_climb_sink_distance(aircraft: &Option<Arc<Aircraft>>, from: &Waypoint, to: &Waypoint, altitude: i32) -> f64 {
-    let (climb_rate, climb_speed, sink_rate, sink_speed) = aircraft.as_ref()
-        .map(|a| (
-            *a.get_climb_rate() as f64, *a.get_climb_speed() as f64,
-            *a.get_sink_rate() as f64, *a.get_sink_speed() as f64,
-        ))
-        .unwrap_or((1000.0, 120.0, 700.0, 80.0));
-
-    let alt_to_toc = (altitude - from.get_elevation()) as f64;
-    let time_to_toc = alt_to_toc / climb_rate / 60.0;
-    let dist_to_toc = time_to_toc * climb_speed;
+    let (climb_gradient, sink_gradient) = calc_gradients(aircraft);
 
-    let alt_to_bod = (altitude - to.get_elevation()) as f64;
-    let time_to_bod = alt_to_bod / sink_rate / 60.0;
-    let dist_to_bod = time_to_bod * sink_speed;
+    let dist_to_toc = (altitude - from.get_elevation()) as f64 / climb_gradient;
+    let dist_to_bod = (altitude - to.get_elevation()) as f64 / sink_gradient;
 
     dist_to_toc + dist_to_bod
 }
@@ -857,16 +1063,23 @@ fn pythagorean_distance(a: &Coordinate, b: &Coordinate) -> f64 {
 #[cfg(test)]
 mod tests {
     use std::cell::Cell;
-    use std::sync::{Arc, RwLock};
+    use std::sync::Arc;
     use crate::earth::coordinate::Coordinate;
+    use crate::earth::Earth;
+    use crate::model::airway::{AirwayGraph, AirwaySegment};
+    use crate::model::constraint::{AltitudeConstraint, Constraints};
+    use crate::model::cruise_level::{FlightRules, LevelRegions, StepClimb};
     use crate::model::fix::Fix;
     use crate::model::navaid::{Navaid, NavaidType};
+    use crate::model::plan::Plan;
+    use crate::model::procedure::tests::make_leg;
+    use crate::model::procedure::{Procedure, ProcedureSelection, ProcedureType};
     use crate::model::sector::Sector;
     use crate::model::test_utils::tests::make_airport_at;
     use crate::model::waypoint::Waypoint;
-    use crate::preference::{USE_FIXES, USE_GPS, USE_RADIO_BEACONS};
+    use crate::preference::{USE_AIRWAYS, USE_FIXES, USE_GPS, USE_RADIO_BEACONS};
 
-    use super::Planner;
+    use super::{add_bod, add_toc, recalc_plan_elevations_with, set_elevations, Planner};
 
     #[test]
     fn test_with_gps() {
@@ -879,8 +1092,7 @@ mod tests {
             add_gps_waypoints: true,
             add_waypoint_bias: false,
             plan_type: USE_GPS.to_string(),
-            navaids: &Arc::new(RwLock::new(Vec::new())),
-            fixes: &Arc::new(RwLock::new(Vec::new())),
+            earth: &Earth::new(),
         };
 
         let ap1 = make_airport_at("YSSY", -34.0, 151.0);
@@ -908,7 +1120,8 @@ mod tests {
         let navaid1 = Arc::new(Navaid::new("NAVAID1".to_string(), NavaidType::Vor, -33.0, 140.0, 10, 10., 10, "10".to_string(), "BIN".to_string()));
         let navaid2 = Arc::new(Navaid::new("NAVAID2".to_string(), NavaidType::Vor, -33.5, 130.0, 10, 10., 10, "10".to_string(), "GLB".to_string()));
         let navaid3 = Arc::new(Navaid::new("NAVAID3".to_string(), NavaidType::Vor, -33.8, 125.0, 10, 10., 10, "10".to_string(), "WAL".to_string()));
-        let navaids = &Arc::new(RwLock::new(vec![navaid1.clone(), navaid2.clone(), navaid3.clone()]));
+        let earth = Earth::new();
+        earth.set_navaids(vec![navaid1.clone(), navaid2.clone(), navaid3.clone()]);
 
         let planner = Planner {
             max_leg_distance: 100.0,
@@ -919,8 +1132,7 @@ mod tests {
             add_gps_waypoints: false,
             add_waypoint_bias: false,
             plan_type: USE_RADIO_BEACONS.to_string(),
-            navaids,
-            fixes: &Arc::new(RwLock::new(Vec::new())),
+            earth: &earth,
         };
 
         let ap1 = make_airport_at("YSSY", -34.0, 151.0);
@@ -942,7 +1154,8 @@ mod tests {
         let fix1 = Arc::new(Fix::new("FIX1".to_string(), -33.0, 140.0));
         let fix2 = Arc::new(Fix::new("FIX2".to_string(), -33.5, 130.0));
         let fix3 = Arc::new(Fix::new("FIX3".to_string(), -33.8, 125.0));
-        let fixes = &Arc::new(RwLock::new(vec![fix1.clone(), fix2.clone(), fix3.clone()]));
+        let earth = Earth::new();
+        earth.set_fixes(vec![fix1.clone(), fix2.clone(), fix3.clone()]);
 
         let planner = Planner {
             max_leg_distance: 100.0,
@@ -953,8 +1166,7 @@ mod tests {
             add_gps_waypoints: false,
             add_waypoint_bias: false,
             plan_type: USE_FIXES.to_string(),
-            navaids: &Arc::new(RwLock::new(Vec::new())),
-            fixes,
+            earth: &earth,
         };
 
         let ap1 = make_airport_at("YSSY", -34.0, 151.0);
@@ -980,8 +1192,7 @@ mod tests {
             add_gps_waypoints: false,
             add_waypoint_bias: false,
             plan_type: USE_RADIO_BEACONS.to_string(),
-            navaids: &Arc::new(RwLock::new(Vec::new())),
-            fixes: &Arc::new(RwLock::new(Vec::new())),
+            earth: &Earth::new(),
         };
 
         let ap1 = make_airport_at("YSSY", -34.0, 151.0);
@@ -1006,8 +1217,7 @@ mod tests {
             add_gps_waypoints: false,
             add_waypoint_bias: false,
             plan_type: USE_RADIO_BEACONS.to_string(),
-            navaids: &Arc::new(RwLock::new(Vec::new())),
-            fixes: &Arc::new(RwLock::new(Vec::new())),
+            earth: &Earth::new(),
         };
 
         let ap1 = make_airport_at("YSSY", -34.0, 151.0);
@@ -1016,6 +1226,7 @@ mod tests {
             loc: Coordinate::new(-33.0, 135.0),
             elevation: Cell::new(0),
             locked: true,
+            constraints: Cell::default(),
         };
 
         let mut sector = Sector::new();
@@ -1026,4 +1237,251 @@ mod tests {
 
         assert!(plan.contains(&wp));
     }
+
+    #[test]
+    fn make_plan_with_airways() {
+        let segment = |from: (&str, f64, f64), to: (&str, f64, f64), name: &str| {
+            AirwaySegment::new(
+                (from.0.to_string(), Coordinate::new(from.1, from.2)),
+                (to.0.to_string(), Coordinate::new(to.1, to.2)),
+                name.to_string(), false, 50, 245, false)
+        };
+        let segments = vec![
+            segment(("SY", -33.95, 151.18), ("BOREE", -33.2, 148.9), "H65"),
+            segment(("BOREE", -33.2, 148.9), ("TESAT", -32.5, 146.5), "H65"),
+            segment(("TESAT", -32.5, 146.5), ("WALGA", -31.9, 139.0), "H65"),
+            segment(("WALGA", -31.9, 139.0), ("CEDUN", -32.1, 133.7), "J30"),
+            segment(("CEDUN", -32.1, 133.7), ("KAL", -30.8, 121.45), "J30"),
+            segment(("SY", -33.95, 151.18), ("ALBUR", -36.0, 147.0), "W45"),
+        ];
+        let navaid = Arc::new(Navaid::new("SY".to_string(), NavaidType::Vor, -33.95, 151.18, 10, 112.1, 100, "12E".to_string(), "SYDNEY".to_string()));
+        let earth = Earth::new();
+        earth.set_navaids(vec![navaid]);
+        earth.set_airways(AirwayGraph::from_segments(&segments));
+
+        let planner = Planner {
+            max_leg_distance: 100.0,
+            min_leg_distance: 25.0,
+            _max_deviation: 10.0,
+            vor_only: false,
+            vor_preferred: true,
+            add_gps_waypoints: false,
+            add_waypoint_bias: false,
+            plan_type: USE_AIRWAYS.to_string(),
+            earth: &earth,
+        };
+
+        let ap1 = make_airport_at("YSSY", -34.0, 151.0);
+        let ap2 = make_airport_at("YPER", -32.1, 120.5);
+
+        let mut sector = Sector::new();
+        sector.set_start(Some(ap1));
+        sector.set_end(Some(ap2));
+        let plan = planner.make_plan(&sector);
+
+        let ids: Vec<&str> = plan.iter().map(|wp| wp.get_id()).collect();
+        assert_eq!(ids, vec!["SY", "BOREE", "TESAT", "WALGA", "CEDUN", "KAL"]);
+
+        // Joined direct at the Sydney VOR
+        assert!(matches!(plan[0], Waypoint::Navaid { .. }));
+        assert!(plan[0].get_airway().is_none());
+
+        let h65 = plan[1].get_airway().unwrap();
+        assert_eq!(h65.get_airway(), "H65");
+        assert_eq!(h65.get_entry(), "SY");
+        assert_eq!(h65.get_exit(), "WALGA");
+
+        let j30 = plan[5].get_airway().unwrap();
+        assert_eq!(j30.get_airway(), "J30");
+        assert_eq!(j30.get_entry(), "WALGA");
+        assert_eq!(j30.get_exit(), "KAL");
+    }
+
+    #[test]
+    fn make_plan_with_airways_none_loaded() {
+        let planner = Planner {
+            max_leg_distance: 100.0,
+            min_leg_distance: 25.0,
+            _max_deviation: 10.0,
+            vor_only: false,
+            vor_preferred: true,
+            add_gps_waypoints: false,
+            add_waypoint_bias: false,
+            plan_type: USE_AIRWAYS.to_string(),
+            earth: &Earth::new(),
+        };
+
+        let ap1 = make_airport_at("YSSY", -34.0, 151.0);
+        let ap2 = make_airport_at("YPER", -32.1, 120.5);
+
+        let mut sector = Sector::new();
+        sector.set_start(Some(ap1));
+        sector.set_end(Some(ap2));
+        let plan = planner.make_plan(&sector);
+
+        assert!(plan.is_empty());
+    }
+
+    #[test]
+    fn make_plan_with_procedures() {
+        let planner = Planner {
+            max_leg_distance: 100.0,
+            min_leg_distance: 25.0,
+            _max_deviation: 10.0,
+            vor_only: false,
+            vor_preferred: true,
+            add_gps_waypoints: true,
+            add_waypoint_bias: false,
+            plan_type: USE_GPS.to_string(),
+            earth: &Earth::new(),
+        };
+
+        let ap1 = make_airport_at("YSSY", -34.0, 151.0);
+        let ap2 = make_airport_at("YPER", -32.1, 120.5);
+
+        let mut sid = Procedure::new(ProcedureType::Sid, "ABBEY3".to_string());
+        sid.add_leg('2', "", make_leg("AAA", -34.2, 150.8, None));
+        sid.add_leg('2', "", make_leg("BBB", -34.0, 150.0, None));
+        let mut star = Procedure::new(ProcedureType::Star, "JULIM1".to_string());
+        star.add_leg('2', "", make_leg("CCC", -32.0, 121.5, None));
+        star.add_leg('2', "", make_leg("DDD", -32.1, 121.0, None));
+
+        let mut sector = Sector::new();
+        sector.set_start(Some(ap1));
+        sector.set_end(Some(ap2));
+        sector.set_sid(Some(ProcedureSelection::new(Arc::new(sid), None, None)));
+        sector.set_star(Some(ProcedureSelection::new(Arc::new(star), None, None)));
+        let plan = planner.make_plan(&sector);
+
+        assert!(plan.len() > 4);
+        assert_eq!(plan[0].get_id(), "AAA");
+        assert_eq!(plan[1].get_id(), "BBB");
+        assert_eq!(plan[2].get_id(), "GPS");
+        assert_eq!(plan[plan.len() - 2].get_id(), "CCC");
+        assert_eq!(plan[plan.len() - 1].get_id(), "DDD");
+    }
+
+    #[test]
+    fn vertical_profile_with_constraints() {
+        let waypoint = |long: f64, altitude: Option<AltitudeConstraint>| Waypoint::Simple {
+            loc: Coordinate::new(0.0, long),
+            elevation: Cell::new(0),
+            locked: false,
+            constraints: Cell::new(Constraints::new(altitude, None)),
+        };
+        let from = Waypoint::Airport { airport: make_airport_at("AAAA", 0.0, 0.0), locked: true };
+        let to = Waypoint::Airport { airport: make_airport_at("BBBB", 0.0, 4.0), locked: true };
+        let toc_and_bod = |waypoints: &Vec<Waypoint>| {
+            let toc = waypoints.iter().find(|wp| matches!(wp, Waypoint::Toc { .. })).unwrap();
+            let bod = waypoints.iter().find(|wp| matches!(wp, Waypoint::Bod { .. })).unwrap();
+            (toc.get_loc().get_longitude(), bod.get_loc().get_longitude())
+        };
+
+        let mut waypoints: Vec<Waypoint> = (1..8).map(|i| waypoint(i as f64 * 0.5, None)).collect();
+        add_toc(&None, &from, &to, &mut waypoints, 20000, &[]);
+        add_bod(&None, &from, &to, &mut waypoints, 20000, &[]);
+        let (toc, bod) = toc_and_bod(&waypoints);
+        assert!((toc - 0.666).abs() < 0.01);
+        assert!((bod - 3.365).abs() < 0.01);
+
+        // Held below 10000ft at the first waypoint and at 5000ft at the last
+        let mut waypoints: Vec<Waypoint> = (1..8).map(|i| waypoint(i as f64 * 0.5, None)).collect();
+        waypoints[0] = waypoint(0.5, Some(AltitudeConstraint::AtOrBelow(10000)));
+        waypoints[6] = waypoint(3.5, Some(AltitudeConstraint::At(5000)));
+        add_toc(&None, &from, &to, &mut waypoints, 20000, &[]);
+        add_bod(&None, &from, &to, &mut waypoints, 20000, &[]);
+        let (toc, bod) = toc_and_bod(&waypoints);
+        assert!((toc - 0.833).abs() < 0.01);
+        assert!((bod - 3.024).abs() < 0.01);
+
+        set_elevations(&None, &from, &to, &waypoints, 20000, &[]);
+        assert_eq!(waypoints[0].get_elevation(), 10000);
+        assert_eq!(waypoints[2].get_elevation(), 20000);
+        assert_eq!(waypoints[waypoints.len() - 1].get_elevation(), 5000);
+
+        // Only 15000ft can be reached by the first waypoint, so that is as high as it goes
+        let mut waypoints: Vec<Waypoint> = (1..8).map(|i| waypoint(i as f64 * 0.5, None)).collect();
+        waypoints[0] = waypoint(0.5, Some(AltitudeConstraint::AtOrAbove(18000)));
+        set_elevations(&None, &from, &to, &waypoints, 20000, &[]);
+        assert_eq!(waypoints[0].get_elevation() / 100, 150);
+
+        let mut plan = Plan::new();
+        let mut sector = Sector::new();
+        sector.set_start(Some(make_airport_at("AAAA", 0.0, 0.0)));
+        sector.set_end(Some(make_airport_at("BBBB", 0.0, 4.0)));
+        for wp in waypoints {
+            sector.add_waypoint(wp);
+        }
+        plan.add_sector(sector);
+        let warnings = plan.get_constraint_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("15027ft"));
+    }
+
+    #[test]
+    fn vertical_profile_with_step_climbs() {
+        let from = Waypoint::Airport { airport: make_airport_at("AAAA", 0.0, 0.0), locked: true };
+        let to = Waypoint::Airport { airport: make_airport_at("BBBB", 0.0, 4.0), locked: true };
+        let make_waypoints = || -> Vec<Waypoint> {
+            (1..8).map(|i| Waypoint::Simple {
+                loc: Coordinate::new(0.0, i as f64 * 0.5),
+                elevation: Cell::new(0),
+                locked: false,
+                constraints: Cell::default(),
+            }).collect()
+        };
+        let markers = |waypoints: &Vec<Waypoint>| -> Vec<(&'static str, i32)> {
+            waypoints.iter()
+                .filter_map(|wp| match wp {
+                    Waypoint::Toc { elevation, .. } => Some(("TOC", elevation.get())),
+                    Waypoint::Bod { elevation, .. } => Some(("BOD", elevation.get())),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        // Up to 14000ft after 120nm
+        let steps = [StepClimb::new(120.0, 14000)];
+        let mut waypoints = make_waypoints();
+        add_toc(&None, &from, &to, &mut waypoints, 10000, &steps);
+        add_bod(&None, &from, &to, &mut waypoints, 10000, &steps);
+        set_elevations(&None, &from, &to, &waypoints, 10000, &steps);
+        assert_eq!(markers(&waypoints), vec![("TOC", 10000), ("TOC", 14000), ("BOD", 14000)]);
+        let second_toc = waypoints.iter().filter(|wp| matches!(wp, Waypoint::Toc { .. })).nth(1).unwrap();
+        assert!((second_toc.get_loc().get_longitude() - 2.133).abs() < 0.01);
+        assert_eq!(waypoints.iter().find(|wp| wp.get_loc().get_longitude() == 1.0).unwrap().get_elevation(), 10000);
+        assert_eq!(waypoints.iter().find(|wp| wp.get_loc().get_longitude() == 2.5).unwrap().get_elevation(), 14000);
+
+        // A step too late to be reached is left out
+        let steps = [StepClimb::new(230.0, 30000)];
+        let mut waypoints = make_waypoints();
+        add_toc(&None, &from, &to, &mut waypoints, 10000, &steps);
+        add_bod(&None, &from, &to, &mut waypoints, 10000, &steps);
+        assert_eq!(markers(&waypoints), vec![("TOC", 10000), ("BOD", 10000)]);
+    }
+
+    #[test]
+    fn recalc_elevations_to_cruise_levels() {
+        let cruise_level = |from: (f64, f64), to: (f64, f64), flight_rules: FlightRules| {
+            let mut plan = Plan::new();
+            plan.set_max_altitude(Some(12000));
+            plan.set_flight_rules(flight_rules);
+            let mut sector = Sector::new();
+            sector.set_start(Some(make_airport_at("AAAA", from.0, from.1)));
+            sector.set_end(Some(make_airport_at("BBBB", to.0, to.1)));
+            plan.add_sector(sector);
+            recalc_plan_elevations_with(&mut plan, &LevelRegions::default());
+            let sector = plan.get_sectors()[0].borrow();
+            sector.get_waypoints().iter()
+                .find(|wp| matches!(wp, Waypoint::Toc { .. }))
+                .map(|wp| wp.get_elevation())
+        };
+
+        assert_eq!(cruise_level((0.0, 0.0), (0.0, 4.0), FlightRules::Ifr), Some(11000));
+        assert_eq!(cruise_level((0.0, 4.0), (0.0, 0.0), FlightRules::Ifr), Some(12000));
+        assert_eq!(cruise_level((0.0, 0.0), (0.0, 4.0), FlightRules::Vfr), Some(11500));
+        assert_eq!(cruise_level((0.0, 4.0), (0.0, 0.0), FlightRules::Vfr), Some(10500));
+        // Too short for 11000ft, so down to the next level for the track
+        assert_eq!(cruise_level((0.0, 0.0), (0.0, 0.5), FlightRules::Ifr), Some(7000));
+    }
 }
@@ -56,6 +56,11 @@ pub const FGFS_USE_DFT_PATH: &str = "Fgfs.UseDefaultPath";
 pub const AIRPORTS_PATH: &str = "Airports.Path";
 pub const NAVAIDS_PATH: &str = "Navaids.Path";
 pub const FIXES_PATH: &str = "Fixes.Path";
+pub const AIRWAYS_PATH: &str = "Airways.Path";
+// Directory of X-Plane CIFP procedure files, one <ICAO>.dat per airport
+pub const CIFP_PATH: &str = "CIFP.Path";
+// Directory of SRTM .hgt tiles for terrain clearance
+pub const TERRAIN_PATH: &str = "Terrain.Path";
 // Shape files for shoreline data
 pub const GSHHG_PATH: &str = "GSHHG.Path";
 pub const AIRCRAFT_TYPE: &str = "Aircraft.type";
@@ -66,10 +71,14 @@ pub const PLAN_TYPE: &str = "Autoplanner.planType";
 pub const USE_RADIO_BEACONS: &str = "Autoplanner.useRadioBeacons";
 pub const USE_FIXES: &str = "Autoplanner.useFixes";
 pub const USE_GPS: &str = "Autoplanner.useGps";
+pub const USE_AIRWAYS: &str = "Autoplanner.useAirways";
 pub const VOR_ONLY: &str = "Autoplanner.vor_only";
 pub const VOR_PREFFERED: &str = "Autoplanner.vor_preferred";
 pub const ADD_WAYPOINTS: &str = "Autoplanner.add_waypoints";
 pub const ADD_WAYPOINT_BIAS: &str = "Autoplanner.add_waypoint_bias";
+pub const TERRAIN_CLEARANCE: &str = "Autoplanner.terrainClearance";
+// Cruise level rules that differ from ICAO, e.g. "LF=090,NZ=270"
+pub const CRUISE_LEVEL_REGIONS: &str = "Autoplanner.cruiseLevelRegions";
 pub const MAP_VIEW_SHOW_AIRPORT: &str = "Mapview.show.airports";
 pub const MAP_VIEW_SHOW_NAVAID: &str = "Mapview.show.navaids";
 pub const MAP_VIEW_CENTRE_LAT: &str = "Mapview.centre.lat";
@@ -29,14 +29,18 @@ use gtk::gdk::ffi::GdkRectangle;
 use adw::prelude::WidgetExt;
 
 use crate::earth::FEET_PER_DEGREE;
-use crate::model::airport::{Airport, Runway, Taxiway};
+use crate::model::airport::{Airport, Runway, StartLocation, Taxiway};
 use crate::model::location::Location;
 
+// How far in pixels from a start location marker the pointer can be and still be over it
+const MARKER_REACH: f64 = 4.0;
+
 pub struct AirportPainter {
     // Define your Airport struct fields here
     pub draw_taxiways: bool,
     pub draw_runways: bool,
     pub draw_compass_rose: bool,
+    pub draw_start_locations: bool,
 }
 
 impl AirportPainter {
@@ -46,32 +50,7 @@ impl AirportPainter {
         // Get the size of the DrawingArea
         let width = drawing_area.width() as f64;
         let height = drawing_area.height() as f64;
-
-        // Calculate the scale factor (pixels per foot)
-        let extents = airport.calc_airport_extent();
-        let height_feet = (extents[1] - extents[0]) * FEET_PER_DEGREE as f64;
-        let width_feet = (extents[3] - extents[2])
-            * FEET_PER_DEGREE as f64
-            * airport.get_lat().to_radians().cos().abs();
-
-        let scale_x = width_feet / width;
-        let scale_y = height_feet / height;
-        let scale = scale_x.max(scale_y);
-
-        // Now calculate the actual width we will take at the scale to
-        // calculate an offset to center the drawing
-        let true_width = width_feet / scale;
-        let true_height = height_feet / scale;
-        // Rectangle to draw in is
-        let offset_x = (width - true_width) / 2.0;
-        let offset_y = (height - true_height) / 2.0;
-
-        let bounding_box = GdkRectangle {
-            x: offset_x as i32,
-            y: offset_y as i32,
-            width: true_width as i32,
-            height: true_height as i32,
-        };
+        let (extents, scale, bounding_box) = place_airport(airport, width, height);
 
         // Draw the runways
 
@@ -105,6 +84,44 @@ impl AirportPainter {
             let _ = cr.restore();
         }
 
+        // Mark the gates and other start locations, the windsocks and the beacon
+        if self.draw_start_locations {
+            let _ = cr.save();
+            let draw_marker = |lat: f64, long: f64, radius: f64| {
+                let (x, y) = to_drawing(airport, &extents, scale, &bounding_box, lat, long);
+                cr.arc(x, y, radius, 0.0, 2.0 * std::f64::consts::PI);
+                let _ = cr.fill();
+            };
+            cr.set_source_rgb(0.9, 0.5, 0.0);
+            for start in airport
+                .get_start_locations()
+                .read()
+                .expect("Could not get airport lock")
+                .iter()
+            {
+                draw_marker(start.get_lat(), start.get_long(), 2.0);
+            }
+            cr.set_source_rgb(0.8, 0.0, 0.0);
+            for windsock in airport
+                .get_windsocks()
+                .read()
+                .expect("Could not get airport lock")
+                .iter()
+            {
+                draw_marker(windsock.get_lat(), windsock.get_long(), 3.0);
+            }
+            cr.set_source_rgb(0.0, 0.6, 0.0);
+            for beacon in airport
+                .get_beacons()
+                .read()
+                .expect("Could not get airport lock")
+                .iter()
+            {
+                draw_marker(beacon.get_lat(), beacon.get_long(), 4.0);
+            }
+            let _ = cr.restore();
+        }
+
         // Draw the compass rose
         if self.draw_compass_rose {
             let _ = cr.save();
@@ -113,6 +130,23 @@ impl AirportPainter {
         }
     }
 
+    /// The start location whose marker is at, or next to, a point in the drawing area
+    pub fn start_location_at(&self, airport: &Airport, drawing_area: &gtk::DrawingArea, x: f64, y: f64) -> Option<StartLocation> {
+        let (extents, scale, bounding_box) = place_airport(airport, drawing_area.width() as f64, drawing_area.height() as f64);
+        airport
+            .get_start_locations()
+            .read()
+            .expect("Could not get airport lock")
+            .iter()
+            .map(|start| {
+                let (sx, sy) = to_drawing(airport, &extents, scale, &bounding_box, start.get_lat(), start.get_long());
+                ((sx - x).hypot(sy - y), start)
+            })
+            .filter(|(distance, _)| *distance <= MARKER_REACH)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, start)| start.clone())
+    }
+
     fn draw_runway(
         &self,
         cr: &Context,
@@ -292,3 +326,44 @@ impl AirportPainter {
         let _ = cr.stroke();
     }
 }
+
+// The extents of the airport, the feet each pixel covers and the rectangle the airport is centred in
+fn place_airport(airport: &Airport, width: f64, height: f64) -> ([f64; 4], f64, GdkRectangle) {
+    // Calculate the scale factor (pixels per foot)
+    let extents = airport.calc_airport_extent();
+    let height_feet = (extents[1] - extents[0]) * FEET_PER_DEGREE as f64;
+    let width_feet = (extents[3] - extents[2])
+        * FEET_PER_DEGREE as f64
+        * airport.get_lat().to_radians().cos().abs();
+
+    let scale_x = width_feet / width;
+    let scale_y = height_feet / height;
+    let scale = scale_x.max(scale_y);
+
+    // Now calculate the actual width we will take at the scale to
+    // calculate an offset to center the drawing
+    let true_width = width_feet / scale;
+    let true_height = height_feet / scale;
+    // Rectangle to draw in is
+    let offset_x = (width - true_width) / 2.0;
+    let offset_y = (height - true_height) / 2.0;
+
+    let bounding_box = GdkRectangle {
+        x: offset_x as i32,
+        y: offset_y as i32,
+        width: true_width as i32,
+        height: true_height as i32,
+    };
+    (extents, scale, bounding_box)
+}
+
+// Where a position at the airport is in the drawing area
+fn to_drawing(airport: &Airport, extents: &[f64; 4], scale: f64, bounding_box: &GdkRectangle, lat: f64, long: f64) -> (f64, f64) {
+    let offset_lat = (lat - extents[0]) * FEET_PER_DEGREE as f64;
+    let offset_long = (long - extents[2])
+        * FEET_PER_DEGREE as f64
+        * airport.get_lat().to_radians().cos();
+    let x = (offset_long / scale) + bounding_box.x as f64;
+    let y = bounding_box.height as f64 - (offset_lat / scale) + bounding_box.y as f64;
+    (x, y)
+}
@@ -23,15 +23,13 @@
  */
 use std::borrow::Cow;
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufRead, BufReader, Error};
+use std::io::{BufRead, Error};
 use std::sync::{Arc, RwLock};
 
-use flate2::read::GzDecoder;
 use log::{error, warn};
 
 use crate::earth::coordinate::Coordinate;
-use crate::model::airport::{Airport, AirportType, LayoutNode, Runway, RunwayType, Taxiway};
+use crate::model::airport::{Airport, AirportType, Beacon, Frequency, FrequencyType, LayoutNode, Runway, RunwayType, StartLocation, StartLocationType, Taxiway, Windsock};
 use crate::model::location::Location;
 
 
@@ -41,9 +39,9 @@ use crate::model::location::Location;
 // in each record.  The records are ordered in a specific way and the parser
 // must follow that order to correctly interpret the data.
 //
-// We only load the basic airport information initially as this is all that is
-// needed to search for or display the airport on the map.  The runways are loaded later when
-// the airport is viewed. The whole file is about 106MB but only a few percent of that is actual
+// We only load the basic airport information and its 1302 metadata initially as this is all that is
+// needed to search for or display the airport on the map.  The runways, frequencies, start locations,
+// beacons and windsocks are loaded later when the airport is viewed. The whole file is about 106MB but only a few percent of that is actual
 // core airport data, the rest being runway, taxiway and other environmental data.
 //
 // The file specification can be found at http://data.x-plane.com/file_specs/XP%20APT1000%20Spec.pdf
@@ -62,6 +60,8 @@ struct ParsedRunway {
     heading_deg: f64,
     surface: String,
     edge_lights: String,
+    displaced_thresholds: [i32; 2],
+    overruns: [i32; 2],
 }
 
 impl AirportParserFG850 {
@@ -69,11 +69,11 @@ impl AirportParserFG850 {
         Self {}
     }
 
-    pub fn load_airports(
+    pub fn load_airports<R: BufRead>(
         &mut self,
         airports: &mut Vec<Arc<Airport>>,
         runway_offsets: &mut HashMap<String, usize>,
-        reader: &mut BufReader<GzDecoder<File>>,
+        reader: &mut R,
     ) -> Result<(), Error> {
         // Skip header rows
         let mut offset: usize = 0;
@@ -155,6 +155,10 @@ impl AirportParserFG850 {
                     latitude = 0.0;
                     longitude = 0.0;
 
+                } else if r_type == "1302" {
+                    if let (Some(airport), Some(key)) = (airport.as_mut(), tokenizer.next()) {
+                        airport.set_metadata(key, &tokenizer.collect::<Vec<&str>>().join(" "));
+                    }
                 } else if r_type == "100" || r_type == "101" || r_type == "102" {
                     if let Some(parsed) = self.parse_runway_record(r_type, &mut tokenizer) {
                         let r_length = parsed.length_ft as f64;
@@ -198,8 +202,8 @@ impl AirportParserFG850 {
                 let r_number = tokenizer.next().unwrap_or("").to_string();
                 let r_lat = token_f64(tokenizer.next());
                 let r_long = token_f64(tokenizer.next());
-                tokenizer.next(); // Length displaced threshold
-                tokenizer.next(); // Length overrun
+                let r_displaced = token_f64(tokenizer.next()) * 3.28;
+                let r_overrun = token_f64(tokenizer.next()) * 3.28;
                 let _markings = tokenizer.next().unwrap_or("");
                 tokenizer.next(); // Approach lights
                 tokenizer.next(); // TDZ flag
@@ -209,6 +213,8 @@ impl AirportParserFG850 {
                 let _number = tokenizer.next().unwrap_or("");
                 let r1_lat = token_f64(tokenizer.next());
                 let r1_long = token_f64(tokenizer.next());
+                let r1_displaced = token_f64(tokenizer.next()) * 3.28;
+                let r1_overrun = token_f64(tokenizer.next()) * 3.28;
 
                 let c1 = Coordinate::new(r_lat, r_long);
                 let c2 = Coordinate::new(r1_lat, r1_long);
@@ -227,6 +233,8 @@ impl AirportParserFG850 {
                     heading_deg: heading,
                     surface: r_surface,
                     edge_lights: r_edge_lights,
+                    displaced_thresholds: [r_displaced.round() as i32, r1_displaced.round() as i32],
+                    overruns: [r_overrun.round() as i32, r1_overrun.round() as i32],
                 })
             }
             // Water runway (101) with two ends
@@ -257,6 +265,8 @@ impl AirportParserFG850 {
                     heading_deg: heading,
                     surface: String::new(),
                     edge_lights: String::new(),
+                    displaced_thresholds: [0; 2],
+                    overruns: [0; 2],
                 })
             }
             // Helipad (102)
@@ -283,6 +293,8 @@ impl AirportParserFG850 {
                     heading_deg: r_hdg,
                     surface: r_surface,
                     edge_lights: r_edge_lights,
+                    displaced_thresholds: [0; 2],
+                    overruns: [0; 2],
                 })
             }
             _ => None,
@@ -302,11 +314,11 @@ impl AirportParserFG850 {
         }
     }
 
-    pub fn load_runways(
+    pub fn load_runways<R: BufRead>(
         &self,
         airport: &Airport,
         runway_offsets: &RwLock<HashMap<String, usize>>,
-        reader: &mut BufReader<GzDecoder<File>>,
+        reader: &mut R,
     ) -> Result<(), String> {
 
         let mut tokenizer: std::str::SplitWhitespace;
@@ -353,7 +365,7 @@ impl AirportParserFG850 {
                     tokenizer.next();
                     let id = tokenizer.next().unwrap_or("");
                     if airport.get_id() == id {
-                        self.load_runways_for_airport(airport, reader)?;
+                        self.load_layout(airport, reader)?;
                         return Ok(());
                     }
                 }
@@ -361,10 +373,12 @@ impl AirportParserFG850 {
         }
     }
 
-    fn load_runways_for_airport(
+    /// Load the runways, taxiways and other layout records that follow an airport's own line,
+    /// stopping at the next airport.
+    pub fn load_layout<R: BufRead>(
         &self,
         airport: &Airport,
-        reader: &mut BufReader<GzDecoder<File>>,
+        reader: &mut R,
     ) -> Result<(), String> {
         let mut match_found = true;
 
@@ -404,7 +418,7 @@ impl AirportParserFG850 {
 
             if r_type == "100" || r_type == "101" || r_type == "102" {
                 if let Some(parsed) = self.parse_runway_record(r_type, &mut tokenizer) {
-                    let runway = Runway::new(
+                    let mut runway = Runway::new(
                         parsed.number,
                         Some(parsed.rtype),
                         parsed.center_lat,
@@ -416,8 +430,47 @@ impl AirportParserFG850 {
                         parsed.surface,
                         parsed.edge_lights,
                     );
+                    runway.set_end_distances(parsed.displaced_thresholds, parsed.overruns);
                     airport.add_runway(runway);
                 }
+            } else if let Some(frequency_type) = FrequencyType::type_for(r_type) {
+                // The 8.33kHz records are in kHz, the older ones in units of 10kHz
+                let divisor = if r_type.len() == 4 { 1000.0 } else { 100.0 };
+                let frequency = token_f64(tokenizer.next()) / divisor;
+                let name = tokenizer.collect::<Vec<&str>>().join(" ");
+                airport.add_frequency(Frequency::new(frequency_type, frequency, name));
+            } else if r_type == "15" {
+                let lat = token_f64(tokenizer.next());
+                let long = token_f64(tokenizer.next());
+                let heading = token_f64(tokenizer.next());
+                let name = tokenizer.collect::<Vec<&str>>().join(" ");
+                airport.add_start_location(StartLocation::new(StartLocationType::Misc, lat, long, heading, String::new(), name));
+            } else if r_type == "1300" {
+                let lat = token_f64(tokenizer.next());
+                let long = token_f64(tokenizer.next());
+                let heading = token_f64(tokenizer.next());
+                let location_type = StartLocationType::type_for(tokenizer.next().unwrap_or(""));
+                let aircraft = match tokenizer.next().unwrap_or("all") {
+                    "all" => String::new(),
+                    aircraft => aircraft.to_string(),
+                };
+                let name = tokenizer.collect::<Vec<&str>>().join(" ");
+                airport.add_start_location(StartLocation::new(location_type, lat, long, heading, aircraft, name));
+            } else if r_type == "18" {
+                let lat = token_f64(tokenizer.next());
+                let long = token_f64(tokenizer.next());
+                let beacon_type = tokenizer.next().unwrap_or("0").parse::<i32>().unwrap_or(0);
+                // A beacon of type 0 has no light
+                if beacon_type != 0 {
+                    let name = tokenizer.collect::<Vec<&str>>().join(" ");
+                    airport.add_beacon(Beacon::new(lat, long, beacon_type, name));
+                }
+            } else if r_type == "19" {
+                let lat = token_f64(tokenizer.next());
+                let long = token_f64(tokenizer.next());
+                let lit = tokenizer.next() == Some("1");
+                let name = tokenizer.collect::<Vec<&str>>().join(" ");
+                airport.add_windsock(Windsock::new(lat, long, lit, name));
             } else if r_type == "110" {
                 // Taxiway processing: start collecting nodes
                 do_taxi = true;
@@ -464,11 +517,11 @@ fn token_f64(t: Option<&str>) -> f64 {
 mod tests {
     use std::{fs, io::BufReader, path::PathBuf};
     use std::collections::HashMap;
-    use std::sync::Arc;
+    use std::sync::{Arc, RwLock};
 
     use flate2::read;
 
-    use crate::model::airport::Airport;
+    use crate::model::airport::{Airport, FrequencyType, StartLocationType};
     use crate::model::location::Location;
 
     use super::AirportParserFG850;
@@ -499,4 +552,74 @@ mod tests {
         assert_eq!(airports[21].get_id(), "RKSG");
         assert_eq!(airports[21].get_max_runway_length(), 8217);
     }
+
+    const APT_DAT: &str = "I
+1000 Version - data cycle 2013.10
+
+1    433 1 0 KSEA Seattle-Tacoma Intl
+1302 city Seattle
+1302 country United States
+1302 iata_code SEA
+1302 icao_code KSEA
+1302 region_code K1
+100 45.11 1 0 0.25 1 3 0 16L 47.46379089 -122.30775900 100.0 0.00 3 12 0 1 34R 47.43119539 -122.30804267 0.00 50.0 3 0 0 1
+102 H1 47.44500000 -122.30000000 90.00 20.00 20.00 1 0 0 0.25 0
+18 47.44000000 -122.31000000 1 BCN
+19 47.45000000 -122.31500000 1 WS
+50 11800 ATIS
+1054 119900 SEA TWR
+53 12190 GND
+15 47.44200000 -122.30300000 90.00 Ramp
+1300 47.44300000 -122.30400000 180.00 gate heavy|jets A1
+1    20 0 0 KBFI Boeing Field
+100 45.11 1 0 0.25 1 3 0 14L 47.54 -122.31 0.00 0.00 3 12 0 1 32R 47.52 -122.30 0.00 0.00 3 0 0 1
+";
+
+    #[test]
+    fn test_parse_layout() {
+        let mut airports: Vec<Arc<Airport>> = Vec::new();
+        let mut runway_offsets: HashMap<String, usize> = HashMap::new();
+        let mut parser = AirportParserFG850::new();
+        parser.load_airports(&mut airports, &mut runway_offsets, &mut BufReader::new(APT_DAT.as_bytes()))
+            .expect("Unable to parse airports");
+        assert_eq!(airports.len(), 2);
+
+        let airport = &airports[0];
+        let metadata = airport.get_metadata();
+        assert_eq!(metadata.get_city(), Some("Seattle"));
+        assert_eq!(metadata.get_country(), Some("United States"));
+        assert_eq!(metadata.get_iata_code(), Some("SEA"));
+        assert_eq!(metadata.get_region_code(), Some("K1"));
+        assert_eq!(airports[1].get_metadata().get_city(), None);
+
+        let offsets = RwLock::new(runway_offsets);
+        parser.load_runways(airport, &offsets, &mut BufReader::new(APT_DAT.as_bytes()))
+            .expect("Unable to parse runways");
+
+        let runways = airport.get_runways().read().unwrap();
+        assert_eq!(runways.len(), 2);
+        assert_eq!(runways[0].displaced_threshold_of("16L"), Some(328));
+        assert_eq!(runways[0].overrun_of("34R"), Some(164));
+        assert_eq!(runways[0].overrun_of("16L"), Some(0));
+        assert_eq!(runways[0].displaced_threshold_of("07"), None);
+
+        let frequencies = airport.get_frequencies().read().unwrap();
+        assert_eq!(frequencies.len(), 3);
+        assert_eq!(frequencies[0].get_type(), FrequencyType::Atis);
+        assert_eq!(frequencies[0].get_frequency(), 118.0);
+        assert_eq!(airport.get_frequency_of(FrequencyType::Tower).map(|f| f.get_frequency()), Some(119.9));
+        assert_eq!(airport.get_frequency_of(FrequencyType::Ground).map(|f| f.get_name().to_string()), Some("GND".to_string()));
+
+        let starts = airport.get_start_locations().read().unwrap();
+        assert_eq!(starts.len(), 2);
+        assert_eq!(starts[0].get_type(), StartLocationType::Misc);
+        assert_eq!(starts[1].get_type(), StartLocationType::Gate);
+        assert_eq!(starts[1].get_aircraft(), "heavy|jets");
+        assert_eq!(starts[1].get_name(), "A1");
+
+        assert_eq!(airport.get_beacons().read().unwrap().len(), 1);
+        let windsocks = airport.get_windsocks().read().unwrap();
+        assert_eq!(windsocks.len(), 1);
+        assert!(windsocks[0].is_lit());
+    }
 }
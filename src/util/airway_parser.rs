@@ -0,0 +1,273 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error};
+use std::sync::Arc;
+
+use flate2::read::GzDecoder;
+use log::{info, warn};
+
+use crate::earth::coordinate::Coordinate;
+use crate::model::airway::AirwaySegment;
+use crate::model::fix::{in_region, Fix};
+use crate::model::location::Location;
+use crate::model::navaid::{Navaid, NavaidType};
+
+// The positions, with their regions, of the fixes and navaids with each ident and 1100 type code
+type Lookup = HashMap<(String, u8), Vec<(Option<String>, Coordinate)>>;
+
+/// Parser for the FlightGear / X-Plane awy.dat file.
+///
+/// Both layouts of the file are understood.
+/// The 640 layout (shipped with FlightGear) carries the position of each end of a segment:
+///   `ABCDE  32.283733 -106.898669 ADGOS  33.281944 -107.280542 2 180 450 J13-J14`
+/// The 1100 layout identifies each end by ident, region and type and gives a direction:
+///   `ABCDE  K1 11 ADGOS  K1 11 N 2 180 450 J13-J14`
+/// For the 1100 layout the ends are located using the fixes and navaids already loaded.
+pub struct AirwayParserFG {}
+
+impl AirwayParserFG {
+    pub fn load_airways(
+        &mut self,
+        segments: &mut Vec<AirwaySegment>,
+        fixes: &[Arc<Fix>],
+        navaids: &[Arc<Navaid>],
+        reader: &mut BufReader<GzDecoder<File>>,
+    ) -> Result<(), Error> {
+        let mut buf = String::new();
+        let mut lookup: Option<Lookup> = None;
+        let mut unresolved = 0;
+
+        // ignore first three lines
+        for _i in 0..3 {
+            buf.clear();
+            match reader.read_line(&mut buf) {
+                Ok(0) => return Ok(()), // EOF
+                Ok(_bytes) => (),
+                Err(msg) => {
+                    match msg.kind() {
+                        std::io::ErrorKind::InvalidData => (),
+                        _ => {
+                            return Err(msg);
+                        }
+                    }
+                    info!("{}", msg.kind());
+                }
+            }
+        }
+        loop {
+            buf.clear();
+            match reader.read_line(&mut buf) {
+                Ok(0) => break, // EOF
+                Ok(_bytes) => (),
+                Err(msg) => {
+                    return Err(msg);
+                }
+            }
+            let is_empty = buf.trim().is_empty();
+
+            if !is_empty && !buf.starts_with("//") && !buf.starts_with("99") {
+                let tokens: Vec<&str> = buf.split_whitespace().collect();
+                if tokens.len() == 10 {
+                    self.parse_640(&tokens, segments);
+                } else if tokens.len() == 11 {
+                    let lookup = lookup.get_or_insert_with(|| Self::build_lookup(fixes, navaids));
+                    if !self.parse_1100(&tokens, lookup, segments) {
+                        unresolved += 1;
+                    }
+                }
+            }
+        }
+        if unresolved > 0 {
+            warn!("{} airway segments reference unknown fixes or navaids", unresolved);
+        }
+        Ok(())
+    }
+
+    fn parse_640(&self, tokens: &[&str], segments: &mut Vec<AirwaySegment>) {
+        let from = Coordinate::new(token_f64(tokens[1]), token_f64(tokens[2]));
+        let to = Coordinate::new(token_f64(tokens[4]), token_f64(tokens[5]));
+        Self::add_segments(tokens[0], from, tokens[3], to, &tokens[6..10], false, segments);
+    }
+
+    fn parse_1100(&self, tokens: &[&str], lookup: &Lookup, segments: &mut Vec<AirwaySegment>) -> bool {
+        let from_type = tokens[2].parse::<u8>().unwrap_or(11);
+        let to_type = tokens[5].parse::<u8>().unwrap_or(11);
+        let (Some(from_candidates), Some(to_candidates)) = (
+            lookup.get(&(tokens[0].to_string(), from_type)),
+            lookup.get(&(tokens[3].to_string(), to_type))) else {
+            return false;
+        };
+
+        // An ident may be used in several places, take the pair in the right regions closest together
+        let from_candidates = in_region(from_candidates.iter().collect(), Some(tokens[1]), |(r, _)| r.as_deref());
+        let to_candidates = in_region(to_candidates.iter().collect(), Some(tokens[4]), |(r, _)| r.as_deref());
+        let mut best: Option<(&Coordinate, &Coordinate)> = None;
+        let mut best_distance = f64::MAX;
+        for (_, f) in &from_candidates {
+            for (_, t) in &to_candidates {
+                let d = f.distance_to(t);
+                if d < best_distance {
+                    best_distance = d;
+                    best = Some((f, t));
+                }
+            }
+        }
+        let Some((from, to)) = best else {
+            return false;
+        };
+
+        match tokens[6] {
+            // Backward only, so store it the way it is flown
+            "B" => Self::add_segments(tokens[3], to.clone(), tokens[0], from.clone(), &tokens[7..11], true, segments),
+            "F" => Self::add_segments(tokens[0], from.clone(), tokens[3], to.clone(), &tokens[7..11], true, segments),
+            _ => Self::add_segments(tokens[0], from.clone(), tokens[3], to.clone(), &tokens[7..11], false, segments),
+        }
+        true
+    }
+
+    /// `details` holds the level (1 = low, 2 = high), base, top and the airway names.
+    fn add_segments(from_id: &str, from: Coordinate, to_id: &str, to: Coordinate, details: &[&str],
+                    one_way: bool, segments: &mut Vec<AirwaySegment>) {
+        let high = details[0] == "2";
+        let base = details[1].parse::<i32>().unwrap_or(0);
+        let top = details[2].parse::<i32>().unwrap_or(0);
+        for name in details[3].split('-').filter(|n| !n.is_empty()) {
+            segments.push(AirwaySegment::new(
+                (from_id.to_string(), from.clone()),
+                (to_id.to_string(), to.clone()),
+                name.to_string(), high, base, top, one_way));
+        }
+    }
+
+    fn build_lookup(fixes: &[Arc<Fix>], navaids: &[Arc<Navaid>]) -> Lookup {
+        let mut lookup: Lookup = HashMap::new();
+        for fix in fixes {
+            lookup.entry((fix.get_id().to_string(), 11))
+                .or_default()
+                .push((fix.get_region().map(|r| r.to_string()), fix.get_loc().clone()));
+        }
+        for navaid in navaids {
+            // Airways only join NDBs and VORs, or DMEs standing in for them
+            let type_code = match navaid.get_type() {
+                NavaidType::Ndb => 2,
+                t if t.is_landing_aid() => continue,
+                _ => 3,
+            };
+            lookup.entry((navaid.get_id().to_string(), type_code))
+                .or_default()
+                .push((None, navaid.get_loc().clone()));
+        }
+        lookup
+    }
+}
+
+fn token_f64(token: &str) -> f64 {
+    token.parse::<f64>().unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::BufReader, path::PathBuf};
+    use std::sync::Arc;
+
+    use flate2::read;
+
+    use crate::model::airway::AirwaySegment;
+    use crate::model::fix::Fix;
+    use crate::model::navaid::{Navaid, NavaidType};
+
+    use super::AirwayParserFG;
+
+    fn parse(file_name: &str, fixes: &[Arc<Fix>], navaids: &[Arc<Navaid>]) -> Vec<AirwaySegment> {
+        let mut segments: Vec<AirwaySegment> = Vec::new();
+
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("resources/test");
+        path.push(file_name);
+        let file = fs::File::open(path);
+
+        match file {
+            Ok(input) => {
+                let mut parser = AirwayParserFG {};
+                let decoder = read::GzDecoder::new(input);
+                let mut reader = BufReader::new(decoder);
+                match parser.load_airways(&mut segments, fixes, navaids, &mut reader) {
+                    Ok(()) => (),
+                    Err(msg) => panic! {"{}", msg},
+                }
+            }
+            Err(e) => panic!("Unable to open test airway data {}", e),
+        }
+        segments
+    }
+
+    #[test]
+    fn test_parse_640() {
+        let segments = parse("airways.dat.gz", &[], &[]);
+
+        assert_eq!(segments.len(), 12);
+        assert_eq!(segments[0].get_from_id(), "SY");
+        assert_eq!(segments[0].get_to_id(), "BOREE");
+        assert_eq!(segments[0].get_name(), "H65");
+        assert!(!segments[0].is_high());
+        // A line naming two airways gives two segments
+        assert_eq!(segments[4].get_name(), "H65");
+        assert_eq!(segments[5].get_name(), "J30");
+        assert_eq!(segments[5].get_from_id(), segments[4].get_from_id());
+        assert!(segments[11].is_high());
+        assert_eq!(*segments[11].get_top(), 600);
+    }
+
+    #[test]
+    fn test_parse_1100() {
+        let make_fix = |id: &str, lat: f64, lon: f64, region: &str| {
+            let mut fix = Fix::new(id.to_string(), lat, lon);
+            fix.set_region(Some(region.to_string()));
+            Arc::new(fix)
+        };
+        // The BOREE in the wrong region is nearer Sydney, but the region decides
+        let fixes = vec![
+            make_fix("BOREE", -33.9, 151.0, "NZ"),
+            make_fix("BOREE", -33.2, 148.9, "YM"),
+            Arc::new(Fix::new("TESAT".to_string(), -32.5, 146.5)),
+        ];
+        let navaids = vec![
+            Arc::new(Navaid::new("SY".to_string(), NavaidType::Vor, -33.95, 151.18, 0, 112.1, 100, "12E".to_string(), "SYDNEY".to_string())),
+        ];
+        let segments = parse("airways1100.dat.gz", &fixes, &navaids);
+
+        // The segment to an unknown fix is dropped
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].get_from_id(), "SY");
+        assert_eq!(segments[0].get_to().get_latitude(), -33.2);
+        assert!(!segments[0].is_one_way());
+        // "B" segments are turned around so they run the way they may be flown
+        assert_eq!(segments[1].get_from_id(), "TESAT");
+        assert_eq!(segments[1].get_to_id(), "BOREE");
+        assert!(segments[1].is_one_way());
+    }
+}
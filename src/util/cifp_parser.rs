@@ -0,0 +1,256 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+use std::cmp::Ordering;
+use std::io::BufRead;
+use std::sync::Arc;
+
+use crate::earth::coordinate::Coordinate;
+use crate::earth::Earth;
+use crate::model::airport::Airport;
+use crate::model::constraint::{AltitudeConstraint, SpeedConstraint};
+use crate::model::cruise_level::parse_level;
+use crate::model::fix::{self, Fix};
+use crate::model::location::Location;
+use crate::model::procedure::{LegPoint, Procedure, ProcedureLeg, ProcedureType};
+
+// Procedure fixes further than this from the airport belong to some other region
+const MAX_FIX_DISTANCE: f64 = 300.0;
+
+// Field positions in a procedure record, counted after the "SID:" etc. prefix
+const ROUTE_TYPE: usize = 1;
+const IDENT: usize = 2;
+const TRANSITION: usize = 3;
+const FIX_ID: usize = 4;
+const FIX_REGION: usize = 5;
+const FIX_SECTION: usize = 6;
+const FIX_SUBSECTION: usize = 7;
+const DESCRIPTION: usize = 8;
+const PATH_TERMINATOR: usize = 11;
+const ALTITUDE_DESCRIPTION: usize = 23;
+const ALTITUDE_1: usize = 25;
+const ALTITUDE_2: usize = 26;
+const SPEED_DESCRIPTION: usize = 28;
+const SPEED_LIMIT: usize = 29;
+
+/// Reads the SIDs, STARs and approaches for an airport from an X-Plane CIFP file, `CIFP/<ICAO>.dat`.
+pub struct CifpParser {}
+
+impl CifpParser {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn load_procedures<R: BufRead>(&self, airport: &Airport, earth: &Earth, reader: &mut R) -> Result<Vec<Procedure>, String> {
+        let mut procedures: Vec<Procedure> = Vec::new();
+        // Legs after the missed approach point belong to the missed approach, which we do not fly
+        let mut missed_approach: Option<(String, char)> = None;
+
+        for line in reader.lines() {
+            let line = line.map_err(|e| format!("Unable to read procedures for {}: {}", airport.get_id(), e))?;
+            let Some((record, data)) = line.split_once(':') else {
+                continue;
+            };
+            let procedure_type = match record {
+                "SID" => ProcedureType::Sid,
+                "STAR" => ProcedureType::Star,
+                "APPCH" => ProcedureType::Approach,
+                _ => continue,
+            };
+            let fields: Vec<&str> = data.trim_end().trim_end_matches(';').split(',').collect();
+            if fields.len() <= SPEED_LIMIT {
+                continue;
+            }
+            let ident = fields[IDENT].trim();
+            let route_type = fields[ROUTE_TYPE].trim().chars().next().unwrap_or(' ');
+            if missed_approach.as_ref().is_some_and(|(i, r)| i == ident && *r == route_type) {
+                continue;
+            }
+            missed_approach = None;
+
+            let fix_id = fields[FIX_ID].trim();
+            let point = self.resolve(airport, earth, fix_id, fields[FIX_REGION].trim(), fields[FIX_SECTION].trim(), fields[FIX_SUBSECTION].trim());
+            let leg = ProcedureLeg::new(
+                fix_id.to_string(),
+                fields[PATH_TERMINATOR].trim().to_string(),
+                point,
+                altitude_constraint(fields[ALTITUDE_DESCRIPTION], fields[ALTITUDE_1], fields[ALTITUDE_2]),
+                speed_constraint(fields[SPEED_DESCRIPTION], fields[SPEED_LIMIT]),
+            );
+            if procedure_type == ProcedureType::Approach && fields[DESCRIPTION].chars().nth(3) == Some('M') {
+                missed_approach = Some((ident.to_string(), route_type));
+            }
+
+            let procedure = match procedures.iter_mut().rposition(|p| p.get_type() == procedure_type && p.get_ident() == ident) {
+                Some(i) => &mut procedures[i],
+                None => {
+                    procedures.push(Procedure::new(procedure_type, ident.to_string()));
+                    procedures.last_mut().expect("procedure just added")
+                }
+            };
+            procedure.add_leg(route_type, fields[TRANSITION].trim(), leg);
+        }
+        Ok(procedures)
+    }
+
+    /// Find the fix for a leg, taking the one in the leg's region nearest the airport where the id is not unique
+    fn resolve(&self, airport: &Airport, earth: &Earth, fix_id: &str, region: &str, section: &str, subsection: &str) -> Option<LegPoint> {
+        if fix_id.is_empty() {
+            return None;
+        }
+        let loc = airport.get_loc();
+        let navaid = || nearest(earth.get_navaids_by_id(fix_id), loc).map(LegPoint::Navaid);
+        let fix = || nearest(fix::in_region(earth.get_fixes_by_id(fix_id), Some(region), |f| f.get_region()), loc).map(LegPoint::Fix);
+        match (section, subsection) {
+            ("P", "G") => {
+                let number = fix_id.trim_start_matches("RW");
+                airport.get_runway_for(number)
+                    .and_then(|runway| runway.threshold_of(number))
+                    .map(|c| LegPoint::Fix(Arc::new(Fix::new(fix_id.to_string(), c.get_latitude(), c.get_longitude()))))
+            }
+            ("D", _) | ("P", "N") => navaid().or_else(fix),
+            _ => fix().or_else(navaid),
+        }
+    }
+}
+
+impl Default for CifpParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn nearest<T: Location>(candidates: Vec<Arc<T>>, loc: &Coordinate) -> Option<Arc<T>> {
+    candidates.into_iter()
+        .map(|c| (c.get_loc().distance_to(loc), c))
+        .filter(|(d, _)| *d < MAX_FIX_DISTANCE)
+        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal))
+        .map(|(_, c)| c)
+}
+
+fn altitude_constraint(description: &str, altitude_1: &str, altitude_2: &str) -> Option<AltitudeConstraint> {
+    let altitude = parse_level(altitude_1)?;
+    match description.trim() {
+        "+" | "H" | "J" | "V" => Some(AltitudeConstraint::AtOrAbove(altitude)),
+        "-" | "Y" => Some(AltitudeConstraint::AtOrBelow(altitude)),
+        // At or above the second altitude and at or below the first
+        "B" => match parse_level(altitude_2) {
+            Some(lower) => Some(AltitudeConstraint::Between(lower.min(altitude), lower.max(altitude))),
+            None => Some(AltitudeConstraint::AtOrBelow(altitude)),
+        },
+        _ => Some(AltitudeConstraint::At(altitude)),
+    }
+}
+
+fn speed_constraint(description: &str, limit: &str) -> Option<SpeedConstraint> {
+    let speed = limit.trim().parse::<i32>().ok()?;
+    match description.trim() {
+        "+" => Some(SpeedConstraint::AtOrAbove(speed)),
+        "-" => Some(SpeedConstraint::AtOrBelow(speed)),
+        _ => Some(SpeedConstraint::At(speed)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+    use std::sync::Arc;
+
+    use crate::earth::Earth;
+    use crate::model::airport::{Runway, RunwayType};
+    use crate::model::constraint::{AltitudeConstraint, SpeedConstraint};
+    use crate::model::fix::Fix;
+    use crate::model::navaid::{Navaid, NavaidType};
+    use crate::model::procedure::ProcedureType;
+    use crate::model::test_utils::tests::make_airport_at;
+
+    use super::CifpParser;
+
+    const CIFP: &str = "\
+PRDAT:ABBEY3,0,0,0;
+SID:010,1,ABBEY3,RW16R,,  , , ,    , ,   ,VA, , , , , ,      ,    ,1570,    , , ,+,     ,00500,     ,18000, ,   ,    ,   , , , , , ,0,D,S;
+SID:020,1,ABBEY3,RW16R,AAA,YM,P,C,E   , ,   ,DF, , , , , ,      ,    ,    ,    , , , ,     ,     ,     ,18000, ,   ,    ,   , , , , , ,0,D,S;
+SID:030,2,ABBEY3,ALL,AAA,YM,P,C,E   , ,   ,TF, , , , , ,      ,    ,    ,    , , ,+,     ,05000,     ,18000,-,210,    ,   , , , , , ,0,D,S;
+SID:040,2,ABBEY3,ALL,SY,YM,D, ,V   , ,   ,TF, , , , , ,      ,    ,    ,    , , ,B,     ,FL120,08000,18000, ,   ,    ,   , , , , , ,0,D,S;
+STAR:010,1,BOREE4,NORTH,BBB,YM,E,A,E   , ,   ,IF, , , , , ,      ,    ,    ,    , , , ,     ,     ,     ,18000, ,   ,    ,   , , , , , ,0,D,S;
+STAR:020,2,BOREE4,ALL,AAA,YM,P,C,E   , ,   ,TF, , , , , ,      ,    ,    ,    , , ,-,     ,09000,     ,18000, ,250,    ,   , , , , , ,0,D,S;
+APPCH:010,I,I16R,,AAA,YM,P,C,E  F, ,   ,IF, , , , , ,      ,    ,    ,    , , ,@,     ,02000,     ,18000, ,   ,    ,   , , , , , ,0,D,S;
+APPCH:020,I,I16R,,RW16R,YM,P,G,GY M, ,   ,TF, , , , , ,      ,    ,    ,    , , , ,     ,     ,     ,18000, ,   ,    ,   , , , , , ,0,D,S;
+APPCH:030,I,I16R,,,  , , ,    , ,   ,CA, , , , , ,      ,    ,    ,    , , ,+,     ,03000,     ,18000, ,   ,    ,   , , , , , ,0,D,S;
+APPCH:040,I,I16R,,SY,YM,D, ,V  H, ,   ,DF, , , , , ,      ,    ,    ,    , , , ,     ,     ,     ,18000, ,   ,    ,   , , , , , ,0,D,S;
+RWY:RW16R,     ,      ,00000, ,IOOX,3,   ;N33580290,E151105630,0000;
+";
+
+    fn make_earth() -> Earth {
+        let earth = Earth::new();
+        earth.set_navaids(vec![Arc::new(Navaid::new("SY".to_string(), NavaidType::Vor, -33.9, 151.2, 0, 112.1, 100, "12E".to_string(), "SYDNEY".to_string()))]);
+        earth.set_fixes(vec![
+            Arc::new(Fix::new("AAA".to_string(), 40.0, 10.0)),
+            Arc::new(Fix::new("AAA".to_string(), -34.1, 151.1)),
+            Arc::new(Fix::new("BBB".to_string(), -32.5, 151.5)),
+        ]);
+        earth
+    }
+
+    #[test]
+    fn test_load_procedures() {
+        let airport = make_airport_at("YSSY", -33.946, 151.177);
+        airport.add_runway(Runway::new("16R".to_string(), Some(RunwayType::Runway), -33.96, 151.18, 12000, 150, 160.0, false, "1".into(), "2".into()));
+
+        let mut reader = BufReader::new(CIFP.as_bytes());
+        let procedures = CifpParser::new().load_procedures(&airport, &make_earth(), &mut reader).unwrap();
+        assert_eq!(procedures.len(), 3);
+
+        let sid = &procedures[0];
+        assert_eq!(sid.get_type(), ProcedureType::Sid);
+        assert_eq!(sid.get_runways(), vec!["16R"]);
+        let legs = sid.get_legs(Some("16R"), None);
+        assert_eq!(legs.len(), 3);
+        assert_eq!(legs[0].get_path_terminator(), "VA");
+        assert_eq!(legs[0].get_altitude(), Some(AltitudeConstraint::AtOrAbove(500)));
+        assert!(legs[0].to_waypoint().is_none());
+        // The AAA nearest the airport
+        let aaa = legs[1].to_waypoint().unwrap();
+        assert_eq!(aaa.get_lat(), -34.1);
+        assert_eq!(legs[1].get_altitude(), Some(AltitudeConstraint::AtOrAbove(5000)));
+        assert_eq!(legs[1].get_speed(), Some(SpeedConstraint::AtOrBelow(210)));
+        assert_eq!(legs[2].to_waypoint().unwrap().get_type_name(), "NAVAID");
+        assert_eq!(legs[2].get_altitude(), Some(AltitudeConstraint::Between(8000, 12000)));
+
+        let star = &procedures[1];
+        assert_eq!(star.get_transitions(), vec!["NORTH"]);
+        let legs = star.get_legs(None, Some("NORTH"));
+        assert_eq!(legs.len(), 2);
+        assert_eq!(legs[1].get_altitude(), Some(AltitudeConstraint::AtOrBelow(9000)));
+        assert_eq!(legs[1].get_speed(), Some(SpeedConstraint::At(250)));
+
+        // The missed approach is not flown
+        let approach = &procedures[2];
+        let legs = approach.get_legs(Some("16R"), None);
+        assert_eq!(legs.len(), 2);
+        assert_eq!(legs[0].get_altitude(), Some(AltitudeConstraint::At(2000)));
+        let threshold = legs[1].to_waypoint().unwrap();
+        assert_eq!(threshold.get_id(), "RW16R");
+    }
+}
@@ -71,11 +71,11 @@ pub struct AircraftPositionInfo {
 }
 
 impl AircraftPositionInfo {
-    pub(crate) fn get_position(&self) -> &Coordinate {
+    pub fn get_position(&self) -> &Coordinate {
         &self.position
     }
 
-    pub(crate) fn get_heading(&self) -> f64 {
+    pub fn get_heading(&self) -> f64 {
         self.heading
     }
 }
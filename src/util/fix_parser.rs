@@ -22,11 +22,9 @@
  *
  */
 
-use std::fs::File;
-use std::io::{BufRead, BufReader, Error};
+use std::io::{BufRead, Error};
 use std::sync::Arc;
 
-use flate2::read::GzDecoder;
 use log::info;
 
 use crate::model::fix::Fix;
@@ -34,10 +32,10 @@ use crate::model::fix::Fix;
 pub struct FixParserFG {}
 
 impl FixParserFG {
-    pub fn load_fixes(
+    pub fn load_fixes<R: BufRead>(
         &mut self,
         fixes: &mut Vec<Arc<Fix>>,
-        reader: &mut BufReader<GzDecoder<File>>,
+        reader: &mut R,
     ) -> Result<(), Error> {
         let mut buf = String::new();
 
@@ -83,7 +81,10 @@ impl FixParserFG {
                     .unwrap_or(0.0);
                 let id = tokenizer.next().unwrap_or("");
 
-                let fix = Fix::new(id.to_string(), latitude, longitude);
+                let mut fix = Fix::new(id.to_string(), latitude, longitude);
+                // From version 1101 the terminal area airport and ICAO region follow the ident
+                fix.set_terminal_area(tokenizer.next().filter(|t| *t != "ENRT").map(|t| t.to_string()));
+                fix.set_region(tokenizer.next().map(|t| t.to_string()));
                 fixes.push(Arc::new(fix));
             }
         }
@@ -127,4 +128,29 @@ mod tests {
         assert_eq!(fixs[0].get_id(), "0000E");
         assert_eq!(fixs[20].get_id(), "03MCT");
     }
+
+    const FIXES_1101: &str = "\
+I
+1101 Version - data cycle 2310, build 20231002, metadata FixXP1101.
+
+ 37.428522222 -122.094536111 AAAME KSFO K2 4530263
+-33.963611111  151.058333333 ABBEY ENRT YM 2
+ 51.337500000   -1.080000000 ABBEY ENRT EG 2
+99
+";
+
+    #[test]
+    fn test_parse_regions() {
+        let mut fixs: Vec<Arc<Fix>> = Vec::new();
+        let mut reader = BufReader::new(FIXES_1101.as_bytes());
+        FixParserFG {}.load_fixes(&mut fixs, &mut reader).unwrap();
+
+        assert_eq!(fixs.len(), 3);
+        assert_eq!(fixs[0].get_id(), "AAAME");
+        assert_eq!(fixs[0].get_terminal_area(), Some("KSFO"));
+        assert_eq!(fixs[0].get_region(), Some("K2"));
+        assert_eq!(fixs[1].get_terminal_area(), None);
+        assert_eq!(fixs[1].get_region(), Some("YM"));
+        assert_eq!(fixs[2].get_region(), Some("EG"));
+    }
 }
@@ -22,6 +22,7 @@
  *
  */
 
+#[cfg(feature = "gui")]
 use gtk::License;
 
 pub const PROGRAM_NAME: &str = "Kelpie Flight Planner";
@@ -31,5 +32,6 @@ pub const VERSION: &str = "2.0.0";
 pub const AUTHOR: &str = "Trevor Campbell";
 pub const WEBSITE: &str = "https://github.com/shartrec/kelpie-flight-planner";
 pub const DOCSITE: &str = "https://shartrec.github.io/kelpie-flight-planner/";
+#[cfg(feature = "gui")]
 pub const LICENSE_TYPE: License = License::Gpl30;
 
@@ -22,18 +22,28 @@
  *
  */
 
+#[cfg(feature = "gui")]
 use adw::prelude::Cast;
+#[cfg(feature = "gui")]
 use adw::subclass::prelude::ObjectSubclassIsExt;
+#[cfg(feature = "gui")]
 use gtk::CustomFilter;
+use std::collections::HashSet;
+use std::sync::Arc;
+
 use regex_lite::{Regex, RegexBuilder};
 
 use crate::earth::coordinate::Coordinate;
+#[cfg(feature = "gui")]
 use crate::model::airport_object::AirportObject;
+#[cfg(feature = "gui")]
 use crate::model::fix_object::FixObject;
 use crate::model::location::Location;
-use crate::model::navaid::{Navaid, NavaidType};
+use crate::model::navaid::Navaid;
+#[cfg(feature = "gui")]
 use crate::model::navaid_object::NavaidObject;
 
+#[cfg(feature = "gui")]
 pub fn new_airport_filter(filter: Box<dyn Filter>) -> CustomFilter {
     CustomFilter::new(move |obj| {
         let airport_object = obj.clone()
@@ -46,6 +56,7 @@ pub fn new_airport_filter(filter: Box<dyn Filter>) -> CustomFilter {
     })
 }
 
+#[cfg(feature = "gui")]
 pub fn set_airport_filter(custom_filter: &CustomFilter, filter: Box<dyn Filter>) {
     custom_filter.set_filter_func(move |obj| {
         let airport_object = obj.clone()
@@ -58,6 +69,7 @@ pub fn set_airport_filter(custom_filter: &CustomFilter, filter: Box<dyn Filter>)
     })
 }
 
+#[cfg(feature = "gui")]
 pub fn new_navaid_filter(filter: Box<dyn Filter>) -> CustomFilter {
     CustomFilter::new(move |obj| {
         let navaid_object = obj.clone()
@@ -70,6 +82,7 @@ pub fn new_navaid_filter(filter: Box<dyn Filter>) -> CustomFilter {
     })
 }
 
+#[cfg(feature = "gui")]
 pub fn set_navaid_filter(custom_filter: &CustomFilter, filter: Box<dyn Filter>) {
     custom_filter.set_filter_func(move |obj| {
         let navaid_object = obj.clone()
@@ -82,6 +95,7 @@ pub fn set_navaid_filter(custom_filter: &CustomFilter, filter: Box<dyn Filter>)
     })
 }
 
+#[cfg(feature = "gui")]
 pub fn new_fix_filter(filter: Box<dyn Filter>) -> CustomFilter {
     CustomFilter::new(move |obj| {
         let fix_object = obj.clone()
@@ -94,6 +108,7 @@ pub fn new_fix_filter(filter: Box<dyn Filter>) -> CustomFilter {
     })
 }
 
+#[cfg(feature = "gui")]
 pub fn set_fix_filter(custom_filter: &CustomFilter, filter: Box<dyn Filter>) {
     custom_filter.set_filter_func(move |obj| {
         let fix_object = obj.clone()
@@ -188,6 +203,26 @@ impl Filter for RangeFilter {
     }
 }
 
+// Filter passing only the locations found by a spatial query such as Earth::get_airports_near,
+// so a search near a point does not have to measure the distance to every location in the list
+pub struct FoundFilter {
+    found: HashSet<usize>,
+}
+
+impl FoundFilter {
+    pub fn new<T: Location>(found: &[Arc<T>]) -> Self {
+        Self {
+            found: found.iter().map(|l| Arc::as_ptr(l) as *const () as usize).collect(),
+        }
+    }
+}
+
+impl Filter for FoundFilter {
+    fn filter(&self, location: &dyn Location) -> bool {
+        self.found.contains(&(location as *const dyn Location as *const () as usize))
+    }
+}
+
 pub struct VorFilter {}
 
 impl VorFilter {
@@ -199,7 +234,7 @@ impl VorFilter {
 impl Filter for VorFilter {
     fn filter(&self, location: &dyn Location) -> bool {
         if let Some(navaid) = location.as_any().downcast_ref::<Navaid>() {
-            navaid.get_type() == NavaidType::Vor
+            navaid.get_type().is_vor()
         } else {
             false
         }
@@ -23,33 +23,50 @@
  */
 #![forbid(unsafe_code)]
 
+#[cfg(feature = "gui")]
 use gtk::FileFilter;
+#[cfg(feature = "gui")]
 use gtk::gio::ListStore;
 use rolling_file::{BasicRollingFileAppender, RollingConditionBasic};
 use simplelog::{ColorChoice, CombinedLogger, Config, ConfigBuilder, TermLogger, TerminalMode, WriteLogger};
 use log::LevelFilter;
 use std::error::Error;
 
-pub(crate) mod airport_painter;
-pub(crate) mod airport_parser;
-pub(crate) mod distance_format;
-pub(crate) mod fix_parser;
-pub(crate) mod hour_format;
-pub(crate) mod lat_long_format;
-pub(crate) mod location_filter;
-pub(crate) mod navaid_parser;
-pub(crate) mod speed_format;
-pub(crate) mod plan_writer_xml;
-pub(crate) mod plan_reader;
-pub(crate) mod plan_writer_route_manager;
-pub(crate) mod fg_link;
-pub(crate) mod info;
+#[cfg(feature = "gui")]
+pub mod airport_painter;
+pub mod airport_parser;
+pub mod airway_parser;
+pub mod cifp_parser;
+pub mod distance_format;
+pub mod fix_parser;
+pub mod hour_format;
+pub mod lat_long_format;
+pub mod location_filter;
+pub mod navaid_parser;
+pub mod speed_format;
+pub mod plan_writer_xml;
+pub mod plan_reader;
+pub mod plan_reader_fms;
+pub mod plan_reader_gpx;
+pub mod plan_reader_route_manager;
+pub mod plan_writer_route_manager;
+pub mod plan_writer_fms;
+pub mod plan_writer_gpx;
+pub mod plan_writer_kml;
+pub mod plan_writer_text;
+pub mod route_string;
+pub mod wind_reader;
+pub mod fg_link;
+pub mod info;
 
-pub fn get_plan_file_filter(ext: &str) -> ListStore {
+#[cfg(feature = "gui")]
+pub fn get_plan_file_filter(exts: &[&str]) -> ListStore {
     let store = ListStore::new::<FileFilter>();
-    let filter = FileFilter::new();
-    filter.add_suffix(ext);
-    store.append(&filter);
+    for ext in exts {
+        let filter = FileFilter::new();
+        filter.add_suffix(ext);
+        store.append(&filter);
+    }
     let filter = FileFilter::new();
     filter.add_pattern("*");
     store.append(&filter);
@@ -23,23 +23,25 @@
  */
 
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufRead, BufReader, Error};
+use std::io::{BufRead, Error};
 use std::sync::Arc;
 
-use flate2::read::GzDecoder;
 use log::info;
 
+use crate::model::location::Location;
 use crate::model::navaid::{Navaid, NavaidType};
 
+// How far in nm a DME can be from a VOR or localizer with the same ident and still be paired with it
+const DME_PAIRING_DISTANCE: f64 = 3.0;
+
 pub struct NavaidParserFG {}
 
 impl NavaidParserFG {
-    pub fn load_navaids(
+    pub fn load_navaids<R: BufRead>(
         &mut self,
         navaids: &mut Vec<Arc<Navaid>>,
         ils: &mut HashMap<String, Vec<(String, f64)>>,
-        reader: &mut BufReader<GzDecoder<File>>,
+        reader: &mut R,
     ) -> Result<(), Error> {
         let mut buf = String::new();
 
@@ -60,10 +62,11 @@ impl NavaidParserFG {
                 }
             }
         }
+        let mut parsed: Vec<Navaid> = Vec::new();
         loop {
             buf.clear();
             match reader.read_line(&mut buf) {
-                Ok(0) => return Ok(()), // EOF
+                Ok(0) => break, // EOF
                 Ok(_bytes) => (),
                 Err(msg) => {
                     return Err(msg);
@@ -71,73 +74,123 @@ impl NavaidParserFG {
             }
             let is_empty = &buf.trim().is_empty();
             if !is_empty {
-                let mut tokenizer = buf.split_whitespace();
-                let r_type = tokenizer.next().unwrap_or("");
-                // Translate other conditions and logic accordingly
-                if r_type == "2" || r_type == "3" {
-                    let navaid_type = NavaidType::type_for(r_type);
-
-                    let latitude = token_number::<f64>(tokenizer.next());
-                    let longitude = token_number::<f64>(tokenizer.next());
-                    let elevation = token_number::<i32>(tokenizer.next());
-                    let mut frequency = token_number::<f64>(tokenizer.next());
-                    if navaid_type.as_ref().unwrap_or(&NavaidType::Ndb) == &NavaidType::Vor {
-                        frequency /= 100.;
-                    }
-                    let range = token_number::<i32>(tokenizer.next());
-                    let mag_var = tokenizer.next().unwrap_or("");
-                    let id = tokenizer.next().unwrap_or("");
-
-                    let mut name = String::new();
-                    name.push_str(tokenizer.next().unwrap_or(""));
-                    for token in tokenizer {
-                        name.push(' ');
-                        name.push_str(token);
-                    }
-
-                    let navaid = Navaid::new(
-                        id.to_string(),
-                        navaid_type.unwrap_or(NavaidType::Ndb),
-                        latitude,
-                        longitude,
-                        elevation,
-                        frequency,
-                        range,
-                        mag_var.to_string(),
-                        name.to_string(),
-                    );
-                    navaids.push(Arc::new(navaid));
-                } else if r_type == "4" || r_type == "5" {
-                    /* latitude */
-                    tokenizer.next();
-                    /* longitude */
-                    tokenizer.next();
-                    /* elevation */
-                    tokenizer.next();
-                    let frequency = token_number::<f64>(tokenizer.next()) / 100.0;
-                    /* magVar */
-                    tokenizer.next();
-                    tokenizer.next();
-                    tokenizer.next();
-                    if let Some(airport_id) = tokenizer.next() {
-                        if let Some(runway_id) = tokenizer.next() {
-                            match ils.get_mut(airport_id) {
-                                Some(list) => {
-                                    list.push((runway_id.to_string(), frequency));
-                                }
-                                None => {
-                                    let list = vec![(runway_id.to_string(), frequency)];
-                                    ils.insert(airport_id.to_string(), list);
-                                }
-                            }
-                        }
-                    }
+                if let Some(navaid) = Self::parse_navaid(&buf, ils) {
+                    parsed.push(navaid);
                 }
             }
         }
+        navaids.extend(pair_dmes(parsed).into_iter().map(Arc::new));
+        Ok(())
+    }
+
+    // Every record has the same leading fields, what the seventh holds and whether an airport
+    // and runway follow the ident depends on the record type.
+    fn parse_navaid(line: &str, ils: &mut HashMap<String, Vec<(String, f64)>>) -> Option<Navaid> {
+        let mut tokenizer = line.split_whitespace();
+        let r_type = tokenizer.next().unwrap_or("");
+        if !matches!(r_type, "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" | "12" | "13") {
+            return None;
+        }
+        let latitude = token_number::<f64>(tokenizer.next());
+        let longitude = token_number::<f64>(tokenizer.next());
+        let elevation = token_number::<i32>(tokenizer.next());
+        let mut frequency = token_number::<f64>(tokenizer.next());
+        // Only NDB frequencies are in kHz, the rest are in units of 10kHz
+        if r_type != "2" {
+            frequency /= 100.;
+        }
+        let range = token_number::<i32>(tokenizer.next());
+        let field = tokenizer.next().unwrap_or("");
+        let id = tokenizer.next().unwrap_or("");
+        let runway = if matches!(r_type, "4" | "5" | "6" | "7" | "8" | "9") {
+            tokenizer.next().zip(tokenizer.next())
+        } else {
+            None
+        };
+        let name = tokenizer.collect::<Vec<&str>>().join(" ");
+
+        let navaid_type = NavaidType::type_for(r_type, &name)?;
+        let mag_var = if navaid_type.is_enroute() { field } else { "" };
+        let mut navaid = Navaid::new(
+            id.to_string(),
+            navaid_type,
+            latitude,
+            longitude,
+            elevation,
+            frequency,
+            range,
+            mag_var.to_string(),
+            name,
+        );
+
+        let field = token_number::<f64>(Some(field));
+        match navaid_type {
+            NavaidType::Ils | NavaidType::Localizer | NavaidType::OuterMarker | NavaidType::MiddleMarker | NavaidType::InnerMarker => {
+                navaid.set_course(field);
+            }
+            // The angle is multiplied by 100000 and added to the course, e.g. 300180.3 is 3.00 degrees on 180.3
+            NavaidType::Glideslope => {
+                navaid.set_glideslope_angle((field / 1000.0).floor() / 100.0);
+                navaid.set_course(field % 1000.0);
+            }
+            NavaidType::Dme | NavaidType::Tacan => navaid.set_dme_bias(field),
+            _ => (),
+        }
+        if let Some((airport_id, runway_id)) = runway {
+            navaid.set_runway(airport_id, runway_id);
+            if matches!(navaid_type, NavaidType::Ils | NavaidType::Localizer) {
+                ils.entry(airport_id.to_string())
+                    .or_default()
+                    .push((runway_id.to_string(), frequency));
+            }
+        }
+        Some(navaid)
     }
 }
 
+/// Fold each DME into the VOR or localizer it is co-located with, so a VOR with a DME becomes a VOR-DME
+/// and a VOR with a TACAN a VORTAC. The DMEs that stand alone are kept.
+fn pair_dmes(mut navaids: Vec<Navaid>) -> Vec<Navaid> {
+    let mut by_id: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, navaid) in navaids.iter().enumerate() {
+        if navaid.get_type().is_vor() || matches!(navaid.get_type(), NavaidType::Ils | NavaidType::Localizer) {
+            by_id.entry(navaid.get_id().to_string()).or_default().push(i);
+        }
+    }
+
+    let mut paired = vec![false; navaids.len()];
+    for i in 0..navaids.len() {
+        let dme = &navaids[i];
+        if !matches!(dme.get_type(), NavaidType::Dme | NavaidType::Tacan) {
+            continue;
+        }
+        let partner = by_id.get(dme.get_id()).and_then(|candidates| {
+            candidates.iter()
+                .copied()
+                .filter(|j| navaids[*j].get_dme_bias().is_none())
+                .find(|j| navaids[*j].get_loc().distance_to(dme.get_loc()) <= DME_PAIRING_DISTANCE)
+        });
+        if let Some(j) = partner {
+            let is_tacan = dme.get_type() == NavaidType::Tacan;
+            let bias = dme.get_dme_bias().unwrap_or(0.0);
+            let partner = &mut navaids[j];
+            partner.set_dme_bias(bias);
+            match partner.get_type() {
+                NavaidType::Vor | NavaidType::VorDme if is_tacan => partner.set_type(NavaidType::Vortac),
+                NavaidType::Vor => partner.set_type(NavaidType::VorDme),
+                _ => (),
+            }
+            paired[i] = true;
+        }
+    }
+
+    navaids.into_iter()
+        .zip(paired)
+        .filter(|(_, paired)| !paired)
+        .map(|(navaid, _)| navaid)
+        .collect()
+}
+
 fn token_number<T: std::str::FromStr + Default>(t: Option<&str>) -> T {
     t.unwrap_or("0.0")
         .parse::<T>()
@@ -187,4 +240,56 @@ mod tests {
             _ => panic!("navaid type is not NDB"),
         }
     }
+
+    const NAV_DAT: &str = "I
+810 Version - data cycle 2013.10
+
+3  47.43538889 -122.30961111    354 11680 130   19.0 SEA  SEATTLE VORTAC
+12 47.43538889 -122.30961111    354 11680 130    0.0 SEA  SEATTLE VORTAC DME
+3  47.53000000 -122.30000000     20 11300  40   19.0 BFI  BOEING VOR
+12 47.53100000 -122.30100000     20 11300  40    0.2 BFI  BOEING VOR DME
+13 47.70000000 -122.40000000    100 11000  25    0.0 PAE  PAINE DME
+13 48.35000000 -122.65000000    200 10860 130    0.0 NUW  WHIDBEY TACAN
+4  47.43000000 -122.31000000    347 11030  18  163.5 ISNQ KSEA 16L ILS-cat-I
+6  47.46000000 -122.31000000    425 11030  10  300163.5 ISNQ KSEA 16L GS
+12 47.46000000 -122.31000000    425 11030  18    0.3 ISNQ KSEA 16L DME-ILS
+7  47.60000000 -122.32000000      0     0   0  163.5 ---- KSEA 16L OM
+99
+";
+
+    #[test]
+    fn test_parse_all_types() {
+        let mut navaids: Vec<Arc<Navaid>> = Vec::new();
+        let mut ils: HashMap<String, Vec<(String, f64)>> = HashMap::new();
+        let mut parser = NavaidParserFG {};
+        let mut reader = BufReader::new(NAV_DAT.as_bytes());
+        parser.load_navaids(&mut navaids, &mut ils, &mut reader).expect("Unable to parse navaids");
+
+        // The DMEs with a VOR or localizer are folded into it
+        assert_eq!(navaids.len(), 7);
+        assert_eq!(navaids[0].get_type(), NavaidType::Vortac);
+        assert_eq!(navaids[0].get_dme_bias(), Some(0.0));
+        assert_eq!(navaids[1].get_type(), NavaidType::VorDme);
+        assert_eq!(navaids[1].get_dme_bias(), Some(0.2));
+        assert_eq!(navaids[2].get_type(), NavaidType::Dme);
+        assert_eq!(navaids[3].get_type(), NavaidType::Tacan);
+        assert!(navaids[3].has_dme());
+
+        let localizer = &navaids[4];
+        assert_eq!(localizer.get_type(), NavaidType::Ils);
+        assert_eq!(*localizer.get_freq(), 110.3);
+        assert_eq!(localizer.get_course(), Some(163.5));
+        assert_eq!(localizer.get_airport_id(), Some("KSEA"));
+        assert_eq!(localizer.get_runway(), Some("16L"));
+        assert_eq!(localizer.get_dme_bias(), Some(0.3));
+        assert_eq!(ils.get("KSEA"), Some(&vec![("16L".to_string(), 110.3)]));
+
+        let glideslope = &navaids[5];
+        assert_eq!(glideslope.get_type(), NavaidType::Glideslope);
+        assert_eq!(glideslope.get_glideslope_angle(), Some(3.0));
+        assert!((glideslope.get_course().unwrap() - 163.5).abs() < 0.001);
+
+        assert_eq!(navaids[6].get_type(), NavaidType::OuterMarker);
+        assert_eq!(navaids[6].get_course(), Some(163.5));
+    }
 }
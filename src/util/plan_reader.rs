@@ -23,122 +23,458 @@
  */
 
 use std::cell::Cell;
+use std::cmp::Ordering;
+use std::fmt;
 use std::fs::File;
+use std::io::Read;
 use std::path::Path;
+use std::sync::Arc;
 
-use adw::subclass::prelude::ObjectSubclassIsExt;
-use xmltree::Element;
+use chrono::{DateTime, Utc};
+use gettextrs::gettext;
+use xmltree::{Element, XMLNode};
 
 use crate::earth::coordinate::Coordinate;
-use crate::earth::get_earth_model;
+use crate::earth::{get_earth_model, Earth};
 use crate::hangar::hangar::get_hangar;
+use crate::model::airway::AirwayLeg;
+use crate::model::constraint::{AltitudeConstraint, Constraints, SpeedConstraint};
+use crate::model::cruise_level::{FlightRules, StepClimb};
+use crate::model::location::Location;
+use crate::model::navaid::Navaid;
 use crate::model::plan::Plan;
+use crate::model::plan_settings::PlanSettings;
+use crate::model::procedure::{ProcedureLeg, ProcedureSelection, ProcedureType};
 use crate::model::sector::Sector;
 use crate::model::waypoint::Waypoint;
+use crate::model::wind::{WindProfile, WindsAloft};
+use crate::util::plan_writer_xml::PLAN_FORMAT_VERSION;
+use crate::util::wind_reader::read_wind_grid;
 
-pub fn read_plan(file_path: &Path) -> Result<Plan, String> {
-    let file = match File::open(file_path) {
-        Ok(file) => file,
-        Err(_) => return Err(String::from("Error reading file")),
-    };
-    let doc = match Element::parse(file) {
+/// Why a plan file could not be read.
+/// The element is described by where it is in the plan, e.g. "sector 2 waypoint 3",
+/// while errors from the XML parser carry the line and column.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlanReadError {
+    Io(String),
+    Xml(String),
+    NotAPlan(String),
+    UnsupportedVersion(u32),
+    MissingAttribute { element: String, attribute: String },
+    InvalidAttribute { element: String, attribute: String, value: String },
+}
+
+impl fmt::Display for PlanReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlanReadError::Io(e) => write!(f, "Error reading file: {}", e),
+            PlanReadError::Xml(e) => write!(f, "Error parsing XML: {}", e),
+            PlanReadError::NotAPlan(name) => write!(f, "Not a plan, the file contains a {} element", name),
+            PlanReadError::UnsupportedVersion(version) =>
+                write!(f, "The plan was saved in format version {}, which is newer than this version of Kelpie can read", version),
+            PlanReadError::MissingAttribute { element, attribute } =>
+                write!(f, "The {} is missing the {} attribute", element, attribute),
+            PlanReadError::InvalidAttribute { element, attribute, value } =>
+                write!(f, "The {} has an invalid {} \"{}\"", element, attribute, value),
+        }
+    }
+}
+
+impl std::error::Error for PlanReadError {}
+
+/// Read a plan saved in Kelpie's own format.
+/// Along with the plan comes a warning for anything in the file that could not be found in the
+/// navigation data, each of which has either become a GPS waypoint or been left out.
+pub fn read_plan(file_path: &Path) -> Result<(Plan, Vec<String>), PlanReadError> {
+    let file = File::open(file_path).map_err(|e| PlanReadError::Io(e.to_string()))?;
+    read_plan_from(file, get_earth_model())
+}
+
+pub fn read_plan_from<R: Read>(reader: R, earth: &Earth) -> Result<(Plan, Vec<String>), PlanReadError> {
+    let mut doc = match Element::parse(reader) {
         Ok(doc) => doc,
-        Err(_) => return Err(String::from("Error parsing XML")),
+        Err(e) => return Err(PlanReadError::Xml(e.to_string())),
     };
 
     if doc.name != "plan" {
-        return Err(String::from("Invalid XML format"));
+        return Err(PlanReadError::NotAPlan(doc.name));
     }
+    let version = match doc.attributes.get("version") {
+        Some(_) => parse_attribute::<u32>(&doc, "version", "plan")?,
+        None => 1,
+    };
+    if version > PLAN_FORMAT_VERSION {
+        return Err(PlanReadError::UnsupportedVersion(version));
+    }
+    migrate(&mut doc, version);
 
     let mut plan = Plan::new();
+    let mut warnings = Vec::new();
+
+    plan.set_created(read_time(&doc, "created", "plan")?);
+    plan.set_departure_time(read_time(&doc, "departure-time", "plan")?);
+    if let Some(author) = doc.attributes.get("author") {
+        plan.set_author(author);
+    }
+    if let Some(remarks) = doc.get_child("remarks").and_then(|r| r.get_text()) {
+        plan.set_remarks(&remarks);
+    }
+    if doc.attributes.contains_key("max-altitude") {
+        plan.set_max_altitude(Some(parse_attribute::<i32>(&doc, "max-altitude", "plan")?));
+    }
+    if doc.attributes.contains_key("flight-rules") {
+        plan.set_flight_rules(parse_attribute::<FlightRules>(&doc, "flight-rules", "plan")?);
+    }
+    if let Some(settings) = doc.get_child("settings") {
+        plan.set_settings(Some(read_settings(settings)?));
+    }
 
     if let Some(aircraft_name) = doc.attributes.get("aircraft") {
-        // Assuming getAircraftManager() and getAircraftByName() functions
-        let aircraft = get_hangar().imp().get(aircraft_name);
+        let aircraft = get_hangar().get(aircraft_name);
+        if aircraft.is_none() {
+            warnings.push(gettext("Aircraft {aircraft} is not in the hangar").replace("{aircraft}", aircraft_name));
+        }
         plan.set_aircraft(&aircraft);
     }
 
-    let sector_list = doc.children;
-    for sector_element in sector_list {
+    if let Some(winds) = doc.attributes.get("winds") {
+        match WindProfile::parse(winds) {
+            Ok(profile) => plan.set_winds(Some(WindsAloft::Profile(profile))),
+            Err(msg) => warnings.push(gettext("Ignoring winds in plan: {error}").replace("{error}", &msg)),
+        }
+    } else if let Some(grid) = doc.attributes.get("wind-grid") {
+        match read_wind_grid(Path::new(grid)) {
+            Ok(winds) => plan.set_winds(Some(winds)),
+            Err(msg) => warnings.push(gettext("Unable to load wind grid {grid}: {error}")
+                .replace("{grid}", grid)
+                .replace("{error}", &msg)),
+        }
+    }
+
+    for (i, sector_element) in elements(&doc).filter(|e| e.name == "sector").enumerate() {
+        let sector_context = format!("sector {}", i + 1);
         let mut sector = Sector::new();
+        if sector_element.attributes.contains_key("cruise-level") {
+            sector.set_cruise_level(Some(parse_attribute::<i32>(sector_element, "cruise-level", &sector_context)?));
+        }
+        if let Some(steps) = sector_element.attributes.get("step-climbs") {
+            let steps = StepClimb::parse_all(steps).map_err(|_| PlanReadError::InvalidAttribute {
+                element: sector_context.clone(),
+                attribute: "step-climbs".to_string(),
+                value: steps.clone(),
+            })?;
+            sector.set_step_climbs(steps);
+        }
 
-        if let Some(from) = sector_element.as_element().unwrap().get_child("from-airport") {
-            let start = get_earth_model().get_airport_by_id(from.attributes.get("id").unwrap());
+        if let Some(from) = sector_element.get_child("from-airport") {
+            let id = get_attribute(from, "id", &format!("{} from-airport", sector_context))?;
+            let start = earth.get_airport_by_id(id);
+            if start.is_none() {
+                warnings.push(gettext("Departure airport {id} was not found").replace("{id}", id));
+            }
             sector.set_start(start);
+            if let Some(runway) = from.attributes.get("runway") {
+                sector.set_departure_runway(Some(runway.clone()));
+            }
         }
 
-        if let Some(to) = sector_element.as_element().unwrap().get_child("to-airport") {
-            let end = get_earth_model().get_airport_by_id(to.attributes.get("id").unwrap());
+        if let Some(to) = sector_element.get_child("to-airport") {
+            let id = get_attribute(to, "id", &format!("{} to-airport", sector_context))?;
+            let end = earth.get_airport_by_id(id);
+            if end.is_none() {
+                warnings.push(gettext("Destination airport {id} was not found").replace("{id}", id));
+            }
             sector.set_end(end);
+            if let Some(runway) = to.attributes.get("runway") {
+                sector.set_arrival_runway(Some(runway.clone()));
+            }
         }
 
-        let waypoints = &sector_element.as_element().unwrap().children;
-        for waypoint_element in waypoints {
-            let e = waypoint_element.as_element().unwrap();
-            if e.name != "waypoint" {
-                continue;
-            }
+        let from = sector_element.get_child("from-airport");
+        let to = sector_element.get_child("to-airport");
+        let sid = from.and_then(|e| read_procedure(e, "sid", ProcedureType::Sid, sector.get_start(), sector.get_departure_runway(), &mut warnings));
+        let star = to.and_then(|e| read_procedure(e, "star", ProcedureType::Star, sector.get_end(), sector.get_arrival_runway(), &mut warnings));
+        let approach = to.and_then(|e| read_procedure(e, "approach", ProcedureType::Approach, sector.get_end(), sector.get_arrival_runway(), &mut warnings));
+        let legs: Vec<&ProcedureLeg> = sid.iter().chain(star.iter()).chain(approach.iter())
+            .flat_map(|p| p.get_legs())
+            .collect();
 
-            let waypoint_type = e.attributes.get("type").unwrap().as_str();
+        // Idents are not unique, so where there are several take the one nearest the previous waypoint
+        let mut last_loc = sector.get_start().map(|wp| wp.get_loc().clone());
 
-            let wp = match waypoint_type {
-                "NAVAID" => {
-                    let navaid = get_earth_model()
-                        .get_navaid_by_id_and_name(e.attributes.get("id").unwrap(), e.attributes.get("name").unwrap());
-                    navaid.map(|n| {
-                        Waypoint::Navaid { navaid: n, elevation: Cell::new(0), locked: false }
-                    })
-                }
-                "AIRPORT" => {
-                    let airport = get_earth_model().get_airport_by_id(e.attributes.get("id").unwrap());
-                    airport.map(|a| {
-                        Waypoint::Airport { airport: a, locked: false }
-                    })
-                }
-                "FIX" => {
-                    let navaid = get_earth_model()
-                        .get_fix_by_id(e.attributes.get("id").unwrap());
-                    navaid.map(|f| {
-                        Waypoint::Fix { fix: f, elevation: Cell::new(0), locked: false }
-                    })
-                }
-                "TOC" => {
-                    let lat = e.attributes.get("latitude").unwrap().parse::<f64>().unwrap_or(0.0);
-                    let long = e.attributes.get("longitude").unwrap().parse::<f64>().unwrap_or(0.0);
-                    Some(Waypoint::Toc { loc: Coordinate::new(lat, long), elevation: Cell::new(0), locked: false })
-                }
-                "BOD" => {
-                    let lat = e.attributes.get("latitude").unwrap().parse::<f64>().unwrap_or(0.0);
-                    let long = e.attributes.get("longitude").unwrap().parse::<f64>().unwrap_or(0.0);
-                    Some(Waypoint::Bod { loc: Coordinate::new(lat, long), elevation: Cell::new(0), locked: false })
-                }
-                "GPS" => {
-                    let lat = e.attributes.get("latitude").unwrap().parse::<f64>().unwrap_or(0.0);
-                    let long = e.attributes.get("longitude").unwrap().parse::<f64>().unwrap_or(0.0);
-                    Some(Waypoint::Simple { loc: Coordinate::new(lat, long), elevation: Cell::new(0), locked: false })
-                }
-                _ => {
-                    None
-                }
+        for (j, e) in elements(sector_element).filter(|e| e.name == "waypoint").enumerate() {
+            let context = format!("{} waypoint {}", sector_context, j + 1);
+            let wp = match read_procedure_waypoint(e, &legs) {
+                Some(wp) => Some(wp),
+                None => read_waypoint(e, &context, &last_loc, earth, &mut warnings)?,
             };
             if let Some(wp) = wp {
-                let elev = e.attributes.get("elevation").unwrap().parse::<i32>().unwrap_or(0);
+                let elev = match e.attributes.get("elevation") {
+                    Some(_) => parse_attribute::<i32>(e, "elevation", &context)?,
+                    None => 0,
+                };
                 wp.set_elevation(&elev);
+                // Saved constraints replace those of a procedure, the user may have changed them
+                if let Some(constraints) = read_constraints(e, &context)? {
+                    wp.set_constraints(constraints);
+                }
+                last_loc = Some(wp.get_loc().clone());
                 sector.add_waypoint(wp);
             }
         }
+        sector.restore_procedures(sid, star, approach);
         plan.add_sector(sector);
-        // mark the plan as clean
-        plan.set_dirty(false);
     }
-    Ok(plan)
+    // mark the plan as clean
+    plan.set_dirty(false);
+    Ok((plan, warnings))
+}
+
+fn read_waypoint(e: &Element, context: &str, last_loc: &Option<Coordinate>, earth: &Earth, warnings: &mut Vec<String>)
+                 -> Result<Option<Waypoint>, PlanReadError> {
+    let waypoint_type = get_attribute(e, "type", context)?;
+    // Files from elsewhere may leave it out, only a malformed value is an error
+    let locked = match e.attributes.get("locked") {
+        Some(_) => parse_attribute::<bool>(e, "locked", context)?,
+        None => false,
+    };
+    let wp = match waypoint_type {
+        "NAVAID" => {
+            // Take the navaid nearest where it was saved, older plans may only have the previous waypoint to go by
+            let saved_loc = read_saved_loc(e, context)?;
+            let mut navaids = earth.get_navaids_by_id(get_attribute(e, "id", context)?);
+            if let Some(name) = e.attributes.get("name") {
+                navaids.retain(|n| n.get_name().eq_ignore_ascii_case(name));
+            }
+            nearest_to(navaids, saved_loc.as_ref().or(last_loc.as_ref())).map(|n| {
+                Waypoint::Navaid { navaid: n, elevation: Cell::new(0), locked, airway: read_airway(e), constraints: Cell::default() }
+            })
+        }
+        "AIRPORT" => {
+            earth.get_airport_by_id(get_attribute(e, "id", context)?).map(|a| {
+                Waypoint::Airport { airport: a, locked }
+            })
+        }
+        "FIX" => {
+            // Take the fix in the region and terminal area saved with it that is nearest where it was saved,
+            // older plans may only have the previous waypoint to go by
+            let saved_loc = read_saved_loc(e, context)?;
+            let region = e.attributes.get("region").map(|r| r.as_str());
+            let terminal_area = e.attributes.get("terminal-area").map(|t| t.as_str());
+            earth.find_fix(get_attribute(e, "id", context)?, region, terminal_area, saved_loc.as_ref().or(last_loc.as_ref())).map(|f| {
+                Waypoint::Fix { fix: f, elevation: Cell::new(0), locked, airway: read_airway(e), constraints: Cell::default() }
+            })
+        }
+        "TOC" => Some(Waypoint::Toc { loc: read_coordinate(e, context)?, elevation: Cell::new(0), locked }),
+        "BOD" => Some(Waypoint::Bod { loc: read_coordinate(e, context)?, elevation: Cell::new(0), locked }),
+        "GPS" => Some(Waypoint::Simple { loc: read_coordinate(e, context)?, elevation: Cell::new(0), locked, constraints: Cell::default() }),
+        _ => {
+            warnings.push(gettext("Unknown waypoint type {type} left out of the plan").replace("{type}", waypoint_type));
+            return Ok(None);
+        }
+    };
+    if wp.is_some() {
+        return Ok(wp);
+    }
+
+    // Anything that can't be found is kept where it was, as long as the file says where that is
+    let id = e.attributes.get("id").map_or("", |id| id.as_str());
+    if e.attributes.contains_key("latitude") && e.attributes.contains_key("longitude") {
+        warnings.push(gettext("{id} was not found and has been replaced by a GPS waypoint").replace("{id}", id));
+        Ok(Some(Waypoint::Simple { loc: read_coordinate(e, context)?, elevation: Cell::new(0), locked, constraints: Cell::default() }))
+    } else {
+        warnings.push(gettext("{id} was not found and has been left out of the plan").replace("{id}", id));
+        Ok(None)
+    }
+}
+
+fn read_procedure(e: &Element, name: &str, procedure_type: ProcedureType, airport: Option<Waypoint>,
+                  runway: Option<&String>, warnings: &mut Vec<String>) -> Option<ProcedureSelection> {
+    let ident = e.attributes.get(name)?;
+    let transition = e.attributes.get(&format!("{}-transition", name)).cloned();
+    let procedure = airport.and_then(|a| {
+        a.get_airport()
+            .get_procedures_of(procedure_type)
+            .into_iter()
+            .find(|p| p.get_ident() == ident)
+    });
+    match procedure {
+        Some(procedure) => Some(ProcedureSelection::new(procedure, runway.cloned(), transition)),
+        None => {
+            warnings.push(gettext("Procedure {ident} was not found, its waypoints are kept as they were saved").replace("{ident}", ident));
+            None
+        }
+    }
+}
+
+// Waypoints flown on a procedure are taken from it, runway thresholds are not in the navigation data
+fn read_procedure_waypoint(e: &Element, legs: &[&ProcedureLeg]) -> Option<Waypoint> {
+    let id = e.attributes.get("id")?;
+    let loc = read_coordinate(e, "").ok();
+    legs.iter()
+        .filter(|leg| leg.get_fix_id() == id)
+        .filter_map(|leg| leg.to_waypoint())
+        .find(|wp| loc.as_ref().is_none_or(|loc| wp.get_loc().distance_to(loc) < MAX_MATCH_DISTANCE))
+}
+
+// Bring a plan written in an older format up to the current version
+fn migrate(doc: &mut Element, version: u32) {
+    if version < 2 {
+        // Which waypoints were chosen rather than planned wasn't kept, so none of them are locked
+        for sector in doc.children.iter_mut().filter_map(XMLNode::as_mut_element) {
+            for waypoint in sector.children.iter_mut().filter_map(XMLNode::as_mut_element).filter(|e| e.name == "waypoint") {
+                waypoint.attributes.entry("locked".to_string()).or_insert("false".to_string());
+            }
+        }
+    }
+}
+
+fn read_settings(e: &Element) -> Result<PlanSettings, PlanReadError> {
+    let context = "plan settings";
+    Ok(PlanSettings::new(
+        get_attribute(e, "units", context)?.to_string(),
+        get_attribute(e, "plan-type", context)?.to_string(),
+        parse_attribute(e, "vor-only", context)?,
+        parse_attribute(e, "vor-preferred", context)?,
+        parse_attribute(e, "add-gps-waypoints", context)?,
+        parse_attribute(e, "max-leg-length", context)?,
+        parse_attribute(e, "min-leg-length", context)?,
+    ))
+}
+
+fn read_time(e: &Element, attribute: &str, context: &str) -> Result<Option<DateTime<Utc>>, PlanReadError> {
+    match e.attributes.get(attribute) {
+        Some(value) => DateTime::parse_from_rfc3339(value)
+            .map(|t| Some(t.with_timezone(&Utc)))
+            .map_err(|_| PlanReadError::InvalidAttribute {
+                element: context.to_string(),
+                attribute: attribute.to_string(),
+                value: value.to_string(),
+            }),
+        None => Ok(None),
+    }
+}
+
+fn elements(e: &Element) -> impl Iterator<Item = &Element> {
+    e.children.iter().filter_map(XMLNode::as_element)
+}
+
+fn get_attribute<'a>(e: &'a Element, attribute: &str, context: &str) -> Result<&'a str, PlanReadError> {
+    e.attributes.get(attribute)
+        .map(|a| a.as_str())
+        .ok_or_else(|| PlanReadError::MissingAttribute { element: context.to_string(), attribute: attribute.to_string() })
+}
+
+fn parse_attribute<T: std::str::FromStr>(e: &Element, attribute: &str, context: &str) -> Result<T, PlanReadError> {
+    let value = get_attribute(e, attribute, context)?;
+    value.trim().parse::<T>().map_err(|_| PlanReadError::InvalidAttribute {
+        element: context.to_string(),
+        attribute: attribute.to_string(),
+        value: value.to_string(),
+    })
+}
+
+fn read_coordinate(e: &Element, context: &str) -> Result<Coordinate, PlanReadError> {
+    Ok(Coordinate::new(parse_attribute(e, "latitude", context)?, parse_attribute(e, "longitude", context)?))
+}
+
+fn nearest_to<T: Location>(candidates: Vec<Arc<T>>, loc: Option<&Coordinate>) -> Option<Arc<T>> {
+    match loc {
+        Some(loc) => candidates.into_iter().min_by(|a, b| {
+            a.get_loc().distance_to(loc)
+                .partial_cmp(&b.get_loc().distance_to(loc))
+                .unwrap_or(Ordering::Equal)
+        }),
+        None => candidates.into_iter().next(),
+    }
+}
+
+/// The VORs and NDBs with the ident. Routes are flown between these, never the ILS components
+/// and markers, which often share an ident with a nearby beacon.
+pub fn enroute_navaids(earth: &Earth, id: &str) -> Vec<Arc<Navaid>> {
+    let mut navaids = earth.get_navaids_by_id(id);
+    navaids.retain(|n| n.get_type().is_enroute());
+    navaids
+}
+
+// A navaid or fix further than this from where a file puts it is taken to be a different one
+const MAX_MATCH_DISTANCE: f64 = 5.0;
+
+/// The candidate nearest to the location, as long as it is close enough to be the same place.
+/// Used by the importers, whose files give a position as well as an ident.
+pub(crate) fn nearest_within<T: Location>(candidates: Vec<Arc<T>>, loc: &Coordinate) -> Option<Arc<T>> {
+    candidates.into_iter()
+        .map(|c| (c.get_loc().distance_to(loc), c))
+        .filter(|(d, _)| *d < MAX_MATCH_DISTANCE)
+        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal))
+        .map(|(_, c)| c)
+}
+
+fn read_constraints(e: &Element, context: &str) -> Result<Option<Constraints>, PlanReadError> {
+    let invalid = |attribute: &str, value: &str| PlanReadError::InvalidAttribute {
+        element: context.to_string(),
+        attribute: attribute.to_string(),
+        value: value.to_string(),
+    };
+    let altitude = match e.attributes.get("alt-restrict").map(|r| r.as_str()) {
+        Some("at") => Some(AltitudeConstraint::At(parse_attribute(e, "alt-limit", context)?)),
+        Some("above") => Some(AltitudeConstraint::AtOrAbove(parse_attribute(e, "alt-limit", context)?)),
+        Some("below") => Some(AltitudeConstraint::AtOrBelow(parse_attribute(e, "alt-limit", context)?)),
+        Some("between") => Some(AltitudeConstraint::Between(
+            parse_attribute(e, "alt-limit", context)?,
+            parse_attribute(e, "alt-upper-limit", context)?,
+        )),
+        Some(restriction) => return Err(invalid("alt-restrict", restriction)),
+        None => None,
+    };
+    let speed = match e.attributes.get("speed-restrict").map(|r| r.as_str()) {
+        Some("at") => Some(SpeedConstraint::At(parse_attribute(e, "speed-limit", context)?)),
+        Some("above") => Some(SpeedConstraint::AtOrAbove(parse_attribute(e, "speed-limit", context)?)),
+        Some("below") => Some(SpeedConstraint::AtOrBelow(parse_attribute(e, "speed-limit", context)?)),
+        Some(restriction) => return Err(invalid("speed-restrict", restriction)),
+        None => None,
+    };
+    if altitude.is_none() && speed.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(Constraints::new(altitude, speed)))
+}
+
+// Where the waypoint was when the plan was saved, plans from older versions don't have it
+fn read_saved_loc(e: &Element, context: &str) -> Result<Option<Coordinate>, PlanReadError> {
+    match e.attributes.contains_key("latitude") && e.attributes.contains_key("longitude") {
+        true => Ok(Some(read_coordinate(e, context)?)),
+        false => Ok(None),
+    }
+}
+
+fn read_airway(e: &Element) -> Option<AirwayLeg> {
+    let airway = e.attributes.get("airway")?;
+    let entry = e.attributes.get("airway-entry").cloned().unwrap_or_default();
+    let exit = e.attributes.get("airway-exit").cloned().unwrap_or_default();
+    Some(AirwayLeg::new(airway.clone(), entry, exit))
 }
 
 #[cfg(test)]
 mod tests {
+    use std::cell::Cell;
     use std::path::PathBuf;
+    use std::sync::Arc;
 
-    use crate::util::plan_reader::read_plan;
+    use chrono::{TimeZone, Utc};
+
+    use crate::earth::coordinate::Coordinate;
+    use crate::earth::Earth;
+    use crate::model::constraint::{AltitudeConstraint, SpeedConstraint};
+    use crate::model::cruise_level::{FlightRules, StepClimb};
+    use crate::model::fix::Fix;
+    use crate::model::navaid::{Navaid, NavaidType};
+    use crate::model::plan_settings::PlanSettings;
+    use crate::model::test_utils::tests::make_airport_at;
+    use crate::model::waypoint::Waypoint;
+    use crate::preference::USE_AIRWAYS;
+    use crate::util::plan_reader::{read_plan, read_plan_from, PlanReadError};
+    use crate::util::plan_writer_xml::{write_plan_to, PLAN_FORMAT_VERSION};
 
     #[test]
     fn test_read_plan() {
@@ -152,4 +488,254 @@ mod tests {
             Err(e) => { panic!("Failed to read plan {}", e) }
         };
     }
-}
\ No newline at end of file
+
+    fn make_earth() -> Earth {
+        let earth = Earth::new();
+        earth.set_airports(vec![make_airport_at("YSSY", -33.946, 151.177), make_airport_at("YBBN", -27.384, 153.117)]);
+        earth.set_navaids(vec![Arc::new(Navaid::new("SY".to_string(), NavaidType::Vor, -33.9, 151.2, 0, 112.1, 100, "12E".to_string(), "SYDNEY".to_string()))]);
+        earth
+    }
+
+    #[test]
+    fn test_unresolved_waypoints() {
+        let fpl = r#"<plan name="YSSY-YBBN">
+            <!-- hand edited -->
+            <sector>
+                <from-airport id="YSSY" />
+                <waypoint id="SY" name="SYDNEY" type="NAVAID" elevation="5000" />
+                <waypoint id="GONE" name="GONE VOR" type="NAVAID" latitude="-31.0" longitude="152.0" elevation="5000" />
+                <waypoint id="LOST" type="FIX" />
+                <to-airport id="YBBN" />
+            </sector>
+        </plan>"#;
+        let (plan, warnings) = read_plan_from(fpl.as_bytes(), &make_earth()).unwrap();
+
+        let sector = plan.get_sectors()[0].borrow();
+        assert_eq!(sector.get_waypoint_count(), 2);
+        assert!(matches!(sector.get_waypoints()[0], Waypoint::Navaid { .. }));
+        assert!(matches!(sector.get_waypoints()[1], Waypoint::Simple { .. }));
+        assert_eq!(sector.get_waypoints()[1].get_elevation(), 5000);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("GONE"));
+        assert!(warnings[1].contains("LOST"));
+    }
+
+    #[test]
+    fn test_unloadable_winds() {
+        let earth = make_earth();
+        let fpl = r#"<plan name="YSSY-YBBN" version="2" wind-grid="/no/such/grid.csv"><sector /></plan>"#;
+        let (plan, warnings) = read_plan_from(fpl.as_bytes(), &earth).unwrap();
+        assert!(plan.get_winds().is_none());
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("/no/such/grid.csv"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let earth = make_earth();
+        let missing_type = r#"<plan><sector><waypoint id="SY" /></sector></plan>"#;
+        assert_eq!(read_plan_from(missing_type.as_bytes(), &earth).err(),
+                   Some(PlanReadError::MissingAttribute { element: "sector 1 waypoint 1".to_string(), attribute: "type".to_string() }));
+
+        let bad_latitude = r#"<plan><sector/><sector><waypoint type="GPS" latitude="north" longitude="151.0" /></sector></plan>"#;
+        assert_eq!(read_plan_from(bad_latitude.as_bytes(), &earth).err(),
+                   Some(PlanReadError::InvalidAttribute {
+                       element: "sector 2 waypoint 1".to_string(),
+                       attribute: "latitude".to_string(),
+                       value: "north".to_string()
+                   }));
+
+        let bad_locked = r#"<plan version="2"><sector><waypoint id="SY" type="NAVAID" locked="yes" /></sector></plan>"#;
+        assert_eq!(read_plan_from(bad_locked.as_bytes(), &earth).err(),
+                   Some(PlanReadError::InvalidAttribute {
+                       element: "sector 1 waypoint 1".to_string(),
+                       attribute: "locked".to_string(),
+                       value: "yes".to_string()
+                   }));
+        let no_locked = r#"<plan version="2"><sector><waypoint id="SY" type="NAVAID" /></sector></plan>"#;
+        let (plan, _) = read_plan_from(no_locked.as_bytes(), &earth).unwrap();
+        assert!(!plan.get_sectors()[0].borrow().get_waypoints()[0].is_locked());
+
+        assert!(matches!(read_plan_from("<plan><sector>".as_bytes(), &earth), Err(PlanReadError::Xml(_))));
+        assert_eq!(read_plan_from("<route/>".as_bytes(), &earth).err(), Some(PlanReadError::NotAPlan("route".to_string())));
+    }
+
+    #[test]
+    fn test_round_trip_keeps_position() {
+        let earth = make_earth();
+        let fpl = r#"<plan><sector><from-airport id="YSSY" /><waypoint id="SY" name="SYDNEY" type="NAVAID" /></sector></plan>"#;
+        let (plan, _) = read_plan_from(fpl.as_bytes(), &earth).unwrap();
+        let mut out = Vec::new();
+        write_plan_to(&plan, &mut out).unwrap();
+
+        // Without the navaid in the navigation data it is still placed where it was
+        let (plan, warnings) = read_plan_from(out.as_slice(), &Earth::new()).unwrap();
+        let sector = plan.get_sectors()[0].borrow();
+        assert!(matches!(sector.get_waypoints()[0], Waypoint::Simple { .. }));
+        assert_eq!(sector.get_waypoints()[0].get_lat(), -33.9);
+        assert_eq!(warnings.len(), 2);
+    }
+
+    #[test]
+    fn test_duplicate_fixes_round_trip() {
+        let make_fix = |lat: f64, lon: f64, region: &str| {
+            let mut fix = Fix::new("ABBEY".to_string(), lat, lon);
+            fix.set_region(Some(region.to_string()));
+            Arc::new(fix)
+        };
+        let earth = make_earth();
+        earth.set_airports(vec![make_airport_at("EGLL", 51.47, -0.46)]);
+        earth.set_fixes(vec![make_fix(51.3375, -1.08, "EG"), make_fix(-33.9636, 151.0583, "YM")]);
+
+        // The region wins over a previous waypoint on the other side of the world
+        let fpl = r#"<plan><sector><from-airport id="EGLL" /><waypoint id="ABBEY" type="FIX" region="YM" /></sector></plan>"#;
+        let (plan, _) = read_plan_from(fpl.as_bytes(), &earth).unwrap();
+        assert_eq!(plan.get_sectors()[0].borrow().get_waypoints()[0].get_lat(), -33.9636);
+
+        let mut out = Vec::new();
+        write_plan_to(&plan, &mut out).unwrap();
+        let (plan, _) = read_plan_from(out.as_slice(), &earth).unwrap();
+        match &plan.get_sectors()[0].borrow().get_waypoints()[0] {
+            Waypoint::Fix { fix, .. } => assert_eq!(fix.get_region(), Some("YM")),
+            _ => panic!("ABBEY should be a fix"),
+        }
+
+        // Without a region the saved position decides
+        let fpl = r#"<plan><sector><from-airport id="EGLL" />
+            <waypoint id="ABBEY" type="FIX" latitude="-33.9636" longitude="151.0583" /></sector></plan>"#;
+        let (plan, _) = read_plan_from(fpl.as_bytes(), &earth).unwrap();
+        assert_eq!(plan.get_sectors()[0].borrow().get_waypoints()[0].get_lat(), -33.9636);
+
+        // A terminal fix is found in the terminal area it was saved with, even where another is nearer
+        let mut terminal_fix = Fix::new("ABBEY".to_string(), 51.0, -1.5);
+        terminal_fix.set_region(Some("EG".to_string()));
+        terminal_fix.set_terminal_area(Some("EGLL".to_string()));
+        earth.set_fixes(vec![make_fix(51.3375, -1.08, "EG"), Arc::new(terminal_fix)]);
+        let fpl = r#"<plan><sector><from-airport id="EGLL" />
+            <waypoint id="ABBEY" type="FIX" region="EG" terminal-area="EGLL" latitude="51.3375" longitude="-1.08" /></sector></plan>"#;
+        let (plan, _) = read_plan_from(fpl.as_bytes(), &earth).unwrap();
+        assert_eq!(plan.get_sectors()[0].borrow().get_waypoints()[0].get_lat(), 51.0);
+    }
+
+    #[test]
+    fn test_duplicate_navaids_use_saved_position() {
+        let make_navaid = |lat: f64, lon: f64| {
+            Arc::new(Navaid::new("BT".to_string(), NavaidType::Ndb, lat, lon, 0, 0.0, 50, "".to_string(), "BATHURST".to_string()))
+        };
+        let earth = make_earth();
+        earth.set_navaids(vec![make_navaid(-33.41, 149.65), make_navaid(47.63, -65.75)]);
+
+        // The saved position wins over the previous waypoint
+        let fpl = r#"<plan><sector><from-airport id="YSSY" />
+            <waypoint id="BT" name="BATHURST" type="NAVAID" latitude="47.63" longitude="-65.75" /></sector></plan>"#;
+        let (plan, _) = read_plan_from(fpl.as_bytes(), &earth).unwrap();
+        assert_eq!(plan.get_sectors()[0].borrow().get_waypoints()[0].get_lat(), 47.63);
+
+        // Without one the navaid nearest the previous waypoint is taken
+        let fpl = r#"<plan><sector><from-airport id="YSSY" /><waypoint id="BT" name="BATHURST" type="NAVAID" /></sector></plan>"#;
+        let (plan, _) = read_plan_from(fpl.as_bytes(), &earth).unwrap();
+        assert_eq!(plan.get_sectors()[0].borrow().get_waypoints()[0].get_lat(), -33.41);
+    }
+
+    #[test]
+    fn test_missing_procedure() {
+        let fpl = r#"<plan><sector>
+                <from-airport id="YSSY" runway="16R" sid="ABBEY3" sid-transition="WEST" />
+                <waypoint id="SY" name="SYDNEY" type="NAVAID" locked="true" latitude="-33.9" longitude="151.2" />
+                <to-airport id="YBBN" />
+            </sector></plan>"#;
+        let (plan, warnings) = read_plan_from(fpl.as_bytes(), &make_earth()).unwrap();
+        let sector = plan.get_sectors()[0].borrow();
+        assert!(sector.get_sid().is_none());
+        assert_eq!(sector.get_waypoint_count(), 1);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("ABBEY3"));
+    }
+
+    #[test]
+    fn test_metadata_round_trip() {
+        let earth = make_earth();
+        let fpl = r#"<plan><sector><from-airport id="YSSY" runway="16R" /><waypoint id="SY" name="SYDNEY" type="NAVAID" /></sector></plan>"#;
+        let (mut plan, _) = read_plan_from(fpl.as_bytes(), &earth).unwrap();
+        assert!(plan.get_created().is_none());
+        let created = Utc.with_ymd_and_hms(2024, 5, 1, 8, 0, 0).unwrap();
+        let departure = Utc.with_ymd_and_hms(2024, 5, 2, 21, 30, 0).unwrap();
+        plan.set_created(Some(created));
+        plan.set_departure_time(Some(departure));
+        plan.set_author("Trevor");
+        plan.set_remarks("Group flight, meet at the holding point");
+        plan.set_max_altitude(Some(9000));
+        let settings = PlanSettings::new("Nm".to_string(), USE_AIRWAYS.to_string(), false, true, false, 100.0, 25.0);
+        plan.set_settings(Some(settings.clone()));
+        plan.get_sectors()[0].borrow_mut().add_waypoint(
+            Waypoint::Simple { loc: Coordinate::new(-33.0, 151.5), elevation: Cell::new(9000), locked: true, constraints: Cell::default() });
+
+        let mut out = Vec::new();
+        write_plan_to(&plan, &mut out).unwrap();
+        let (plan, warnings) = read_plan_from(out.as_slice(), &earth).unwrap();
+
+        assert!(warnings.is_empty());
+        assert_eq!(plan.get_created(), &Some(created));
+        assert_eq!(plan.get_departure_time(), &Some(departure));
+        assert_eq!(plan.get_author(), "Trevor");
+        assert_eq!(plan.get_remarks(), "Group flight, meet at the holding point");
+        assert_eq!(plan.get_max_altitude(), &Some(9000));
+        assert_eq!(plan.get_settings(), &Some(settings));
+        let sector = plan.get_sectors()[0].borrow();
+        assert_eq!(sector.get_departure_runway(), Some(&"16R".to_string()));
+        assert!(!sector.get_waypoints()[0].is_locked());
+        assert!(sector.get_waypoints()[1].is_locked());
+        assert!(!plan.is_dirty());
+    }
+
+    #[test]
+    fn test_constraints_round_trip() {
+        let earth = make_earth();
+        let fpl = r#"<plan><sector><from-airport id="YSSY" />
+            <waypoint id="SY" name="SYDNEY" type="NAVAID" alt-restrict="below" alt-limit="6000" speed-restrict="at" speed-limit="210" />
+            <waypoint type="GPS" latitude="-33.0" longitude="151.5" alt-restrict="between" alt-limit="8000" alt-upper-limit="10000" />
+            </sector></plan>"#;
+        let (plan, _) = read_plan_from(fpl.as_bytes(), &earth).unwrap();
+
+        let mut out = Vec::new();
+        write_plan_to(&plan, &mut out).unwrap();
+        let (plan, _) = read_plan_from(out.as_slice(), &earth).unwrap();
+
+        let sector = plan.get_sectors()[0].borrow();
+        let constraints = sector.get_waypoints()[0].get_constraints();
+        assert_eq!(constraints.get_altitude(), Some(AltitudeConstraint::AtOrBelow(6000)));
+        assert_eq!(constraints.get_speed(), Some(SpeedConstraint::At(210)));
+        let constraints = sector.get_waypoints()[1].get_constraints();
+        assert_eq!(constraints.get_altitude(), Some(AltitudeConstraint::Between(8000, 10000)));
+        assert_eq!(constraints.get_speed(), None);
+
+        let bad_restriction = r#"<plan><sector><waypoint type="GPS" latitude="-33.0" longitude="151.5" locked="false" alt-restrict="near" /></sector></plan>"#;
+        assert!(matches!(read_plan_from(bad_restriction.as_bytes(), &earth), Err(PlanReadError::InvalidAttribute { .. })));
+    }
+
+    #[test]
+    fn test_cruise_levels_round_trip() {
+        let earth = make_earth();
+        let fpl = r#"<plan flight-rules="VFR"><sector cruise-level="9500" step-climbs="100/FL115"><from-airport id="YSSY" /></sector></plan>"#;
+        let (plan, _) = read_plan_from(fpl.as_bytes(), &earth).unwrap();
+
+        let mut out = Vec::new();
+        write_plan_to(&plan, &mut out).unwrap();
+        let (plan, _) = read_plan_from(out.as_slice(), &earth).unwrap();
+
+        assert_eq!(plan.get_flight_rules(), FlightRules::Vfr);
+        let sector = plan.get_sectors()[0].borrow();
+        assert_eq!(sector.get_cruise_level(), Some(9500));
+        assert_eq!(sector.get_step_climbs(), &vec![StepClimb::new(100.0, 11500)]);
+
+        let bad_steps = r#"<plan><sector step-climbs="high"/></plan>"#;
+        assert!(matches!(read_plan_from(bad_steps.as_bytes(), &earth), Err(PlanReadError::InvalidAttribute { .. })));
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let fpl = format!(r#"<plan version="{}"></plan>"#, PLAN_FORMAT_VERSION + 1);
+        assert_eq!(read_plan_from(fpl.as_bytes(), &Earth::new()).err(),
+                   Some(PlanReadError::UnsupportedVersion(PLAN_FORMAT_VERSION + 1)));
+    }
+}
@@ -0,0 +1,271 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::earth::coordinate::Coordinate;
+use crate::earth::{get_earth_model, Earth};
+use crate::hangar::hangar::get_hangar;
+use crate::model::airway::AirwayLeg;
+use crate::model::navaid::NavaidType;
+use crate::model::plan::Plan;
+use crate::model::sector::Sector;
+use crate::model::waypoint::Waypoint;
+use crate::util::plan_reader::nearest_within;
+use crate::util::plan_writer_fms::{FMS_AIRPORT, FMS_FIX, FMS_NDB, FMS_VOR};
+
+struct FmsEntry {
+    entry_type: i32,
+    ident: String,
+    via: String,
+    altitude: i32,
+    loc: Coordinate,
+}
+
+pub fn read_plan_fms(file_path: &Path) -> Result<Plan, String> {
+    let file = match File::open(file_path) {
+        Ok(file) => file,
+        Err(_) => return Err(String::from("Error reading file")),
+    };
+    let mut plan = read_plan_fms_from(BufReader::new(file), get_earth_model())?;
+    plan.set_aircraft(&get_hangar().get_default_aircraft());
+    plan.set_dirty(false);
+    Ok(plan)
+}
+
+/// Read an X-Plane version 1100 .fms flight plan.
+/// Airports, navaids and fixes are looked up in the earth model, anything that can't be found
+/// becomes a GPS waypoint at the position given in the file.
+/// An airport part way along the route ends one sector and starts the next.
+pub fn read_plan_fms_from<R: BufRead>(reader: R, earth: &Earth) -> Result<Plan, String> {
+    let mut lines = reader.lines().map_while(Result::ok);
+
+    match lines.next() {
+        Some(l) if l.trim() == "I" || l.trim() == "A" => (),
+        _ => return Err(String::from("Not an X-Plane flight plan")),
+    }
+    match lines.next() {
+        Some(l) if l.split_whitespace().next() == Some("1100") => (),
+        _ => return Err(String::from("Unsupported X-Plane flight plan version, only 1100 can be read")),
+    }
+
+    let mut entries = Vec::new();
+    for line in lines {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 6 {
+            continue;
+        }
+        // Header lines such as ADEP, CYCLE and NUMENR don't start with an entry type
+        let Ok(entry_type) = fields[0].parse::<i32>() else {
+            continue;
+        };
+        let altitude = fields[3].parse::<f64>().unwrap_or(0.0);
+        let lat = fields[4].parse::<f64>().map_err(|_| format!("Invalid latitude for {}", fields[1]))?;
+        let long = fields[5].parse::<f64>().map_err(|_| format!("Invalid longitude for {}", fields[1]))?;
+        entries.push(FmsEntry {
+            entry_type,
+            ident: fields[1].to_string(),
+            via: fields[2].to_string(),
+            altitude: altitude.round() as i32,
+            loc: Coordinate::new(lat, long),
+        });
+    }
+    if entries.len() < 2 {
+        return Err(String::from("The flight plan has no route"));
+    }
+
+    let airways = airway_legs(&entries);
+
+    let mut plan = Plan::new();
+    let mut sector = Sector::new();
+    let last = entries.len() - 1;
+    for (i, entry) in entries.iter().enumerate() {
+        if entry.entry_type == FMS_AIRPORT && (i == 0 || i == last || entry.via == "DRCT") {
+            let airport = nearest_within(earth.get_airports_by_id(&entry.ident), &entry.loc);
+            if i == 0 || i == last {
+                let airport = airport.ok_or(format!("Unknown airport {}", entry.ident))?;
+                if i == 0 {
+                    sector.set_start(Some(airport));
+                } else {
+                    sector.set_end(Some(airport));
+                }
+                continue;
+            }
+            if let Some(airport) = airport {
+                sector.set_end(Some(airport.clone()));
+                plan.add_sector(sector);
+                sector = Sector::new();
+                sector.set_start(Some(airport));
+                continue;
+            }
+        }
+        let wp = make_waypoint(entry, airways[i].clone(), earth);
+        sector.add_waypoint(wp);
+    }
+    plan.add_sector(sector);
+    plan.set_dirty(false);
+    Ok(plan)
+}
+
+fn make_waypoint(entry: &FmsEntry, airway: Option<AirwayLeg>, earth: &Earth) -> Waypoint {
+    let wp = match entry.entry_type {
+        FMS_VOR | FMS_NDB => {
+            let mut navaids = earth.get_navaids_by_id(&entry.ident);
+            if entry.entry_type == FMS_VOR {
+                navaids.retain(|n| n.get_type().is_vor());
+            } else {
+                navaids.retain(|n| n.get_type() == NavaidType::Ndb);
+            }
+            nearest_within(navaids, &entry.loc).map(|n| {
+                Waypoint::Navaid { navaid: n, elevation: Cell::new(0), locked: false, airway, constraints: Cell::default() }
+            })
+        }
+        FMS_FIX => {
+            nearest_within(earth.get_fixes_by_id(&entry.ident), &entry.loc).map(|f| {
+                Waypoint::Fix { fix: f, elevation: Cell::new(0), locked: false, airway, constraints: Cell::default() }
+            })
+        }
+        _ => None,
+    };
+    let wp = wp.unwrap_or_else(|| Waypoint::Simple { loc: entry.loc.clone(), elevation: Cell::new(0), locked: false, constraints: Cell::default() });
+    wp.set_elevation(&entry.altitude);
+    wp
+}
+
+// X-Plane records the airway each waypoint is reached by, Kelpie also wants to know where each airway is joined and left
+fn airway_legs(entries: &[FmsEntry]) -> Vec<Option<AirwayLeg>> {
+    let mut legs = vec![None; entries.len()];
+    let mut i = 0;
+    while i < entries.len() {
+        let via = entries[i].via.as_str();
+        if matches!(via, "ADEP" | "ADES" | "DRCT") || i == 0 {
+            i += 1;
+            continue;
+        }
+        let mut exit = i;
+        while exit + 1 < entries.len() && entries[exit + 1].via == via {
+            exit += 1;
+        }
+        let leg = AirwayLeg::new(via.to_string(), entries[i - 1].ident.clone(), entries[exit].ident.clone());
+        for l in legs.iter_mut().take(exit + 1).skip(i) {
+            *l = Some(leg.clone());
+        }
+        i = exit + 1;
+    }
+    legs
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::sync::Arc;
+
+    use crate::earth::Earth;
+    use crate::model::fix::Fix;
+    use crate::model::navaid::{Navaid, NavaidType};
+    use crate::model::test_utils::tests::make_airport_at;
+    use crate::model::waypoint::Waypoint;
+    use crate::util::plan_writer_fms::export_plan_fms_to;
+
+    use super::read_plan_fms_from;
+
+    const PLAN: &str = "I
+1100 Version
+CYCLE 2401
+ADEP YSSY
+ADES YBBN
+NUMENR 6
+1 YSSY ADEP 21.000000 -33.946000 151.177000
+3 SY DRCT 9000.000000 -33.900000 151.200000
+11 BOREE H65 9000.000000 -32.000000 151.500000
+2 SY H65 9000.000000 -30.000000 152.000000
+28 +29.000_+152.500 DRCT 9000.000000 -29.000000 152.500000
+1 YBBN ADES 13.000000 -27.384000 153.117000
+";
+
+    fn make_earth() -> Earth {
+        let earth = Earth::new();
+        earth.set_airports(vec![make_airport_at("YSSY", -33.946, 151.177), make_airport_at("YBBN", -27.384, 153.117)]);
+        earth.set_navaids(vec![
+            Arc::new(Navaid::new("SY".to_string(), NavaidType::Vor, -33.9, 151.2, 0, 112.1, 100, "12E".to_string(), "SYDNEY".to_string())),
+            Arc::new(Navaid::new("SY".to_string(), NavaidType::Ndb, -30.0, 152.0, 0, 350.0, 25, "0".to_string(), "SOMEWHERE".to_string())),
+        ]);
+        earth.set_fixes(vec![Arc::new(Fix::new("BOREE".to_string(), -32.0, 151.5))]);
+        earth
+    }
+
+    #[test]
+    fn test_read_fms() {
+        let earth = make_earth();
+        let plan = read_plan_fms_from(Cursor::new(PLAN), &earth).unwrap();
+
+        assert_eq!(plan.get_sectors().len(), 1);
+        let sector = plan.get_sectors()[0].borrow();
+        assert_eq!(sector.get_start().unwrap().get_id(), "YSSY");
+        assert_eq!(sector.get_end().unwrap().get_id(), "YBBN");
+
+        let wps = sector.get_waypoints();
+        assert_eq!(wps.len(), 4);
+        assert_eq!(wps[0].get_name(), "SYDNEY");
+        assert!(matches!(wps[1], Waypoint::Fix { .. }));
+        assert_eq!(wps[2].get_name(), "SOMEWHERE");
+        assert!(matches!(wps[3], Waypoint::Simple { .. }));
+        assert!(wps.iter().all(|wp| wp.get_elevation() == 9000));
+
+        let airway = wps[2].get_airway().unwrap();
+        assert_eq!(airway.get_airway(), "H65");
+        assert_eq!(airway.get_entry(), "SY");
+        assert_eq!(airway.get_exit(), "SY");
+        assert!(wps[0].get_airway().is_none());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let earth = make_earth();
+        let plan = read_plan_fms_from(Cursor::new(PLAN), &earth).unwrap();
+
+        let mut out = Vec::new();
+        export_plan_fms_to(&plan, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("NUMENR 6\n"));
+        assert!(text.contains("11 BOREE H65 9000.000000 -32.000000 151.500000\n"));
+
+        let again = read_plan_fms_from(Cursor::new(text), &earth).unwrap();
+        let wps = again.get_sectors()[0].borrow().get_waypoints().clone();
+        assert_eq!(wps.len(), 4);
+        assert!(matches!(wps[3], Waypoint::Simple { .. }));
+        assert_eq!(wps[3].get_lat(), -29.0);
+    }
+
+    #[test]
+    fn test_bad_files() {
+        let earth = make_earth();
+        assert!(read_plan_fms_from(Cursor::new("<plan/>"), &earth).is_err());
+        assert!(read_plan_fms_from(Cursor::new("I\n3 version\n"), &earth).is_err());
+        assert!(read_plan_fms_from(Cursor::new(PLAN.replace("YBBN", "ZZZZ")), &earth).is_err());
+    }
+}
@@ -0,0 +1,201 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+use std::cell::Cell;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use xmltree::Element;
+
+use crate::earth::coordinate::Coordinate;
+use crate::earth::{get_earth_model, Earth, FEET_PER_METRE};
+use crate::hangar::hangar::get_hangar;
+use crate::model::plan::Plan;
+use crate::model::sector::Sector;
+use crate::model::waypoint::Waypoint;
+use crate::util::plan_reader::{enroute_navaids, nearest_within};
+
+// A point this close to a navaid, fix or airport is taken to be it, whatever it is called
+const POSITION_TOLERANCE: f64 = 0.5;
+
+pub fn import_plan_gpx(file_path: &Path) -> Result<Plan, String> {
+    let file = match File::open(file_path) {
+        Ok(file) => file,
+        Err(_) => return Err(String::from("Error reading file")),
+    };
+    let mut plan = import_plan_gpx_from(file, get_earth_model())?;
+    plan.set_aircraft(&get_hangar().get_default_aircraft());
+    plan.set_dirty(false);
+    Ok(plan)
+}
+
+/// Read the routes in a GPX file, one sector for each route.
+/// Points are matched to navaids, fixes and airports by name, or failing that by being within
+/// a small distance of one. Anything else becomes a GPS waypoint.
+/// Where the first or last point of a route is an airport it becomes the departure or destination.
+pub fn import_plan_gpx_from<R: Read>(reader: R, earth: &Earth) -> Result<Plan, String> {
+    let doc = match Element::parse(reader) {
+        Ok(doc) => doc,
+        Err(_) => return Err(String::from("Error parsing XML")),
+    };
+    if doc.name != "gpx" {
+        return Err(String::from("Not a GPX file"));
+    }
+
+    let mut plan = Plan::new();
+    for rte in doc.children.iter().filter_map(|c| c.as_element()).filter(|e| e.name == "rte") {
+        let mut points = Vec::new();
+        for pt in rte.children.iter().filter_map(|c| c.as_element()).filter(|e| e.name == "rtept") {
+            points.push(make_waypoint(pt, earth)?);
+        }
+
+        let mut sector = Sector::new();
+        if matches!(points.first(), Some(Waypoint::Airport { .. })) {
+            sector.set_start(Some(points.remove(0).get_airport()));
+        }
+        if matches!(points.last(), Some(Waypoint::Airport { .. })) && sector.get_start().is_some() {
+            sector.set_end(points.pop().map(|wp| wp.get_airport()));
+        }
+        for wp in points {
+            sector.add_waypoint(wp);
+        }
+        plan.add_sector(sector);
+    }
+    if plan.get_sectors().is_empty() {
+        return Err(String::from("There are no routes in the GPX file"));
+    }
+    plan.set_dirty(false);
+    Ok(plan)
+}
+
+fn make_waypoint(pt: &Element, earth: &Earth) -> Result<Waypoint, String> {
+    let coordinate = |name: &str| {
+        pt.attributes.get(name)
+            .and_then(|v| v.parse::<f64>().ok())
+            .ok_or(format!("Route point without a valid {}", name))
+    };
+    let loc = Coordinate::new(coordinate("lat")?, coordinate("lon")?);
+    let name = child_text(pt, "name").unwrap_or_default();
+
+    let wp = match child_text(pt, "type").as_deref() {
+        Some("TOC") => Waypoint::Toc { loc, elevation: Cell::new(0), locked: false },
+        Some("BOD") => Waypoint::Bod { loc, elevation: Cell::new(0), locked: false },
+        _ => find_waypoint(&name, &loc, earth)
+            .unwrap_or(Waypoint::Simple { loc, elevation: Cell::new(0), locked: false, constraints: Cell::default() }),
+    };
+    if let Some(ele) = child_text(pt, "ele").and_then(|e| e.parse::<f64>().ok()) {
+        wp.set_elevation(&((ele * FEET_PER_METRE).round() as i32));
+    }
+    Ok(wp)
+}
+
+fn find_waypoint(name: &str, loc: &Coordinate, earth: &Earth) -> Option<Waypoint> {
+    let navaid = |navaid| Waypoint::Navaid { navaid, elevation: Cell::new(0), locked: false, airway: None, constraints: Cell::default() };
+    let fix = |fix| Waypoint::Fix { fix, elevation: Cell::new(0), locked: false, airway: None, constraints: Cell::default() };
+    let airport = |airport| Waypoint::Airport { airport, locked: false };
+
+    nearest_within(enroute_navaids(earth, name), loc).map(navaid)
+        .or_else(|| nearest_within(earth.get_fixes_by_id(name), loc).map(fix))
+        .or_else(|| nearest_within(earth.get_airports_by_id(name), loc).map(airport))
+        .or_else(|| earth.get_nearest_navaid(loc, POSITION_TOLERANCE, |n| n.get_type().is_enroute()).map(navaid))
+        .or_else(|| earth.get_nearest_fix(loc, POSITION_TOLERANCE, |_| true).map(fix))
+        .or_else(|| earth.get_nearest_airport(loc, POSITION_TOLERANCE, |_| true).map(airport))
+}
+
+fn child_text(e: &Element, name: &str) -> Option<String> {
+    Some(e.get_child(name)?.get_text()?.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::sync::Arc;
+
+    use crate::earth::coordinate::Coordinate;
+    use crate::earth::Earth;
+    use crate::model::fix::Fix;
+    use crate::model::navaid::{Navaid, NavaidType};
+    use crate::model::plan::Plan;
+    use crate::model::sector::Sector;
+    use crate::model::test_utils::tests::make_airport_at;
+    use crate::model::waypoint::Waypoint;
+    use crate::util::plan_writer_gpx::export_plan_gpx_to;
+
+    use super::import_plan_gpx_from;
+
+    #[test]
+    fn test_round_trip() {
+        let earth = Earth::new();
+        let yssy = make_airport_at("YSSY", -33.946, 151.177);
+        let ybbn = make_airport_at("YBBN", -27.384, 153.117);
+        earth.set_airports(vec![yssy.clone(), ybbn.clone()]);
+        let navaid = Arc::new(Navaid::new("SY".to_string(), NavaidType::Vor, -33.9, 151.2, 0, 112.1, 100, "12E".to_string(), "SYDNEY".to_string()));
+        earth.set_navaids(vec![navaid.clone()]);
+        let fix = Arc::new(Fix::new("BOREE".to_string(), -32.0, 151.5));
+        earth.set_fixes(vec![fix.clone()]);
+
+        let mut plan = Plan::new();
+        let mut sector = Sector::new();
+        sector.set_start(Some(yssy));
+        sector.set_end(Some(ybbn));
+        sector.add_waypoint(Waypoint::Navaid { navaid, elevation: Cell::new(9000), locked: false, airway: None, constraints: Cell::default() });
+        sector.add_waypoint(Waypoint::Toc { loc: Coordinate::new(-33.0, 151.3), elevation: Cell::new(9000), locked: false });
+        sector.add_waypoint(Waypoint::Fix { fix, elevation: Cell::new(9000), locked: false, airway: None, constraints: Cell::default() });
+        sector.add_waypoint(Waypoint::Simple { loc: Coordinate::new(-30.0, 152.0), elevation: Cell::new(8500), locked: false, constraints: Cell::default() });
+        plan.add_sector(sector);
+
+        let mut out = Vec::new();
+        export_plan_gpx_to(&plan, &mut out).unwrap();
+        let imported = import_plan_gpx_from(out.as_slice(), &earth).unwrap();
+
+        assert_eq!(imported.get_sectors().len(), 1);
+        let sector = imported.get_sectors()[0].borrow();
+        assert_eq!(sector.get_start().unwrap().get_id(), "YSSY");
+        assert_eq!(sector.get_end().unwrap().get_id(), "YBBN");
+        let wps = sector.get_waypoints();
+        let types: Vec<&str> = wps.iter().map(|wp| wp.get_type_name()).collect();
+        assert_eq!(types, vec!["NAVAID", "TOC", "FIX", "GPS"]);
+        assert_eq!(wps[0].get_elevation(), 9000);
+        assert_eq!(wps[3].get_elevation(), 8500);
+    }
+
+    #[test]
+    fn test_match_by_position() {
+        let earth = Earth::new();
+        earth.set_navaids(vec![
+            Arc::new(Navaid::new("SY".to_string(), NavaidType::Vor, -33.9, 151.2, 0, 112.1, 100, "12E".to_string(), "SYDNEY".to_string())),
+            Arc::new(Navaid::new("ISS".to_string(), NavaidType::Ils, -33.0, 151.001, 0, 109.5, 18, "12E".to_string(), "SYDNEY 16R ILS".to_string())),
+        ]);
+        let gpx = r#"<gpx version="1.1" xmlns="http://www.topografix.com/GPX/1/1"><rte>
+            <rtept lat="-33.901" lon="151.201"><name>WPT001</name></rtept>
+            <rtept lat="-33.0" lon="151.0"><name>WPT002</name><ele>1000</ele></rtept>
+            </rte></gpx>"#;
+        let plan = import_plan_gpx_from(gpx.as_bytes(), &earth).unwrap();
+        let sector = plan.get_sectors()[0].borrow();
+        assert_eq!(sector.get_waypoints()[0].get_name(), "SYDNEY");
+        assert!(matches!(sector.get_waypoints()[1], Waypoint::Simple { .. }));
+        assert_eq!(sector.get_waypoints()[1].get_elevation(), 3281);
+    }
+}
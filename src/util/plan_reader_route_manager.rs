@@ -0,0 +1,267 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+use std::cell::Cell;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+
+use log::warn;
+use xmltree::Element;
+
+use crate::earth::coordinate::Coordinate;
+use crate::earth::{get_earth_model, Earth};
+use crate::hangar::hangar::get_hangar;
+use crate::model::aircraft::Aircraft;
+use crate::model::airport::Airport;
+use crate::model::constraint::{AltitudeConstraint, Constraints, SpeedConstraint};
+use crate::model::plan::Plan;
+use crate::model::sector::Sector;
+use crate::model::waypoint::Waypoint;
+use crate::util::plan_reader::{enroute_navaids, nearest_within};
+
+pub fn import_plan_fg(file_path: &Path) -> Result<Plan, String> {
+    let file = match File::open(file_path) {
+        Ok(file) => file,
+        Err(_) => return Err(String::from("Error reading file")),
+    };
+    import_plan_fg_from(file, get_earth_model(), |speed| {
+        get_hangar().get_by_cruise_speed(speed).or_else(|| get_hangar().get_default_aircraft())
+    })
+}
+
+/// Read a Flightgear route manager plan.
+/// Each departure in the route starts a new sector, so plans exported with several sectors come back the same.
+/// Waypoint idents are looked up in the earth model, anything that can't be found becomes a GPS waypoint.
+/// The aircraft is the one `find_aircraft` gives for the cruise speed in the route.
+pub fn import_plan_fg_from<R: Read, F>(reader: R, earth: &Earth, find_aircraft: F) -> Result<Plan, String>
+where
+    F: Fn(i32) -> Option<Arc<Aircraft>>,
+{
+    let doc = match Element::parse(reader) {
+        Ok(doc) => doc,
+        Err(_) => return Err(String::from("Error parsing XML")),
+    };
+    if doc.name != "PropertyList" || doc.get_child("route").is_none() {
+        return Err(String::from("Not a Flightgear route manager plan"));
+    }
+
+    let mut plan = Plan::new();
+    let mut sector: Option<Sector> = None;
+    let mut cruise_speed = None;
+
+    for element in doc.children.iter().filter_map(|c| c.as_element()) {
+        match element.name.as_str() {
+            "departure" => {
+                if let Some(s) = sector.take() {
+                    plan.add_sector(s);
+                }
+                let mut s = Sector::new();
+                s.set_start(Some(find_airport(element, earth)?));
+                sector = Some(s);
+            }
+            "destination" => {
+                sector.get_or_insert_with(Sector::new).set_end(Some(find_airport(element, earth)?));
+            }
+            "cruise" => {
+                let altitude = child_value::<i32>(element, "altitude-ft")
+                    .or_else(|| child_value::<i32>(element, "flight-level").map(|fl| fl * 100));
+                if altitude.is_some() {
+                    plan.set_max_altitude(altitude);
+                }
+                if let Some(knots) = child_value::<i32>(element, "knots") {
+                    cruise_speed = Some(knots);
+                }
+            }
+            "route" => {
+                let s = sector.get_or_insert_with(Sector::new);
+                for wp in element.children.iter().filter_map(|c| c.as_element()).filter(|e| e.name == "wp") {
+                    if let Some(wp) = make_waypoint(wp, earth) {
+                        s.add_waypoint(wp);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    if let Some(s) = sector.take() {
+        plan.add_sector(s);
+    }
+
+    if let Some(knots) = cruise_speed {
+        let aircraft = find_aircraft(knots);
+        if aircraft.is_none() {
+            warn!("No aircraft in the hangar cruises at {} knots", knots);
+        }
+        plan.set_aircraft(&aircraft);
+    }
+    plan.set_dirty(false);
+    Ok(plan)
+}
+
+fn find_airport(element: &Element, earth: &Earth) -> Result<Arc<Airport>, String> {
+    let id = child_value::<String>(element, "airport").ok_or(format!("No airport for the {}", element.name))?;
+    earth.get_airport_by_id(&id).ok_or(format!("Unknown airport {}", id))
+}
+
+fn make_waypoint(e: &Element, earth: &Earth) -> Option<Waypoint> {
+    let wp_type = child_value::<String>(e, "type").unwrap_or_default();
+    // The runways are covered by the departure and destination, and the rest have no position
+    if matches!(wp_type.as_str(), "runway" | "discontinuity" | "vectors" | "hdgToAlt") {
+        return None;
+    }
+    let lat = child_value::<f64>(e, "lat")?;
+    let long = child_value::<f64>(e, "lon")?;
+    let loc = Coordinate::new(lat, long);
+    let ident = child_value::<String>(e, "ident").unwrap_or_default();
+
+    let wp = if let Some(navaid) = nearest_within(enroute_navaids(earth, &ident), &loc) {
+        Waypoint::Navaid { navaid, elevation: Cell::new(0), locked: false, airway: None, constraints: Cell::default() }
+    } else if let Some(fix) = nearest_within(earth.get_fixes_by_id(&ident), &loc) {
+        Waypoint::Fix { fix, elevation: Cell::new(0), locked: false, airway: None, constraints: Cell::default() }
+    } else if let Some(airport) = nearest_within(earth.get_airports_by_id(&ident), &loc) {
+        Waypoint::Airport { airport, locked: false }
+    } else {
+        match ident.as_str() {
+            "TOC" => Waypoint::Toc { loc, elevation: Cell::new(0), locked: false },
+            "BOD" => Waypoint::Bod { loc, elevation: Cell::new(0), locked: false },
+            _ => Waypoint::Simple { loc, elevation: Cell::new(0), locked: false, constraints: Cell::default() },
+        }
+    };
+    // Flightgear uses a large negative altitude for "no altitude"
+    let altitude = child_value::<f64>(e, "altitude-ft").filter(|a| *a >= 0.0).map(|a| a.round() as i32);
+    let speed = child_value::<f64>(e, "speed").map(|s| s.round() as i32);
+    let alt_constraint = match child_value::<String>(e, "alt-restrict").as_deref() {
+        Some("at") => altitude.map(AltitudeConstraint::At),
+        Some("above") => altitude.map(AltitudeConstraint::AtOrAbove),
+        Some("below") => altitude.map(AltitudeConstraint::AtOrBelow),
+        _ => None,
+    };
+    let speed_constraint = match child_value::<String>(e, "speed-restrict").as_deref() {
+        Some("at") => speed.map(SpeedConstraint::At),
+        Some("above") => speed.map(SpeedConstraint::AtOrAbove),
+        Some("below") => speed.map(SpeedConstraint::AtOrBelow),
+        _ => None,
+    };
+    if alt_constraint.is_some() || speed_constraint.is_some() {
+        wp.set_constraints(Constraints::new(alt_constraint, speed_constraint));
+    }
+    // A restricted altitude is a limit, only a computed one is where the waypoint was planned
+    if let Some(altitude) = altitude.filter(|_| alt_constraint.is_none()) {
+        wp.set_elevation(&altitude);
+    }
+    Some(wp)
+}
+
+fn child_value<T: std::str::FromStr>(e: &Element, name: &str) -> Option<T> {
+    e.get_child(name)?.get_text()?.trim().parse::<T>().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::sync::Arc;
+
+    use crate::earth::coordinate::Coordinate;
+    use crate::earth::Earth;
+    use crate::model::aircraft::{Aircraft, AircraftFuel};
+    use crate::model::constraint::{AltitudeConstraint, Constraints, SpeedConstraint};
+    use crate::model::fix::Fix;
+    use crate::model::navaid::{Navaid, NavaidType};
+    use crate::model::plan::Plan;
+    use crate::model::sector::Sector;
+    use crate::model::test_utils::tests::make_airport_at;
+    use crate::model::waypoint::Waypoint;
+    use crate::util::plan_writer_route_manager::export_plan_fg_to;
+
+    use super::import_plan_fg_from;
+
+    #[test]
+    fn test_round_trip() {
+        let earth = Earth::new();
+        let yssy = make_airport_at("YSSY", -33.946, 151.177);
+        let ybbn = make_airport_at("YBBN", -27.384, 153.117);
+        let ycfs = make_airport_at("YCFS", -30.320, 153.116);
+        earth.set_airports(vec![yssy.clone(), ybbn.clone(), ycfs.clone()]);
+        let navaid = Arc::new(Navaid::new("SY".to_string(), NavaidType::Vor, -33.9, 151.2, 0, 112.1, 100, "12E".to_string(), "SYDNEY".to_string()));
+        earth.set_navaids(vec![navaid.clone()]);
+        let fix = Arc::new(Fix::new("BOREE".to_string(), -32.0, 151.5));
+        earth.set_fixes(vec![fix.clone()]);
+        let aircraft = Arc::new(Aircraft::new("Test".to_string(), 140, 7000, 110, 1000, 100, 500, AircraftFuel::default(), false));
+
+        let mut plan = Plan::new();
+        plan.set_aircraft(&Some(aircraft.clone()));
+        plan.set_max_altitude(Some(9000));
+        let mut sector = Sector::new();
+        sector.set_start(Some(yssy));
+        sector.set_end(Some(ycfs.clone()));
+        sector.add_waypoint(Waypoint::Navaid { navaid, elevation: Cell::new(9000), locked: false, airway: None, constraints: Cell::default() });
+        sector.add_waypoint(Waypoint::Fix { fix, elevation: Cell::new(9000), locked: false, airway: None, constraints: Cell::default() });
+        sector.add_waypoint(Waypoint::Simple { loc: Coordinate::new(-31.0, 152.0), elevation: Cell::new(8500), locked: false, constraints: Cell::default() });
+        let constraints = Constraints::new(Some(AltitudeConstraint::AtOrBelow(6000)), Some(SpeedConstraint::AtOrBelow(210)));
+        sector.add_waypoint(Waypoint::Simple { loc: Coordinate::new(-30.5, 152.5), elevation: Cell::new(5500), locked: false, constraints: Cell::new(constraints) });
+        plan.add_sector(sector);
+        let mut sector = Sector::new();
+        sector.set_start(Some(ycfs));
+        sector.set_end(Some(ybbn));
+        plan.add_sector(sector);
+
+        let mut out = Vec::new();
+        export_plan_fg_to(&plan, &mut out).unwrap();
+
+        let imported = import_plan_fg_from(out.as_slice(), &earth, |speed| {
+            if speed == 140 { Some(aircraft.clone()) } else { None }
+        }).unwrap();
+
+        assert_eq!(imported.get_sectors().len(), 2);
+        assert_eq!(imported.get_name(), "YSSY-YBBN");
+        assert_eq!(*imported.get_max_altitude(), Some(9000));
+        assert_eq!(imported.get_aircraft().as_ref().unwrap().get_name(), "Test");
+
+        let first = imported.get_sectors()[0].borrow();
+        assert_eq!(first.get_start().unwrap().get_id(), "YSSY");
+        assert_eq!(first.get_end().unwrap().get_id(), "YCFS");
+        let wps = first.get_waypoints();
+        assert_eq!(wps.len(), 4);
+        assert_eq!(wps[0].get_name(), "SYDNEY");
+        assert!(matches!(wps[1], Waypoint::Fix { .. }));
+        assert!(matches!(wps[2], Waypoint::Simple { .. }));
+        assert_eq!(wps[2].get_elevation(), 8500);
+        assert_eq!(wps[2].get_lat(), -31.0);
+        assert!(wps[2].get_constraints().is_empty());
+        assert_eq!(wps[3].get_constraints(), constraints);
+
+        let second = imported.get_sectors()[1].borrow();
+        assert_eq!(second.get_start().unwrap().get_id(), "YCFS");
+        assert_eq!(second.get_end().unwrap().get_id(), "YBBN");
+    }
+
+    #[test]
+    fn test_not_a_route() {
+        let earth = Earth::new();
+        assert!(import_plan_fg_from("<plan/>".as_bytes(), &earth, |_| None).is_err());
+        assert!(import_plan_fg_from("<PropertyList><route/><departure><airport>ZZZZ</airport></departure></PropertyList>".as_bytes(), &earth, |_| None).is_err());
+    }
+}
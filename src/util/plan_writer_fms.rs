@@ -0,0 +1,170 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use chrono::{Datelike, NaiveDate, Utc};
+
+use crate::model::location::Location;
+use crate::model::navaid::NavaidType;
+use crate::model::plan::Plan;
+use crate::model::waypoint::Waypoint;
+
+// X-Plane 11/12 flight plan entry types
+pub(crate) const FMS_AIRPORT: i32 = 1;
+pub(crate) const FMS_NDB: i32 = 2;
+pub(crate) const FMS_VOR: i32 = 3;
+pub(crate) const FMS_FIX: i32 = 11;
+pub(crate) const FMS_LAT_LON: i32 = 28;
+
+pub fn export_plan_fms(plan: &Plan, file_path: &Path) -> Result<(), String> {
+    let out = match File::create(file_path) {
+        Ok(file) => file,
+        Err(_) => return Err(String::from("Error reading file")),
+    };
+    export_plan_fms_to(plan, out)
+}
+
+/// Write the plan as an X-Plane version 1100 .fms flight plan.
+/// A plan with several sectors is flown as one route with the intermediate airports as waypoints.
+/// Top of climb and beginning of descent are left out, the X-Plane FMS works out its own.
+pub fn export_plan_fms_to<W: Write>(plan: &Plan, mut out: W) -> Result<(), String> {
+    let sectors = plan.get_sectors();
+    let departure = sectors.first().and_then(|s| s.borrow().get_start());
+    let destination = sectors.last().and_then(|s| s.borrow().get_end());
+    let departure_runway = sectors.first().and_then(|s| s.borrow().get_departure_runway().cloned());
+    let arrival_runway = sectors.last().and_then(|s| s.borrow().get_arrival_runway().cloned());
+    let (departure, destination) = match (departure, destination) {
+        (Some(d), Some(a)) => (d, a),
+        _ => return Err(String::from("The plan needs a departure and destination airport")),
+    };
+
+    let mut entries: Vec<String> = Vec::new();
+    for (i, sector) in sectors.iter().enumerate() {
+        let sector = sector.borrow();
+        if let Some(start) = sector.get_start() {
+            // Where a sector starts at the airport the previous one ended at, it is only listed once
+            let via = if i == 0 { "ADEP" } else { "DRCT" };
+            let repeated = i > 0 && sectors[i - 1].borrow().get_end().is_some_and(|e| e.get_id() == start.get_id());
+            if !repeated {
+                entries.push(make_entry(&start, via));
+            }
+        }
+        for wp in sector.get_waypoints() {
+            if matches!(wp, Waypoint::Toc { .. } | Waypoint::Bod { .. }) {
+                continue;
+            }
+            let via = wp.get_airway().map_or("DRCT", |a| a.get_airway());
+            entries.push(make_entry(wp, via));
+        }
+        if let Some(end) = sector.get_end() {
+            let via = if i == sectors.len() - 1 { "ADES" } else { "DRCT" };
+            entries.push(make_entry(&end, via));
+        }
+    }
+
+    let mut text = String::new();
+    text.push_str("I\n");
+    text.push_str("1100 Version\n");
+    text.push_str(&format!("CYCLE {}\n", airac_cycle(Utc::now().date_naive())));
+    text.push_str(&format!("ADEP {}\n", departure.get_id()));
+    if let Some(runway) = departure_runway {
+        text.push_str(&format!("DEPRWY RW{}\n", runway));
+    }
+    text.push_str(&format!("ADES {}\n", destination.get_id()));
+    if let Some(runway) = arrival_runway {
+        text.push_str(&format!("DESRWY RW{}\n", runway));
+    }
+    text.push_str(&format!("NUMENR {}\n", entries.len()));
+    for entry in entries {
+        text.push_str(&entry);
+        text.push('\n');
+    }
+
+    match out.write_all(text.as_bytes()) {
+        Ok(_) => { Ok(()) }
+        Err(e) => { Err(e.to_string()) }
+    }
+}
+
+fn make_entry(waypoint: &Waypoint, via: &str) -> String {
+    let (entry_type, ident) = match waypoint {
+        Waypoint::Airport { airport, .. } => (FMS_AIRPORT, airport.get_id().to_string()),
+        Waypoint::Navaid { navaid, .. } => match navaid.get_type() {
+            t if t.is_vor() => (FMS_VOR, navaid.get_id().to_string()),
+            NavaidType::Ndb => (FMS_NDB, navaid.get_id().to_string()),
+            // X-Plane has no entry type for a DME on its own or a landing aid, so it goes by position
+            _ => (FMS_LAT_LON, lat_lon_ident(waypoint)),
+        },
+        Waypoint::Fix { fix, .. } => (FMS_FIX, fix.get_id().to_string()),
+        _ => (FMS_LAT_LON, lat_lon_ident(waypoint)),
+    };
+    format!("{} {} {} {:.6} {:.6} {:.6}",
+            entry_type, ident, via, waypoint.get_elevation() as f64, waypoint.get_lat(), waypoint.get_long())
+}
+
+// Latitude/longitude entries are named for their position, e.g. -33.950_+151.180
+fn lat_lon_ident(waypoint: &Waypoint) -> String {
+    format!("{:+.3}_{:+.3}", waypoint.get_lat(), waypoint.get_long())
+}
+
+/// The AIRAC cycle in effect on the given date, e.g. "2401".
+/// Cycles last 28 days and are numbered from the first one to start in each year.
+fn airac_cycle(date: NaiveDate) -> String {
+    // Cycle 2001 became effective on 2 January 2020
+    let epoch = NaiveDate::from_ymd_opt(2020, 1, 2).unwrap();
+    let cycles = (date - epoch).num_days().div_euclid(28);
+    let effective = epoch + chrono::Duration::days(cycles * 28);
+    format!("{:02}{:02}", effective.year() % 100, effective.ordinal0() / 28 + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::sync::Arc;
+
+    use chrono::NaiveDate;
+
+    use crate::model::navaid::{Navaid, NavaidType};
+    use crate::model::waypoint::Waypoint;
+
+    use super::{airac_cycle, make_entry};
+
+    #[test]
+    fn test_airac_cycle() {
+        assert_eq!(airac_cycle(NaiveDate::from_ymd_opt(2020, 1, 2).unwrap()), "2001");
+        assert_eq!(airac_cycle(NaiveDate::from_ymd_opt(2024, 1, 24).unwrap()), "2313");
+        assert_eq!(airac_cycle(NaiveDate::from_ymd_opt(2024, 1, 25).unwrap()), "2401");
+        assert_eq!(airac_cycle(NaiveDate::from_ymd_opt(2025, 12, 31).unwrap()), "2513");
+    }
+
+    #[test]
+    fn test_lone_dme_entry() {
+        let navaid = Arc::new(Navaid::new("SY".to_string(), NavaidType::Dme, -33.95, 151.18, 0, 112.1, 100, "12E".to_string(), "SYDNEY DME".to_string()));
+        let wp = Waypoint::Navaid { navaid, elevation: Cell::new(5000), locked: false, airway: None, constraints: Cell::default() };
+        assert_eq!(make_entry(&wp, "DRCT"), "28 -33.950_+151.180 DRCT 5000.000000 -33.950000 151.180000");
+    }
+}
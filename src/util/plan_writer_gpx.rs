@@ -0,0 +1,93 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use xmltree::{Element, EmitterConfig, XMLNode};
+
+use crate::earth::FEET_PER_METRE;
+use crate::model::plan::Plan;
+use crate::model::waypoint::Waypoint;
+
+pub fn export_plan_gpx(plan: &Plan, file_path: &Path) -> Result<(), String> {
+    let out = match File::create(file_path) {
+        Ok(file) => file,
+        Err(_) => return Err(String::from("Error reading file")),
+    };
+    export_plan_gpx_to(plan, out)
+}
+
+/// Write the plan as GPX 1.1, with a route for each sector.
+/// Elevations are in metres as GPX expects.
+pub fn export_plan_gpx_to<W: Write>(plan: &Plan, out: W) -> Result<(), String> {
+    let mut gpx = Element::new("gpx");
+    gpx.attributes.insert("version".to_string(), "1.1".to_string());
+    gpx.attributes.insert("creator".to_string(), "Kelpie Flight Planner".to_string());
+    gpx.attributes.insert("xmlns".to_string(), "http://www.topografix.com/GPX/1/1".to_string());
+
+    for sector in plan.get_sectors() {
+        let sector = sector.borrow();
+        let mut rte = Element::new("rte");
+        rte.children.push(XMLNode::Element(make_text("name", &sector.get_name())));
+
+        if let Some(start) = sector.get_start() {
+            rte.children.push(XMLNode::Element(make_point(&start)));
+        }
+        for wp in sector.get_waypoints() {
+            rte.children.push(XMLNode::Element(make_point(wp)));
+        }
+        if let Some(end) = sector.get_end() {
+            rte.children.push(XMLNode::Element(make_point(&end)));
+        }
+        gpx.children.push(XMLNode::Element(rte));
+    }
+
+    let config = EmitterConfig::new()
+        .perform_indent(true);
+
+    match gpx.write_with_config(out, config) {
+        Ok(_) => { Ok(()) }
+        Err(e) => { Err(e.to_string()) }
+    }
+}
+
+fn make_point(waypoint: &Waypoint) -> Element {
+    let mut pt = Element::new("rtept");
+    pt.attributes.insert("lat".to_string(), format!("{:.6}", waypoint.get_lat()));
+    pt.attributes.insert("lon".to_string(), format!("{:.6}", waypoint.get_long()));
+    let ele = waypoint.get_elevation() as f64 / FEET_PER_METRE;
+    pt.children.push(XMLNode::Element(make_text("ele", &format!("{:.1}", ele))));
+    pt.children.push(XMLNode::Element(make_text("name", waypoint.get_id())));
+    pt.children.push(XMLNode::Element(make_text("desc", waypoint.get_name())));
+    pt.children.push(XMLNode::Element(make_text("type", waypoint.get_type_name())));
+    pt
+}
+
+fn make_text(name: &str, text: &str) -> Element {
+    let mut element = Element::new(name);
+    element.children.push(XMLNode::Text(text.to_string()));
+    element
+}
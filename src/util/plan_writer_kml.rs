@@ -0,0 +1,139 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use xmltree::{Element, EmitterConfig, XMLNode};
+
+use crate::earth::FEET_PER_METRE;
+use crate::model::plan::Plan;
+use crate::model::waypoint::Waypoint;
+
+pub fn export_plan_kml(plan: &Plan, file_path: &Path) -> Result<(), String> {
+    let out = match File::create(file_path) {
+        Ok(file) => file,
+        Err(_) => return Err(String::from("Error reading file")),
+    };
+    export_plan_kml_to(plan, out)
+}
+
+/// Write the plan as KML for Google Earth.
+/// The route is drawn as a line at the planned altitudes, with a placemark at each airport,
+/// navaid, top of climb and beginning of descent.
+pub fn export_plan_kml_to<W: Write>(plan: &Plan, out: W) -> Result<(), String> {
+    let mut kml = Element::new("kml");
+    kml.attributes.insert("xmlns".to_string(), "http://www.opengis.net/kml/2.2".to_string());
+
+    let mut document = Element::new("Document");
+    document.children.push(XMLNode::Element(make_text("name", &plan.get_name())));
+
+    let mut points: Vec<Waypoint> = Vec::new();
+    for sector in plan.get_sectors() {
+        let sector = sector.borrow();
+        // A sector starting where the last one ended only needs the airport once
+        if let Some(start) = sector.get_start().filter(|s| points.last().is_none_or(|p| p.get_id() != s.get_id())) {
+            points.push(start);
+        }
+        points.extend(sector.get_waypoints().iter().cloned());
+        if let Some(end) = sector.get_end() {
+            points.push(end);
+        }
+    }
+
+    let mut route = Element::new("Placemark");
+    route.children.push(XMLNode::Element(make_text("name", &plan.get_name())));
+    let mut line = Element::new("LineString");
+    line.children.push(XMLNode::Element(make_text("extrude", "1")));
+    line.children.push(XMLNode::Element(make_text("altitudeMode", "absolute")));
+    let coordinates: Vec<String> = points.iter().map(make_coordinates).collect();
+    line.children.push(XMLNode::Element(make_text("coordinates", &coordinates.join(" "))));
+    route.children.push(XMLNode::Element(line));
+    document.children.push(XMLNode::Element(route));
+
+    for wp in points.iter().filter(|wp| matches!(wp,
+        Waypoint::Airport { .. } | Waypoint::Navaid { .. } | Waypoint::Toc { .. } | Waypoint::Bod { .. })) {
+        let mut placemark = Element::new("Placemark");
+        placemark.children.push(XMLNode::Element(make_text("name", wp.get_id())));
+        placemark.children.push(XMLNode::Element(make_text("description", wp.get_name())));
+        let mut point = Element::new("Point");
+        point.children.push(XMLNode::Element(make_text("altitudeMode", "absolute")));
+        point.children.push(XMLNode::Element(make_text("coordinates", &make_coordinates(wp))));
+        placemark.children.push(XMLNode::Element(point));
+        document.children.push(XMLNode::Element(placemark));
+    }
+    kml.children.push(XMLNode::Element(document));
+
+    let config = EmitterConfig::new()
+        .perform_indent(true);
+
+    match kml.write_with_config(out, config) {
+        Ok(_) => { Ok(()) }
+        Err(e) => { Err(e.to_string()) }
+    }
+}
+
+// KML wants longitude first and the altitude in metres
+fn make_coordinates(waypoint: &Waypoint) -> String {
+    format!("{:.6},{:.6},{:.0}", waypoint.get_long(), waypoint.get_lat(), waypoint.get_elevation() as f64 / FEET_PER_METRE)
+}
+
+fn make_text(name: &str, text: &str) -> Element {
+    let mut element = Element::new(name);
+    element.children.push(XMLNode::Text(text.to_string()));
+    element
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use crate::earth::coordinate::Coordinate;
+    use crate::model::plan::Plan;
+    use crate::model::sector::Sector;
+    use crate::model::test_utils::tests::make_airport_at;
+    use crate::model::waypoint::Waypoint;
+
+    use super::export_plan_kml_to;
+
+    #[test]
+    fn test_export() {
+        let mut plan = Plan::new();
+        let mut sector = Sector::new();
+        sector.set_start(Some(make_airport_at("YSSY", -33.946, 151.177)));
+        sector.set_end(Some(make_airport_at("YBBN", -27.384, 153.117)));
+        sector.add_waypoint(Waypoint::Toc { loc: Coordinate::new(-33.0, 151.3), elevation: Cell::new(10000), locked: false });
+        sector.add_waypoint(Waypoint::Simple { loc: Coordinate::new(-30.0, 152.0), elevation: Cell::new(10000), locked: false, constraints: Cell::default() });
+        plan.add_sector(sector);
+
+        let mut out = Vec::new();
+        export_plan_kml_to(&plan, &mut out).unwrap();
+        let kml = String::from_utf8(out).unwrap();
+
+        assert!(kml.contains("151.300000,-33.000000,3048 152.000000,-30.000000,3048"));
+        // The GPS waypoint is on the line but has no placemark of its own
+        assert_eq!(kml.matches("<Placemark>").count(), 4);
+    }
+}
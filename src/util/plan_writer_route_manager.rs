@@ -23,13 +23,16 @@
  */
 
 use std::fs::File;
+use std::io::Write;
 use std::ops::Deref;
 use std::path::Path;
 
 use xmltree::{Element, EmitterConfig, XMLNode};
 
+use crate::model::constraint::{AltitudeConstraint, SpeedConstraint};
 use crate::model::location::Location;
 use crate::model::plan::Plan;
+use crate::model::procedure::ProcedureSelection;
 use crate::model::waypoint::Waypoint;
 
 pub fn export_plan_fg(plan: &Plan, file_path: &Path) -> Result<(), String> {
@@ -37,6 +40,10 @@ pub fn export_plan_fg(plan: &Plan, file_path: &Path) -> Result<(), String> {
         Ok(file) => file,
         Err(_) => return Err(String::from("Error reading file")),
     };
+    export_plan_fg_to(plan, out)
+}
+
+pub fn export_plan_fg_to<W: Write>(plan: &Plan, out: W) -> Result<(), String> {
     let mut plan_element = Element::new("PropertyList");
     let mut element = Element::new("version");
     element.attributes.insert("type".to_string(), "int".to_string());
@@ -50,15 +57,17 @@ pub fn export_plan_fg(plan: &Plan, file_path: &Path) -> Result<(), String> {
     plan_element.children.push(XMLNode::Element(element));
 
     for sector in plan.get_sectors().deref() {
-        if let Some(start) = sector.borrow().get_start() {
-            if let Some(from) = make_airport(&start, true) {
-                plan_element.children.push(XMLNode::Element(from));
-            }
+        let sector = sector.borrow();
+        if let Some(start) = sector.get_start() {
+            let mut departure = make_airport(&start, sector.get_departure_runway(), true);
+            add_procedure(&mut departure, "sid", sector.get_sid());
+            plan_element.children.push(XMLNode::Element(departure));
         }
-        if let Some(end) = sector.borrow().get_end() {
-            if let Some(to) = make_airport(&end, true) {
-                plan_element.children.push(XMLNode::Element(to));
-            }
+        if let Some(end) = sector.get_end() {
+            let mut destination = make_airport(&end, sector.get_arrival_runway(), false);
+            add_procedure(&mut destination, "star", sector.get_star());
+            add_procedure(&mut destination, "approach", sector.get_approach());
+            plan_element.children.push(XMLNode::Element(destination));
         }
         if let Some(cruise) = make_cruise(plan) {
             plan_element.children.push(XMLNode::Element(cruise));
@@ -66,7 +75,7 @@ pub fn export_plan_fg(plan: &Plan, file_path: &Path) -> Result<(), String> {
 
 
         let mut route = Element::new("route"); //$NON-NLS-1$
-        for (wp_ordinal, wp) in sector.borrow().get_waypoints()
+        for (wp_ordinal, wp) in sector.get_waypoints()
             .iter().enumerate() {
             let wpt = make_waypoint(wp, wp_ordinal as i32, plan);
             route.children.push(XMLNode::Element(wpt));
@@ -101,26 +110,33 @@ fn make_cruise(plan: &Plan) -> Option<Element> {
 }
 
 
-fn make_airport(waypoint: &Waypoint, start: bool) -> Option<Element> {
-    // Pick a runway, any runway
+fn make_airport(waypoint: &Waypoint, runway: Option<&String>, start: bool) -> Element {
     let airport = waypoint.get_airport();
-    let runway = airport.get_longest_runway();
-    if let Some(runway) = runway {
-        let mut wp = Element::new(if start { "departure" } else { "destination" });
+    let mut wp = Element::new(if start { "departure" } else { "destination" });
 
-        let mut ap = Element::new("airport");
-        ap.attributes.insert("type".to_string(), "string".to_string());
-        ap.children.push(XMLNode::Text(airport.get_id().to_string()));
-        wp.children.push(XMLNode::Element(ap));
+    let mut ap = Element::new("airport");
+    ap.attributes.insert("type".to_string(), "string".to_string());
+    ap.children.push(XMLNode::Text(airport.get_id().to_string()));
+    wp.children.push(XMLNode::Element(ap));
 
+    // Use the chosen runway, otherwise pick a runway, any runway. Flightgear will choose one itself if there are none.
+    let runway = runway.cloned().or_else(|| airport.get_longest_runway().map(|r| r.number().to_string()));
+    if let Some(runway) = runway {
         let mut rw = Element::new("runway");
         rw.attributes.insert("type".to_string(), "string".to_string());
-        rw.children.push(XMLNode::Text(runway.number().to_string()));
+        rw.children.push(XMLNode::Text(runway));
         wp.children.push(XMLNode::Element(rw));
+    }
+
+    wp
+}
 
-        Some(wp)
-    } else {
-        None
+fn add_procedure(airport: &mut Element, name: &str, procedure: Option<&ProcedureSelection>) {
+    if let Some(procedure) = procedure {
+        let mut element = Element::new(name);
+        element.attributes.insert("type".to_string(), "string".to_string());
+        element.children.push(XMLNode::Text(procedure.get_ident().to_string()));
+        airport.children.push(XMLNode::Element(element));
     }
 }
 
@@ -139,19 +155,44 @@ fn make_waypoint(waypoint: &Waypoint, wp_ordinal: i32, plan: &Plan) -> Element {
     ident.children.push(XMLNode::Text(waypoint.get_id().to_string()));
     wp.children.push(XMLNode::Element(ident));
 
+    // Constraints are passed on, otherwise the planned altitude is given as one Flightgear computed
+    let constraints = waypoint.get_constraints();
+    let (alt_restrict, altitude) = match constraints.get_altitude() {
+        Some(AltitudeConstraint::At(a)) => ("at", a),
+        Some(AltitudeConstraint::AtOrAbove(a)) => ("above", a),
+        Some(AltitudeConstraint::AtOrBelow(a)) => ("below", a),
+        // The route manager has no window restriction, the lower limit is the one that keeps us clear of terrain
+        Some(AltitudeConstraint::Between(lower, _)) => ("above", lower),
+        None => ("computed", waypoint.get_elevation()),
+    };
     let mut restrict = Element::new("alt-restrict");
     restrict.attributes.insert("type".to_string(), "string".to_string());
-    restrict.children.push(XMLNode::Text("at".to_string()));
+    restrict.children.push(XMLNode::Text(alt_restrict.to_string()));
     wp.children.push(XMLNode::Element(restrict));
 
     let mut elev = Element::new("altitude-ft");
     elev.attributes.insert("type".to_string(), "double".to_string());
-    elev.children.push(XMLNode::Text(format!("{:.0}", waypoint.get_elevation())));
+    elev.children.push(XMLNode::Text(format!("{:.0}", altitude)));
     wp.children.push(XMLNode::Element(elev));
 
+    let speed = match constraints.get_speed() {
+        Some(limit) => {
+            let speed_restrict = match limit {
+                SpeedConstraint::At(_) => "at",
+                SpeedConstraint::AtOrAbove(_) => "above",
+                SpeedConstraint::AtOrBelow(_) => "below",
+            };
+            let mut restrict = Element::new("speed-restrict");
+            restrict.attributes.insert("type".to_string(), "string".to_string());
+            restrict.children.push(XMLNode::Text(speed_restrict.to_string()));
+            wp.children.push(XMLNode::Element(restrict));
+            limit.get_speed()
+        }
+        None => plan.get_speed_to(waypoint),
+    };
     let mut spd = Element::new("speed");
     spd.attributes.insert("type".to_string(), "double".to_string());
-    spd.children.push(XMLNode::Text(format!("{:.0}", plan.get_speed_to(waypoint))));
+    spd.children.push(XMLNode::Text(format!("{:.0}", speed)));
     wp.children.push(XMLNode::Element(spd));
 
     let mut lat = Element::new("lat");
@@ -165,4 +206,46 @@ fn make_waypoint(waypoint: &Waypoint, wp_ordinal: i32, plan: &Plan) -> Element {
     wp.children.push(XMLNode::Element(lon));
 
     wp
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::model::constraint::{AltitudeConstraint, SpeedConstraint};
+    use crate::model::fix::Fix;
+    use crate::model::plan::Plan;
+    use crate::model::procedure::{LegPoint, Procedure, ProcedureLeg, ProcedureSelection, ProcedureType};
+    use crate::model::sector::Sector;
+    use crate::model::test_utils::tests::make_airport_at;
+
+    use super::export_plan_fg_to;
+
+    #[test]
+    fn test_export_procedure_constraints() {
+        let fix = |id: &str, lat: f64| Some(LegPoint::Fix(Arc::new(Fix::new(id.to_string(), lat, 151.3))));
+        let mut sid = Procedure::new(ProcedureType::Sid, "ABBEY3".to_string());
+        sid.add_leg('2', "", ProcedureLeg::new("AAA".to_string(), "TF".to_string(), fix("AAA", -33.5),
+            Some(AltitudeConstraint::AtOrAbove(5000)), Some(SpeedConstraint::AtOrBelow(210))));
+        sid.add_leg('2', "", ProcedureLeg::new("BBB".to_string(), "TF".to_string(), fix("BBB", -33.0),
+            Some(AltitudeConstraint::Between(8000, 12000)), None));
+
+        let mut plan = Plan::new();
+        let mut sector = Sector::new();
+        sector.set_start(Some(make_airport_at("YSSY", -33.946, 151.177)));
+        sector.set_end(Some(make_airport_at("YBBN", -27.384, 153.117)));
+        sector.set_sid(Some(ProcedureSelection::new(Arc::new(sid), None, None)));
+        plan.add_sector(sector);
+
+        let mut out = Vec::new();
+        export_plan_fg_to(&plan, &mut out).unwrap();
+        let xml = String::from_utf8(out).unwrap();
+
+        assert!(xml.contains("<sid type=\"string\">ABBEY3</sid>"));
+        assert!(xml.contains("<alt-restrict type=\"string\">above</alt-restrict>"));
+        assert!(xml.contains("<altitude-ft type=\"double\">5000</altitude-ft>"));
+        assert!(xml.contains("<speed-restrict type=\"string\">below</speed-restrict>"));
+        assert!(xml.contains("<speed type=\"double\">210</speed>"));
+        assert!(xml.contains("<altitude-ft type=\"double\">8000</altitude-ft>"));
+    }
+}
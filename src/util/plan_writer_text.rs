@@ -0,0 +1,405 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+use std::fs::File;
+use std::io::Write;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::model::airport::{Airport, FrequencyType, RunwayType};
+use crate::model::location::Location;
+use crate::model::plan::Plan;
+use crate::model::sector::Sector;
+use crate::model::waypoint::Waypoint;
+use crate::util::hour_format::HourFormat;
+
+const LEG_HEADINGS: [&str; 13] = ["Ident", "Name", "Freq", "MC", "Hdg", "Dist", "Alt", "GS", "Leg", "Total", "Fuel", "Rem", "ATA"];
+
+/// One line of the nav log, already formatted for printing
+struct NavLogLeg {
+    ident: String,
+    name: String,
+    freq: String,
+    course: String,
+    heading: String,
+    distance: String,
+    altitude: String,
+    ground_speed: String,
+    leg_time: String,
+    total_time: String,
+    fuel_used: String,
+    fuel_remaining: String,
+}
+
+impl NavLogLeg {
+    fn columns(&self) -> [&str; 12] {
+        [&self.ident, &self.name, &self.freq, &self.course, &self.heading, &self.distance, &self.altitude,
+            &self.ground_speed, &self.leg_time, &self.total_time, &self.fuel_used, &self.fuel_remaining]
+    }
+}
+
+pub fn write_nav_log(plan: &Plan, file_path: &Path) -> Result<(), String> {
+    let out = match File::create(file_path) {
+        Ok(file) => file,
+        Err(_) => return Err(String::from("Error reading file")),
+    };
+    write_nav_log_to(plan, out)
+}
+
+pub fn write_nav_log_html(plan: &Plan, file_path: &Path) -> Result<(), String> {
+    let out = match File::create(file_path) {
+        Ok(file) => file,
+        Err(_) => return Err(String::from("Error reading file")),
+    };
+    write_nav_log_html_to(plan, out)
+}
+
+/// Write the plan as a plain text nav log, one line per leg with a summary for each sector
+/// and the runways at the departure and arrival airports.
+pub fn write_nav_log_to<W: Write>(plan: &Plan, mut out: W) -> Result<(), String> {
+    let mut log = String::new();
+
+    log.push_str(&format!("Plan: {}\n", plan.get_name()));
+    if let Some(aircraft) = plan.get_aircraft() {
+        log.push_str(&format!("Aircraft: {}\n", aircraft.get_name()));
+    }
+    log.push_str(&format!("Cruise altitude: {}\n", plan.get_plan_altitude()));
+
+    for sector in plan.get_sectors().deref() {
+        let sector = sector.borrow();
+        log.push_str(&format!("\n{}\n", sector.get_name()));
+        if let Some(start) = sector.get_start() {
+            log.push_str(&format_airport_text("Departure", &start.get_airport(), sector.get_departure_runway()));
+        }
+        log.push('\n');
+        log.push_str(&format_leg_text(&LEG_HEADINGS));
+        for leg in make_legs(plan, &sector) {
+            let c = leg.columns();
+            log.push_str(&format_leg_text(&[c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7], c[8], c[9], c[10], c[11], "[    ]"]));
+        }
+        let distance = sector.get_distance_as_string(plan);
+        let duration = sector.get_duration_as_string(plan);
+        log.push_str(&format_leg_text(&["Total", "", "", "", "", &distance, "", "", "", &duration, "", "", ""]));
+        for (label, value) in make_fuel_summary(plan, &sector) {
+            log.push_str(&format!("{:<12} {:>8}\n", label, value));
+        }
+        if let Some(end) = sector.get_end() {
+            log.push('\n');
+            log.push_str(&format_airport_text("Arrival", &end.get_airport(), sector.get_arrival_runway()));
+        }
+    }
+
+    let warnings = plan.get_endurance_warnings().into_iter()
+        .chain(plan.get_terrain_warnings())
+        .chain(plan.get_constraint_warnings())
+        .chain(plan.get_cruise_level_warnings());
+    for warning in warnings {
+        log.push_str(&format!("\nWARNING: {}", warning));
+    }
+    log.push('\n');
+
+    out.write_all(log.as_bytes()).map_err(|e| e.to_string())
+}
+
+/// Write the plan as an HTML kneeboard to be printed, with the same content as the text nav log
+/// and a blank box on each leg to note the actual time of arrival.
+pub fn write_nav_log_html_to<W: Write>(plan: &Plan, mut out: W) -> Result<(), String> {
+    let mut html = String::new();
+
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>{}</title>\n", escape(&plan.get_name())));
+    html.push_str("<style>\n\
+        body { font-family: sans-serif; font-size: 10pt; }\n\
+        table { border-collapse: collapse; margin-bottom: 1em; }\n\
+        th, td { border: 1px solid black; padding: 2px 6px; }\n\
+        td.num { text-align: right; }\n\
+        td.ata { min-width: 4em; }\n\
+        tr.total td { font-weight: bold; }\n\
+        .sector { page-break-after: always; }\n\
+        .warning { color: red; }\n\
+        </style>\n</head>\n<body>\n");
+
+    html.push_str(&format!("<h1>{}</h1>\n<p>", escape(&plan.get_name())));
+    if let Some(aircraft) = plan.get_aircraft() {
+        html.push_str(&format!("Aircraft: {}<br>\n", escape(aircraft.get_name())));
+    }
+    html.push_str(&format!("Cruise altitude: {}</p>\n", plan.get_plan_altitude()));
+
+    for sector in plan.get_sectors().deref() {
+        let sector = sector.borrow();
+        html.push_str(&format!("<div class=\"sector\">\n<h2>{}</h2>\n", escape(&sector.get_name())));
+        if let Some(start) = sector.get_start() {
+            html.push_str(&format_airport_html("Departure", &start.get_airport(), sector.get_departure_runway()));
+        }
+
+        html.push_str("<table class=\"legs\">\n<tr>");
+        for heading in LEG_HEADINGS {
+            html.push_str(&format!("<th>{}</th>", heading));
+        }
+        html.push_str("</tr>\n");
+        for leg in make_legs(plan, &sector) {
+            html.push_str("<tr>");
+            for (i, column) in leg.columns().iter().enumerate() {
+                let class = if i < 3 { "" } else { " class=\"num\"" };
+                html.push_str(&format!("<td{}>{}</td>", class, escape(column)));
+            }
+            html.push_str("<td class=\"ata\"></td></tr>\n");
+        }
+        html.push_str(&format!(
+            "<tr class=\"total\"><td colspan=\"5\">Total</td><td class=\"num\">{}</td><td colspan=\"3\"></td>\
+            <td class=\"num\">{}</td><td colspan=\"3\"></td></tr>\n</table>\n",
+            escape(&sector.get_distance_as_string(plan)), sector.get_duration_as_string(plan)));
+
+        let fuel = make_fuel_summary(plan, &sector);
+        if !fuel.is_empty() {
+            html.push_str("<table class=\"fuel\">\n");
+            for (label, value) in fuel {
+                html.push_str(&format!("<tr><th>{}</th><td class=\"num\">{}</td></tr>\n", label, value));
+            }
+            html.push_str("</table>\n");
+        }
+        if let Some(end) = sector.get_end() {
+            html.push_str(&format_airport_html("Arrival", &end.get_airport(), sector.get_arrival_runway()));
+        }
+        html.push_str("</div>\n");
+    }
+
+    let warnings = plan.get_endurance_warnings().into_iter()
+        .chain(plan.get_terrain_warnings())
+        .chain(plan.get_constraint_warnings())
+        .chain(plan.get_cruise_level_warnings());
+    for warning in warnings {
+        html.push_str(&format!("<p class=\"warning\">WARNING: {}</p>\n", escape(&warning)));
+    }
+    html.push_str("</body>\n</html>\n");
+
+    out.write_all(html.as_bytes()).map_err(|e| e.to_string())
+}
+
+fn make_legs(plan: &Plan, sector: &Sector) -> Vec<NavLogLeg> {
+    let time_format = HourFormat::new();
+    let fuel_remaining = |wp: &Waypoint| plan.get_fuel_remaining_at(wp).map_or("".to_string(), |f| format!("{:.1}", f));
+    let fuel_planned = plan.get_aircraft().as_ref().is_some_and(|a| a.get_fuel().is_planned());
+
+    let mut legs = Vec::new();
+    if let Some(start) = sector.get_start() {
+        legs.push(NavLogLeg {
+            ident: start.get_id().to_string(),
+            name: start.get_name().to_string(),
+            freq: "".to_string(),
+            course: "".to_string(),
+            heading: "".to_string(),
+            distance: "".to_string(),
+            altitude: start.get_elevation().to_string(),
+            ground_speed: "".to_string(),
+            leg_time: "".to_string(),
+            total_time: "".to_string(),
+            fuel_used: "".to_string(),
+            fuel_remaining: fuel_remaining(&start),
+        });
+    }
+    let mut time = 0.0;
+    for wp in sector.get_waypoints().iter().chain(sector.get_end().iter()) {
+        let leg_time = plan.get_time_to(wp);
+        time += leg_time;
+        legs.push(NavLogLeg {
+            ident: wp.get_id().to_string(),
+            name: wp.get_name().to_string(),
+            freq: wp.get_freq().map_or("".to_string(), |f| f.to_string()),
+            course: format!("{:03.0}", plan.get_leg_magnetic_course_to(wp)),
+            heading: format!("{:03.0}", plan.get_leg_heading_to(wp)),
+            distance: plan.get_leg_distance_to_as_string(wp),
+            altitude: wp.get_elevation().to_string(),
+            ground_speed: plan.get_ground_speed_to_as_string(wp),
+            leg_time: time_format.format(&leg_time),
+            total_time: time_format.format(&time),
+            fuel_used: if fuel_planned { format!("{:.1}", plan.get_fuel_burn_to(wp)) } else { "".to_string() },
+            fuel_remaining: fuel_remaining(wp),
+        });
+    }
+    legs
+}
+
+fn make_fuel_summary(plan: &Plan, sector: &Sector) -> Vec<(&'static str, String)> {
+    match sector.get_fuel_plan(plan) {
+        Some(fuel) => vec![
+            ("Taxi", format!("{:.1}", fuel.get_taxi())),
+            ("Trip", format!("{:.1}", fuel.get_trip())),
+            ("Contingency", format!("{:.1}", fuel.get_contingency())),
+            ("Alternate", format!("{:.1}", fuel.get_alternate())),
+            ("Reserve", format!("{:.1}", fuel.get_reserve())),
+            ("Required", format!("{:.1}", fuel.get_required())),
+            ("Extra", format!("{:.1}", fuel.get_extra())),
+        ],
+        None => Vec::new(),
+    }
+}
+
+// Each runway with its length and the ILS frequency for either end where there is one
+fn make_runways(airport: &Airport) -> Vec<(String, String, String)> {
+    let runways = airport.get_runways().read().expect("Can't get airport lock");
+    runways.iter()
+        .filter(|runway| runway.runway_type().is_some_and(|t| t != RunwayType::Helipad))
+        .map(|runway| {
+            let ils = airport.get_ils(runway.number());
+            let ils_opp = runway.opposite_number().and_then(|op| airport.get_ils(&op));
+            let ils = match (ils, ils_opp) {
+                (None, None) => "".to_string(),
+                (ils, ils_opp) => format!(
+                    "{} / {}",
+                    ils.map_or("-".to_string(), |f| format!("{:0.3}", f)),
+                    ils_opp.map_or("-".to_string(), |f| format!("{:0.3}", f))),
+            };
+            (runway.number_pair(), runway.length().to_string(), ils)
+        })
+        .collect()
+}
+
+// The ATIS, clearance, ground and tower frequencies needed on the ground
+fn make_frequencies(airport: &Airport) -> Vec<String> {
+    let frequencies = airport.get_frequencies().read().expect("Can't get airport lock");
+    [FrequencyType::Atis, FrequencyType::Clearance, FrequencyType::Ground, FrequencyType::Tower, FrequencyType::Unicom].iter()
+        .filter_map(|t| frequencies.iter().find(|f| f.get_type() == *t))
+        .map(|f| format!("{} {:0.3}", f.get_type(), f.get_frequency()))
+        .collect()
+}
+
+fn format_airport_text(label: &str, airport: &Arc<Airport>, runway: Option<&String>) -> String {
+    let mut text = format!("{}: {} {}, elevation {}", label, airport.get_id(), airport.get_name(), airport.get_elevation());
+    if let Some(runway) = runway {
+        text.push_str(&format!(", runway {}", runway));
+    }
+    text.push('\n');
+    for (number, length, ils) in make_runways(airport) {
+        let line = format!("  Runway {:<9} {:>6} {}", number, length, ils);
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+    let frequencies = make_frequencies(airport);
+    if !frequencies.is_empty() {
+        text.push_str(&format!("  {}\n", frequencies.join(", ")));
+    }
+    text
+}
+
+fn format_airport_html(label: &str, airport: &Arc<Airport>, runway: Option<&String>) -> String {
+    let mut html = format!(
+        "<h3>{}: {} {}</h3>\n<p>Elevation {}",
+        label, escape(airport.get_id()), escape(airport.get_name()), airport.get_elevation());
+    if let Some(runway) = runway {
+        html.push_str(&format!(", runway {}", escape(runway)));
+    }
+    html.push_str("</p>\n");
+    let runways = make_runways(airport);
+    if !runways.is_empty() {
+        html.push_str("<table class=\"runways\">\n<tr><th>Runway</th><th>Length</th><th>ILS</th></tr>\n");
+        for (number, length, ils) in runways {
+            html.push_str(&format!("<tr><td>{}</td><td class=\"num\">{}</td><td>{}</td></tr>\n", escape(&number), length, ils));
+        }
+        html.push_str("</table>\n");
+    }
+    let frequencies = make_frequencies(airport);
+    if !frequencies.is_empty() {
+        html.push_str(&format!("<p>{}</p>\n", escape(&frequencies.join(", "))));
+    }
+    html
+}
+
+fn format_leg_text(c: &[&str; 13]) -> String {
+    let line = format!(
+        "{:<8} {:<20.20} {:>7} {:>4} {:>4} {:>9} {:>6} {:>8} {:>6} {:>6} {:>8} {:>8} {}",
+        c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7], c[8], c[9], c[10], c[11], c[12]);
+    format!("{}\n", line.trim_end())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::sync::Arc;
+
+    use crate::earth::coordinate::Coordinate;
+    use crate::model::aircraft::{Aircraft, AircraftFuel};
+    use crate::model::airport::{Frequency, FrequencyType, Runway, RunwayType};
+    use crate::model::plan::Plan;
+    use crate::model::sector::Sector;
+    use crate::model::test_utils::tests::make_airport_at;
+    use crate::model::waypoint::Waypoint;
+
+    use super::{write_nav_log_html_to, write_nav_log_to};
+
+    fn make_plan() -> Plan {
+        let start = make_airport_at("YAAA", 0.0, 10.0);
+        start.add_runway(Runway::new("09".to_string(), Some(RunwayType::Runway), 0.0, 10.0, 5000, 30, 90.0, false, "1".to_string(), "0".to_string()));
+        start.add_frequency(Frequency::new(FrequencyType::Tower, 118.1, "TWR".to_string()));
+        start.add_frequency(Frequency::new(FrequencyType::Atis, 127.25, "ATIS".to_string()));
+        let mut plan = Plan::new();
+        let mut s = Sector::new();
+        s.set_start(Some(start));
+        s.set_departure_runway(Some("27".to_string()));
+        s.set_end(Some(make_airport_at("YBBB", 0.0, 12.0)));
+        s.add_waypoint(Waypoint::Simple { loc: Coordinate::new(0.0, 11.0), elevation: Cell::new(5000), locked: false, constraints: Cell::default() });
+        plan.add_sector(s);
+        let fuel = AircraftFuel::new(12.0, 10.0, 6.0, 30.0).with_allowances(1.0, 10.0, 30.0, 45.0);
+        let aircraft = Arc::new(Aircraft::new("Test & Co".to_string(), 100, 5000, 100, 500, 100, 500, fuel, false));
+        plan.set_aircraft(&Some(aircraft));
+        plan
+    }
+
+    #[test]
+    fn test_text() {
+        let mut out = Vec::new();
+        write_nav_log_to(&make_plan(), &mut out).unwrap();
+        let log = String::from_utf8(out).unwrap();
+
+        assert!(log.contains("Departure: YAAA"));
+        assert!(log.contains("elevation 1, runway 27"));
+        assert!(log.contains("Arrival: YBBB"));
+        assert!(log.contains("Runway 09/27"));
+        assert!(log.contains("ATIS 127.250, Tower 118.100"));
+        // A box to fill in for each leg, the departure airport included
+        assert_eq!(log.matches("[    ]").count(), 3);
+        assert!(log.contains("Required"));
+    }
+
+    #[test]
+    fn test_html() {
+        let mut out = Vec::new();
+        write_nav_log_html_to(&make_plan(), &mut out).unwrap();
+        let html = String::from_utf8(out).unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("Aircraft: Test &amp; Co"));
+        assert!(html.contains("<td>09/27</td>"));
+        assert_eq!(html.matches("<td class=\"ata\"></td>").count(), 3);
+        assert!(html.trim_end().ends_with("</html>"));
+    }
+}
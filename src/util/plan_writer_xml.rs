@@ -23,31 +23,100 @@
  */
 
 use std::fs::File;
+use std::io::Write;
 use std::ops::Deref;
 use std::path::Path;
 
+use chrono::SecondsFormat;
 use xmltree::{Element, EmitterConfig, XMLNode};
 
+use crate::model::constraint::{AltitudeConstraint, Constraints, SpeedConstraint};
+use crate::model::cruise_level::StepClimb;
 use crate::model::location::Location;
 use crate::model::plan::Plan;
+use crate::model::procedure::ProcedureSelection;
 use crate::model::waypoint::Waypoint;
+use crate::model::wind::WindsAloft;
+
+/// The version of the plan format written, older plans are brought up to date as they are read.
+/// 1 - the original format, no version attribute
+/// 2 - plan metadata, planner settings and locked waypoints
+pub const PLAN_FORMAT_VERSION: u32 = 2;
 
 pub fn write_plan(plan: &Plan, file_path: &Path) -> Result<(), String> {
     let out = match File::create(file_path) {
         Ok(file) => file,
         Err(_) => return Err(String::from("Error reading file")),
     };
+    write_plan_to(plan, out)
+}
+
+pub fn write_plan_to<W: Write>(plan: &Plan, out: W) -> Result<(), String> {
     let mut plan_element = Element::new("plan");
+    plan_element.attributes.insert("version".to_string(), PLAN_FORMAT_VERSION.to_string());
     plan_element.attributes.insert("name".to_string(), plan.get_name());
+    if let Some(created) = plan.get_created() {
+        plan_element.attributes.insert("created".to_string(), created.to_rfc3339_opts(SecondsFormat::Secs, true));
+    }
+    if !plan.get_author().is_empty() {
+        plan_element.attributes.insert("author".to_string(), plan.get_author().to_string());
+    }
+    if let Some(departure_time) = plan.get_departure_time() {
+        plan_element.attributes.insert("departure-time".to_string(), departure_time.to_rfc3339_opts(SecondsFormat::Secs, true));
+    }
+    if let Some(max_altitude) = plan.get_max_altitude() {
+        plan_element.attributes.insert("max-altitude".to_string(), max_altitude.to_string());
+    }
+    plan_element.attributes.insert("flight-rules".to_string(), plan.get_flight_rules().to_string());
     if let Some(aircraft) = plan.get_aircraft() {
         plan_element.attributes.insert("aircraft".to_string(), aircraft.get_name().to_string());
     }
+    match plan.get_winds() {
+        Some(WindsAloft::Profile(profile)) => {
+            plan_element.attributes.insert("winds".to_string(), profile.to_string());
+        }
+        Some(WindsAloft::Grid { path, .. }) => {
+            plan_element.attributes.insert("wind-grid".to_string(), path.to_string_lossy().to_string());
+        }
+        None => (),
+    }
+    if !plan.get_remarks().is_empty() {
+        let mut remarks = Element::new("remarks");
+        remarks.children.push(XMLNode::Text(plan.get_remarks().to_string()));
+        plan_element.children.push(XMLNode::Element(remarks));
+    }
+    if let Some(settings) = plan.get_settings() {
+        let mut settings_element = Element::new("settings");
+        let attributes = [
+            ("units", settings.get_units().to_string()),
+            ("plan-type", settings.get_plan_type().to_string()),
+            ("vor-only", settings.is_vor_only().to_string()),
+            ("vor-preferred", settings.is_vor_preferred().to_string()),
+            ("add-gps-waypoints", settings.is_add_gps_waypoints().to_string()),
+            ("max-leg-length", settings.get_max_leg_length().to_string()),
+            ("min-leg-length", settings.get_min_leg_length().to_string()),
+        ];
+        for (name, value) in attributes {
+            settings_element.attributes.insert(name.to_string(), value);
+        }
+        plan_element.children.push(XMLNode::Element(settings_element));
+    }
     for sector in plan.get_sectors().deref() {
         let mut sector_element = Element::new("sector");
+        if let Some(cruise_level) = sector.borrow().get_cruise_level() {
+            sector_element.attributes.insert("cruise-level".to_string(), cruise_level.to_string());
+        }
+        if !sector.borrow().get_step_climbs().is_empty() {
+            sector_element.attributes.insert("step-climbs".to_string(), StepClimb::format_all(sector.borrow().get_step_climbs()));
+        }
 
         if let Some(start) = sector.borrow().get_start() {
             let mut from = Element::new("from-airport");
             from.attributes.insert("id".to_string(), start.get_id().to_string());
+            if let Some(runway) = sector.borrow().get_departure_runway() {
+                from.attributes.insert("runway".to_string(), runway.clone());
+            }
+            write_procedure(&mut from, "sid", sector.borrow().get_sid());
             sector_element.children.push(XMLNode::Element(from));
         }
 
@@ -56,27 +125,46 @@ pub fn write_plan(plan: &Plan, file_path: &Path) -> Result<(), String> {
             wp_element.attributes.insert("name".to_string(), wp.get_name().to_string());
             wp_element.attributes.insert("type".to_string(), wp.get_type_name().to_string());
             match wp {
-                Waypoint::Navaid { navaid: n, elevation: _b, locked: _c } => {
+                Waypoint::Navaid { navaid: n, .. } => {
                     wp_element.attributes.insert("id".to_string(), n.get_id().to_string());
                 }
                 Waypoint::Airport { airport: a, locked: _b } => {
                     wp_element.attributes.insert("id".to_string(), a.get_id().to_string());
                 }
-                Waypoint::Fix { fix: f, elevation: _b, locked: _c } => {
+                Waypoint::Fix { fix: f, .. } => {
                     wp_element.attributes.insert("id".to_string(), f.get_id().to_string());
+                    // Fix idents are only unique within a region
+                    if let Some(region) = f.get_region() {
+                        wp_element.attributes.insert("region".to_string(), region.to_string());
+                    }
+                    if let Some(terminal_area) = f.get_terminal_area() {
+                        wp_element.attributes.insert("terminal-area".to_string(), terminal_area.to_string());
+                    }
                 }
-                _ => {
-                    wp_element.attributes.insert("latitude".to_string(), format!("{:.4}", wp.get_lat()));
-                    wp_element.attributes.insert("longitude".to_string(), format!("{:.4}", wp.get_long()));
-                }
+                _ => {}
             }
+            // Every waypoint has its position so it can still be placed if the navigation data changes
+            wp_element.attributes.insert("latitude".to_string(), format!("{:.4}", wp.get_lat()));
+            wp_element.attributes.insert("longitude".to_string(), format!("{:.4}", wp.get_long()));
             wp_element.attributes.insert("elevation".to_string(), format!("{}", wp.get_elevation()));
+            wp_element.attributes.insert("locked".to_string(), wp.is_locked().to_string());
+            write_constraints(&mut wp_element, wp.get_constraints());
+            if let Some(airway) = wp.get_airway() {
+                wp_element.attributes.insert("airway".to_string(), airway.get_airway().to_string());
+                wp_element.attributes.insert("airway-entry".to_string(), airway.get_entry().to_string());
+                wp_element.attributes.insert("airway-exit".to_string(), airway.get_exit().to_string());
+            }
             sector_element.children.push(XMLNode::Element(wp_element));
         }
 
         if let Some(end) = sector.borrow().get_end() {
             let mut to = Element::new("to-airport");
             to.attributes.insert("id".to_string(), end.get_id().to_string());
+            if let Some(runway) = sector.borrow().get_arrival_runway() {
+                to.attributes.insert("runway".to_string(), runway.clone());
+            }
+            write_procedure(&mut to, "star", sector.borrow().get_star());
+            write_procedure(&mut to, "approach", sector.borrow().get_approach());
             sector_element.children.push(XMLNode::Element(to));
         }
 
@@ -91,3 +179,41 @@ pub fn write_plan(plan: &Plan, file_path: &Path) -> Result<(), String> {
         Err(e) => { Err(e.to_string()) }
     }
 }
+
+// The procedure is kept by name, its legs are read again from the procedure data
+fn write_procedure(airport: &mut Element, name: &str, procedure: Option<&ProcedureSelection>) {
+    if let Some(procedure) = procedure {
+        airport.attributes.insert(name.to_string(), procedure.get_ident().to_string());
+        if let Some(transition) = procedure.get_transition() {
+            airport.attributes.insert(format!("{}-transition", name), transition.clone());
+        }
+    }
+}
+
+// Constraints are written whether set by the user or taken from a procedure
+fn write_constraints(wp_element: &mut Element, constraints: Constraints) {
+    let altitude = match constraints.get_altitude() {
+        Some(AltitudeConstraint::At(a)) => Some(("at", a, None)),
+        Some(AltitudeConstraint::AtOrAbove(a)) => Some(("above", a, None)),
+        Some(AltitudeConstraint::AtOrBelow(a)) => Some(("below", a, None)),
+        Some(AltitudeConstraint::Between(lower, upper)) => Some(("between", lower, Some(upper))),
+        None => None,
+    };
+    if let Some((restriction, altitude, upper)) = altitude {
+        wp_element.attributes.insert("alt-restrict".to_string(), restriction.to_string());
+        wp_element.attributes.insert("alt-limit".to_string(), altitude.to_string());
+        if let Some(upper) = upper {
+            wp_element.attributes.insert("alt-upper-limit".to_string(), upper.to_string());
+        }
+    }
+    let speed = match constraints.get_speed() {
+        Some(SpeedConstraint::At(s)) => Some(("at", s)),
+        Some(SpeedConstraint::AtOrAbove(s)) => Some(("above", s)),
+        Some(SpeedConstraint::AtOrBelow(s)) => Some(("below", s)),
+        None => None,
+    };
+    if let Some((restriction, speed)) = speed {
+        wp_element.attributes.insert("speed-restrict".to_string(), restriction.to_string());
+        wp_element.attributes.insert("speed-limit".to_string(), speed.to_string());
+    }
+}
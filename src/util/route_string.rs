@@ -0,0 +1,428 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::sync::{Arc, LazyLock};
+
+use regex_lite::Regex;
+
+use crate::earth::coordinate::Coordinate;
+use crate::earth::{get_earth_model, Earth};
+use crate::model::airway::{AirwayGraph, AirwayLeg};
+use crate::model::cruise_level::parse_level;
+use crate::model::fix::Fix;
+use crate::model::plan::Plan;
+use crate::model::sector::Sector;
+use crate::model::waypoint::Waypoint;
+use crate::util::plan_reader::{enroute_navaids, nearest_within};
+
+// e.g. 3330S15100E or 33S151E
+static LAT_LONG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\d{2})(\d{2})?([NS])(\d{3})(\d{2})?([EW])$").unwrap()
+});
+// e.g. N0450F350, K0830S1130, M082F350 or N0120VFR
+static SPEED_LEVEL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(N\d{4}|K\d{4}|M\d{3})(F\d{3}|A\d{3}|S\d{4}|M\d{4}|VFR)$").unwrap()
+});
+
+const KM_PER_NM: f64 = 1.852;
+// Where levels are written as flight levels rather than altitudes
+const TRANSITION_ALTITUDE: i32 = 10000;
+
+/// A route read from an ICAO route string, with the cruising speed and level it gives.
+pub struct ParsedRoute {
+    sector: Sector,
+    speed: Option<i32>,
+    altitude: Option<i32>,
+}
+
+impl ParsedRoute {
+    pub fn get_sector(&self) -> &Sector {
+        &self.sector
+    }
+
+    pub fn into_sector(self) -> Sector {
+        self.sector
+    }
+
+    /// Cruising speed in knots, if the route starts with one given as a true airspeed
+    pub fn get_speed(&self) -> Option<i32> {
+        self.speed
+    }
+
+    /// Cruising level in feet
+    pub fn get_altitude(&self) -> Option<i32> {
+        self.altitude
+    }
+}
+
+pub fn parse_route(route: &str) -> Result<ParsedRoute, String> {
+    parse_route_with(route, get_earth_model())
+}
+
+/// Parse an ICAO style route such as `N0450F350 YSSY DCT SY H65 TESAT DCT YBBN`.
+/// Airways are expanded to the waypoints along them. Where an ident is used by more than one
+/// navaid, fix or airport, the one nearest the previous point is taken.
+/// An airport at the start or end of the route becomes the departure or destination.
+/// The points named in the route are locked, so planning again keeps to them.
+pub fn parse_route_with(route: &str, earth: &Earth) -> Result<ParsedRoute, String> {
+    let graph = earth.get_airways().read().expect("Unable to get lock on airways");
+
+    let mut points: Vec<Waypoint> = Vec::new();
+    let mut airway: Option<String> = None;
+    let mut speed = None;
+    let mut altitude = None;
+    let mut level = None;
+
+    for (i, token) in route.split_whitespace().map(|t| t.to_uppercase()).enumerate() {
+        let (token, change) = match token.split_once('/') {
+            Some((t, c)) => (t.to_string(), Some(c.to_string())),
+            None => (token, None),
+        };
+        if token == "DCT" {
+            continue;
+        }
+        if let Some((s, l)) = parse_speed_level(&token) {
+            if i == 0 {
+                speed = s;
+                altitude = l;
+            }
+            level = l;
+            continue;
+        }
+
+        if airway.is_none() && points.last().is_some_and(|p| is_on_airway(&graph, p, &token)) {
+            airway = Some(token);
+            continue;
+        }
+
+        let new_points = match airway.take() {
+            Some(name) => {
+                let prev = points.last().expect("An airway always follows a point");
+                follow_airway(&graph, earth, prev, &name, &token)?
+            }
+            None => {
+                let prev = points.last().map(|p| p.get_loc().clone());
+                vec![find_point(earth, &token, &prev).ok_or(format!("Unknown waypoint {}", token))?]
+            }
+        };
+        for wp in new_points {
+            if let Some(level) = level {
+                wp.set_elevation(&level);
+            }
+            points.push(wp);
+        }
+
+        if let Some(change) = change {
+            let (_, l) = parse_speed_level(&change).ok_or(format!("Invalid speed and level {}", change))?;
+            level = l;
+        }
+    }
+    if let Some(name) = airway {
+        return Err(format!("The route ends on airway {}", name));
+    }
+
+    let mut sector = Sector::new();
+    if matches!(points.first(), Some(Waypoint::Airport { .. })) {
+        sector.set_start(Some(points.remove(0).get_airport()));
+    }
+    if matches!(points.last(), Some(Waypoint::Airport { .. })) && sector.get_start().is_some() {
+        sector.set_end(points.pop().map(|wp| wp.get_airport()));
+    }
+    for wp in points {
+        sector.add_waypoint(wp);
+    }
+    Ok(ParsedRoute { sector, speed, altitude })
+}
+
+/// Make an ICAO style route string for the plan, e.g. `N0450F350 YSSY DCT SY H65 TESAT DCT YBBN`.
+/// The departure and destination are included so the route can be pasted straight back in.
+pub fn make_route_string(plan: &Plan) -> String {
+    let mut tokens: Vec<String> = Vec::new();
+
+    if let Some(aircraft) = plan.get_aircraft() {
+        let altitude = plan.get_max_altitude().unwrap_or(*aircraft.get_cruise_altitude());
+        let level = if altitude >= TRANSITION_ALTITUDE {
+            format!("F{:03}", altitude / 100)
+        } else {
+            format!("A{:03}", altitude / 100)
+        };
+        tokens.push(format!("N{:04}{}", aircraft.get_cruise_speed(), level));
+    }
+
+    let mut points: Vec<Waypoint> = Vec::new();
+    for sector in plan.get_sectors() {
+        let sector = sector.borrow();
+        // A sector starting where the last one ended only needs the airport once
+        if let Some(start) = sector.get_start().filter(|s| points.last().is_none_or(|p| p.get_id() != s.get_id())) {
+            points.push(start);
+        }
+        points.extend(sector.get_waypoints().iter()
+            .filter(|wp| !matches!(wp, Waypoint::Toc { .. } | Waypoint::Bod { .. }))
+            .cloned());
+        if let Some(end) = sector.get_end() {
+            points.push(end);
+        }
+    }
+
+    for (i, wp) in points.iter().enumerate() {
+        let ident = match wp {
+            Waypoint::Simple { .. } => format_lat_long(wp.get_loc()),
+            _ => wp.get_id().to_string(),
+        };
+        if i == 0 {
+            tokens.push(ident);
+            continue;
+        }
+        match wp.get_airway() {
+            Some(leg) => {
+                let airway = leg.get_airway();
+                let same_as_last = points[i - 1].get_airway().is_some_and(|l| l.get_airway() == airway);
+                let same_as_next = points.get(i + 1)
+                    .and_then(|p| p.get_airway())
+                    .is_some_and(|l| l.get_airway() == airway);
+                if !same_as_last {
+                    tokens.push(airway.to_string());
+                }
+                // Only the point where the route leaves the airway is named
+                if !same_as_next || leg.get_exit() == wp.get_id() {
+                    tokens.push(ident);
+                }
+            }
+            None => {
+                tokens.push("DCT".to_string());
+                tokens.push(ident);
+            }
+        }
+    }
+    tokens.join(" ")
+}
+
+fn parse_speed_level(token: &str) -> Option<(Option<i32>, Option<i32>)> {
+    let caps = SPEED_LEVEL.captures(token)?;
+    let (speed, level) = (&caps[1], &caps[2]);
+    let number = |s: &str| s[1..].parse::<f64>().unwrap_or(0.0);
+    let speed = match &speed[..1] {
+        "N" => Some(number(speed) as i32),
+        "K" => Some((number(speed) / KM_PER_NM).round() as i32),
+        // A Mach number needs the temperature to give a speed
+        _ => None,
+    };
+    Some((speed, parse_level(level)))
+}
+
+fn parse_lat_long(token: &str) -> Option<Coordinate> {
+    let caps = LAT_LONG.captures(token)?;
+    let part = |deg: usize, min: usize| {
+        let d = caps[deg].parse::<f64>().unwrap_or(0.0);
+        let m = caps.get(min).map_or(0.0, |m| m.as_str().parse::<f64>().unwrap_or(0.0));
+        d + m / 60.0
+    };
+    let mut lat = part(1, 2);
+    let mut long = part(4, 5);
+    if &caps[3] == "S" {
+        lat = -lat;
+    }
+    if &caps[6] == "W" {
+        long = -long;
+    }
+    Some(Coordinate::new(lat, long))
+}
+
+fn format_lat_long(loc: &Coordinate) -> String {
+    let minutes = |v: f64| (v.abs() * 60.0).round() as i32;
+    let lat = minutes(loc.get_latitude());
+    let long = minutes(loc.get_longitude());
+    format!("{:02}{:02}{}{:03}{:02}{}",
+            lat / 60, lat % 60, if loc.get_latitude() < 0.0 { 'S' } else { 'N' },
+            long / 60, long % 60, if loc.get_longitude() < 0.0 { 'W' } else { 'E' })
+}
+
+// The navaid, fix or airport with the ident nearest the previous point
+fn find_point(earth: &Earth, token: &str, prev: &Option<Coordinate>) -> Option<Waypoint> {
+    if let Some(loc) = parse_lat_long(token) {
+        return Some(Waypoint::Simple { loc, elevation: Cell::new(0), locked: true, constraints: Cell::default() });
+    }
+    let mut candidates: Vec<Waypoint> = Vec::new();
+    // With nothing to measure from, a route normally starts at an airport
+    candidates.extend(earth.get_airports_by_id(token).into_iter()
+        .map(|airport| Waypoint::Airport { airport, locked: true }));
+    candidates.extend(enroute_navaids(earth, token).into_iter()
+        .map(|navaid| Waypoint::Navaid { navaid, elevation: Cell::new(0), locked: true, airway: None, constraints: Cell::default() }));
+    candidates.extend(earth.get_fixes_by_id(token).into_iter()
+        .map(|fix| Waypoint::Fix { fix, elevation: Cell::new(0), locked: true, airway: None, constraints: Cell::default() }));
+    match prev {
+        Some(prev) => candidates.into_iter().min_by(|a, b| {
+            a.get_loc().distance_to(prev)
+                .partial_cmp(&b.get_loc().distance_to(prev))
+                .unwrap_or(Ordering::Equal)
+        }),
+        None => candidates.into_iter().next(),
+    }
+}
+
+fn airway_node(graph: &AirwayGraph, wp: &Waypoint) -> Option<usize> {
+    graph.get_nodes_near(wp.get_loc(), 1.0)
+        .into_iter()
+        .find(|i| graph.get_nodes()[*i].get_id() == wp.get_id())
+}
+
+fn is_on_airway(graph: &AirwayGraph, wp: &Waypoint, airway: &str) -> bool {
+    airway_node(graph, wp).is_some_and(|i| {
+        graph.get_nodes()[i].get_edges().iter().any(|e| e.get_airway() == airway)
+    })
+}
+
+fn follow_airway(graph: &AirwayGraph, earth: &Earth, from: &Waypoint, airway: &str, to: &str) -> Result<Vec<Waypoint>, String> {
+    let start = airway_node(graph, from).ok_or(format!("{} is not on airway {}", from.get_id(), airway))?;
+    let path = graph.get_route_along(airway, start, to)
+        .ok_or(format!("Airway {} does not go from {} to {}", airway, from.get_id(), to))?;
+    let leg = AirwayLeg::new(airway.to_string(), from.get_id().to_string(), to.to_string());
+    Ok(path.iter().enumerate().map(|(n, i)| {
+        let node = &graph.get_nodes()[*i];
+        let airway = Some(leg.clone());
+        // Only the point the airway is followed to was named in the route
+        let locked = n == path.len() - 1;
+        if let Some(navaid) = nearest_within(enroute_navaids(earth, node.get_id()), node.get_loc()) {
+            return Waypoint::Navaid { navaid, elevation: Cell::new(0), locked, airway, constraints: Cell::default() };
+        }
+        let fix = nearest_within(earth.get_fixes_by_id(node.get_id()), node.get_loc())
+            .unwrap_or_else(|| Arc::new(Fix::new(node.get_id().to_string(), node.get_loc().get_latitude(), node.get_loc().get_longitude())));
+        Waypoint::Fix { fix, elevation: Cell::new(0), locked, airway, constraints: Cell::default() }
+    }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::earth::coordinate::Coordinate;
+    use crate::earth::Earth;
+    use crate::model::aircraft::Aircraft;
+    use crate::model::airway::{AirwayGraph, AirwaySegment};
+    use crate::model::fix::Fix;
+    use crate::model::navaid::{Navaid, NavaidType};
+    use crate::model::plan::Plan;
+    use crate::model::test_utils::tests::make_airport_at;
+    use crate::model::waypoint::Waypoint;
+
+    use super::{format_lat_long, make_route_string, parse_lat_long, parse_route_with};
+
+    fn make_earth() -> Earth {
+        let earth = Earth::new();
+        earth.set_airports(vec![make_airport_at("YSSY", -33.946, 151.177), make_airport_at("YBBN", -27.384, 153.117)]);
+        earth.set_navaids(vec![
+            Arc::new(Navaid::new("SY".to_string(), NavaidType::Vor, -33.9, 151.2, 0, 112.1, 100, "12E".to_string(), "SYDNEY".to_string())),
+            Arc::new(Navaid::new("SY".to_string(), NavaidType::Ndb, 60.0, -1.3, 0, 350.0, 25, "0".to_string(), "SUMBURGH".to_string())),
+        ]);
+        earth.set_fixes(vec![
+            Arc::new(Fix::new("BOREE".to_string(), -32.0, 151.5)),
+            Arc::new(Fix::new("TESAT".to_string(), -30.0, 152.0)),
+        ]);
+        let segment = |from: (&str, f64, f64), to: (&str, f64, f64)| AirwaySegment::new(
+            (from.0.to_string(), Coordinate::new(from.1, from.2)),
+            (to.0.to_string(), Coordinate::new(to.1, to.2)),
+            "H65".to_string(), true, 180, 600, false);
+        earth.set_airways(AirwayGraph::from_segments(&[
+            segment(("SY", -33.9, 151.2), ("BOREE", -32.0, 151.5)),
+            segment(("BOREE", -32.0, 151.5), ("TESAT", -30.0, 152.0)),
+        ]));
+        earth
+    }
+
+    #[test]
+    fn test_parse_route() {
+        let earth = make_earth();
+        let route = parse_route_with("N0450F350 YSSY DCT SY H65 TESAT DCT 2900S15230E DCT YBBN", &earth).unwrap();
+        assert_eq!(route.get_speed(), Some(450));
+        assert_eq!(route.get_altitude(), Some(35000));
+
+        let sector = route.get_sector();
+        assert_eq!(sector.get_start().unwrap().get_id(), "YSSY");
+        assert_eq!(sector.get_end().unwrap().get_id(), "YBBN");
+        let wps = sector.get_waypoints();
+        let ids: Vec<&str> = wps.iter().map(|wp| wp.get_id()).collect();
+        assert_eq!(ids, vec!["SY", "BOREE", "TESAT", "GPS"]);
+        // The Sydney VOR, not the one in Scotland
+        assert_eq!(wps[0].get_name(), "SYDNEY");
+        assert_eq!(wps[1].get_airway().unwrap().get_entry(), "SY");
+        assert_eq!(wps[2].get_airway().unwrap().get_exit(), "TESAT");
+        assert!(wps[3].get_airway().is_none());
+        assert!(*wps[0].is_locked() && !*wps[1].is_locked() && *wps[2].is_locked());
+        assert_eq!(wps[3].get_lat(), -29.0);
+        assert_eq!(wps[3].get_long(), 152.5);
+        assert!(wps.iter().all(|wp| wp.get_elevation() == 35000));
+    }
+
+    #[test]
+    fn test_bad_routes() {
+        let earth = make_earth();
+        assert!(parse_route_with("YSSY DCT NOWHERE DCT YBBN", &earth).is_err());
+        assert!(parse_route_with("YSSY DCT SY H65", &earth).is_err());
+        assert!(parse_route_with("YSSY DCT BOREE H65 YBBN", &earth).is_err());
+    }
+
+    #[test]
+    fn test_speed_level_change() {
+        let earth = make_earth();
+        let route = parse_route_with("K0830S1130 SY/N0450A090 BOREE", &earth).unwrap();
+        assert_eq!(route.get_speed(), Some(448));
+        assert_eq!(route.get_altitude(), Some(37073));
+        let wps = route.get_sector().get_waypoints();
+        assert_eq!(wps[0].get_elevation(), 37073);
+        assert_eq!(wps[1].get_elevation(), 9000);
+    }
+
+    #[test]
+    fn test_lat_long() {
+        let loc = parse_lat_long("3330S15100E").unwrap();
+        assert_eq!(loc.get_latitude(), -33.5);
+        assert_eq!(loc.get_longitude(), 151.0);
+        let loc = parse_lat_long("51N001W").unwrap();
+        assert_eq!(loc.get_latitude(), 51.0);
+        assert_eq!(loc.get_longitude(), -1.0);
+        assert!(parse_lat_long("SY").is_none());
+
+        assert_eq!(format_lat_long(&Coordinate::new(-33.5, 151.0)), "3330S15100E");
+        assert_eq!(format_lat_long(&Coordinate::new(51.9999, -0.5)), "5200N00030W");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let earth = make_earth();
+        let text = "YSSY DCT SY H65 TESAT DCT 2900S15230E DCT YBBN";
+        let route = parse_route_with(text, &earth).unwrap();
+
+        let mut plan = Plan::new();
+        plan.add_sector(route.into_sector());
+        assert_eq!(make_route_string(&plan), text);
+
+        plan.set_aircraft(&Some(Arc::new(Aircraft::default())));
+        assert_eq!(make_route_string(&plan), format!("N0140A070 {}", text));
+        plan.set_max_altitude(Some(12000));
+        assert!(make_route_string(&plan).starts_with("N0140F120 YSSY"));
+        assert!(matches!(plan.get_sectors()[0].borrow().get_waypoints()[2], Waypoint::Fix { .. }));
+    }
+}
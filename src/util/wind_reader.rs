@@ -0,0 +1,94 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::earth::coordinate::Coordinate;
+use crate::model::wind::{WindProfile, WindsAloft};
+
+/// Read a gridded winds aloft file.
+///
+/// Each line gives a grid point followed by its wind profile, in the same form as is entered
+/// in the plan view, e.g.
+///   `-34.0 151.0 3000:270/15 9000:280/30 18000:280/45`
+/// Blank lines and lines starting with `#` are ignored.
+pub fn read_wind_grid(file_path: &Path) -> Result<WindsAloft, String> {
+    let file = match File::open(file_path) {
+        Ok(file) => file,
+        Err(_) => return Err(String::from("Error reading file")),
+    };
+    let reader = BufReader::new(file);
+    let mut points = Vec::new();
+
+    for (line_no, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut tokens = line.splitn(3, char::is_whitespace);
+        let lat = tokens.next().and_then(|t| t.parse::<f64>().ok());
+        let long = tokens.next().and_then(|t| t.parse::<f64>().ok());
+        let (Some(lat), Some(long)) = (lat, long) else {
+            return Err(format!("Invalid grid point at line {}", line_no + 1));
+        };
+        let profile = WindProfile::parse(tokens.next().unwrap_or(""))
+            .map_err(|e| format!("{} at line {}", e, line_no + 1))?;
+        points.push((Coordinate::new(lat, long), profile));
+    }
+
+    if points.is_empty() {
+        return Err(String::from("No winds found in file"));
+    }
+    Ok(WindsAloft::Grid { path: file_path.to_path_buf(), points })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::earth::coordinate::Coordinate;
+    use crate::model::wind::WindsAloft;
+
+    use super::read_wind_grid;
+
+    #[test]
+    fn test_read_wind_grid() {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("resources/test/winds.txt");
+
+        let winds = read_wind_grid(&path).unwrap();
+        match &winds {
+            WindsAloft::Grid { points, .. } => assert_eq!(points.len(), 4),
+            _ => panic!("Expected a wind grid"),
+        }
+        let wind = winds.wind_at(&Coordinate::new(-33.9, 151.2), 9000);
+        assert_eq!(wind.get_direction().round(), 280.0);
+        assert_eq!(wind.get_speed().round(), 30.0);
+        let wind = winds.wind_at(&Coordinate::new(-37.7, 144.8), 0);
+        assert_eq!(wind.get_direction().round(), 200.0);
+    }
+}
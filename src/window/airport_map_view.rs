@@ -31,11 +31,12 @@ mod imp {
     use std::thread;
     use gettextrs::gettext;
     use glib::subclass::InitializingObject;
-    use gtk::{cairo::Context, DrawingArea, Label};
+    use gtk::{cairo::Context, DrawingArea, Entry, Label};
     use gtk::glib::{clone, MainContext};
 
     use crate::event::Event;
-    use crate::model::airport::{Airport, RunwayType};
+    use crate::model::airport::{Airport, FrequencyType, RunwayType, StartLocation, StartLocationType};
+    use crate::model::wind::Wind;
     use crate::util::airport_painter::AirportPainter;
 
     use super::*;
@@ -47,6 +48,10 @@ mod imp {
         pub airport_map_window: TemplateChild<DrawingArea>,
         #[template_child]
         pub runway_list: TemplateChild<Label>,
+        #[template_child]
+        pub surface_wind: TemplateChild<Entry>,
+        #[template_child]
+        pub airport_info: TemplateChild<Label>,
 
         airport: RefCell<Option<Arc<Airport>>>,
     }
@@ -57,6 +62,7 @@ mod imp {
             self.airport.replace(None);
             self.airport_map_window.queue_draw();
             self.runway_list.set_label("");
+            self.airport_info.set_label("");
 
             let (tx, rx) = async_channel::bounded::<Event>(1);
             // Ensure the runways & taxiways are loaded. This can happen in another thread.
@@ -71,6 +77,7 @@ mod imp {
                     if let Event::AirportsLoaded = ev {
                         view.airport.replace(Some(airport.clone()));
                         view.draw_runway_list(&airport);
+                        view.draw_airport_info(&airport);
                         view.airport_map_window.queue_draw()
                     }
                 }
@@ -86,6 +93,7 @@ mod imp {
                     draw_taxiways: true,
                     draw_runways: true,
                     draw_compass_rose: true,
+                    draw_start_locations: true,
                 };
                 airport_painter.draw_airport(&airport, area, cr);
             }
@@ -94,6 +102,7 @@ mod imp {
         fn draw_runway_list(&self, airport: &Airport) {
             let mut buf = String::new();
             buf.push_str(&*gettext("Runways"));
+            let wind = Wind::parse(self.surface_wind.text().trim()).ok();
             let runways = airport
                 .get_runways()
                 .read()
@@ -115,10 +124,122 @@ mod imp {
                     };
                     buf.push('\n');
                     buf.push_str(text.as_str());
+                    for number in runway.ends() {
+                        let displaced = runway.displaced_threshold_of(&number).unwrap_or(0);
+                        let overrun = runway.overrun_of(&number).unwrap_or(0);
+                        if displaced > 0 || overrun > 0 {
+                            buf.push('\n');
+                            buf.push_str(&format!("  {:<4} {}", number, gettext("displaced threshold {displaced} overrun {overrun}")
+                                .replace("{displaced}", &displaced.to_string())
+                                .replace("{overrun}", &overrun.to_string())));
+                        }
+                    }
+                    if let Some(wind) = &wind {
+                        for number in runway.ends() {
+                            if let Some(heading) = runway.heading_of(&number) {
+                                buf.push('\n');
+                                buf.push_str(&format_components(&number, wind.components(heading)));
+                            }
+                        }
+                    }
                 }
             }
+            let helipads: Vec<&str> = runways.iter()
+                .filter(|r| r.runway_type().is_some_and(|t| t == RunwayType::Helipad))
+                .map(|r| r.number())
+                .collect();
+            if !helipads.is_empty() {
+                buf.push('\n');
+                buf.push_str(&gettext("Helipads {helipads}").replace("{helipads}", &helipads.join(" ")));
+            }
             self.runway_list.set_label(buf.as_str());
         }
+
+        // Where the airport is, who to call and where to park
+        fn draw_airport_info(&self, airport: &Airport) {
+            let mut lines = Vec::new();
+            let metadata = airport.get_metadata();
+            let place: Vec<&str> = [metadata.get_city(), metadata.get_state(), metadata.get_country()]
+                .into_iter()
+                .flatten()
+                .collect();
+            if !place.is_empty() {
+                lines.push(place.join(", "));
+            }
+            let codes: Vec<String> = [("ICAO", metadata.get_icao_code()), ("IATA", metadata.get_iata_code()), ("FAA", metadata.get_faa_code())]
+                .into_iter()
+                .filter_map(|(name, code)| code.map(|c| format!("{} {}", name, c)))
+                .collect();
+            if !codes.is_empty() {
+                lines.push(codes.join("  "));
+            }
+
+            for frequency in airport.get_frequencies().read().expect("Could not get airport lock").iter() {
+                lines.push(format!("{:<10} {:>7.3} {}", frequency_type_label(frequency.get_type()), frequency.get_frequency(), frequency.get_name()));
+            }
+
+            let starts = airport.get_start_locations().read().expect("Could not get airport lock");
+            let mut counts: Vec<(StartLocationType, usize)> = Vec::new();
+            for start in starts.iter() {
+                match counts.iter_mut().find(|(t, _)| *t == start.get_type()) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((start.get_type(), 1)),
+                }
+            }
+            if !counts.is_empty() {
+                lines.push(counts.iter()
+                    .map(|(t, count)| format!("{} {}", start_location_type_label(*t), count))
+                    .collect::<Vec<String>>()
+                    .join("  "));
+            }
+            let windsocks = airport.get_windsocks().read().expect("Could not get airport lock").len();
+            let beacons = airport.get_beacons().read().expect("Could not get airport lock").len();
+            if windsocks > 0 || beacons > 0 {
+                lines.push(gettext("Windsocks {windsocks}  Beacons {beacons}")
+                    .replace("{windsocks}", &windsocks.to_string())
+                    .replace("{beacons}", &beacons.to_string()));
+            }
+            self.airport_info.set_label(&lines.join("\n"));
+        }
+    }
+
+    fn frequency_type_label(frequency_type: FrequencyType) -> String {
+        match frequency_type {
+            FrequencyType::Atis => gettext("ATIS"),
+            FrequencyType::Unicom => gettext("Unicom"),
+            FrequencyType::Clearance => gettext("Clearance"),
+            FrequencyType::Ground => gettext("Ground"),
+            FrequencyType::Tower => gettext("Tower"),
+            FrequencyType::Approach => gettext("Approach"),
+            FrequencyType::Departure => gettext("Departure"),
+        }
+    }
+
+    fn start_location_type_label(location_type: StartLocationType) -> String {
+        match location_type {
+            StartLocationType::Gate => gettext("Gate"),
+            StartLocationType::Hangar => gettext("Hangar"),
+            StartLocationType::TieDown => gettext("Tie down"),
+            StartLocationType::Misc => gettext("Ramp"),
+        }
+    }
+
+    // The name and type of a start location and the aircraft that can use it, for its tooltip on the map
+    fn describe_start_location(start: &StartLocation) -> String {
+        let aircraft = if start.get_aircraft().is_empty() {
+            gettext("All aircraft")
+        } else {
+            start.get_aircraft().split('|').collect::<Vec<&str>>().join(", ")
+        };
+        format!("{}\n{}  {}", start.get_name(), start_location_type_label(start.get_type()), aircraft)
+    }
+
+    // A negative headwind is a tailwind, crosswinds are shown with the side they blow from
+    fn format_components(number: &str, (headwind, crosswind): (f64, f64)) -> String {
+        let side = if crosswind.round() > 0.0 { "R" } else if crosswind.round() < 0.0 { "L" } else { "" };
+        format!("  {:<4} {}", number, gettext("headwind {head} crosswind {cross}")
+            .replace("{head}", &format!("{:>3.0}", headwind))
+            .replace("{cross}", &format!("{:>2.0}{}", crosswind.abs(), side)))
     }
 
     #[glib::object_subclass]
@@ -142,6 +263,35 @@ mod imp {
             self.parent_constructed();
             self.initialise();
 
+            self.surface_wind.connect_changed(clone!(#[weak(rename_to = view)] self, move |_entry| {
+                let airport = view.airport.borrow().clone();
+                if let Some(airport) = airport {
+                    view.draw_runway_list(&airport);
+                }
+            }));
+
+            // Name the gate or other start location under the pointer
+            self.airport_map_window.set_has_tooltip(true);
+            self.airport_map_window.connect_query_tooltip(clone!(#[weak(rename_to = view)] self, #[upgrade_or] false, move |area, x, y, _kbm, tooltip| {
+                let airport = view.airport.borrow().clone();
+                let painter = AirportPainter {
+                    draw_taxiways: true,
+                    draw_runways: true,
+                    draw_compass_rose: true,
+                    draw_start_locations: true,
+                };
+                match airport.and_then(|airport| painter.start_location_at(&airport, area, x as f64, y as f64)) {
+                    Some(start) => {
+                        tooltip.set_text(Some(&describe_start_location(&start)));
+                        true
+                    }
+                    None => {
+                        tooltip.set_text(None);
+                        false
+                    }
+                }
+            }));
+
             self.airport_map_window.set_draw_func(
                 clone!(#[weak(rename_to = view)] self, move |area, cr, _x, _y| {
                     view.draw_function(area, cr);
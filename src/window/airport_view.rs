@@ -46,7 +46,7 @@ mod imp {
     use crate::model::airport_object::AirportObject;
     use crate::model::location::Location;
     use crate::util::lat_long_format::LatLongFormat;
-    use crate::util::location_filter::{AndFilter, NameIdFilter, new_airport_filter, NilFilter, RangeFilter, set_airport_filter};
+    use crate::util::location_filter::{AndFilter, FoundFilter, NameIdFilter, new_airport_filter, NilFilter, set_airport_filter};
     use crate::window::util::{build_column_factory, get_airport_map_view, get_fix_view, get_navaid_view, get_plan_view, show_airport_map_view, show_error_dialog, show_fix_view, show_navaid_view};
 
     use super::*;
@@ -69,6 +69,10 @@ mod imp {
         #[template_child]
         pub col_elev: TemplateChild<ColumnViewColumn>,
         #[template_child]
+        pub col_city: TemplateChild<ColumnViewColumn>,
+        #[template_child]
+        pub col_country: TemplateChild<ColumnViewColumn>,
+        #[template_child]
         pub airport_search_name: TemplateChild<Entry>,
         #[template_child]
         pub airport_search_lat: TemplateChild<Entry>,
@@ -91,6 +95,8 @@ mod imp {
             self.col_name.set_sorter(Some(&Self::get_name_sorter()));
             self.col_lat.set_sorter(Some(&Self::get_lat_sorter()));
             self.col_lon.set_sorter(Some(&Self::get_long_sorter()));
+            self.col_city.set_sorter(Some(&Self::get_city_sorter()));
+            self.col_country.set_sorter(Some(&Self::get_country_sorter()));
 
             let sorter = self.airport_list.sorter();
 
@@ -147,7 +153,8 @@ mod imp {
                             return;
                         }
                     };
-                    let filter = RangeFilter::new(Coordinate::new(lat_as_float, long_as_float), 100.0);
+                    let near = crate::earth::get_earth_model().get_airports_near(&Coordinate::new(lat_as_float, long_as_float), 100.0);
+                    let filter = FoundFilter::new(&near);
                     combined_filter.add(Box::new(filter));
                 }
             }
@@ -220,6 +227,20 @@ mod imp {
             Self::get_common_sorter(f)
         }
 
+        fn get_city_sorter() -> CustomSorter {
+            let f = |a: Arc<Airport>, b: Arc<Airport>| {
+                Ordering::from(a.get_metadata().get_city().cmp(&b.get_metadata().get_city()))
+            };
+            Self::get_common_sorter(f)
+        }
+
+        fn get_country_sorter() -> CustomSorter {
+            let f = |a: Arc<Airport>, b: Arc<Airport>| {
+                Ordering::from(a.get_metadata().get_country().cmp(&b.get_metadata().get_country()))
+            };
+            Self::get_common_sorter(f)
+        }
+
         fn get_lat_sorter() -> CustomSorter {
             let f = |a: Arc<Airport>, b: Arc<Airport>| {
                 Ordering::from(a.get_lat().partial_cmp(&b.get_lat()).unwrap())
@@ -329,6 +350,20 @@ mod imp {
                 view.attach_context_menu(&label, context_id);
             }))));
 
+            self.col_city.set_factory(Some(&build_column_factory(clone!(#[weak(rename_to = view)] self, move |label: Label, airport: &AirportObject| {
+                label.set_label(airport.imp().airport().get_metadata().get_city().unwrap_or(""));
+                label.set_xalign(0.0);
+                let context_id = airport.imp().airport().get_id().to_string();
+                view.attach_context_menu(&label, context_id);
+            }))));
+
+            self.col_country.set_factory(Some(&build_column_factory(clone!(#[weak(rename_to = view)] self, move |label: Label, airport: &AirportObject| {
+                label.set_label(airport.imp().airport().get_metadata().get_country().unwrap_or(""));
+                label.set_xalign(0.0);
+                let context_id = airport.imp().airport().get_id().to_string();
+                view.attach_context_menu(&label, context_id);
+            }))));
+
             self.col_elev.set_factory(Some(&build_column_factory(clone!(#[weak(rename_to = view)] self, move |label: Label, airport: &AirportObject| {
                 label.set_label(&airport.imp().airport().get_elevation().to_string());
                 label.set_xalign(1.0);
@@ -0,0 +1,208 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+use gtk::{gio, glib};
+use adw::subclass::prelude::ObjectSubclassIsExt;
+
+use crate::model::constraint::Constraints;
+
+mod imp {
+    use std::cell::RefCell;
+
+    use gtk::{Button, CompositeTemplate, DropDown, Entry, Label, glib, TemplateChild};
+    use gtk::glib::clone;
+    use gtk::glib::subclass::InitializingObject;
+    use adw::prelude::{ButtonExt, EditableExt, GtkWindowExt, WidgetExt};
+    use adw::subclass::prelude::{CompositeTemplate, ObjectImpl, ObjectImplExt, ObjectSubclass, ObjectSubclassExt, WidgetClassExt, WindowImpl};
+    use gettextrs::gettext;
+    use gtk::subclass::widget::{CompositeTemplateInitializingExt, WidgetImpl};
+
+    use crate::model::constraint::{AltitudeConstraint, Constraints, SpeedConstraint};
+
+    // The positions in the restriction lists
+    const NONE: u32 = 0;
+    const AT: u32 = 1;
+    const AT_OR_ABOVE: u32 = 2;
+    const AT_OR_BELOW: u32 = 3;
+    const BETWEEN: u32 = 4;
+
+    #[derive(CompositeTemplate, Default)]
+    #[template(resource = "/com/shartrec/kelpie_planner/constraint_dialog.ui")]
+    pub struct ConstraintDialog {
+        #[template_child]
+        pub altitude_restriction: TemplateChild<DropDown>,
+        #[template_child]
+        pub altitude: TemplateChild<Entry>,
+        #[template_child]
+        pub altitude_upper: TemplateChild<Entry>,
+        #[template_child]
+        pub speed_restriction: TemplateChild<DropDown>,
+        #[template_child]
+        pub speed: TemplateChild<Entry>,
+        #[template_child]
+        pub error: TemplateChild<Label>,
+        #[template_child]
+        pub btn_ok: TemplateChild<Button>,
+        #[template_child]
+        pub btn_cancel: TemplateChild<Button>,
+
+        pub on_accept: RefCell<Option<Box<dyn Fn(Constraints)>>>,
+    }
+
+    impl ConstraintDialog {
+        pub fn set_constraints(&self, constraints: Constraints) {
+            let (restriction, altitude, upper) = match constraints.get_altitude() {
+                Some(AltitudeConstraint::At(a)) => (AT, Some(a), None),
+                Some(AltitudeConstraint::AtOrAbove(a)) => (AT_OR_ABOVE, Some(a), None),
+                Some(AltitudeConstraint::AtOrBelow(a)) => (AT_OR_BELOW, Some(a), None),
+                Some(AltitudeConstraint::Between(lower, upper)) => (BETWEEN, Some(lower), Some(upper)),
+                None => (NONE, None, None),
+            };
+            self.altitude_restriction.set_selected(restriction);
+            self.altitude.set_text(&altitude.map(|a| a.to_string()).unwrap_or_default());
+            self.altitude_upper.set_text(&upper.map(|a| a.to_string()).unwrap_or_default());
+
+            let (restriction, speed) = match constraints.get_speed() {
+                Some(SpeedConstraint::At(s)) => (AT, Some(s)),
+                Some(SpeedConstraint::AtOrAbove(s)) => (AT_OR_ABOVE, Some(s)),
+                Some(SpeedConstraint::AtOrBelow(s)) => (AT_OR_BELOW, Some(s)),
+                None => (NONE, None),
+            };
+            self.speed_restriction.set_selected(restriction);
+            self.speed.set_text(&speed.map(|s| s.to_string()).unwrap_or_default());
+            self.restriction_changed();
+        }
+
+        // Only the values the restrictions need can be entered
+        fn restriction_changed(&self) {
+            self.altitude.set_sensitive(self.altitude_restriction.selected() != NONE);
+            self.altitude_upper.set_sensitive(self.altitude_restriction.selected() == BETWEEN);
+            self.speed.set_sensitive(self.speed_restriction.selected() != NONE);
+        }
+
+        pub fn get_constraints(&self) -> Result<Constraints, String> {
+            let value = |entry: &Entry, name: String| {
+                entry.text().trim().parse::<i32>()
+                    .ok()
+                    .filter(|v| *v >= 0)
+                    .ok_or(gettext("Enter the {value}").replace("{value}", &name))
+            };
+            let altitude = match self.altitude_restriction.selected() {
+                AT => Some(AltitudeConstraint::At(value(&self.altitude, gettext("altitude"))?)),
+                AT_OR_ABOVE => Some(AltitudeConstraint::AtOrAbove(value(&self.altitude, gettext("altitude"))?)),
+                AT_OR_BELOW => Some(AltitudeConstraint::AtOrBelow(value(&self.altitude, gettext("altitude"))?)),
+                BETWEEN => {
+                    let lower = value(&self.altitude, gettext("altitude"))?;
+                    let upper = value(&self.altitude_upper, gettext("upper altitude"))?;
+                    if upper < lower {
+                        return Err(gettext("The upper altitude is below the lower altitude"));
+                    }
+                    Some(AltitudeConstraint::Between(lower, upper))
+                }
+                _ => None,
+            };
+            let speed = match self.speed_restriction.selected() {
+                AT => Some(SpeedConstraint::At(value(&self.speed, gettext("speed"))?)),
+                AT_OR_ABOVE => Some(SpeedConstraint::AtOrAbove(value(&self.speed, gettext("speed"))?)),
+                AT_OR_BELOW => Some(SpeedConstraint::AtOrBelow(value(&self.speed, gettext("speed"))?)),
+                _ => None,
+            };
+            Ok(Constraints::new(altitude, speed))
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ConstraintDialog {
+        const NAME: &'static str = "ConstraintDialog";
+        type Type = super::ConstraintDialog;
+        type ParentType = gtk::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+            klass.set_accessible_role(gtk::AccessibleRole::Group);
+        }
+
+        fn instance_init(obj: &InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for ConstraintDialog {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            self.altitude_restriction.connect_selected_notify(clone!(#[weak(rename_to = window)] self, move |_list| {
+                window.restriction_changed();
+            }));
+            self.speed_restriction.connect_selected_notify(clone!(#[weak(rename_to = window)] self, move |_list| {
+                window.restriction_changed();
+            }));
+
+            self.btn_cancel.connect_clicked(clone!(#[weak(rename_to = window)] self, move |_button| {
+                window.obj().close();
+            }));
+
+            self.btn_ok.connect_clicked(clone!(#[weak(rename_to = window)] self, move |_button| {
+                match window.get_constraints() {
+                    Ok(constraints) => {
+                        if let Some(on_accept) = window.on_accept.borrow().as_ref() {
+                            on_accept(constraints);
+                        }
+                        window.obj().close();
+                    }
+                    Err(message) => window.error.set_label(&message),
+                }
+            }));
+        }
+    }
+
+    impl WidgetImpl for ConstraintDialog {}
+
+    impl WindowImpl for ConstraintDialog {}
+}
+
+glib::wrapper! {
+    pub struct ConstraintDialog(ObjectSubclass<imp::ConstraintDialog>)
+        @extends gtk::Window, gtk::Widget,
+        @implements gio::ActionGroup, gio::ActionMap, gtk::Accessible, gtk::Buildable,
+                    gtk::ConstraintTarget, gtk::Native, gtk::Root, gtk::ShortcutManager;
+}
+
+impl ConstraintDialog {
+    pub fn new() -> Self {
+        glib::Object::new::<ConstraintDialog>()
+    }
+
+    /// Edit the constraints at a waypoint, calling `on_accept` with them when they are valid
+    pub fn edit<F: Fn(Constraints) + 'static>(&self, constraints: Constraints, on_accept: F) {
+        self.imp().set_constraints(constraints);
+        self.imp().on_accept.replace(Some(Box::new(on_accept)));
+    }
+}
+
+impl Default for ConstraintDialog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,161 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+use gtk::{gio, glib};
+use adw::subclass::prelude::ObjectSubclassIsExt;
+
+use crate::model::cruise_level::StepClimb;
+
+mod imp {
+    use std::cell::RefCell;
+
+    use gtk::{Button, CompositeTemplate, Entry, Label, glib, TemplateChild};
+    use gtk::glib::clone;
+    use gtk::glib::subclass::InitializingObject;
+    use adw::prelude::{ButtonExt, EditableExt, GtkWindowExt};
+    use adw::subclass::prelude::{CompositeTemplate, ObjectImpl, ObjectImplExt, ObjectSubclass, ObjectSubclassExt, WidgetClassExt, WindowImpl};
+    use gettextrs::gettext;
+    use gtk::subclass::widget::{CompositeTemplateInitializingExt, WidgetImpl};
+
+    use crate::model::cruise_level::{parse_level, StepClimb};
+
+    #[derive(CompositeTemplate, Default)]
+    #[template(resource = "/com/shartrec/kelpie_planner/cruise_level_dialog.ui")]
+    pub struct CruiseLevelDialog {
+        #[template_child]
+        pub cruise_level: TemplateChild<Entry>,
+        #[template_child]
+        pub step_climbs: TemplateChild<Entry>,
+        #[template_child]
+        pub levels: TemplateChild<Label>,
+        #[template_child]
+        pub error: TemplateChild<Label>,
+        #[template_child]
+        pub btn_ok: TemplateChild<Button>,
+        #[template_child]
+        pub btn_cancel: TemplateChild<Button>,
+
+        pub on_accept: RefCell<Option<Box<dyn Fn(Option<i32>, Vec<StepClimb>)>>>,
+    }
+
+    impl CruiseLevelDialog {
+        pub fn set_cruise_level(&self, cruise_level: Option<i32>, step_climbs: &[StepClimb], levels: &[i32]) {
+            self.cruise_level.set_text(&cruise_level.map(|l| format!("FL{:03}", l / 100)).unwrap_or_default());
+            self.step_climbs.set_text(&StepClimb::format_all(step_climbs));
+            if levels.is_empty() {
+                self.levels.set_label("");
+            } else {
+                let levels = levels.iter()
+                    .map(|l| format!("FL{:03}", l / 100))
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                self.levels.set_label(&gettext("Levels for the track: {levels}").replace("{levels}", &levels));
+            }
+        }
+
+        // A blank cruise level leaves the planner to choose one
+        pub fn get_cruise_level(&self) -> Result<(Option<i32>, Vec<StepClimb>), String> {
+            let text = self.cruise_level.text();
+            let cruise_level = if text.trim().is_empty() {
+                None
+            } else {
+                match parse_level(&text) {
+                    Some(level) if level > 0 => Some(level),
+                    _ => return Err(gettext("Enter the cruise level in feet or as a flight level")),
+                }
+            };
+            let step_climbs = StepClimb::parse_all(&self.step_climbs.text())?;
+            Ok((cruise_level, step_climbs))
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for CruiseLevelDialog {
+        const NAME: &'static str = "CruiseLevelDialog";
+        type Type = super::CruiseLevelDialog;
+        type ParentType = gtk::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+            klass.set_accessible_role(gtk::AccessibleRole::Group);
+        }
+
+        fn instance_init(obj: &InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for CruiseLevelDialog {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            self.btn_cancel.connect_clicked(clone!(#[weak(rename_to = window)] self, move |_button| {
+                window.obj().close();
+            }));
+
+            self.btn_ok.connect_clicked(clone!(#[weak(rename_to = window)] self, move |_button| {
+                match window.get_cruise_level() {
+                    Ok((cruise_level, step_climbs)) => {
+                        if let Some(on_accept) = window.on_accept.borrow().as_ref() {
+                            on_accept(cruise_level, step_climbs);
+                        }
+                        window.obj().close();
+                    }
+                    Err(message) => window.error.set_label(&message),
+                }
+            }));
+        }
+    }
+
+    impl WidgetImpl for CruiseLevelDialog {}
+
+    impl WindowImpl for CruiseLevelDialog {}
+}
+
+glib::wrapper! {
+    pub struct CruiseLevelDialog(ObjectSubclass<imp::CruiseLevelDialog>)
+        @extends gtk::Window, gtk::Widget,
+        @implements gio::ActionGroup, gio::ActionMap, gtk::Accessible, gtk::Buildable,
+                    gtk::ConstraintTarget, gtk::Native, gtk::Root, gtk::ShortcutManager;
+}
+
+impl CruiseLevelDialog {
+    pub fn new() -> Self {
+        glib::Object::new::<CruiseLevelDialog>()
+    }
+
+    /// Edit the cruise level and step climbs of a sector, showing the levels the track allows,
+    /// calling `on_accept` with them when they are valid
+    pub fn edit<F: Fn(Option<i32>, Vec<StepClimb>) + 'static>(&self, cruise_level: Option<i32>, step_climbs: &[StepClimb],
+                                                                 levels: &[i32], on_accept: F) {
+        self.imp().set_cruise_level(cruise_level, step_climbs, levels);
+        self.imp().on_accept.replace(Some(Box::new(on_accept)));
+    }
+}
+
+impl Default for CruiseLevelDialog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
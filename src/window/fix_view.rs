@@ -48,7 +48,7 @@ mod imp {
     use crate::model::location::Location;
     use crate::model::waypoint::Waypoint;
     use crate::util::lat_long_format::LatLongFormat;
-    use crate::util::location_filter::{AndFilter, IdFilter, new_fix_filter, NilFilter, RangeFilter, set_fix_filter};
+    use crate::util::location_filter::{AndFilter, FoundFilter, IdFilter, new_fix_filter, NilFilter, set_fix_filter};
     use crate::window::util::{build_column_factory, get_airport_view, get_plan_view, show_airport_view, show_error_dialog, show_navaid_view};
 
     use super::*;
@@ -63,6 +63,8 @@ mod imp {
         #[template_child]
         pub col_id: TemplateChild<ColumnViewColumn>,
         #[template_child]
+        pub col_region: TemplateChild<ColumnViewColumn>,
+        #[template_child]
         pub col_lat: TemplateChild<ColumnViewColumn>,
         #[template_child]
         pub col_lon: TemplateChild<ColumnViewColumn>,
@@ -77,8 +79,8 @@ mod imp {
 
         popover: RefCell<Option<PopoverMenu>>,
         filter_list_model: RefCell<Option<FilterListModel>>,
-        // Fix ID for the row that opened the popover (if any)
-        context_fix_id: RefCell<Option<String>>,
+        // Fix for the row that opened the popover (if any), the id alone may be shared by several
+        context_fix: RefCell<Option<Arc<Fix>>>,
 
     }
 
@@ -86,6 +88,7 @@ mod imp {
         pub fn initialise(&self) {
             // Add a sorter
             self.col_id.set_sorter(Some(&Self::get_id_sorter()));
+            self.col_region.set_sorter(Some(&Self::get_region_sorter()));
             self.col_lat.set_sorter(Some(&Self::get_lat_sorter()));
             self.col_lon.set_sorter(Some(&Self::get_long_sorter()));
 
@@ -143,7 +146,8 @@ mod imp {
                             return;
                         }
                     };
-                    let filter = RangeFilter::new(Coordinate::new(lat_as_float, long_as_float), 100.0);
+                    let near = crate::earth::get_earth_model().get_fixes_near(&Coordinate::new(lat_as_float, long_as_float), 100.0);
+                    let filter = FoundFilter::new(&near);
                     combined_filter.add(Box::new(filter));
                 }
             }
@@ -163,6 +167,8 @@ mod imp {
                     fix: fix.clone(),
                     elevation: Cell::new(0),
                     locked: true,
+                    airway: None,
+                    constraints: Cell::default(),
                 });
             }
         }
@@ -172,10 +178,8 @@ mod imp {
         }
 
         fn get_context_or_selected_fix(&self) -> Option<Arc<Fix>> {
-            if let Some(id) = self.context_fix_id.borrow().as_ref() {
-                if let Some(ap) = crate::earth::get_earth_model().get_fix_by_id(id.as_str()) {
-                    return Some(ap);
-                }
+            if let Some(fix) = self.context_fix.borrow().as_ref() {
+                return Some(fix.clone());
             }
             self.get_selected_fix()
         }
@@ -212,6 +216,13 @@ mod imp {
             Self::get_common_sorter(f)
         }
 
+        fn get_region_sorter() -> CustomSorter {
+            let f = |a: Arc<Fix>, b: Arc<Fix>| {
+                Ordering::from(a.get_region().cmp(&b.get_region()))
+            };
+            Self::get_common_sorter(f)
+        }
+
         fn get_lat_sorter() -> CustomSorter {
             let f = |a: Arc<Fix>, b: Arc<Fix>| {
                 Ordering::from(a.get_lat().partial_cmp(&b.get_lat()).unwrap())
@@ -255,13 +266,13 @@ mod imp {
     }
 
     impl FixView {
-        fn attach_context_menu(&self, label: &Label, context_id: String) {
+        fn attach_context_menu(&self, label: &Label, context_fix: Arc<Fix>) {
             let gesture = gtk::GestureClick::new();
             gesture.set_button(3);
             gesture.connect_released(clone!(#[weak(rename_to = view)] self, #[weak(rename_to = l)] label, move |gesture, _n, x, y| {
                 gesture.set_state(gtk::EventSequenceState::Claimed);
                 // remember which fix opened this menu
-                view.context_fix_id.replace(Some(context_id.clone()));
+                view.context_fix.replace(Some(context_fix.clone()));
                 if let Some(popover) = view.popover.borrow().as_ref() {
                     popover.unparent();
                     popover.set_parent(&l);
@@ -294,24 +305,27 @@ mod imp {
             self.col_id.set_factory(Some(&build_column_factory(clone!(#[weak(rename_to = view)] self, move |label: Label, fix: &FixObject| {
                 label.set_label(fix.imp().fix().get_id());
                 label.set_xalign(0.0);
-                let context_id = fix.imp().fix().get_id().to_string();
-                view.attach_context_menu(&label, context_id);
+                view.attach_context_menu(&label, fix.imp().fix());
                 fix.imp().set_ui(Some(label.clone()));
             }))));
 
 
+            self.col_region.set_factory(Some(&build_column_factory(clone!(#[weak(rename_to = view)] self, move |label: Label, fix: &FixObject| {
+                label.set_label(fix.imp().fix().get_region().unwrap_or(""));
+                label.set_xalign(0.0);
+                view.attach_context_menu(&label, fix.imp().fix());
+            }))));
+
             self.col_lat.set_factory(Some(&build_column_factory(clone!(#[weak(rename_to = view)] self, move |label: Label, fix: &FixObject| {
                 label.set_label(&fix.imp().fix().get_lat_as_string());
                 label.set_xalign(0.0);
-                let context_id = fix.imp().fix().get_id().to_string();
-                view.attach_context_menu(&label, context_id);
+                view.attach_context_menu(&label, fix.imp().fix());
             }))));
 
             self.col_lon.set_factory(Some(&build_column_factory(clone!(#[weak(rename_to = view)] self, move |label: Label, fix: &FixObject| {
                 label.set_label(&fix.imp().fix().get_long_as_string());
                 label.set_xalign(0.0);
-                let context_id = fix.imp().fix().get_id().to_string();
-                view.attach_context_menu(&label, context_id);
+                view.attach_context_menu(&label, fix.imp().fix());
             }))));
 
 
@@ -36,8 +36,11 @@ use adw::subclass::prelude::*;
 use gettextrs::gettext;
 use crate::{event, listen_events};
 use crate::event::{Event, EventType};
-use crate::util::{get_plan_file_filter, plan_writer_route_manager, plan_writer_xml};
+use crate::util::{get_plan_file_filter, plan_writer_fms, plan_writer_gpx, plan_writer_kml, plan_writer_route_manager, plan_writer_text, plan_writer_xml};
 use crate::util::plan_reader::read_plan;
+use crate::util::plan_reader_fms::read_plan_fms;
+use crate::util::plan_reader_gpx::import_plan_gpx;
+use crate::util::plan_reader_route_manager::import_plan_fg;
 use crate::window::airport_map_view::AirportMapView;
 use crate::window::airport_view::AirportView;
 use crate::window::fix_view::FixView;
@@ -49,6 +52,10 @@ use crate::window::world_map_view::WorldMapView;
 enum SaveType {
     Native,
     FgRouteManager,
+    XPlane,
+    Gpx,
+    Kml,
+    NavLog,
 }
 
 
@@ -100,7 +107,7 @@ impl Window {
         let dialog = FileDialog::new();
         dialog.set_modal(true);
         dialog.set_title("Open Plan");
-        let store = get_plan_file_filter("fgfp");
+        let store = get_plan_file_filter(&["fgfp", "fms", "xml", "gpx"]);
         dialog.set_filters(Some(&store));
 
         let x1 = &win.unwrap();
@@ -110,14 +117,33 @@ impl Window {
                     clone!(#[weak(rename_to = window)] self, move | result: Result<File, _>| {
                 if let Ok(file) = result {
                     if let Some(path) = file.path() {
-                        if let Ok(mut plan) = read_plan(&path) {
-                            let view = PlanView::new();
-                            let page = window.plan_tab_view.add_page(&view, None);
-                            view.imp().set_parent_page(page.clone());
-                            page.set_title(plan.get_name().as_str());
-                            plan.set_path(Some(path));
-                            window.plan_tab_view.set_selected_page(&page);
-                            view.imp().set_plan(plan);
+                        let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+                        let imported = matches!(ext.as_deref(), Some("fms") | Some("xml") | Some("gpx"));
+                        let result = match ext.as_deref() {
+                            Some("fms") => read_plan_fms(&path).map(|plan| (plan, Vec::new())),
+                            Some("xml") => import_plan_fg(&path).map(|plan| (plan, Vec::new())),
+                            Some("gpx") => import_plan_gpx(&path).map(|plan| (plan, Vec::new())),
+                            _ => read_plan(&path).map_err(|e| e.to_string()),
+                        };
+                        match result {
+                            Ok((mut plan, warnings)) => {
+                                let view = PlanView::new();
+                                let page = window.plan_tab_view.add_page(&view, None);
+                                view.imp().set_parent_page(page.clone());
+                                // An imported plan has not been saved in our own format yet
+                                if !imported {
+                                    plan.set_path(Some(path));
+                                }
+                                page.set_title(plan.get_name().as_str());
+                                window.plan_tab_view.set_selected_page(&page);
+                                view.imp().set_plan(plan);
+                                if !warnings.is_empty() {
+                                    window.show_alert(&format!("{}\n\n{}", gettext("Some of the plan could not be loaded"), warnings.join("\n")));
+                                }
+                            }
+                            Err(s) => {
+                                window.show_alert(&gettext("Failed to open plan: {error}").replace("{error}", &s.to_string()));
+                            }
                         }
                     };
                 }
@@ -133,6 +159,39 @@ impl Window {
         self.do_save("Export Plan", SaveType::FgRouteManager);
     }
 
+    pub(crate) fn export_plan_xplane(&self) {
+        self.do_save("Export Plan", SaveType::XPlane);
+    }
+
+    pub(crate) fn export_plan_gpx(&self) {
+        self.do_save("Export Plan", SaveType::Gpx);
+    }
+
+    pub(crate) fn export_plan_kml(&self) {
+        self.do_save("Export Plan", SaveType::Kml);
+    }
+
+    pub(crate) fn export_nav_log(&self) {
+        self.do_save("Export Nav Log", SaveType::NavLog);
+    }
+
+    pub(crate) fn undo(&self) {
+        if let Some(view) = self.selected_plan_view() {
+            view.imp().undo();
+        }
+    }
+
+    pub(crate) fn redo(&self) {
+        if let Some(view) = self.selected_plan_view() {
+            view.imp().redo();
+        }
+    }
+
+    fn selected_plan_view(&self) -> Option<PlanView> {
+        self.plan_tab_view.selected_page()
+            .and_then(|page| page.child().downcast::<PlanView>().ok())
+    }
+
     fn do_save(&self, title: &str, save_type: SaveType) {
         if let Some(page) = self.plan_tab_view.selected_page() {
             self.save_page_plan(title, save_type, &page, false);
@@ -147,6 +206,10 @@ impl Window {
             let ext = match save_type {
                 SaveType::Native => "fgfp",
                 SaveType::FgRouteManager => "xml",
+                SaveType::XPlane => "fms",
+                SaveType::Gpx => "gpx",
+                SaveType::Kml => "kml",
+                SaveType::NavLog => "html",
             };
             let mut name = plan.get_name();
             let full_title = format!("{} : {}", title, name);
@@ -160,7 +223,7 @@ impl Window {
             dialog.set_modal(true);
             dialog.set_title(full_title.as_ref());
             dialog.set_initial_name(Some(name.as_str()));
-            let store = get_plan_file_filter(ext);
+            let store = get_plan_file_filter(&[ext]);
             dialog.set_filters(Some(&store));
 
             let x1 = &win.unwrap();
@@ -174,6 +237,10 @@ impl Window {
                             let writer = match save_type {
                                         SaveType::Native => plan_writer_xml::write_plan,
                                         SaveType::FgRouteManager => plan_writer_route_manager::export_plan_fg,
+                                        SaveType::XPlane => plan_writer_fms::export_plan_fms,
+                                        SaveType::Gpx => plan_writer_gpx::export_plan_gpx,
+                                        SaveType::Kml => plan_writer_kml::export_plan_kml,
+                                        SaveType::NavLog => plan_writer_text::write_nav_log_html,
                                     };
                             if let Some(path) = file.path() {
                                 let binding = view.imp().get_plan();
@@ -211,6 +278,16 @@ impl Window {
     }
 
 
+    fn show_alert(&self, message: &str) {
+        let buttons = vec![gettext("Ok")];
+        let alert = AlertDialog::builder()
+            .modal(true)
+            .message(message)
+            .buttons(buttons)
+            .build();
+        alert.show(self.get_window_handle().as_ref());
+    }
+
     fn get_window_handle(&self) -> Option<gtk::Window> {
         match self.plan_tab_view.root() {
             Some(r) => {
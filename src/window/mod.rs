@@ -29,10 +29,13 @@ use adw::prelude::GtkWindowExt;
 
 mod airport_map_view;
 mod airport_view;
+mod constraint_dialog;
+mod cruise_level_dialog;
 mod fix_view;
 pub(crate) mod imp;
 mod navaid_view;
 mod plan_view;
+mod procedure_dialog;
 pub(crate) mod util;
 mod world_map_view;
 pub(crate) mod map_utils;
@@ -169,9 +169,11 @@ impl NavaidRenderer {
             let position = projector.project(navaid.get_lat(), navaid.get_long());
             vertices.push(Vertex { position });
 
-            // Now indices
+            // Now indices, the minor beacons and landing aids are only drawn when zoomed in.
+            // A glideslope sits beside its localizer so is not drawn at all.
             match navaid.get_type() {
-                NavaidType::Vor => indices_vor.push(i as u32),
+                NavaidType::Glideslope => (),
+                t if t.is_vor() || t == NavaidType::Dme || t == NavaidType::Tacan => indices_vor.push(i as u32),
                 _ => indices_ndb.push(i as u32),
             }
         }
@@ -36,6 +36,7 @@ mod imp {
     use gtk::gdk::{Key, ModifierType, Rectangle};
     use gtk::gio::{MenuModel, SimpleAction, SimpleActionGroup};
     use gtk::glib::clone;
+    use gettextrs::gettext;
     use log::error;
 
     use crate::earth::coordinate::Coordinate;
@@ -48,7 +49,7 @@ mod imp {
     use crate::model::navaid_object::NavaidObject;
     use crate::model::waypoint::Waypoint;
     use crate::util::lat_long_format::LatLongFormat;
-    use crate::util::location_filter::{AndFilter, NameIdFilter, new_navaid_filter, NilFilter, RangeFilter, set_navaid_filter};
+    use crate::util::location_filter::{AndFilter, FoundFilter, NameIdFilter, new_navaid_filter, NilFilter, set_navaid_filter};
     use crate::window::util::{build_column_factory, get_airport_view, get_fix_view, get_plan_view, show_airport_view, show_error_dialog, show_fix_view};
 
     use super::*;
@@ -63,6 +64,8 @@ mod imp {
         #[template_child]
         pub col_id: TemplateChild<ColumnViewColumn>,
         #[template_child]
+        pub col_type: TemplateChild<ColumnViewColumn>,
+        #[template_child]
         pub col_name: TemplateChild<ColumnViewColumn>,
         #[template_child]
         pub col_lat: TemplateChild<ColumnViewColumn>,
@@ -71,6 +74,8 @@ mod imp {
         #[template_child]
         pub col_freq: TemplateChild<ColumnViewColumn>,
         #[template_child]
+        pub col_details: TemplateChild<ColumnViewColumn>,
+        #[template_child]
         pub navaid_search_name: TemplateChild<Entry>,
         #[template_child]
         pub navaid_search_lat: TemplateChild<Entry>,
@@ -90,6 +95,7 @@ mod imp {
         pub fn initialise(&self) {
             // Add a sorter
             self.col_id.set_sorter(Some(&Self::get_id_sorter()));
+            self.col_type.set_sorter(Some(&Self::get_type_sorter()));
             self.col_name.set_sorter(Some(&Self::get_name_sorter()));
             self.col_lat.set_sorter(Some(&Self::get_lat_sorter()));
             self.col_lon.set_sorter(Some(&Self::get_long_sorter()));
@@ -148,7 +154,8 @@ mod imp {
                             return;
                         }
                     };
-                    let filter = RangeFilter::new(Coordinate::new(lat_as_float, long_as_float), 100.0);
+                    let near = crate::earth::get_earth_model().get_navaids_near(&Coordinate::new(lat_as_float, long_as_float), 100.0);
+                    let filter = FoundFilter::new(&near);
                     combined_filter.add(Box::new(filter));
                 }
             }
@@ -169,6 +176,8 @@ mod imp {
                     navaid: navaid.clone(),
                     elevation: Cell::new(0),
                     locked: true,
+                    airway: None,
+                    constraints: Cell::default(),
                 });
             }
         }
@@ -217,6 +226,31 @@ mod imp {
             Self::get_common_sorter(f)
         }
 
+        fn get_type_sorter() -> CustomSorter {
+            let f = |a: Arc<Navaid>, b: Arc<Navaid>| {
+                Ordering::from(a.get_type().to_string().cmp(&b.get_type().to_string()))
+            };
+            Self::get_common_sorter(f)
+        }
+
+        // The runway, course, glideslope and DME of the navaids that have them
+        fn get_details(navaid: &Navaid) -> String {
+            let mut details = Vec::new();
+            if let (Some(airport_id), Some(runway)) = (navaid.get_airport_id(), navaid.get_runway()) {
+                details.push(format!("{} {}", airport_id, runway));
+            }
+            if let Some(course) = navaid.get_course() {
+                details.push(gettext("course {course}°").replace("{course}", &format!("{:03.0}", course)));
+            }
+            if let Some(angle) = navaid.get_glideslope_angle() {
+                details.push(gettext("glideslope {angle}°").replace("{angle}", &format!("{:.2}", angle)));
+            }
+            if let Some(bias) = navaid.get_dme_bias() {
+                details.push(gettext("DME bias {bias}nm").replace("{bias}", &format!("{:.1}", bias)));
+            }
+            details.join(", ")
+        }
+
         fn get_name_sorter() -> CustomSorter {
             let f = |a: Arc<Navaid>, b: Arc<Navaid>| {
                 Ordering::from(a.get_name().partial_cmp(b.get_name()).unwrap())
@@ -313,6 +347,14 @@ mod imp {
                 navaid.imp().set_ui(Some(label.clone()));
             }))));
 
+            self.col_type.set_factory(Some(&build_column_factory(clone!(#[weak(rename_to = view)] self, move |label: Label, navaid: &NavaidObject| {
+                label.set_label(&navaid.imp().navaid().get_type().to_string());
+                label.set_xalign(0.0);
+                let context_id = navaid.imp().navaid().get_id().to_string();
+                let context_name = navaid.imp().navaid().get_name().to_string();
+                view.attach_context_menu(&label, (context_id, context_name));
+            }))));
+
             self.col_name.set_factory(Some(&build_column_factory(clone!(#[weak(rename_to = view)] self, move |label: Label, navaid: &NavaidObject| {
                 label.set_label(navaid.imp().navaid().get_name());
                 label.set_xalign(0.0);
@@ -345,6 +387,14 @@ mod imp {
                 view.attach_context_menu(&label, (context_id, context_name));
             }))));
 
+            self.col_details.set_factory(Some(&build_column_factory(clone!(#[weak(rename_to = view)] self, move |label: Label, navaid: &NavaidObject| {
+                label.set_label(&Self::get_details(&navaid.imp().navaid()));
+                label.set_xalign(0.0);
+                let context_id = navaid.imp().navaid().get_id().to_string();
+                let context_name = navaid.imp().navaid().get_name().to_string();
+                view.attach_context_menu(&label, (context_id, context_name));
+            }))));
+
             self.navaid_list.connect_activate(
                 clone!(#[weak(rename_to = view)] self, move | _list_view, position | {
                     if let Some(navaid) = view.get_model_navaid(position) {
@@ -31,34 +31,48 @@ mod imp {
     use crate::earth::coordinate::Coordinate;
     use crate::event::{Event, EventType};
     use crate::hangar::hangar::get_hangar;
+    use crate::hangar::hangar_list_model::HangarListModel;
     use crate::model::airport::Airport;
+    use crate::model::cruise_level::{FlightRules, LevelRegions};
+    use crate::model::fuel::FuelPlan;
     use crate::model::location::Location;
     use crate::model::plan::Plan;
+    use crate::model::plan_history::{PlanField, PlanHistory};
     use crate::model::plan_object::PlanObject;
+    use crate::model::procedure::ProcedureType;
     use crate::model::runway_object::RunwayObject;
     use crate::model::sector::Sector;
     use crate::model::sector_object::SectorObject;
     use crate::model::waypoint::Waypoint;
     use crate::model::waypoint_object::WaypointObject;
+    use crate::model::wind::{WindProfile, WindsAloft};
     use crate::planner::planner;
     use crate::planner::planner::Planner;
     use crate::preference::{AUTO_PLAN, USE_MAGNETIC_HEADINGS};
+    use crate::util::route_string::{make_route_string, parse_route};
+    use crate::util::wind_reader::read_wind_grid;
+    use crate::window::constraint_dialog::ConstraintDialog;
+    use crate::window::cruise_level_dialog::CruiseLevelDialog;
+    use crate::window::procedure_dialog::ProcedureDialog;
     use crate::window::util::{build_column_factory, build_tree_column_factory, expand_tree, get_airport_map_view, get_airport_view, get_fix_view, get_navaid_view, get_tree_path, get_world_map_view, show_airport_map_view, show_airport_view, show_fix_view, show_navaid_view, show_world_map_view};
     use crate::{earth, event, listen_events};
     use adw::gio::ListModel;
+    use chrono::{DateTime, NaiveDateTime, Utc};
     use adw::TabPage;
     use gettextrs::gettext;
     use glib::subclass::InitializingObject;
     use gtk::gdk::{Key, ModifierType, Rectangle};
-    use gtk::gio::{MenuModel, SimpleAction, SimpleActionGroup};
+    use gtk::gio::{Cancellable, File, MenuModel, SimpleAction, SimpleActionGroup};
     use gtk::glib::{clone, Propagation};
-    use gtk::{prelude::WidgetExt, Builder, Button, CheckButton, ColumnView, ColumnViewColumn, DropDown, Entry, Label, ListScrollFlags, PopoverMenu, ScrolledWindow, SingleSelection, Stack, StringObject, TreeListModel, TreeListRow};
+    use gtk::{prelude::WidgetExt, AlertDialog, Builder, Button, CheckButton, ColumnView, ColumnViewColumn, DropDown, Entry, FileDialog, Label, ListScrollFlags, PopoverMenu, ScrolledWindow, SingleSelection, Stack, StringObject, TreeListModel, TreeListRow, Window};
     use log::{error};
-    use std::cell::{Ref, RefCell};
+    use std::cell::{Cell, Ref, RefCell};
     use std::ops::{Deref, DerefMut};
     use std::rc::Rc;
     use std::sync::Arc;
 
+    const DEPARTURE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
     #[derive(Default, CompositeTemplate)]
     #[template(resource = "/com/shartrec/kelpie_planner/plan_view.ui")]
     pub struct PlanView {
@@ -80,6 +94,22 @@ mod imp {
         pub btn_max_alt: TemplateChild<CheckButton>,
         #[template_child]
         pub max_alt: TemplateChild<Entry>,
+        #[template_child]
+        pub flight_rules: TemplateChild<DropDown>,
+        #[template_child]
+        pub winds: TemplateChild<Entry>,
+        #[template_child]
+        pub btn_wind_grid: TemplateChild<Button>,
+        #[template_child]
+        pub route: TemplateChild<Entry>,
+        #[template_child]
+        pub btn_copy_route: TemplateChild<Button>,
+        #[template_child]
+        pub author: TemplateChild<Entry>,
+        #[template_child]
+        pub departure_time: TemplateChild<Entry>,
+        #[template_child]
+        pub remarks: TemplateChild<Entry>,
 
         #[template_child]
         pub plan_tree: TemplateChild<ColumnView>,
@@ -101,8 +131,21 @@ mod imp {
         pub col_time: TemplateChild<ColumnViewColumn>,
         #[template_child]
         pub col_speed: TemplateChild<ColumnViewColumn>,
+        #[template_child]
+        pub col_wind: TemplateChild<ColumnViewColumn>,
+        #[template_child]
+        pub col_wca: TemplateChild<ColumnViewColumn>,
+        #[template_child]
+        pub col_gs: TemplateChild<ColumnViewColumn>,
+        #[template_child]
+        pub col_fuel: TemplateChild<ColumnViewColumn>,
+        #[template_child]
+        pub col_fuel_rem: TemplateChild<ColumnViewColumn>,
 
         pub plan: Rc<RefCell<Plan>>,
+        history: RefCell<PlanHistory>,
+        // Bumped by every change to the plan, so a replan finishing after it is dropped
+        replan_generation: Cell<u32>,
 
         popover: RefCell<Option<PopoverMenu>>,
         page: RefCell<Option<TabPage>>,
@@ -120,15 +163,252 @@ mod imp {
             {   // Block limits scope of mutable borrow of the plan
                 let mut plan = self.plan.borrow_mut();
                 plan.add_sector(Sector::new());
-                plan.set_aircraft(&get_hangar().imp().get_default_aircraft());
+                plan.set_aircraft(&get_hangar().get_default_aircraft());
                 plan.set_dirty(false);
             }
+            self.history.borrow_mut().clear();
             self.refresh(None);
         }
 
         pub(crate) fn set_plan(&self, plan: Plan) {
             self.plan.replace(plan);
+            self.history.borrow_mut().clear();
+            self.new_replan_generation();
+            self.show_winds();
+            self.show_details();
+            self.refresh(None);
+        }
+
+        // Remember the plan as it is, so the edit about to be made to it can be undone
+        fn record_edit(&self, description: &str) {
+            self.history.borrow_mut().record(description, &self.plan.borrow());
+            self.new_replan_generation();
+        }
+
+        fn new_replan_generation(&self) -> u32 {
+            let generation = self.replan_generation.get().wrapping_add(1);
+            self.replan_generation.set(generation);
+            generation
+        }
+
+        pub(crate) fn undo(&self) {
+            self.new_replan_generation();
+            let description = self.history.borrow_mut().undo(&mut self.plan.borrow_mut());
+            if let Some(description) = description {
+                self.history_changed(gettext("Undo {edit}").replace("{edit}", &description));
+            }
+        }
+
+        pub(crate) fn redo(&self) {
+            self.new_replan_generation();
+            let description = self.history.borrow_mut().redo(&mut self.plan.borrow_mut());
+            if let Some(description) = description {
+                self.history_changed(gettext("Redo {edit}").replace("{edit}", &description));
+            }
+        }
+
+        fn history_changed(&self, status: String) {
+            self.show_winds();
+            self.show_details();
+            self.refresh(None);
+            if let Some(map_view) = get_world_map_view(self.plan_window.deref()) {
+                map_view.imp().set_plan(self.plan.clone());
+            }
+            event::manager().notify_listeners(Event::PlanChanged);
+            event::manager().notify_listeners(Event::StatusChange(status));
+        }
+
+        fn show_details(&self) {
+            let (max_altitude, flight_rules, author, remarks, departure_time) = {
+                let plan = self.plan.borrow();
+                (*plan.get_max_altitude(), plan.get_flight_rules(), plan.get_author().to_string(), plan.get_remarks().to_string(), *plan.get_departure_time())
+            };
+            match max_altitude {
+                Some(max_altitude) => {
+                    self.max_alt.set_text(&max_altitude.to_string());
+                    self.btn_max_alt.set_active(true);
+                }
+                None => self.btn_max_alt.set_active(false),
+            }
+            self.flight_rules.set_selected(match flight_rules {
+                FlightRules::Ifr => 0,
+                FlightRules::Vfr => 1,
+            });
+            self.show_aircraft();
+            self.author.set_text(&author);
+            self.remarks.set_text(&remarks);
+            self.departure_time.remove_css_class("error");
+            self.departure_time.set_text(&departure_time.map_or("".to_string(), |t| t.format(DEPARTURE_TIME_FORMAT).to_string()));
+        }
+
+        fn show_aircraft(&self) {
+            let name = self.plan.borrow().get_aircraft().as_ref().map(|a| a.get_name().to_string());
+            if let Some(name) = name {
+                let hangar = get_hangar();
+                for (i, (_k, a)) in hangar.get_all().read().expect("could not get hangar lock").iter().enumerate() {
+                    if a.get_name() == name {
+                        self.aircraft_combo.set_selected(i as u32);
+                    }
+                }
+            }
+        }
+
+        fn set_max_altitude(&self, max_altitude: Option<i32>) {
+            let mut plan = self.plan.borrow_mut();
+            if *plan.get_max_altitude() != max_altitude {
+                self.history.borrow_mut().record_field(&gettext("Change maximum altitude"), PlanField::MaxAltitude(*plan.get_max_altitude()));
+                plan.set_max_altitude(max_altitude);
+            }
+        }
+
+        // The cruise levels follow the flight rules, so the elevations are worked out again
+        fn set_flight_rules(&self, flight_rules: FlightRules) {
+            if self.plan.borrow().get_flight_rules() == flight_rules {
+                return;
+            }
+            self.record_edit(&gettext("Change flight rules"));
+            let mut plan = self.plan.borrow_mut();
+            plan.set_flight_rules(flight_rules);
+            planner::recalc_plan_elevations(plan.deref_mut());
+            drop(plan);
             self.refresh(None);
+            event::manager().notify_listeners(Event::PlanChanged);
+        }
+
+        fn set_departure_time(&self, departure_time: Option<DateTime<Utc>>) {
+            let mut plan = self.plan.borrow_mut();
+            if *plan.get_departure_time() != departure_time {
+                self.history.borrow_mut().record_field(&gettext("Change departure time"), PlanField::DepartureTime(*plan.get_departure_time()));
+                plan.set_departure_time(departure_time);
+            }
+        }
+
+        fn set_departure_time_from_entry(&self) {
+            let text = self.departure_time.text();
+            if text.trim().is_empty() {
+                self.set_departure_time(None);
+                return;
+            }
+            match NaiveDateTime::parse_from_str(text.trim(), DEPARTURE_TIME_FORMAT) {
+                Ok(time) => self.set_departure_time(Some(time.and_utc())),
+                Err(_) => {
+                    self.departure_time.add_css_class("error");
+                    let msg = gettext("Departure time should be given as YYYY-MM-DD HH:MM");
+                    event::manager().notify_listeners(Event::StatusChange(msg));
+                }
+            }
+        }
+
+        fn show_winds(&self) {
+            self.winds.remove_css_class("error");
+            match self.plan.borrow().get_winds() {
+                Some(WindsAloft::Profile(profile)) => {
+                    self.winds.set_text(&profile.to_string());
+                    self.winds.set_placeholder_text(Some(&gettext("Calm")));
+                }
+                Some(WindsAloft::Grid { path, .. }) => {
+                    // The grid can't be edited here, so show where it came from instead
+                    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                    self.winds.set_text("");
+                    self.winds.set_placeholder_text(Some(&name));
+                }
+                None => {
+                    self.winds.set_text("");
+                    self.winds.set_placeholder_text(Some(&gettext("Calm")));
+                }
+            }
+        }
+
+        fn set_winds(&self, winds: Option<WindsAloft>) {
+            let mut plan = self.plan.borrow_mut();
+            if *plan.get_winds() != winds {
+                self.history.borrow_mut().record_field(&gettext("Change winds"), PlanField::Winds(plan.get_winds().clone()));
+                plan.set_winds(winds);
+            }
+        }
+
+        fn set_winds_from_entry(&self) {
+            let text = self.winds.text();
+            if text.trim().is_empty() {
+                self.set_winds(None);
+            } else {
+                match WindProfile::parse(text.trim()) {
+                    Ok(profile) => self.set_winds(Some(WindsAloft::Profile(profile))),
+                    Err(msg) => {
+                        self.winds.add_css_class("error");
+                        event::manager().notify_listeners(Event::StatusChange(msg));
+                        return;
+                    }
+                }
+            }
+            self.show_winds();
+            self.refresh(None);
+        }
+
+        fn load_wind_grid(&self) {
+            let dialog = FileDialog::new();
+            dialog.set_modal(true);
+            dialog.set_title(&gettext("Load Wind Grid"));
+
+            let win = self.obj().root().and_downcast::<Window>();
+            dialog.open(win.as_ref(), Some(&Cancellable::default()),
+                        clone!(#[weak(rename_to = view)] self, move | result: Result<File, _>| {
+                if let Ok(file) = result {
+                    if let Some(path) = file.path() {
+                        match read_wind_grid(&path) {
+                            Ok(winds) => {
+                                view.record_edit(&gettext("Load wind grid"));
+                                view.plan.borrow_mut().set_winds(Some(winds));
+                                view.show_winds();
+                                view.refresh(None);
+                            }
+                            Err(msg) => {
+                                let buttons = vec![gettext("Ok")];
+                                let alert = AlertDialog::builder()
+                                    .message(gettext("Failed to load wind grid: {}").replace("{}", &msg))
+                                    .buttons(buttons)
+                                    .build();
+                                alert.show(view.obj().root().and_downcast::<Window>().as_ref());
+                            }
+                        }
+                    }
+                }
+            }));
+        }
+
+        fn set_route_from_entry(&self) {
+            let text = self.route.text();
+            if text.trim().is_empty() {
+                return;
+            }
+            match parse_route(text.trim()) {
+                Ok(route) => {
+                    self.record_edit(&gettext("Enter route"));
+                    let altitude = route.get_altitude();
+                    {   // Block limits scope of mutable borrow of the plan
+                        let mut plan = self.plan.borrow_mut();
+                        if altitude.is_some() {
+                            plan.set_max_altitude(altitude);
+                        }
+                        plan.get_sectors_mut().clear();
+                        plan.add_sector(route.into_sector());
+                        planner::recalc_plan_elevations(plan.deref_mut());
+                    }
+                    self.show_details();
+                    self.refresh(None);
+                    event::manager().notify_listeners(Event::PlanChanged);
+                }
+                Err(msg) => {
+                    self.route.add_css_class("error");
+                    event::manager().notify_listeners(Event::StatusChange(msg));
+                }
+            }
+        }
+
+        fn copy_route(&self) {
+            let route = make_route_string(&self.plan.borrow());
+            self.obj().clipboard().set_text(&route);
+            event::manager().notify_listeners(Event::StatusChange(gettext("Copied route {route}").replace("{route}", &route)));
         }
 
         pub(crate) fn get_plan(&self) -> Rc<RefCell<Plan>> {
@@ -233,6 +513,14 @@ mod imp {
                 self.plan_tree.scroll_to(sel, None, ListScrollFlags::SELECT, None);
             }
 
+            let mut warnings = plan.borrow().get_endurance_warnings();
+            warnings.extend(plan.borrow().get_terrain_warnings());
+            warnings.extend(plan.borrow().get_constraint_warnings());
+            warnings.extend(plan.borrow().get_cruise_level_warnings());
+            if !warnings.is_empty() {
+                event::manager().notify_listeners(Event::StatusChange(warnings.join("; ")));
+            }
+
         }
 
         fn make_plan(&self) {
@@ -242,6 +530,7 @@ mod imp {
             }
 
             let sector_count = plan.get_sectors().len();
+            let generation = self.new_replan_generation();
             let (tx, rx) = async_channel::bounded::<(usize, Vec<Waypoint>)>(sector_count);
 
             // Collect the sectors to replan and spawn a thread to do the replanning, sending the new waypoints back through the channel when done.  This allows the UI to remain responsive while replanning is happening.
@@ -254,6 +543,7 @@ mod imp {
             for sector in plan.get_sectors_mut().iter_mut() {
                 sector.borrow_mut().remove_all_waypoints();
             }
+            plan.set_settings(Some(Planner::new().get_settings()));
             planner::recalc_plan_elevations(plan.deref_mut());
 
             drop(plan);
@@ -297,6 +587,10 @@ mod imp {
 
             gtk::glib::MainContext::default().spawn_local(gtk::glib::clone!(#[weak(rename_to = view)] self, async move {
                 while let Ok((i, new_sector)) = rx.recv().await {
+                    // The plan was changed, undone or replanned again while this was running
+                    if view.replan_generation.get() != generation {
+                        return;
+                    }
                     // Check the sector count still matches
                     let mut plan = view.plan.borrow_mut();
                     let sectors = plan.get_sectors_mut();
@@ -370,14 +664,13 @@ mod imp {
 
         pub fn add_airport_to_plan(&self, loc: Arc<Airport>) {
             let mut added = false;
+            self.record_edit(&gettext("Add airport"));
+            let mut plan = self.plan.borrow_mut();
             // make sure we have 1 sector
-            let empty = self.plan.borrow().get_sectors().is_empty();
-            if empty {
-                self.new_sector();
+            if plan.get_sectors().is_empty() {
+                plan.add_sector(Sector::new());
             }
 
-            let mut plan = self.plan.borrow_mut();
-
             // See if a sector is selected
             let sector = match self.get_selected_path() {
                 Some((tree_path, _)) => {
@@ -407,6 +700,7 @@ mod imp {
         }
 
         pub fn add_waypoint_to_plan(&self, waypoint: Waypoint) {
+            self.record_edit(&gettext("Add waypoint"));
             let mut plan = self.plan.borrow_mut();
             match self.get_selected_path() {
                 Some((tree_path, _)) => {
@@ -440,6 +734,7 @@ mod imp {
         fn new_sector(&self) {
             let mut prev_airport_id = "".to_string();
             let mut prev = false;
+            self.record_edit(&gettext("New sector"));
             let mut plan = self.plan.borrow_mut();
 
             if let Some(prev_sector) = plan.get_sectors().last() {
@@ -487,6 +782,7 @@ mod imp {
                         new_sector.add_waypoint_optimised(wp.clone());
                     }
                 }
+                self.history.borrow_mut().record(&gettext("Add return sector"), &plan);
                 plan.add_sector(new_sector);
             }
 
@@ -502,19 +798,20 @@ mod imp {
 
             if let Some((tree_path, sel_pos)) = self.get_selected_path() {
                 if tree_path.len() > 1 {
-                    let mut sector = plan.get_sectors_mut()[tree_path[0] as usize].borrow_mut();
+                    let sector = plan.get_sectors()[tree_path[0] as usize].clone();
                     // Only if a waypoint.  index > 0 and < waypoint count
                     let mut wp_index = tree_path[1] as usize;
-                    if sector.get_start().is_some() {
+                    if sector.borrow().get_start().is_some() {
                         if wp_index == 0 {
                             return;
                         }
                         wp_index -= 1;
                     }
                     if wp_index > 0
-                        && wp_index < sector.get_waypoint_count()
+                        && wp_index < sector.borrow().get_waypoint_count()
                     {
-                        sector.move_waypoint_up(wp_index);
+                        self.history.borrow_mut().record(&gettext("Move waypoint"), &plan);
+                        sector.borrow_mut().move_waypoint_up(wp_index);
                         new_selection = Some(sel_pos - 1);
                     } else {
                         return;
@@ -522,6 +819,7 @@ mod imp {
                 } else {
                     let sector_index = tree_path[0];
                     if sector_index > 0 {
+                        self.history.borrow_mut().record(&gettext("Move sector"), &plan);
                         plan.move_sector_up(sector_index as usize);
                         new_selection = None;
                     } else {
@@ -541,18 +839,19 @@ mod imp {
 
             if let Some((tree_path, sel_pos)) = self.get_selected_path() {
                 if tree_path.len() > 1 {
-                    let mut sector = plan.get_sectors_mut()[tree_path[0] as usize].borrow_mut();
+                    let sector = plan.get_sectors()[tree_path[0] as usize].clone();
                     // Only if a waypoint.  index > 0 and < waypoint count
                     let mut wp_index = tree_path[1] as usize;
-                    if sector.get_start().is_some() {
+                    if sector.borrow().get_start().is_some() {
                         if wp_index == 0 {
                             return;
                         }
                         wp_index -= 1;
                     }
-                    if wp_index + 1 < sector.get_waypoint_count()
+                    if wp_index + 1 < sector.borrow().get_waypoint_count()
                     {
-                        sector.move_waypoint_down(wp_index);
+                        self.history.borrow_mut().record(&gettext("Move waypoint"), &plan);
+                        sector.borrow_mut().move_waypoint_down(wp_index);
                         new_selection = Some(sel_pos + 1);
                     } else {
                         return;
@@ -560,6 +859,7 @@ mod imp {
                 } else {
                     let sector_index = tree_path[0];
                     if sector_index < (plan.get_sectors().len() - 1) as u32 {
+                        self.history.borrow_mut().record(&gettext("Move sector"), &plan);
                         plan.move_sector_down(sector_index as usize);
                         new_selection = None;
                     } else {
@@ -577,27 +877,31 @@ mod imp {
             let mut plan = self.plan.borrow_mut();
 
             if tree_path.len() > 1 {
-                let mut sector = plan.get_sectors_mut()[tree_path[0] as usize].borrow_mut();
+                let sector = plan.get_sectors()[tree_path[0] as usize].clone();
                 // Only if a waypoint.  index > 0 and < waypoint count
                 let mut wp_index = tree_path[1] as usize;
 
                 // If we have a start and the index is 0, then we are removing the start
-                if sector.get_start().is_some() && wp_index == 0 {
-                    sector.set_start(None);
+                if sector.borrow().get_start().is_some() && wp_index == 0 {
+                    self.history.borrow_mut().record(&gettext("Remove airport"), &plan);
+                    sector.borrow_mut().set_start(None);
                 } else {
                     // Decrement the index if we have a start
-                    if sector.get_start().is_some() {
+                    if sector.borrow().get_start().is_some() {
                         wp_index -= 1;
                     }
-                    if wp_index + 1 < sector.get_waypoint_count() {
-                        sector.remove_waypoint(wp_index);
-                    } else if wp_index == sector.get_waypoint_count() && sector.get_end().is_some() {
-                        sector.set_end(None);
+                    if wp_index + 1 < sector.borrow().get_waypoint_count() {
+                        self.history.borrow_mut().record(&gettext("Remove waypoint"), &plan);
+                        sector.borrow_mut().remove_waypoint(wp_index);
+                    } else if wp_index == sector.borrow().get_waypoint_count() && sector.borrow().get_end().is_some() {
+                        self.history.borrow_mut().record(&gettext("Remove airport"), &plan);
+                        sector.borrow_mut().set_end(None);
                     }
                 }
             } else {
                 let sector_index = tree_path[0];
                 if sector_index < plan.get_sectors().len() as u32 {
+                    self.history.borrow_mut().record(&gettext("Remove sector"), &plan);
                     plan.remove_sector_at(sector_index as usize);
                 }
             }
@@ -658,26 +962,193 @@ mod imp {
             None
         }
 
+        // Use an end of the runway at the tree path for the departure or arrival at its airport
+        fn use_runway_at_tree_path(&self, tree_path: &[u32], opposite: bool) {
+            if tree_path.len() < 3 {
+                return;
+            }
+            let sector = self.plan.borrow().get_sectors()[tree_path[0] as usize].clone();
+            let (airport, departure) = {
+                let sector = sector.borrow();
+                let wp_index = tree_path[1] as usize;
+                match (sector.get_start(), sector.get_end()) {
+                    (Some(start), _) if wp_index == 0 => (start.get_airport(), true),
+                    (start, Some(end)) if wp_index == sector.get_waypoint_count() + usize::from(start.is_some()) => (end.get_airport(), false),
+                    _ => return,
+                }
+            };
+            let runway = airport.get_runways().read().expect("Can't get airport lock").get(tree_path[2] as usize).cloned();
+            let number = runway.and_then(|r| if opposite { r.opposite_number() } else { Some(r.number().to_string()) });
+            if let Some(number) = number {
+                self.record_edit(&gettext("Choose runway"));
+                if departure {
+                    sector.borrow_mut().set_departure_runway(Some(number));
+                } else {
+                    sector.borrow_mut().set_arrival_runway(Some(number));
+                }
+                self.plan_updated();
+                event::manager().notify_listeners(Event::PlanChanged);
+            }
+        }
+
+        // Choose a SID for the departure, or a STAR or approach for the arrival, of the sector at the tree path
+        fn choose_procedure_at_tree_path(&self, tree_path: &[u32], procedure_type: ProcedureType) {
+            let Some(sector_index) = tree_path.first() else {
+                return;
+            };
+            let sector = self.plan.borrow().get_sectors()[*sector_index as usize].clone();
+            let (airport, current, runway) = {
+                let sector = sector.borrow();
+                match procedure_type {
+                    ProcedureType::Sid => (sector.get_start(), sector.get_sid().cloned(), sector.get_departure_runway().cloned()),
+                    ProcedureType::Star => (sector.get_end(), sector.get_star().cloned(), sector.get_arrival_runway().cloned()),
+                    ProcedureType::Approach => (sector.get_end(), sector.get_approach().cloned(), sector.get_arrival_runway().cloned()),
+                }
+            };
+            let Some(airport) = airport.map(|wp| wp.get_airport()) else {
+                return;
+            };
+            if current.is_none() && airport.get_procedures_of(procedure_type).is_empty() {
+                event::manager().notify_listeners(Event::StatusChange(
+                    gettext("There are no published procedures for {airport}").replace("{airport}", airport.get_id())));
+                return;
+            }
+
+            let dialog = ProcedureDialog::new();
+            dialog.set_modal(true);
+            dialog.set_transient_for(self.obj().root().and_downcast::<Window>().as_ref());
+            let title = match procedure_type {
+                ProcedureType::Sid => gettext("Departure procedure"),
+                ProcedureType::Star => gettext("Arrival procedure"),
+                ProcedureType::Approach => gettext("Approach"),
+            };
+            dialog.set_title(Some(&title));
+            dialog.choose(airport, procedure_type, current.as_ref(), runway.as_ref(),
+                          clone!(#[weak(rename_to = view)] self, move |selection| {
+                view.record_edit(&gettext("Choose procedure"));
+                match procedure_type {
+                    ProcedureType::Sid => sector.borrow_mut().set_sid(selection),
+                    ProcedureType::Star => sector.borrow_mut().set_star(selection),
+                    ProcedureType::Approach => sector.borrow_mut().set_approach(selection),
+                }
+                view.plan_updated();
+                event::manager().notify_listeners(Event::PlanChanged);
+            }));
+            dialog.present();
+        }
+
+        // Set the altitude and speed constraints at the waypoint at the tree path
+        fn edit_constraints_at_tree_path(&self, tree_path: &[u32]) {
+            if tree_path.len() != 2 {
+                return;
+            }
+            let sector = self.plan.borrow().get_sectors()[tree_path[0] as usize].clone();
+            let (wp_index, constraints) = {
+                let sector = sector.borrow();
+                let Some(wp_index) = (tree_path[1] as usize).checked_sub(usize::from(sector.get_start().is_some())) else {
+                    return;
+                };
+                match sector.get_waypoints().get(wp_index) {
+                    Some(wp) if !matches!(wp, Waypoint::Toc { .. } | Waypoint::Bod { .. } | Waypoint::Airport { .. }) =>
+                        (wp_index, wp.get_constraints()),
+                    _ => return,
+                }
+            };
+
+            let dialog = ConstraintDialog::new();
+            dialog.set_modal(true);
+            dialog.set_transient_for(self.obj().root().and_downcast::<Window>().as_ref());
+            dialog.edit(constraints, clone!(#[weak(rename_to = view)] self, move |constraints| {
+                view.record_edit(&gettext("Set constraints"));
+                if let Some(wp) = sector.borrow().get_waypoints().get(wp_index) {
+                    wp.set_constraints(constraints);
+                }
+                sector.borrow_mut().set_dirty(true);
+                planner::recalc_plan_elevations(view.plan.borrow_mut().deref_mut());
+                view.refresh(None);
+                event::manager().notify_listeners(Event::PlanChanged);
+            }));
+            dialog.present();
+        }
+
+        // Set the cruise level and step climbs of the sector at the tree path
+        fn edit_cruise_level_at_tree_path(&self, tree_path: &[u32]) {
+            let Some(sector_index) = tree_path.first() else {
+                return;
+            };
+            let flight_rules = self.plan.borrow().get_flight_rules();
+            let Some(sector) = self.plan.borrow().get_sectors().get(*sector_index as usize).cloned() else {
+                return;
+            };
+            let (cruise_level, step_climbs, levels) = {
+                let sector = sector.borrow();
+                let levels = match (sector.get_start(), sector.get_magnetic_track()) {
+                    (Some(start), Some(track)) => LevelRegions::from_preferences()
+                        .get_rule(start.get_id())
+                        .get_levels(track, flight_rules),
+                    _ => Vec::new(),
+                };
+                (sector.get_cruise_level(), sector.get_step_climbs().clone(), levels)
+            };
+
+            let dialog = CruiseLevelDialog::new();
+            dialog.set_modal(true);
+            dialog.set_transient_for(self.obj().root().and_downcast::<Window>().as_ref());
+            dialog.edit(cruise_level, &step_climbs, &levels, clone!(#[weak(rename_to = view)] self, move |cruise_level, step_climbs| {
+                view.record_edit(&gettext("Set cruise level"));
+                sector.borrow_mut().set_cruise_level(cruise_level);
+                sector.borrow_mut().set_step_climbs(step_climbs);
+                planner::recalc_plan_elevations(view.plan.borrow_mut().deref_mut());
+                view.refresh(None);
+                event::manager().notify_listeners(Event::PlanChanged);
+            }));
+            dialog.present();
+        }
+
+        // Choose the runways at every airport in the plan for the surface wind there
+        fn select_runways_for_wind(&self) {
+            let winds = self.plan.borrow().get_winds().clone();
+            let Some(winds) = winds else {
+                event::manager().notify_listeners(Event::StatusChange(gettext("Enter the winds to choose runways for them")));
+                return;
+            };
+            self.record_edit(&gettext("Choose runways"));
+            for sector in self.plan.borrow().get_sectors() {
+                let (start, end) = (sector.borrow().get_start(), sector.borrow().get_end());
+                if let Some(start) = start {
+                    sector.borrow_mut().select_departure_runway(&winds.wind_at(start.get_loc(), start.get_elevation()));
+                }
+                if let Some(end) = end {
+                    sector.borrow_mut().select_arrival_runway(&winds.wind_at(end.get_loc(), end.get_elevation()));
+                }
+            }
+            self.plan_updated();
+            event::manager().notify_listeners(Event::PlanChanged);
+        }
+
         fn setup_aircraft_combo(&self) {
             self.aircraft_combo.set_factory(Some(&build_column_factory(|label: Label, string_object: &StringObject| {
                 label.set_label(string_object.string().as_ref());
                 label.set_xalign(0.0);
             })));
 
-            let selection_model = SingleSelection::new(Some(get_hangar().clone()));
+            let selection_model = SingleSelection::new(Some(HangarListModel::new()));
             self.aircraft_combo.set_model(Some(&selection_model));
 
             self.aircraft_combo.connect_selected_notify(clone!(#[weak(rename_to = view)] self, move | combo | {
                 // Get the selection
                 let index = combo.selected();
-                if let Some(aircraft) = get_hangar().imp().aircraft_at(index) {
+                if let Some(aircraft) = get_hangar().aircraft_at(index) {
                     let mut plan1 = view.plan.borrow_mut();
-                    plan1.set_aircraft(&Some(aircraft));
+                    if plan1.get_aircraft().as_ref().map(|a| a.get_name()) != Some(aircraft.get_name()) {
+                        view.history.borrow_mut().record(&gettext("Change aircraft"), &plan1);
+                        plan1.set_aircraft(&Some(aircraft));
+                    }
                 }
             }));
 
             // set the selection initially to the default
-            let hangar = get_hangar().imp();
+            let hangar = get_hangar();
             for (i, (_k, a)) in hangar.get_all().read().expect("could not get hangar lock").iter().enumerate() {
                 if *a.is_default() {
                     self.aircraft_combo.set_selected(i as u32);
@@ -731,12 +1202,24 @@ mod imp {
                 } else if item.is::<WaypointObject>() {
                     let waypoint = item.downcast_ref::<WaypointObject>().unwrap();
                     let cell = waypoint.imp().waypoint();
-                    label.set_label(cell.borrow().as_ref().unwrap().get_name());
+                    let name = cell.borrow().as_ref().unwrap().get_name().to_string();
+                    match waypoint.imp().procedures() {
+                        Some(procedures) => label.set_label(&gettext("{name} via {procedures}")
+                            .replace("{name}", &name)
+                            .replace("{procedures}", &procedures)),
+                        None => label.set_label(&name),
+                    }
                     waypoint.imp().set_ui(Some(label.clone()));
                 } else if item.is::<RunwayObject>() {
                     let runway = item.downcast_ref::<RunwayObject>().unwrap();
                     let cell = runway.imp().runway();
-                    label.set_label(cell.borrow().as_ref().unwrap().number_pair().as_str());
+                    let runways = cell.borrow().as_ref().unwrap().number_pair();
+                    match runway.imp().in_use() {
+                        Some(in_use) => label.set_label(&gettext("{runways} (using {runway})")
+                            .replace("{runways}", &runways)
+                            .replace("{runway}", &in_use)),
+                        None => label.set_label(runways.as_str()),
+                    }
                 }
                 label.set_xalign(0.0);
 
@@ -902,9 +1385,109 @@ mod imp {
                 }
                 label.set_xalign(0.0);
             }))));
+            self.col_wind.set_factory(Some(&build_column_factory(clone!(#[weak(rename_to = view)] self, move|label: Label, row: &TreeListRow| {
+                // get the item from the tree list row
+                let item = row.item().unwrap();
+                label.set_label("");
+                if item.is::<WaypointObject>() {
+                    let waypoint = item.downcast_ref::<WaypointObject>().unwrap();
+                    let cell = waypoint.imp().waypoint();
+                    let plan = view.plan.borrow();
+                    if plan.get_winds().is_some() {
+                        let wind = plan.get_leg_wind_to(cell.borrow().as_ref().unwrap());
+                        let value = format!("{:03.0}/{:.0}", wind.get_direction(), wind.get_speed());
+                        label.set_label(value.as_str());
+                    }
+                }
+                label.set_xalign(0.0);
+            }))));
+            self.col_wca.set_factory(Some(&build_column_factory(clone!(#[weak(rename_to = view)] self, move|label: Label, row: &TreeListRow| {
+                // get the item from the tree list row
+                let item = row.item().unwrap();
+                if item.is::<SectorObject>() {
+                    label.set_label("");
+                } else if item.is::<WaypointObject>() {
+                    let waypoint = item.downcast_ref::<WaypointObject>().unwrap();
+                    let cell = waypoint.imp().waypoint();
+                    let correction = view.plan.borrow().get_wind_correction_to(cell.borrow().as_ref().unwrap());
+                    let value = &(format!("{:+4.0}", correction.get_wca()));
+                    label.set_label(value.as_str());
+                }
+                label.set_xalign(1.0);
+            }))));
+            self.col_gs.set_factory(Some(&build_column_factory(clone!(#[weak(rename_to = view)] self, move|label: Label, row: &TreeListRow| {
+                // get the item from the tree list row
+                let item = row.item().unwrap();
+                if item.is::<SectorObject>() {
+                    label.set_label("");
+                } else if item.is::<WaypointObject>() {
+                    let waypoint = item.downcast_ref::<WaypointObject>().unwrap();
+                    let cell = waypoint.imp().waypoint();
+                    let value = view.plan.borrow().get_ground_speed_to_as_string(cell.borrow().as_ref().unwrap());
+                    label.set_label(value.as_str());
+                }
+                label.set_xalign(0.0);
+            }))));
+
+            self.col_fuel.set_factory(Some(&build_column_factory(clone!(#[weak(rename_to = view)] self, move|label: Label, row: &TreeListRow| {
+                // get the item from the tree list row
+                let item = row.item().unwrap();
+                let plan = view.plan.borrow();
+                label.set_label("");
+                label.set_tooltip_text(None);
+                label.remove_css_class("error");
+                if item.is::<SectorObject>() {
+                    let sector = item.downcast_ref::<SectorObject>().unwrap();
+                    let cell = sector.imp().sector();
+                    if let Some(fuel_plan) = cell.borrow().get_fuel_plan(&plan) {
+                        label.set_label(format!("{:.1}", fuel_plan.get_trip()).as_str());
+                        label.set_tooltip_text(Some(&fuel_breakdown(&fuel_plan)));
+                        if fuel_plan.exceeds_endurance() {
+                            label.add_css_class("error");
+                        }
+                    }
+                } else if item.is::<WaypointObject>() {
+                    let waypoint = item.downcast_ref::<WaypointObject>().unwrap();
+                    let cell = waypoint.imp().waypoint();
+                    let burn = plan.get_fuel_burn_to(cell.borrow().as_ref().unwrap());
+                    if burn > 0.0 {
+                        label.set_label(format!("{:.1}", burn).as_str());
+                    }
+                }
+                label.set_xalign(1.0);
+            }))));
+            self.col_fuel_rem.set_factory(Some(&build_column_factory(clone!(#[weak(rename_to = view)] self, move|label: Label, row: &TreeListRow| {
+                // get the item from the tree list row
+                let item = row.item().unwrap();
+                let plan = view.plan.borrow();
+                label.set_label("");
+                label.remove_css_class("error");
+                if item.is::<SectorObject>() {
+                    let sector = item.downcast_ref::<SectorObject>().unwrap();
+                    let cell = sector.imp().sector();
+                    if let Some(fuel_plan) = cell.borrow().get_fuel_plan(&plan) {
+                        label.set_label(format!("{:.1}", fuel_plan.get_extra()).as_str());
+                        if fuel_plan.exceeds_endurance() {
+                            label.add_css_class("error");
+                        }
+                    }
+                } else if item.is::<WaypointObject>() {
+                    let waypoint = item.downcast_ref::<WaypointObject>().unwrap();
+                    let cell = waypoint.imp().waypoint();
+                    if let Some(remaining) = plan.get_fuel_remaining_at(cell.borrow().as_ref().unwrap()) {
+                        label.set_label(format!("{:.1}", remaining).as_str());
+                        if remaining < 0.0 {
+                            label.add_css_class("error");
+                        }
+                    }
+                }
+                label.set_xalign(1.0);
+            }))));
 
             self.btn_make_plan
                 .connect_clicked(clone!(#[weak(rename_to = view)] self, move |_search| {
+                    // Replanning after an edit is part of that edit, only an explicit replan is undone on its own
+                    view.record_edit(&gettext("Make plan"));
                     view.make_plan();
                     view.refresh(None);
                 }));
@@ -967,43 +1550,93 @@ mod imp {
 
             self.btn_max_alt
                 .connect_toggled(clone!(#[weak(rename_to = view)] self, move | button | {
-                let mut plan = view.plan.borrow_mut();
                     if button.is_active() {
-                        match view.max_alt.text().parse::<i32>() {
-                            Ok(n) => {
-                                plan.set_max_altitude(Some(n));
-                            }
-                            _ => {
-                                plan.set_max_altitude(None);
-                            }
-                        }
+                        view.set_max_altitude(view.max_alt.text().parse::<i32>().ok());
                         view.max_alt.set_sensitive(true);
                     } else {
-                        plan.set_max_altitude(None);
+                        view.set_max_altitude(None);
                         view.max_alt.set_sensitive(false);
                     }
                 }));
 
             self.max_alt.connect_changed(clone!(#[weak(rename_to = view)] self, move| editable | {
                 if view.btn_max_alt.is_active() {
-                    let mut plan = view.plan.borrow_mut();
-                    match editable.text().parse::<i32>() {
-                        Ok(n) => {
-                            plan.set_max_altitude(Some(n));
-                        }
-                        _ => {
-                            plan.set_max_altitude(None);
-                        }
-                    }
+                    view.set_max_altitude(editable.text().parse::<i32>().ok());
                 }
             }));
 
+            self.flight_rules.connect_selected_notify(clone!(#[weak(rename_to = view)] self, move| list | {
+                view.set_flight_rules(if list.selected() == 1 { FlightRules::Vfr } else { FlightRules::Ifr });
+            }));
+
+            self.winds.connect_activate(clone!(#[weak(rename_to = view)] self, move| _entry | {
+                view.set_winds_from_entry();
+            }));
+
+            self.winds.connect_changed(clone!(#[weak(rename_to = view)] self, move| _entry | {
+                view.winds.remove_css_class("error");
+            }));
+
+            self.btn_wind_grid
+                .connect_clicked(clone!(#[weak(rename_to = view)] self, move |_| {
+                    view.load_wind_grid();
+                }));
+
+            self.route.connect_activate(clone!(#[weak(rename_to = view)] self, move| _entry | {
+                view.set_route_from_entry();
+            }));
+
+            self.route.connect_changed(clone!(#[weak(rename_to = view)] self, move| _entry | {
+                view.route.remove_css_class("error");
+            }));
+
+            self.btn_copy_route
+                .connect_clicked(clone!(#[weak(rename_to = view)] self, move |_| {
+                    view.copy_route();
+                }));
+
+            self.author.connect_changed(clone!(#[weak(rename_to = view)] self, move| entry | {
+                let mut plan = view.plan.borrow_mut();
+                if plan.get_author() != entry.text() {
+                    view.history.borrow_mut().record_field(&gettext("Change author"), PlanField::Author(plan.get_author().to_string()));
+                    plan.set_author(&entry.text());
+                }
+            }));
+
+            self.remarks.connect_changed(clone!(#[weak(rename_to = view)] self, move| entry | {
+                let mut plan = view.plan.borrow_mut();
+                if plan.get_remarks() != entry.text() {
+                    view.history.borrow_mut().record_field(&gettext("Change remarks"), PlanField::Remarks(plan.get_remarks().to_string()));
+                    plan.set_remarks(&entry.text());
+                }
+            }));
+
+            self.departure_time.connect_activate(clone!(#[weak(rename_to = view)] self, move| _entry | {
+                view.set_departure_time_from_entry();
+            }));
+
+            self.departure_time.connect_changed(clone!(#[weak(rename_to = view)] self, move| _entry | {
+                view.departure_time.remove_css_class("error");
+            }));
+
             // Set up the popup menu
             let actions = SimpleActionGroup::new();
             self.plan_window
                 .get()
                 .insert_action_group("plan", Some(&actions));
 
+            let action = SimpleAction::new("undo", None);
+            action.connect_activate(clone!(#[weak(rename_to = view)] self, move |_action, _parameter| {
+                view.undo();
+            }));
+            actions.add_action(&action);
+
+            let action = SimpleAction::new("redo", None);
+            action.connect_activate(clone!(#[weak(rename_to = view)] self, move |_action, _parameter| {
+                view.redo();
+            }));
+            actions.add_action(&action);
+
             let action = SimpleAction::new("remove", None);
             action.connect_activate(clone!(#[weak(rename_to = view)] self, move |_action, _parameter| {
                 if let Some(tp) = view.context_tree_path.borrow().as_ref() {
@@ -1012,6 +1645,59 @@ mod imp {
             }));
             actions.add_action(&action);
 
+            let action = SimpleAction::new("use_runway", None);
+            action.connect_activate(clone!(#[weak(rename_to = view)] self, move |_action, _parameter| {
+                let tp = view.context_tree_path.borrow().clone();
+                if let Some(tp) = tp {
+                    view.use_runway_at_tree_path(&tp, false);
+                }
+            }));
+            actions.add_action(&action);
+
+            let action = SimpleAction::new("use_opposite_runway", None);
+            action.connect_activate(clone!(#[weak(rename_to = view)] self, move |_action, _parameter| {
+                let tp = view.context_tree_path.borrow().clone();
+                if let Some(tp) = tp {
+                    view.use_runway_at_tree_path(&tp, true);
+                }
+            }));
+            actions.add_action(&action);
+
+            for (name, procedure_type) in [("choose_sid", ProcedureType::Sid), ("choose_star", ProcedureType::Star), ("choose_approach", ProcedureType::Approach)] {
+                let action = SimpleAction::new(name, None);
+                action.connect_activate(clone!(#[weak(rename_to = view)] self, move |_action, _parameter| {
+                    let tp = view.context_tree_path.borrow().clone();
+                    if let Some(tp) = tp {
+                        view.choose_procedure_at_tree_path(&tp, procedure_type);
+                    }
+                }));
+                actions.add_action(&action);
+            }
+
+            let action = SimpleAction::new("edit_constraints", None);
+            action.connect_activate(clone!(#[weak(rename_to = view)] self, move |_action, _parameter| {
+                let tp = view.context_tree_path.borrow().clone();
+                if let Some(tp) = tp {
+                    view.edit_constraints_at_tree_path(&tp);
+                }
+            }));
+            actions.add_action(&action);
+
+            let action = SimpleAction::new("edit_cruise_level", None);
+            action.connect_activate(clone!(#[weak(rename_to = view)] self, move |_action, _parameter| {
+                let tp = view.context_tree_path.borrow().clone();
+                if let Some(tp) = tp {
+                    view.edit_cruise_level_at_tree_path(&tp);
+                }
+            }));
+            actions.add_action(&action);
+
+            let action = SimpleAction::new("select_runways", None);
+            action.connect_activate(clone!(#[weak(rename_to = view)] self, move |_action, _parameter| {
+                view.select_runways_for_wind();
+            }));
+            actions.add_action(&action);
+
             let action = SimpleAction::new("view", None);
             action.connect_activate(clone!(#[weak(rename_to = view)] self, move |_action, _parameter| {
                if let Some(tp) = view.context_tree_path.borrow().as_ref() {
@@ -1090,6 +1776,21 @@ mod imp {
         }
     }
 
+    fn fuel_breakdown(fuel_plan: &FuelPlan) -> String {
+        [
+            (gettext("Taxi"), fuel_plan.get_taxi()),
+            (gettext("Trip"), fuel_plan.get_trip()),
+            (gettext("Contingency"), fuel_plan.get_contingency()),
+            (gettext("Alternate"), fuel_plan.get_alternate()),
+            (gettext("Reserve"), fuel_plan.get_reserve()),
+            (gettext("Required"), fuel_plan.get_required()),
+            (gettext("Usable fuel"), fuel_plan.get_capacity()),
+        ].iter()
+            .map(|(name, fuel)| format!("{}: {:.1}", name, fuel))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
     impl BoxImpl for PlanView {}
 
     impl WidgetImpl for PlanView {}
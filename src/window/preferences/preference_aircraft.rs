@@ -38,6 +38,7 @@ mod imp {
     use gtk::subclass::widget::{CompositeTemplate, CompositeTemplateInitializingExt, WidgetImpl};
 
     use crate::hangar::hangar::get_hangar;
+    use crate::hangar::hangar_list_model::HangarListModel;
     use crate::window::preferences::preference_edit_aircraft::AircraftDialog;
     use crate::window::util::build_column_factory;
 
@@ -65,7 +66,7 @@ mod imp {
                 label.set_xalign(0.0);
             })));
 
-            let selection_model = SingleSelection::new(Some(get_hangar().clone()));
+            let selection_model = SingleSelection::new(Some(HangarListModel::new()));
             self.aircraft_list.set_model(Some(&selection_model));
         }
     }
@@ -117,7 +118,7 @@ mod imp {
                         let s = selection.selection();
                         if !s.is_empty() {
                             let index = s.nth(0);
-                            if let Some(aircraft) = get_hangar().imp().aircraft_at(index) {
+                            if let Some(aircraft) = get_hangar().aircraft_at(index) {
                                 dialog.imp().set_aircraft(Some(aircraft.get_name().to_string()));
                             }
                         }
@@ -147,8 +148,8 @@ mod imp {
                         let s = selection.selection();
                         if !s.is_empty() {
                             let index = s.nth(0);
-                            if let Some(aircraft) = get_hangar().imp().aircraft_at(index) {
-                                get_hangar().imp().remove(aircraft.get_name());
+                            if let Some(aircraft) = get_hangar().aircraft_at(index) {
+                                get_hangar().remove(aircraft.get_name());
                             }
                         }
                     }
@@ -160,8 +161,8 @@ mod imp {
                         let s = selection.selection();
                         if !s.is_empty() {
                             let index = s.nth(0);
-                            if let Some(aircraft) = get_hangar().imp().aircraft_at(index) {
-                                get_hangar().imp().set_default(aircraft.get_name());
+                            if let Some(aircraft) = get_hangar().aircraft_at(index) {
+                                get_hangar().set_default(aircraft.get_name());
                             }
                         }
                     }
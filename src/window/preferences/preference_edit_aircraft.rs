@@ -33,12 +33,12 @@ mod imp {
     use gtk::glib::clone;
     use gtk::glib::subclass::InitializingObject;
     use adw::prelude::{ButtonExt, EditableExt, GtkWindowExt, WidgetExt};
-    use adw::subclass::prelude::{CompositeTemplate, ObjectImpl, ObjectImplExt, ObjectSubclass, ObjectSubclassExt, ObjectSubclassIsExt, WidgetClassExt, WindowImpl};
+    use adw::subclass::prelude::{CompositeTemplate, ObjectImpl, ObjectImplExt, ObjectSubclass, ObjectSubclassExt, WidgetClassExt, WindowImpl};
     use gtk::subclass::widget::{CompositeTemplateInitializingExt, WidgetImpl};
 
     use crate::hangar::hangar::get_hangar;
-    use crate::model::aircraft::Aircraft;
-    use crate::window::preferences::preference_edit_aircraft::{number_from, validate_not_empty, validate_numeric};
+    use crate::model::aircraft::{Aircraft, AircraftFuel};
+    use crate::window::preferences::preference_edit_aircraft::{decimal_from, number_from, validate_decimal, validate_not_empty, validate_numeric};
 
     #[derive(CompositeTemplate, Default)]
     #[template(resource = "/com/shartrec/kelpie_planner/preference_aircraft_dialog.ui")]
@@ -58,6 +58,22 @@ mod imp {
         #[template_child]
         pub ac_sink_rate: TemplateChild<Entry>,
         #[template_child]
+        pub ac_fuel_capacity: TemplateChild<Entry>,
+        #[template_child]
+        pub ac_fuel_climb_burn: TemplateChild<Entry>,
+        #[template_child]
+        pub ac_fuel_cruise_burn: TemplateChild<Entry>,
+        #[template_child]
+        pub ac_fuel_descent_burn: TemplateChild<Entry>,
+        #[template_child]
+        pub ac_fuel_taxi: TemplateChild<Entry>,
+        #[template_child]
+        pub ac_fuel_contingency: TemplateChild<Entry>,
+        #[template_child]
+        pub ac_fuel_alternate: TemplateChild<Entry>,
+        #[template_child]
+        pub ac_fuel_reserve: TemplateChild<Entry>,
+        #[template_child]
         pub btn_ok: TemplateChild<Button>,
         #[template_child]
         pub btn_cancel: TemplateChild<Button>,
@@ -68,7 +84,7 @@ mod imp {
         pub fn set_aircraft(&self, name: Option<String>) {
             match name {
                 Some(name) => {
-                    let hangar = get_hangar().imp();
+                    let hangar = get_hangar();
                     match hangar.get(name.as_str()) {
                         Some(aircraft) => {
                             self.ac_name.set_text(aircraft.get_name());
@@ -78,6 +94,15 @@ mod imp {
                             self.ac_climb_rate.set_text(&aircraft.get_climb_rate().to_string());
                             self.ac_sink_speed.set_text(&aircraft.get_sink_speed().to_string());
                             self.ac_sink_rate.set_text(&aircraft.get_sink_rate().to_string());
+                            let fuel = aircraft.get_fuel();
+                            self.ac_fuel_capacity.set_text(&fuel.get_capacity().to_string());
+                            self.ac_fuel_climb_burn.set_text(&fuel.get_climb_burn().to_string());
+                            self.ac_fuel_cruise_burn.set_text(&fuel.get_cruise_burn().to_string());
+                            self.ac_fuel_descent_burn.set_text(&fuel.get_descent_burn().to_string());
+                            self.ac_fuel_taxi.set_text(&fuel.get_taxi().to_string());
+                            self.ac_fuel_contingency.set_text(&fuel.get_contingency().to_string());
+                            self.ac_fuel_alternate.set_text(&fuel.get_alternate_minutes().to_string());
+                            self.ac_fuel_reserve.set_text(&fuel.get_reserve_minutes().to_string());
                             self.ac_name.set_sensitive(false);
                         }
                         None => {
@@ -93,6 +118,14 @@ mod imp {
                     self.ac_climb_rate.set_text("");
                     self.ac_sink_speed.set_text("");
                     self.ac_sink_rate.set_text("");
+                    self.ac_fuel_capacity.set_text("");
+                    self.ac_fuel_climb_burn.set_text("");
+                    self.ac_fuel_cruise_burn.set_text("");
+                    self.ac_fuel_descent_burn.set_text("");
+                    self.ac_fuel_taxi.set_text("");
+                    self.ac_fuel_contingency.set_text("");
+                    self.ac_fuel_alternate.set_text("");
+                    self.ac_fuel_reserve.set_text("");
                     self.ac_name.set_sensitive(true);
                 }
             }
@@ -105,7 +138,15 @@ mod imp {
                 validate_numeric(&self.ac_climb_speed, "Climb Speed") &&
                 validate_numeric(&self.ac_climb_rate, "Climb Rate") &&
                 validate_numeric(&self.ac_sink_speed, "Sink Speed") &&
-                validate_numeric(&self.ac_sink_rate, "Sink Rate")
+                validate_numeric(&self.ac_sink_rate, "Sink Rate") &&
+                validate_decimal(&self.ac_fuel_capacity, "Usable Fuel") &&
+                validate_decimal(&self.ac_fuel_climb_burn, "Climb Fuel Burn") &&
+                validate_decimal(&self.ac_fuel_cruise_burn, "Cruise Fuel Burn") &&
+                validate_decimal(&self.ac_fuel_descent_burn, "Descent Fuel Burn") &&
+                validate_decimal(&self.ac_fuel_taxi, "Taxi Fuel") &&
+                validate_decimal(&self.ac_fuel_contingency, "Contingency Fuel") &&
+                validate_decimal(&self.ac_fuel_alternate, "Alternate Fuel") &&
+                validate_decimal(&self.ac_fuel_reserve, "Final Reserve")
         }
 
         fn save_aircraft(&self) -> bool {
@@ -119,7 +160,7 @@ mod imp {
                 alert.show(Some(&self.obj().clone()));
                 false
             } else {
-                let hangar = get_hangar().imp();
+                let hangar = get_hangar();
                 let string = self.ac_name.text();
                 let name = string.as_str();
                 let aircraft = Aircraft::new(
@@ -130,6 +171,17 @@ mod imp {
                     number_from(&self.ac_climb_rate),
                     number_from(&self.ac_sink_speed),
                     number_from(&self.ac_sink_rate),
+                    AircraftFuel::new(
+                        decimal_from(&self.ac_fuel_climb_burn),
+                        decimal_from(&self.ac_fuel_cruise_burn),
+                        decimal_from(&self.ac_fuel_descent_burn),
+                        decimal_from(&self.ac_fuel_capacity),
+                    ).with_allowances(
+                        decimal_from(&self.ac_fuel_taxi),
+                        decimal_from(&self.ac_fuel_contingency),
+                        decimal_from(&self.ac_fuel_alternate),
+                        decimal_from(&self.ac_fuel_reserve),
+                    ),
                     false,
                 );
                 hangar.put(aircraft);
@@ -208,6 +260,24 @@ fn validate_numeric(entry: &Entry, name: &str) -> bool {
     }
 }
 
+fn decimal_from(entry: &Entry) -> f64 {
+    entry.text().as_str().parse::<f64>().unwrap_or(0.0)
+}
+
+// Fuel figures are optional, so an empty field is allowed and taken as zero
+fn validate_decimal(entry: &Entry, name: &str) -> bool {
+    if entry.text().as_str().is_empty() {
+        return true;
+    }
+    match entry.text().as_str().parse::<f64>() {
+        Ok(_) => { true }
+        Err(_) => {
+            show_error_dialog(&entry.root(), format!("{} should be numeric", name).as_str());
+            false
+        }
+    }
+}
+
 fn validate_not_empty(entry: &Entry, name: &str) -> bool {
     if entry.text().as_str().is_empty() {
         show_error_dialog(&entry.root(), format!("{} is required", name).as_str());
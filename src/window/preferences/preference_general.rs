@@ -59,6 +59,18 @@ mod imp {
         #[template_child]
         fix_browse: TemplateChild<Button>,
         #[template_child]
+        awy_path: TemplateChild<Entry>,
+        #[template_child]
+        awy_browse: TemplateChild<Button>,
+        #[template_child]
+        terrain_path: TemplateChild<Entry>,
+        #[template_child]
+        terrain_browse: TemplateChild<Button>,
+        #[template_child]
+        cifp_path: TemplateChild<Entry>,
+        #[template_child]
+        cifp_browse: TemplateChild<Button>,
+        #[template_child]
         btn_use_mag_hdg: TemplateChild<CheckButton>,
         #[template_child]
         btn_dist_nm: TemplateChild<CheckButton>,
@@ -87,9 +99,13 @@ mod imp {
             self.apt_path.set_text(prefs.get::<String>(AIRPORTS_PATH).unwrap_or("".to_string()).as_str());
             self.nav_path.set_text(prefs.get::<String>(NAVAIDS_PATH).unwrap_or("".to_string()).as_str());
             self.fix_path.set_text(prefs.get::<String>(FIXES_PATH).unwrap_or("".to_string()).as_str());
+            self.awy_path.set_text(prefs.get::<String>(AIRWAYS_PATH).unwrap_or("".to_string()).as_str());
+            self.terrain_path.set_text(prefs.get::<String>(TERRAIN_PATH).unwrap_or("".to_string()).as_str());
+            self.cifp_path.set_text(prefs.get::<String>(CIFP_PATH).unwrap_or("".to_string()).as_str());
             self.apt_path.set_sensitive(!use_dft);
             self.nav_path.set_sensitive(!use_dft);
             self.fix_path.set_sensitive(!use_dft);
+            self.awy_path.set_sensitive(!use_dft);
             self.fg_path.set_sensitive(use_dft);
 
         }
@@ -123,6 +139,7 @@ mod imp {
                 view.apt_path.set_sensitive(!button.is_active());
                 view.nav_path.set_sensitive(!button.is_active());
                 view.fix_path.set_sensitive(!button.is_active());
+                view.awy_path.set_sensitive(!button.is_active());
 
                 if !button.is_active() {
                     if view.apt_path.text().is_empty() {
@@ -134,6 +151,9 @@ mod imp {
                     if view.fix_path.text().is_empty() {
                         view.fix_path.set_text(&view.fg_path.text());
                     }
+                    if view.awy_path.text().is_empty() {
+                        view.awy_path.set_text(&view.fg_path.text());
+                    }
                 }
             }));
 
@@ -167,6 +187,15 @@ mod imp {
             self.fix_path.connect_changed(|editable| {
                 manager().put(FIXES_PATH, editable.text());
             });
+            self.awy_path.connect_changed(|editable| {
+                manager().put(AIRWAYS_PATH, editable.text());
+            });
+            self.terrain_path.connect_changed(|editable| {
+                manager().put(TERRAIN_PATH, editable.text());
+            });
+            self.cifp_path.connect_changed(|editable| {
+                manager().put(CIFP_PATH, editable.text());
+            });
             self.fg_browse.connect_clicked(clone!(#[weak(rename_to = view)] self, move | button | {
                 process_file_browse(view.fg_path.clone(), button.clone(), &gettext("Flightgear data directory"), true);
             }));
@@ -179,6 +208,15 @@ mod imp {
             self.fix_browse.connect_clicked(clone!(#[weak(rename_to = view)] self, move | button | {
                 process_file_browse(view.fix_path.clone(), button.clone(), &gettext("Location for Flightgear fix data"), false);
             }));
+            self.awy_browse.connect_clicked(clone!(#[weak(rename_to = view)] self, move | button | {
+                process_file_browse(view.awy_path.clone(), button.clone(), &gettext("Location for Flightgear airway data"), false);
+            }));
+            self.terrain_browse.connect_clicked(clone!(#[weak(rename_to = view)] self, move | button | {
+                process_file_browse(view.terrain_path.clone(), button.clone(), &gettext("Directory of terrain elevation tiles"), true);
+            }));
+            self.cifp_browse.connect_clicked(clone!(#[weak(rename_to = view)] self, move | button | {
+                process_file_browse(view.cifp_path.clone(), button.clone(), &gettext("Directory of X-Plane CIFP procedure files"), true);
+            }));
         }
     }
 
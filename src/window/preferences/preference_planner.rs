@@ -27,11 +27,12 @@ use gtk::{self, glib};
 mod imp {
     use gtk::{CheckButton, CompositeTemplate, Entry, glib, TemplateChild};
     use gtk::glib::subclass::InitializingObject;
-    use adw::prelude::{CheckButtonExt, EditableExt};
+    use adw::prelude::{CheckButtonExt, EditableExt, WidgetExt};
     use adw::subclass::prelude::{BoxImpl, CompositeTemplate, ObjectImpl, ObjectImplExt, ObjectSubclass, WidgetClassExt};
     use gtk::subclass::widget::{CompositeTemplateInitializingExt, WidgetImpl};
 
-    use crate::preference::{ADD_WAYPOINTS, AUTO_PLAN, MAX_DEVIATION, MAX_LEG_LENGTH, MIN_LEG_LENGTH, PLAN_TYPE, USE_FIXES, USE_GPS, USE_RADIO_BEACONS, VOR_ONLY, VOR_PREFFERED};
+    use crate::model::cruise_level::{DEFAULT_CRUISE_LEVEL_REGIONS, LevelRegions};
+    use crate::preference::{ADD_WAYPOINTS, AUTO_PLAN, CRUISE_LEVEL_REGIONS, MAX_DEVIATION, MAX_LEG_LENGTH, MIN_LEG_LENGTH, PLAN_TYPE, TERRAIN_CLEARANCE, USE_AIRWAYS, USE_FIXES, USE_GPS, USE_RADIO_BEACONS, VOR_ONLY, VOR_PREFFERED};
 
     #[derive(CompositeTemplate, Default)]
     #[template(resource = "/com/shartrec/kelpie_planner/preference_planner.ui")]
@@ -45,12 +46,18 @@ mod imp {
         #[template_child]
         planner_deviation: TemplateChild<Entry>,
         #[template_child]
+        planner_terrain_clearance: TemplateChild<Entry>,
+        #[template_child]
+        planner_cruise_level_regions: TemplateChild<Entry>,
+        #[template_child]
         btn_use_radios: TemplateChild<CheckButton>,
         #[template_child]
         btn_use_fixes: TemplateChild<CheckButton>,
         #[template_child]
         btn_use_gps: TemplateChild<CheckButton>,
         #[template_child]
+        btn_use_airways: TemplateChild<CheckButton>,
+        #[template_child]
         btn_vor_only: TemplateChild<CheckButton>,
         #[template_child]
         btn_vor_preferred: TemplateChild<CheckButton>,
@@ -66,11 +73,14 @@ mod imp {
             self.planner_max_leg.set_text(prefs.get::<String>(MAX_LEG_LENGTH).unwrap_or("100".to_string()).as_str());
             self.planner_min_leg.set_text(prefs.get::<String>(MIN_LEG_LENGTH).unwrap_or("10".to_string()).as_str());
             self.planner_deviation.set_text(prefs.get::<String>(MAX_DEVIATION).unwrap_or("10".to_string()).as_str());
+            self.planner_terrain_clearance.set_text(prefs.get::<String>(TERRAIN_CLEARANCE).unwrap_or("1000".to_string()).as_str());
+            self.planner_cruise_level_regions.set_text(prefs.get::<String>(CRUISE_LEVEL_REGIONS).unwrap_or(DEFAULT_CRUISE_LEVEL_REGIONS.to_string()).as_str());
             if let Some(_type) = prefs.get::<String>(PLAN_TYPE) {
                 match _type.as_str() {
                     USE_RADIO_BEACONS => self.btn_use_radios.set_active(true),
                     USE_FIXES => self.btn_use_fixes.set_active(true),
                     USE_GPS => self.btn_use_gps.set_active(true),
+                    USE_AIRWAYS => self.btn_use_airways.set_active(true),
                     _ => ()
                 }
             }
@@ -115,6 +125,18 @@ mod imp {
             self.planner_deviation.connect_changed(|editable| {
                 crate::preference::manager().put(MAX_DEVIATION, editable.text());
             });
+            self.planner_terrain_clearance.connect_changed(|editable| {
+                crate::preference::manager().put(TERRAIN_CLEARANCE, editable.text());
+            });
+            // Only regions that can be read are saved
+            self.planner_cruise_level_regions.connect_changed(|entry| {
+                if LevelRegions::parse(&entry.text()).is_ok() {
+                    entry.remove_css_class("error");
+                    crate::preference::manager().put(CRUISE_LEVEL_REGIONS, entry.text());
+                } else {
+                    entry.add_css_class("error");
+                }
+            });
             self.btn_use_radios.connect_toggled(|button| {
                 if button.is_active() {
                     crate::preference::manager().put(PLAN_TYPE, USE_RADIO_BEACONS);
@@ -130,6 +152,11 @@ mod imp {
                     crate::preference::manager().put(PLAN_TYPE, USE_GPS);
                 }
             });
+            self.btn_use_airways.connect_toggled(|button| {
+                if button.is_active() {
+                    crate::preference::manager().put(PLAN_TYPE, USE_AIRWAYS);
+                }
+            });
             self.btn_vor_only.connect_toggled(|button| {
                 crate::preference::manager().put(VOR_ONLY, button.is_active());
             });
@@ -0,0 +1,228 @@
+/*
+ * Copyright (c) 2003-2003-2023. Trevor Campbell and others.
+ *
+ * This file is part of Kelpie Flight Planner.
+ *
+ * Kelpie Flight Planner is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * Kelpie Flight Planner is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Kelpie Flight Planner; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Contributors:
+ *      Trevor Campbell
+ *
+ */
+
+use std::sync::Arc;
+
+use gtk::{gio, glib};
+use adw::subclass::prelude::ObjectSubclassIsExt;
+
+use crate::model::airport::Airport;
+use crate::model::procedure::{ProcedureSelection, ProcedureType};
+
+mod imp {
+    use std::cell::RefCell;
+    use std::sync::Arc;
+
+    use gtk::{Button, CompositeTemplate, DropDown, Label, StringList, glib, TemplateChild};
+    use gtk::glib::clone;
+    use gtk::glib::subclass::InitializingObject;
+    use adw::prelude::{ButtonExt, GtkWindowExt, WidgetExt};
+    use adw::subclass::prelude::{CompositeTemplate, ObjectImpl, ObjectImplExt, ObjectSubclass, ObjectSubclassExt, WidgetClassExt, WindowImpl};
+    use gettextrs::gettext;
+    use gtk::subclass::widget::{CompositeTemplateInitializingExt, WidgetImpl};
+
+    use crate::model::airport::Airport;
+    use crate::model::procedure::{Procedure, ProcedureSelection, ProcedureType};
+
+    #[derive(CompositeTemplate, Default)]
+    #[template(resource = "/com/shartrec/kelpie_planner/procedure_dialog.ui")]
+    pub struct ProcedureDialog {
+        #[template_child]
+        pub procedure_list: TemplateChild<DropDown>,
+        #[template_child]
+        pub runway_list: TemplateChild<DropDown>,
+        #[template_child]
+        pub transition_list: TemplateChild<DropDown>,
+        #[template_child]
+        pub legs: TemplateChild<Label>,
+        #[template_child]
+        pub btn_ok: TemplateChild<Button>,
+        #[template_child]
+        pub btn_cancel: TemplateChild<Button>,
+
+        airport: RefCell<Option<Arc<Airport>>>,
+        procedures: RefCell<Vec<Arc<Procedure>>>,
+        runways: RefCell<Vec<String>>,
+        transitions: RefCell<Vec<String>>,
+        pub on_accept: RefCell<Option<Box<dyn Fn(Option<ProcedureSelection>)>>>,
+    }
+
+    impl ProcedureDialog {
+        pub fn set_choices(&self, airport: Arc<Airport>, procedure_type: ProcedureType,
+                           current: Option<&ProcedureSelection>, runway: Option<&String>) {
+            let procedures = airport.get_procedures_of(procedure_type);
+            let selected = current
+                .and_then(|c| procedures.iter().position(|p| p.get_ident() == c.get_ident()))
+                .map_or(0, |i| i + 1);
+            let mut names = vec![gettext("None")];
+            names.extend(procedures.iter().map(|p| p.get_ident().to_string()));
+            self.airport.replace(Some(airport));
+            self.procedures.replace(procedures);
+
+            self.procedure_list.set_model(Some(&string_list(&names)));
+            self.procedure_list.set_selected(selected as u32);
+            self.procedure_changed(current.and_then(|c| c.get_runway()).or(runway), current.and_then(|c| c.get_transition()));
+        }
+
+        fn selected_procedure(&self) -> Option<Arc<Procedure>> {
+            let index = self.procedure_list.selected() as usize;
+            if index == 0 {
+                return None;
+            }
+            self.procedures.borrow().get(index - 1).cloned()
+        }
+
+        // The runway and transition lists depend on the procedure
+        fn procedure_changed(&self, runway: Option<&String>, transition: Option<&String>) {
+            let procedure = self.selected_procedure();
+            let runways: Vec<String> = match (&procedure, self.airport.borrow().as_ref()) {
+                (Some(p), _) if p.get_type() == ProcedureType::Approach => p.get_runways(),
+                (Some(p), Some(airport)) => airport.get_runways()
+                    .read()
+                    .expect("Can't get airport lock")
+                    .iter()
+                    .flat_map(|r| r.ends())
+                    .filter(|end| p.serves_runway(end))
+                    .collect(),
+                _ => vec![],
+            };
+            let transitions = procedure.as_ref().map(|p| p.get_transitions()).unwrap_or_default();
+            let runway_index = runway.and_then(|r| runways.iter().position(|x| x == r)).map_or(0, |i| i + 1);
+            let transition_index = transition.and_then(|t| transitions.iter().position(|x| x == t)).map_or(0, |i| i + 1);
+
+            let mut runway_names = vec![gettext("Any")];
+            runway_names.extend(runways.iter().cloned());
+            let mut transition_names = vec![gettext("None")];
+            transition_names.extend(transitions.iter().cloned());
+            self.runways.replace(runways);
+            self.transitions.replace(transitions);
+
+            self.runway_list.set_model(Some(&string_list(&runway_names)));
+            self.runway_list.set_selected(runway_index as u32);
+            self.transition_list.set_model(Some(&string_list(&transition_names)));
+            self.transition_list.set_selected(transition_index as u32);
+            self.show_legs();
+        }
+
+        pub fn get_selection(&self) -> Option<ProcedureSelection> {
+            let procedure = self.selected_procedure()?;
+            let chosen = |list: &DropDown, names: &RefCell<Vec<String>>| {
+                let index = list.selected() as usize;
+                if index == 0 { None } else { names.borrow().get(index - 1).cloned() }
+            };
+            let runway = match procedure.get_type() {
+                // An approach is only ever to its own runway
+                ProcedureType::Approach => procedure.get_runways().first().cloned(),
+                _ => chosen(&self.runway_list, &self.runways),
+            };
+            let transition = chosen(&self.transition_list, &self.transitions);
+            Some(ProcedureSelection::new(procedure, runway, transition))
+        }
+
+        fn show_legs(&self) {
+            let legs = self.get_selection()
+                .map(|s| s.get_waypoints().iter().map(|wp| wp.get_id().to_string()).collect::<Vec<String>>().join(" "))
+                .unwrap_or_default();
+            self.legs.set_label(&legs);
+        }
+    }
+
+    fn string_list(names: &[String]) -> StringList {
+        let names: Vec<&str> = names.iter().map(|s| s.as_str()).collect();
+        StringList::new(&names)
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ProcedureDialog {
+        const NAME: &'static str = "ProcedureDialog";
+        type Type = super::ProcedureDialog;
+        type ParentType = gtk::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            Self::bind_template(klass);
+            klass.set_accessible_role(gtk::AccessibleRole::Group);
+        }
+
+        fn instance_init(obj: &InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for ProcedureDialog {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            self.procedure_list.connect_selected_notify(clone!(#[weak(rename_to = window)] self, move |_list| {
+                window.procedure_changed(None, None);
+            }));
+            self.runway_list.connect_selected_notify(clone!(#[weak(rename_to = window)] self, move |_list| {
+                window.show_legs();
+            }));
+            self.transition_list.connect_selected_notify(clone!(#[weak(rename_to = window)] self, move |_list| {
+                window.show_legs();
+            }));
+
+            self.btn_cancel.connect_clicked(clone!(#[weak(rename_to = window)] self, move |_button| {
+                window.obj().close();
+            }));
+
+            self.btn_ok.connect_clicked(clone!(#[weak(rename_to = window)] self, move |_button| {
+                if let Some(on_accept) = window.on_accept.borrow().as_ref() {
+                    on_accept(window.get_selection());
+                }
+                window.obj().close();
+            }));
+        }
+    }
+
+    impl WidgetImpl for ProcedureDialog {}
+
+    impl WindowImpl for ProcedureDialog {}
+}
+
+glib::wrapper! {
+    pub struct ProcedureDialog(ObjectSubclass<imp::ProcedureDialog>)
+        @extends gtk::Window, gtk::Widget,
+        @implements gio::ActionGroup, gio::ActionMap, gtk::Accessible, gtk::Buildable,
+                    gtk::ConstraintTarget, gtk::Native, gtk::Root, gtk::ShortcutManager;
+}
+
+impl ProcedureDialog {
+    pub fn new() -> Self {
+        glib::Object::new::<ProcedureDialog>()
+    }
+
+    /// Offer the procedures of the type at the airport, calling `on_accept` with the one chosen, or None to remove it
+    pub fn choose<F: Fn(Option<ProcedureSelection>) + 'static>(&self, airport: Arc<Airport>, procedure_type: ProcedureType,
+                  current: Option<&ProcedureSelection>, runway: Option<&String>, on_accept: F) {
+        self.imp().set_choices(airport, procedure_type, current, runway);
+        self.imp().on_accept.replace(Some(Box::new(on_accept)));
+    }
+}
+
+impl Default for ProcedureDialog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -54,7 +54,6 @@ mod imp {
     use log::error;
     use scheduling::SchedulerHandle;
     use std::cell::{Cell, RefCell};
-    use std::cmp::Ordering::Equal;
     use std::ops::Deref;
     use std::rc::Rc;
     use std::sync::Arc;
@@ -218,7 +217,7 @@ mod imp {
             let zoom = self.zoom_level.get();
             let range = 2.0 / zoom;
 
-            // Collect any visible airport in a 2-degree radius and then sort them, returning the nearest
+            // Find the nearest visible airport within the range (in degrees, converted to nm)
             if self.btn_show_airports.is_active() {
                 let rwl = if zoom > 8.0 {
                     0
@@ -229,20 +228,9 @@ mod imp {
                 };
                 let inc_heli = zoom > 6.0;
 
-                let airports = earth::get_earth_model().get_airports().read().unwrap();
-                let airport = airports.iter().filter(|a| {
-                    (f64::abs(pos.get_latitude() - a.get_lat()) < range as f64)
-                        && (f64::abs(pos.get_longitude() - a.get_long()) < range as f64)
-                        && (a.get_max_runway_length() > rwl || (inc_heli && a.get_type().unwrap() == AirportType::Heliport))
-                })
-                    .min_by(|a, b| {
-                        a.get_loc().distance_to(&pos)
-                            .partial_cmp(&b.get_loc().distance_to(&pos))
-                            .unwrap_or(Equal)
-                    });
-                if let Some(airport) = airport {
-                    return Some(airport.clone());
-                }
+                return earth::get_earth_model().get_nearest_airport(pos, range as f64 * 60.0, |a| {
+                    a.get_max_runway_length() > rwl || (inc_heli && a.get_type().unwrap() == AirportType::Heliport)
+                });
             }
 
             None
@@ -251,24 +239,16 @@ mod imp {
             let zoom = self.zoom_level.get();
             let range = 2.0 / zoom;
 
-            // Collect any visible airport in a 2-degree radius and then sort them, returning the nearest
+            // Find the nearest visible navaid within the range (in degrees, converted to nm)
             if self.btn_show_navaids.is_active() {
                 let inc_ndb = zoom > 6.0;
 
-                let navaids = earth::get_earth_model().get_navaids().read().unwrap();
-                let navaid = navaids.iter().filter(|a| {
-                    (f64::abs(pos.get_latitude() - a.get_lat()) < range as f64)
-                        && (f64::abs(pos.get_longitude() - a.get_long()) < range as f64)
-                        && (inc_ndb || a.get_type() == NavaidType::Vor)
-                })
-                    .min_by(|a, b| {
-                        a.get_loc().distance_to(&pos)
-                            .partial_cmp(&b.get_loc().distance_to(&pos))
-                            .unwrap_or(Equal)
-                    });
-                if let Some(navaid) = navaid {
-                    return Some(navaid.clone());
-                }
+                return earth::get_earth_model().get_nearest_navaid(pos, range as f64 * 60.0, |n| {
+                    match n.get_type() {
+                        NavaidType::Glideslope => false,
+                        t => inc_ndb || t.is_vor() || t == NavaidType::Dme || t == NavaidType::Tacan,
+                    }
+                });
             }
 
             None
@@ -597,7 +577,7 @@ mod imp {
                     if let Some(navaid) = view.find_navaid_for_point(&loc) {
                         if let Some(ref mut plan_view) = get_plan_view(&view.map_window.get()) {
                             // get the plan
-                            plan_view.imp().add_waypoint_to_plan(Waypoint::Navaid {navaid: navaid.clone(), elevation: Cell::new(0), locked: true,});
+                            plan_view.imp().add_waypoint_to_plan(Waypoint::Navaid {navaid: navaid.clone(), elevation: Cell::new(0), locked: true, airway: None, constraints: Cell::default()});
                         }
                     }
                 }